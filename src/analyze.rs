@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
-    intrinsics::get_intrinsic_arity,
-    lang::{Arity, ArityCombineError, Block, Branch, Loop, Term, Type},
+    intrinsics::{get_exact_arity, get_intrinsic_arity, split_format_template},
+    lang::{
+        Arity, ArityCombineError, Block, Branch, Enum, Loop, Match, Record, RecordOp,
+        ResultantType, Term, Try, Type,
+    },
     program::{NamespaceId, Program},
 };
 
@@ -10,6 +13,7 @@ use crate::{
 pub enum AnalysisError {
     IndefiniteSize,
     IncompatibleTypes,
+    NonExhaustive,
     Pending,
 }
 
@@ -48,9 +52,22 @@ fn block_is_always_truthy(b: &Block) -> BlockTruthiness {
     let is_truthy: bool = match last {
         Term::String(t) => !t.is_empty(),
         Term::Number(t) => !t.is_nan() && *t != 0f64,
+        Term::Integer(t) => *t != 0,
+        Term::Big(t) => !t.is_zero(),
+        Term::Decimal(t) => !t.is_zero(),
         Term::Bool(true) | Term::Address(_) => true,
         Term::Bool(false) => false,
-        Term::Name(_, _) | Term::Branch(_) | Term::Loop(_) => return BlockTruthiness::Unknown,
+        Term::Name(_, _)
+        | Term::Bind(_)
+        | Term::Local(_)
+        | Term::Break
+        | Term::Continue
+        | Term::Return
+        | Term::Branch(_)
+        | Term::Loop(_)
+        | Term::Try(_)
+        | Term::Record(_, _)
+        | Term::Match(_) => return BlockTruthiness::Unknown,
     };
 
     if is_truthy {
@@ -60,18 +77,95 @@ fn block_is_always_truthy(b: &Block) -> BlockTruthiness {
     }
 }
 
-pub fn analyze_condition(analysis: &Analysis, b: &Block) -> BlockAnalysisResult {
-    let mut res = analyze_block(analysis, b)?;
-    res.pop_any();
-    Ok(res)
+// Bound locals are typed against the pops of the running arity, so blocks are
+// analyzed as a continuation of everything before them in the function
+type Locals = HashMap<usize, ResultantType>;
+
+// The arities of paths leaving the innermost loop body early
+#[derive(Default)]
+struct LoopExits {
+    breaks: Option<Arity>,
+    continues: Option<Arity>,
+}
+
+#[derive(Default)]
+struct PathState {
+    locals: Locals,
+    loop_exits: LoopExits,
+    // The arities of paths returning early from the function
+    returns: Option<Arity>,
+    // The arity at the first uncaught throw, used when no path finishes the function
+    throws: Option<Arity>,
+    // The same for exits, which no catch handles
+    exits: Option<Arity>,
+}
+
+// None when no path reaches the end of the block
+type PathResult = Result<Option<Arity>, AnalysisError>;
+
+fn combine_paths(combined: &mut Option<Arity>, a: Arity) -> Result<(), AnalysisError> {
+    *combined = Some(match combined.take() {
+        Some(before) => Arity::parallel(&before, &a)?,
+        None => a,
+    });
+    Ok(())
+}
+
+fn analyze_condition(
+    analysis: &Analysis,
+    running: Arity,
+    state: &mut PathState,
+    b: &Block,
+) -> PathResult {
+    let res = analyze_block_from(analysis, running, state, b)?;
+    Ok(res.map(|mut res| {
+        res.pop_any();
+        res
+    }))
 }
 
 pub fn analyze_block(analysis: &Analysis, b: &Block) -> BlockAnalysisResult {
-    let mut a = Arity::noop();
+    let mut state = PathState::default();
+    let mut res = analyze_block_from(analysis, Arity::noop(), &mut state, b)?;
+    if let Some(returns) = state.returns {
+        combine_paths(&mut res, returns)?;
+    }
+    res.or(state.throws)
+        .or(state.exits)
+        .ok_or(AnalysisError::IndefiniteSize)
+}
+
+fn analyze_block_from(
+    analysis: &Analysis,
+    mut a: Arity,
+    state: &mut PathState,
+    b: &Block,
+) -> PathResult {
+    let mut previous: Option<&Term> = None;
     for term in &b.terms {
-        a = Arity::serial(&a, &analyze_term(analysis, term)?)?;
+        let next = match (previous, term) {
+            (Some(Term::String(template)), Term::Name(n, _)) if n == "format" => {
+                Some(Arity::serial(&a, &analyze_format(template)?)?)
+            }
+            _ => analyze_term(analysis, a, state, term)?,
+        };
+        let Some(next) = next else {
+            return Ok(None);
+        };
+        a = next;
+        previous = Some(term);
     }
-    Ok(a)
+    Ok(Some(a))
+}
+
+// The number of values consumed by format is only known for literal templates
+fn analyze_format(template: &str) -> BlockAnalysisResult {
+    let Some(pieces) = split_format_template(template) else {
+        return Err(AnalysisError::IndefiniteSize);
+    };
+    let mut pops = vec![Type::String];
+    pops.extend(std::iter::repeat_n(Type::Unknown, pieces.len() - 1));
+    Ok((pops, vec![Type::String]).into())
 }
 
 fn analyze_name(analysis: &Analysis, n: &str) -> BlockAnalysisResult {
@@ -95,31 +189,46 @@ fn analyze_name(analysis: &Analysis, n: &str) -> BlockAnalysisResult {
     arity_result.clone()
 }
 
-fn analyze_branch(analysis: &Analysis, branch: &Branch) -> BlockAnalysisResult {
-    let mut running = Arity::noop();
+// Exact when the operands are all integers, bignums or decimals, which promote to the widest
+fn exact_arity(running: &Arity, n: &str) -> Option<Arity> {
+    let pops = get_exact_arity(n, Type::Integer)?.pops.len();
+    let operands = running.pushes.len().checked_sub(pops)?;
+    let mut exact = Type::Integer;
+    for t in &running.pushes[operands..] {
+        match t {
+            ResultantType::Normal(Type::Integer) => {}
+            ResultantType::Normal(t @ (Type::Big | Type::Decimal)) => exact = exact.union(t),
+            ResultantType::Normal(_) | ResultantType::Dependent(_) => return None,
+        }
+    }
+    get_exact_arity(n, exact)
+}
 
-    let mut combined: Option<Arity> = None;
-    let mut add_termination = |a: Arity| -> Result<(), AnalysisError> {
-        combined = Some(if let Some(before) = combined.take() {
-            match Arity::parallel(&before, &a) {
-                Ok(n) => n,
-                Err(ArityCombineError::DifferingSizes) => {
-                    return Err(AnalysisError::IndefiniteSize);
-                }
-                Err(ArityCombineError::IncompatibleTypes) => {
-                    return Err(AnalysisError::IncompatibleTypes);
-                }
-            }
-        } else {
-            a
-        });
+// Fields can hold any value, only the record itself is typed, so what is read from them is only
+// checked where it is used
+fn analyze_record(analysis: &Analysis, record: &Record, op: RecordOp) -> Arity {
+    let record_type = Type::record(analysis.namespace, record.type_name());
+    match op {
+        RecordOp::Construct => (vec![Type::Unknown; record.fields.len()], vec![record_type]).into(),
+        RecordOp::Get(_) => Arity::unary(record_type, Type::Field),
+        RecordOp::With(_) => Arity::binary(Type::Unknown, record_type.clone(), record_type),
+    }
+}
 
-        Ok(())
-    };
+fn analyze_branch(
+    analysis: &Analysis,
+    start: Arity,
+    state: &mut PathState,
+    branch: &Branch,
+) -> PathResult {
+    let mut running = start;
+    let mut combined: Option<Arity> = None;
 
     for arm in &branch.arms {
-        let condition_arity = analyze_condition(analysis, &arm.0)?;
-        running = Arity::serial(&running, &condition_arity)?;
+        let Some(condition) = analyze_condition(analysis, running, state, &arm.0)? else {
+            return Ok(combined);
+        };
+        running = condition;
 
         let (possible, last_arm) = match block_is_always_truthy(&arm.0) {
             BlockTruthiness::AlwaysTruthy => (true, true),
@@ -127,91 +236,218 @@ fn analyze_branch(analysis: &Analysis, branch: &Branch) -> BlockAnalysisResult {
             BlockTruthiness::Unknown => (true, false),
         };
 
-        if possible {
-            let block_arity = analyze_block(analysis, &arm.1)?;
-            let arity = Arity::serial(&running, &block_arity)?;
-            add_termination(arity)?;
+        if possible
+            && let Some(arity) = analyze_block_from(analysis, running.clone(), state, &arm.1)?
+        {
+            combine_paths(&mut combined, arity)?;
         }
 
         if last_arm {
-            return Ok(combined.expect("Unable to combine last branch arm"));
+            return Ok(combined);
         }
     }
 
-    add_termination(running)?;
+    combine_paths(&mut combined, running)?;
 
-    Ok(combined.expect("Unable to combine branch arms"))
+    Ok(combined)
 }
 
-fn analyze_loop(analysis: &Analysis, loop_v: &Loop) -> BlockAnalysisResult {
-    let pre_arity = if let Some(pre) = &loop_v.pre_condition {
-        Some(analyze_condition(analysis, pre)?)
-    } else {
-        None
+// Every variant of the matched enum needs an arm, which starts from the variant's fields
+fn analyze_match(
+    analysis: &Analysis,
+    start: &Arity,
+    state: &mut PathState,
+    m: &Match,
+) -> PathResult {
+    let mut matched: Option<&Rc<Enum>> = None;
+    let mut declared_in = analysis.namespace;
+    let mut variants = vec![];
+    for (name, _) in &m.arms {
+        let Some(variant) = analysis.program.resolve_variant(analysis.namespace, name) else {
+            return Err(AnalysisError::Pending);
+        };
+        // The enum is declared alongside its variants' constructors
+        if let Some((id, _)) = analysis.program.resolve_function(analysis.namespace, name) {
+            declared_in = id;
+        }
+        let variant_of = variant
+            .variant_of
+            .as_ref()
+            .expect("Resolved variants belong to an enum");
+        if matched.is_some_and(|e| e != variant_of) {
+            return Err(AnalysisError::IncompatibleTypes);
+        }
+        matched = Some(variant_of);
+        variants.push(variant);
+    }
+    let Some(matched) = matched else {
+        return Ok(None);
     };
+    // Checked before the arms, whose differing sizes would otherwise hide a missing arm
+    if matched.variants.len() > m.arms.len() {
+        return Err(AnalysisError::NonExhaustive);
+    }
 
-    let main_arity = analyze_block(analysis, &loop_v.body)?;
-
-    let post_arity = if let Some(post) = &loop_v.post_condition {
-        Some(analyze_condition(analysis, post)?)
-    } else {
-        None
-    };
+    let matched_type = Type::record(declared_in, &matched.name);
+    let mut combined = None;
+    for (variant, (_, body)) in variants.iter().zip(&m.arms) {
+        let fields = vec![Type::Field; variant.fields.len()];
+        let unpacked = (vec![matched_type.clone()], fields).into();
+        let running = Arity::serial(start, &unpacked)?;
+        if let Some(arity) = analyze_block_from(analysis, running, state, body)? {
+            combine_paths(&mut combined, arity)?;
+        }
+    }
+    Ok(combined)
+}
 
-    if pre_arity.is_none() && post_arity.is_none() {
+fn analyze_loop(
+    analysis: &Analysis,
+    start: Arity,
+    state: &mut PathState,
+    loop_v: &Loop,
+) -> PathResult {
+    if loop_v.pre_condition.is_none()
+        && loop_v.post_condition.is_none()
+        && !loop_v.body.contains_loop_control(&Term::Break)
+        && !loop_v.body.contains_return()
+    {
         return Err(AnalysisError::IndefiniteSize);
     }
 
-    let mut running_arity = Arity::noop();
-    let mut possible_arity = None;
-    let mut seen_states = vec![];
-
-    let record_next_exit_arity = |running: &mut Arity,
-                                  possible: &mut Option<Arity>,
-                                  next: &Arity|
-     -> Result<(), AnalysisError> {
-        *running = Arity::serial(running, next)?;
+    let outer_exits = std::mem::take(&mut state.loop_exits);
+    let res = analyze_loop_paths(analysis, start, state, loop_v);
+    let exits = std::mem::replace(&mut state.loop_exits, outer_exits);
 
-        let next_possible = match possible {
-            Some(possible) => Arity::parallel(possible, running)?,
-            None => running.clone(),
-        };
-
-        *possible = Some(next_possible);
+    let mut possible_arity = res?;
+    if let Some(breaks) = exits.breaks {
+        combine_paths(&mut possible_arity, breaks)?;
+    }
+    Ok(possible_arity)
+}
 
-        Ok(())
-    };
+// Follows iterations until the arity at the start of an iteration repeats, returning
+// the combined arity of the paths that exit through a condition
+fn analyze_loop_paths(
+    analysis: &Analysis,
+    start: Arity,
+    state: &mut PathState,
+    loop_v: &Loop,
+) -> PathResult {
+    let mut running_arity = Some(start);
+    let mut possible_arity = None;
+    let mut seen_states = vec![];
 
-    loop {
-        if seen_states.contains(&running_arity) {
+    while let Some(mut running) = running_arity.take() {
+        if seen_states.contains(&running) {
             break;
         }
-        seen_states.push(running_arity.clone());
-
-        if let Some(pre) = pre_arity.as_ref() {
-            record_next_exit_arity(&mut running_arity, &mut possible_arity, pre)?;
+        seen_states.push(running.clone());
+
+        if let Some(pre) = loop_v.pre_condition.as_ref() {
+            let Some(condition) = analyze_condition(analysis, running, state, pre)? else {
+                break;
+            };
+            combine_paths(&mut possible_arity, condition.clone())?;
+            running = condition;
         }
 
-        running_arity = Arity::serial(&running_arity, &main_arity)?;
+        let mut body = analyze_block_from(analysis, running, state, &loop_v.body)?;
 
-        if let Some(post) = post_arity.as_ref() {
-            record_next_exit_arity(&mut running_arity, &mut possible_arity, post)?;
+        // Continuing skips the rest of the body, but not the post condition
+        if let Some(continues) = state.loop_exits.continues.take() {
+            combine_paths(&mut body, continues)?;
         }
+
+        let Some(body) = body else {
+            break;
+        };
+
+        running_arity = match loop_v.post_condition.as_ref() {
+            Some(post) => {
+                let condition = analyze_condition(analysis, body, state, post)?;
+                if let Some(condition) = &condition {
+                    combine_paths(&mut possible_arity, condition.clone())?;
+                }
+                condition
+            }
+            None => Some(body),
+        };
     }
 
-    Ok(possible_arity.expect("Must have filled possible_arity at least once"))
+    Ok(possible_arity)
 }
 
-pub fn analyze_term(analysis: &Analysis, term: &Term) -> BlockAnalysisResult {
-    match term {
-        Term::String(_) => Ok(Arity::literal(Type::String)),
-        Term::Number(_) => Ok(Arity::literal(Type::Number)),
-        Term::Bool(_) => Ok(Arity::literal(Type::Bool)),
-        Term::Address(_) => Ok(Arity::literal(Type::Address)),
-        Term::Name(n, _) => analyze_name(analysis, n.as_str()),
-        Term::Branch(branch) => analyze_branch(analysis, branch),
-        Term::Loop(loop_v) => analyze_loop(analysis, loop_v),
+fn analyze_try(analysis: &Analysis, start: &Arity, state: &mut PathState, t: &Try) -> PathResult {
+    // Throws in the body are caught, so only the handler's can leave the function
+    let outer_throws = state.throws.take();
+    let mut combined = analyze_block_from(analysis, start.clone(), state, &t.body)?;
+    state.throws = outer_throws;
+    // The handler receives the caught value on top of the stack from before the body
+    let caught = Arity::serial(start, &Arity::literal(Type::Unknown))?;
+    if let Some(handled) = analyze_block_from(analysis, caught, state, &t.handler)? {
+        combine_paths(&mut combined, handled)?;
     }
+    Ok(combined)
+}
+
+fn analyze_term(
+    analysis: &Analysis,
+    mut running: Arity,
+    state: &mut PathState,
+    term: &Term,
+) -> PathResult {
+    let arity = match term {
+        Term::String(_) => Arity::literal(Type::String),
+        Term::Number(_) => Arity::literal(Type::Number),
+        Term::Integer(_) => Arity::literal(Type::Integer),
+        Term::Big(_) => Arity::literal(Type::Big),
+        Term::Decimal(_) => Arity::literal(Type::Decimal),
+        Term::Bool(_) => Arity::literal(Type::Bool),
+        Term::Address(_) => Arity::literal(Type::Address),
+        Term::Record(record, op) => analyze_record(analysis, record, *op),
+        Term::Name(n, _) if n == "throw" => {
+            let thrown = Arity::serial(&running, &analyze_name(analysis, n)?)?;
+            state.throws.get_or_insert(thrown);
+            return Ok(None);
+        }
+        Term::Name(n, _) if n == "exit" => {
+            let exited = Arity::serial(&running, &analyze_name(analysis, n)?)?;
+            state.exits.get_or_insert(exited);
+            return Ok(None);
+        }
+        Term::Name(n, _) => match exact_arity(&running, n) {
+            Some(arity) => arity,
+            None => analyze_name(analysis, n.as_str())?,
+        },
+        Term::Bind(bound) => {
+            for local in bound.iter().rev() {
+                state.locals.insert(local.slot, running.bind_local());
+            }
+            return Ok(Some(running));
+        }
+        Term::Local(local) => {
+            running.push_local(&state.locals[&local.slot]);
+            return Ok(Some(running));
+        }
+        Term::Break => {
+            combine_paths(&mut state.loop_exits.breaks, running)?;
+            return Ok(None);
+        }
+        Term::Continue => {
+            combine_paths(&mut state.loop_exits.continues, running)?;
+            return Ok(None);
+        }
+        Term::Return => {
+            combine_paths(&mut state.returns, running)?;
+            return Ok(None);
+        }
+        Term::Branch(branch) => return analyze_branch(analysis, running, state, branch),
+        Term::Loop(loop_v) => return analyze_loop(analysis, running, state, loop_v),
+        Term::Try(t) => return analyze_try(analysis, &running, state, t),
+        Term::Match(m) => return analyze_match(analysis, &running, state, m),
+    };
+    Ok(Some(Arity::serial(&running, &arity)?))
 }
 
 fn get_arity_at(by_namespace: &mut AritiesByNamespace, i: NamespaceId) -> &mut NamespaceArities {
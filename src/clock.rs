@@ -0,0 +1,37 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Time source for the interpreter, replaced in tests to keep timing deterministic
+pub trait Clock {
+    /// Milliseconds since the unix epoch
+    fn now(&self) -> f64;
+    /// Milliseconds since an arbitrary fixed point, only useful for measuring durations
+    fn monotonic(&self) -> f64;
+    fn sleep(&self, ms: f64);
+}
+
+pub struct SystemClock;
+
+static MONOTONIC_START: OnceLock<Instant> = OnceLock::new();
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0f64, |d| d.as_secs_f64() * 1000f64)
+    }
+
+    fn monotonic(&self) -> f64 {
+        MONOTONIC_START
+            .get_or_init(Instant::now)
+            .elapsed()
+            .as_secs_f64()
+            * 1000f64
+    }
+
+    fn sleep(&self, ms: f64) {
+        std::thread::sleep(Duration::from_secs_f64(ms / 1000f64));
+    }
+}
@@ -1,9 +1,16 @@
+#define _POSIX_C_SOURCE 200809L
+
+#include <ctype.h>
+#include <dirent.h>
 #include <errno.h>
+#include <inttypes.h>
 #include <math.h>
 #include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <sys/stat.h>
+#include <time.h>
 
 typedef int status_t;
 
@@ -18,12 +25,64 @@ typedef struct {
   int refs;
 } string_source_t;
 
+// Records share their field names with every other record of the same type, which is told apart
+// by the namespace that declares it as well as by name
+typedef struct {
+  int namespace;
+  const char *name;
+  const char *const *fields;
+  int field_count;
+  value_t *values;
+  int refs;
+} record_t;
+
+// Integers don't fit beside the other values in a double, so they are kept here
+typedef struct {
+  int64_t value;
+  int refs;
+} integer_t;
+
+// Bignums are shared like integers and never change. Their limbs are base 2^32,
+// least significant first and without trailing zeros, and their decimal text
+// is only written once it is needed
+typedef struct {
+  uint32_t *limbs;
+  int length;
+  int negative;
+  char *text;
+  int refs;
+} big_t;
+
+// Decimals are `unscaled / 10^scale`, shared like bignums. The scale is kept so
+// that `1.50d` prints both of its places
+typedef struct {
+  big_t unscaled;
+  int scale;
+  char *text;
+  int refs;
+} decimal_t;
+
 typedef struct {
   string_source_t strings[100];
+  record_t records[100];
+  integer_t integers[1000];
+  big_t bigs[1000];
+  decimal_t decimals[1000];
   value_t values[1000];
   int value_count;
+  int argc;
+  char **argv;
+  uint64_t random_state;
+  struct timespec start;
+  status_t status;
+  value_t thrown;
 } interpreter_state_t;
 
+typedef struct {
+  value_t *values;
+  int value_count;
+} stack_snapshot_t;
+
 static interpreter_state_t STATE = {0};
 
 static const status_t OK = 0;
@@ -33,6 +92,17 @@ static const status_t STACK_OVERFLOW = 102;
 static const status_t STRING_MAX = 103;
 static const status_t TYPE_MISMATCH = 104;
 static const status_t ASSERT_FAILED = 105;
+static const status_t INVALID_EXIT_CODE = 106;
+static const status_t INVALID_FORMAT = 107;
+static const status_t INVALID_ARGUMENT = 108;
+static const status_t THROWN = 109;
+static const status_t RECORD_MAX = 110;
+static const status_t NO_MATCH = 111;
+static const status_t INTEGER_MAX = 112;
+static const status_t INTEGER_OVERFLOW = 113;
+static const status_t DIVISION_BY_ZERO = 114;
+static const status_t BIG_MAX = 115;
+static const status_t DECIMAL_MAX = 116;
 
 static const status_t STRING_TOO_LONG = 202;
 static const status_t STDIN_FAILED = 203;
@@ -69,7 +139,7 @@ static const uint64_t FALSE_BYTES = 0x7fffb00000000000L;
 
 #define stack_read_number(x, offset)                                           \
   value_t x;                                                                   \
-  checked(resolve_number_value(STATE.values[STATE.value_count + offset], &x));
+  checked(read_number_slot(&STATE.values[STATE.value_count + offset], &x));
 
 #define stack_read_string(x, offset)                                           \
   string_source_t *x;                                                          \
@@ -109,15 +179,590 @@ status_t resolve_string_value(value_t v, string_source_t **target) {
   return OK;
 }
 
+value_t record_index_to_value(uint64_t record_index) {
+  record_index |= (0x7fff8ULL << 44);
+  value_t v = *(value_t *)&record_index;
+  return v;
+}
+
+void maybe_resolve_record_value(value_t v, record_t **target) {
+  uint64_t s = *(uint64_t *)&v;
+  if (isnan(v) && s >> 44 == 0x7fff8ULL) {
+    *target = STATE.records + (s & 0xFFFFF);
+  } else {
+    *target = NULL;
+  }
+}
+
+status_t resolve_record_value(value_t v, int namespace, const char *name,
+                              record_t **target) {
+  maybe_resolve_record_value(v, target);
+  if (*target == NULL || (*target)->namespace != namespace ||
+      strcmp((*target)->name, name) != 0) {
+    return TYPE_MISMATCH;
+  }
+  return OK;
+}
+
+value_t integer_index_to_value(uint64_t integer_index) {
+  integer_index |= (0x7fff7ULL << 44);
+  value_t v = *(value_t *)&integer_index;
+  return v;
+}
+
+void maybe_resolve_integer_value(value_t v, integer_t **target) {
+  uint64_t s = *(uint64_t *)&v;
+  if (isnan(v) && s >> 44 == 0x7fff7ULL) {
+    *target = STATE.integers + (s & 0xFFFFF);
+  } else {
+    *target = NULL;
+  }
+}
+
+status_t resolve_integer_value(value_t v, int64_t *target) {
+  integer_t *integer;
+  maybe_resolve_integer_value(v, &integer);
+  if (integer == NULL) {
+    return TYPE_MISMATCH;
+  }
+  *target = integer->value;
+  return OK;
+}
+
+value_t big_index_to_value(uint64_t big_index) {
+  big_index |= (0x7fff6ULL << 44);
+  value_t v = *(value_t *)&big_index;
+  return v;
+}
+
+void maybe_resolve_big_value(value_t v, big_t **target) {
+  uint64_t s = *(uint64_t *)&v;
+  if (isnan(v) && s >> 44 == 0x7fff6ULL) {
+    *target = STATE.bigs + (s & 0xFFFFF);
+  } else {
+    *target = NULL;
+  }
+}
+
+value_t decimal_index_to_value(uint64_t decimal_index) {
+  decimal_index |= (0x7fff5ULL << 44);
+  value_t v = *(value_t *)&decimal_index;
+  return v;
+}
+
+void maybe_resolve_decimal_value(value_t v, decimal_t **target) {
+  uint64_t s = *(uint64_t *)&v;
+  if (isnan(v) && s >> 44 == 0x7fff5ULL) {
+    *target = STATE.decimals + (s & 0xFFFFF);
+  } else {
+    *target = NULL;
+  }
+}
+
+// Drops trailing zero limbs, and the sign of zero
+big_t big_new(uint32_t *limbs, int length, int negative) {
+  while (length > 0 && limbs[length - 1] == 0) {
+    length--;
+  }
+  big_t result = {limbs, length, negative && length > 0, NULL, 0};
+  return result;
+}
+
+// Widens an integer into `limbs`, which must have room for two
+big_t big_from_integer(int64_t v, uint32_t *limbs) {
+  uint64_t magnitude = v < 0 ? -(uint64_t)v : (uint64_t)v;
+  limbs[0] = (uint32_t)magnitude;
+  limbs[1] = (uint32_t)(magnitude >> 32);
+  return big_new(limbs, 2, v < 0);
+}
+
+big_t big_copy(const big_t *big) {
+  uint32_t *limbs = malloc((big->length + 1) * sizeof(uint32_t));
+  memcpy(limbs, big->limbs, big->length * sizeof(uint32_t));
+  return big_new(limbs, big->length, big->negative);
+}
+
+// Decimal digits with an optional leading `-`
+big_t big_parse(const char *digits) {
+  int negative = *digits == '-';
+  digits += negative;
+  size_t count = strlen(digits);
+  uint32_t *limbs = calloc(count / 9 + 2, sizeof(uint32_t));
+  int length = 0;
+  for (size_t i = 0; i < count; i++) {
+    uint64_t carry = digits[i] - '0';
+    for (int j = 0; j < length; j++) {
+      carry += (uint64_t)limbs[j] * 10;
+      limbs[j] = (uint32_t)carry;
+      carry >>= 32;
+    }
+    if (carry > 0) {
+      limbs[length++] = (uint32_t)carry;
+    }
+  }
+  return big_new(limbs, length, negative);
+}
+
+// Drops the fractional part, `v` must be finite
+big_t big_from_double(double v) {
+  char digits[400];
+  snprintf(digits, sizeof(digits), "%.0f", trunc(v));
+  return big_parse(digits);
+}
+
+int big_to_integer(const big_t *big, int64_t *target) {
+  if (big->length > 2) {
+    return 0;
+  }
+  uint64_t magnitude = big->length > 0 ? big->limbs[0] : 0;
+  if (big->length > 1) {
+    magnitude |= (uint64_t)big->limbs[1] << 32;
+  }
+  if (magnitude > (uint64_t)INT64_MAX + big->negative) {
+    return 0;
+  }
+  *target = big->negative && magnitude > 0 ? -(int64_t)(magnitude - 1) - 1
+                                           : (int64_t)magnitude;
+  return 1;
+}
+
+int big_compare_limbs(const big_t *a, const big_t *b) {
+  if (a->length != b->length) {
+    return a->length < b->length ? -1 : 1;
+  }
+  for (int i = a->length - 1; i >= 0; i--) {
+    if (a->limbs[i] != b->limbs[i]) {
+      return a->limbs[i] < b->limbs[i] ? -1 : 1;
+    }
+  }
+  return 0;
+}
+
+int big_compare(const big_t *a, const big_t *b) {
+  if (a->negative != b->negative) {
+    return a->negative ? -1 : 1;
+  }
+  int magnitude = big_compare_limbs(a, b);
+  return a->negative ? -magnitude : magnitude;
+}
+
+// Subtracts the magnitude of `b`, which must not be larger, in place
+int big_sub_limbs(uint32_t *limbs, int length, const big_t *b) {
+  uint32_t borrow = 0;
+  for (int i = 0; i < length; i++) {
+    uint32_t subtrahend = i < b->length ? b->limbs[i] : 0;
+    uint32_t difference = limbs[i] - subtrahend - borrow;
+    borrow = limbs[i] < subtrahend || (limbs[i] == subtrahend && borrow);
+    limbs[i] = difference;
+  }
+  while (length > 0 && limbs[length - 1] == 0) {
+    length--;
+  }
+  return length;
+}
+
+// Adds `b` with the given sign, so subtracting flips it
+big_t big_add(const big_t *a, const big_t *b, int b_negative) {
+  int length = (a->length > b->length ? a->length : b->length) + 1;
+  uint32_t *limbs = calloc(length, sizeof(uint32_t));
+  if (a->negative == b_negative) {
+    uint64_t carry = 0;
+    for (int i = 0; i < length; i++) {
+      carry += (uint64_t)(i < a->length ? a->limbs[i] : 0) +
+               (i < b->length ? b->limbs[i] : 0);
+      limbs[i] = (uint32_t)carry;
+      carry >>= 32;
+    }
+    return big_new(limbs, length, a->negative);
+  }
+  // The smaller magnitude is taken from the larger, which decides the sign
+  int swapped = big_compare_limbs(a, b) < 0;
+  const big_t *larger = swapped ? b : a;
+  memcpy(limbs, larger->limbs, larger->length * sizeof(uint32_t));
+  length = big_sub_limbs(limbs, larger->length, swapped ? a : b);
+  return big_new(limbs, length, swapped ? b_negative : a->negative);
+}
+
+big_t big_mul(const big_t *a, const big_t *b) {
+  int length = a->length + b->length;
+  uint32_t *limbs = calloc(length + 1, sizeof(uint32_t));
+  for (int i = 0; i < a->length; i++) {
+    uint64_t carry = 0;
+    for (int j = 0; j < b->length; j++) {
+      carry += (uint64_t)a->limbs[i] * b->limbs[j] + limbs[i + j];
+      limbs[i + j] = (uint32_t)carry;
+      carry >>= 32;
+    }
+    limbs[i + b->length] = (uint32_t)carry;
+  }
+  return big_new(limbs, length, a->negative != b->negative);
+}
+
+// Divides in place and returns the remainder
+uint32_t big_div_small(big_t *big, uint32_t divisor) {
+  uint64_t remainder = 0;
+  for (int i = big->length - 1; i >= 0; i--) {
+    uint64_t current = (remainder << 32) | big->limbs[i];
+    big->limbs[i] = (uint32_t)(current / divisor);
+    remainder = current % divisor;
+  }
+  *big = big_new(big->limbs, big->length, big->negative);
+  return (uint32_t)remainder;
+}
+
+// Long division one bit at a time, truncating towards zero so the remainder
+// has the sign of `a`. `b` must not be zero
+void big_div_rem(const big_t *a, const big_t *b, big_t *quotient,
+                 big_t *remainder) {
+  uint32_t *quotient_limbs = calloc(a->length + 1, sizeof(uint32_t));
+  big_t rest = {calloc(b->length + 1, sizeof(uint32_t)), 0, 0, NULL, 0};
+  for (int bit = a->length * 32 - 1; bit >= 0; bit--) {
+    uint32_t carry = (a->limbs[bit / 32] >> (bit % 32)) & 1;
+    for (int i = 0; i < rest.length; i++) {
+      uint32_t next = rest.limbs[i] >> 31;
+      rest.limbs[i] = (rest.limbs[i] << 1) | carry;
+      carry = next;
+    }
+    if (carry > 0) {
+      rest.limbs[rest.length++] = carry;
+    }
+    if (big_compare_limbs(&rest, b) >= 0) {
+      rest.length = big_sub_limbs(rest.limbs, rest.length, b);
+      quotient_limbs[bit / 32] |= 1U << (bit % 32);
+    }
+  }
+  *quotient = big_new(quotient_limbs, a->length, a->negative != b->negative);
+  *remainder = big_new(rest.limbs, rest.length, a->negative);
+}
+
+// Powers that would need more bits than this are refused rather than
+// exhausting memory
+static const uint64_t BIG_MAX_POW_BITS = 1 << 24;
+
+status_t big_pow(const big_t *base, uint64_t exponent, big_t *result) {
+  uint64_t bits = 0;
+  if (base->length > 0) {
+    bits = (base->length - 1) * 32;
+    for (uint32_t high = base->limbs[base->length - 1]; high > 0; high >>= 1) {
+      bits++;
+    }
+  }
+  if (bits > 1 && exponent > BIG_MAX_POW_BITS / bits) {
+    return INTEGER_OVERFLOW;
+  }
+  uint32_t one_limbs[2];
+  big_t one = big_from_integer(1, one_limbs);
+  big_t power = big_copy(&one);
+  big_t square = big_copy(base);
+  for (; exponent > 0; exponent >>= 1) {
+    if (exponent & 1) {
+      big_t next = big_mul(&power, &square);
+      free(power.limbs);
+      power = next;
+    }
+    if (exponent > 1) {
+      big_t next = big_mul(&square, &square);
+      free(square.limbs);
+      square = next;
+    }
+  }
+  free(square.limbs);
+  *result = power;
+  return OK;
+}
+
+// Written nine decimal digits at a time, least significant first, and kept
+// for as long as the bignum lives
+const char *big_to_string(big_t *big) {
+  if (big->text != NULL) {
+    return big->text;
+  }
+  big_t rest = big_copy(big);
+  char *text = malloc(big->length * 10 + 2);
+  int len = 0;
+  do {
+    uint32_t chunk = big_div_small(&rest, 1000000000);
+    for (int i = 0; i < 9 && (rest.length > 0 || chunk > 0 || i == 0); i++) {
+      text[len++] = '0' + chunk % 10;
+      chunk /= 10;
+    }
+  } while (rest.length > 0);
+  free(rest.limbs);
+  if (big->negative) {
+    text[len++] = '-';
+  }
+  for (int i = 0; i < len / 2; i++) {
+    char c = text[i];
+    text[i] = text[len - 1 - i];
+    text[len - 1 - i] = c;
+  }
+  text[len] = '\0';
+  big->text = text;
+  return text;
+}
+
+// Integers and bignums can both be used where an exact value is expected,
+// with integers widened into `limbs`
+int read_exact_value(value_t v, big_t *target, uint32_t *limbs) {
+  big_t *big;
+  maybe_resolve_big_value(v, &big);
+  if (big != NULL) {
+    *target = *big;
+    return 1;
+  }
+  int64_t integer;
+  if (resolve_integer_value(v, &integer) == OK) {
+    *target = big_from_integer(integer, limbs);
+    return 1;
+  }
+  return 0;
+}
+
+// Quotients that don't end sooner are truncated after this many places
+static const int DECIMAL_DIVISION_SCALE = 32;
+
+// Values needing more places than this are refused rather than exhausting
+// memory
+static const int DECIMAL_MAX_SCALE = 1 << 16;
+
+big_t big_power_of_ten(int exponent) {
+  uint32_t limbs[2];
+  big_t ten = big_from_integer(10, limbs);
+  big_t result;
+  big_pow(&ten, exponent, &result);
+  return result;
+}
+
+// Integers, bignums and decimals can all be used where a decimal is expected,
+// with integers widened into `limbs`
+int read_exact_decimal(value_t v, decimal_t *target, uint32_t *limbs) {
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(v, &decimal);
+  if (decimal != NULL) {
+    *target = *decimal;
+    return 1;
+  }
+  decimal_t widened = {{0}, 0, NULL, 0};
+  if (read_exact_value(v, &widened.unscaled, limbs)) {
+    *target = widened;
+    return 1;
+  }
+  return 0;
+}
+
+int is_decimal_value(value_t v) {
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(v, &decimal);
+  return decimal != NULL;
+}
+
+// Decimal digits with an optional leading `-` and fraction, like `-1.25`
+decimal_t decimal_parse(const char *text) {
+  char *digits = malloc(strlen(text) + 1);
+  int len = 0;
+  int scale = 0;
+  int fraction = 0;
+  for (const char *c = text; *c != '\0'; c++) {
+    if (*c == '.') {
+      fraction = 1;
+    } else {
+      digits[len++] = *c;
+      scale += fraction;
+    }
+  }
+  digits[len] = '\0';
+  decimal_t result = {big_parse(digits), scale, NULL, 0};
+  free(digits);
+  return result;
+}
+
+decimal_t decimal_copy(const decimal_t *decimal) {
+  decimal_t result = {big_copy(&decimal->unscaled), decimal->scale, NULL, 0};
+  return result;
+}
+
+// The unscaled value at `scale` places, which must be at least the current
+// scale
+big_t decimal_rescaled(const decimal_t *decimal, int scale) {
+  if (scale == decimal->scale) {
+    return big_copy(&decimal->unscaled);
+  }
+  big_t factor = big_power_of_ten(scale - decimal->scale);
+  big_t result = big_mul(&decimal->unscaled, &factor);
+  free(factor.limbs);
+  return result;
+}
+
+// Compared by value, so `1.5d` equals `1.50d`
+int decimal_compare(const decimal_t *a, const decimal_t *b) {
+  if (a->scale == b->scale) {
+    return big_compare(&a->unscaled, &b->unscaled);
+  }
+  int scale = a->scale > b->scale ? a->scale : b->scale;
+  big_t first = decimal_rescaled(a, scale);
+  big_t second = decimal_rescaled(b, scale);
+  int result = big_compare(&first, &second);
+  free(first.limbs);
+  free(second.limbs);
+  return result;
+}
+
+// Adds `b` with the given sign like bignums, at the larger of the two scales
+decimal_t decimal_add(const decimal_t *a, const decimal_t *b, int b_negative) {
+  int scale = a->scale > b->scale ? a->scale : b->scale;
+  big_t first = decimal_rescaled(a, scale);
+  big_t second = decimal_rescaled(b, scale);
+  decimal_t result = {big_add(&first, &second, b_negative), scale, NULL, 0};
+  free(first.limbs);
+  free(second.limbs);
+  return result;
+}
+
+// Keeps the places of both sides, so `1.5d 1.5d *` is `2.25d`
+decimal_t decimal_mul(const decimal_t *a, const decimal_t *b) {
+  decimal_t result = {big_mul(&a->unscaled, &b->unscaled), a->scale + b->scale,
+                      NULL, 0};
+  return result;
+}
+
+// Exact when the quotient ends within DECIMAL_DIVISION_SCALE places or the
+// operands' own, otherwise truncated towards zero there like integer division.
+// `b` must not be zero
+decimal_t decimal_div(const decimal_t *a, const decimal_t *b) {
+  int kept = a->scale > b->scale ? a->scale : b->scale;
+  int scale = kept > DECIMAL_DIVISION_SCALE ? kept : DECIMAL_DIVISION_SCALE;
+  big_t factor = big_power_of_ten(scale + b->scale - a->scale);
+  big_t dividend = big_mul(&a->unscaled, &factor);
+  big_t quotient;
+  big_t remainder;
+  big_div_rem(&dividend, &b->unscaled, &quotient, &remainder);
+  free(factor.limbs);
+  free(dividend.limbs);
+  free(remainder.limbs);
+  // Trailing zeros past the kept places are dropped
+  while (scale > kept) {
+    big_t shorter = big_copy(&quotient);
+    if (big_div_small(&shorter, 10) != 0) {
+      free(shorter.limbs);
+      break;
+    }
+    free(quotient.limbs);
+    quotient = shorter;
+    scale--;
+  }
+  decimal_t result = {quotient, scale, NULL, 0};
+  return result;
+}
+
+// Has the sign of `a`, like integer remainders. `b` must not be zero
+decimal_t decimal_rem(const decimal_t *a, const decimal_t *b) {
+  int scale = a->scale > b->scale ? a->scale : b->scale;
+  big_t first = decimal_rescaled(a, scale);
+  big_t second = decimal_rescaled(b, scale);
+  big_t quotient;
+  big_t remainder;
+  big_div_rem(&first, &second, &quotient, &remainder);
+  free(first.limbs);
+  free(second.limbs);
+  free(quotient.limbs);
+  decimal_t result = {remainder, scale, NULL, 0};
+  return result;
+}
+
+// Drops the fractional part, and reports whether there was one
+big_t decimal_trunc(const decimal_t *decimal, int *fractional) {
+  big_t factor = big_power_of_ten(decimal->scale);
+  big_t whole;
+  big_t fraction;
+  big_div_rem(&decimal->unscaled, &factor, &whole, &fraction);
+  if (fractional != NULL) {
+    *fractional = fraction.length > 0;
+  }
+  free(factor.limbs);
+  free(fraction.limbs);
+  return whole;
+}
+
+// The exponent must be a whole number that isn't negative
+status_t decimal_pow(const decimal_t *base, const decimal_t *exponent,
+                     decimal_t *result) {
+  if (exponent->unscaled.negative) {
+    return INVALID_ARGUMENT;
+  }
+  int fractional;
+  big_t whole = decimal_trunc(exponent, &fractional);
+  int64_t power;
+  int fits = big_to_integer(&whole, &power);
+  free(whole.limbs);
+  if (fractional) {
+    return INVALID_ARGUMENT;
+  }
+  if (!fits || (base->scale > 0 && power > DECIMAL_MAX_SCALE / base->scale)) {
+    return INTEGER_OVERFLOW;
+  }
+  result->scale = base->scale * power;
+  result->text = NULL;
+  result->refs = 0;
+  return big_pow(&base->unscaled, power, &result->unscaled);
+}
+
+// Written once it is needed, and kept for as long as the decimal lives
+const char *decimal_to_string(decimal_t *decimal) {
+  if (decimal->text != NULL) {
+    return decimal->text;
+  }
+  big_t magnitude = decimal->unscaled;
+  magnitude.negative = 0;
+  magnitude.text = NULL;
+  const char *digits = big_to_string(&magnitude);
+  int count = strlen(digits);
+  int scale = decimal->scale;
+  int padded = count > scale ? count : scale + 1;
+  char *text = malloc(padded + 3);
+  int len = 0;
+  if (decimal->unscaled.negative) {
+    text[len++] = '-';
+  }
+  for (int i = 0; i < padded; i++) {
+    if (i == padded - scale) {
+      text[len++] = '.';
+    }
+    int digit = i - (padded - count);
+    text[len++] = digit < 0 ? '0' : digits[digit];
+  }
+  text[len] = '\0';
+  free(magnitude.text);
+  decimal->text = text;
+  return text;
+}
+
 status_t resolve_number_value(value_t v, double *target) {
   if (isnan(v)) {
     uint64_t s = *(uint64_t *)&v;
 
-    if (s == TRUE_BYTES || s == FALSE_BYTES) {
-      return TYPE_MISMATCH;
+    integer_t *integer;
+    maybe_resolve_integer_value(v, &integer);
+    if (integer != NULL) {
+      *target = (double)integer->value;
+      return OK;
+    }
+
+    big_t *big;
+    maybe_resolve_big_value(v, &big);
+    if (big != NULL) {
+      *target = strtod(big_to_string(big), NULL);
+      return OK;
+    }
+
+    decimal_t *decimal;
+    maybe_resolve_decimal_value(v, &decimal);
+    if (decimal != NULL) {
+      *target = strtod(decimal_to_string(decimal), NULL);
+      return OK;
     }
 
-    if (s >> 48 == 0x7ff9ULL) {
+    if (s == TRUE_BYTES || s == FALSE_BYTES || s >> 44 == 0x7fff8ULL ||
+        s >> 44 == 0x7fff9ULL) {
       return TYPE_MISMATCH;
     }
   }
@@ -132,6 +777,30 @@ void inc_ref_count(value_t v) {
   if (source != NULL) {
     source->refs++;
   }
+
+  record_t *record;
+  maybe_resolve_record_value(v, &record);
+  if (record != NULL) {
+    record->refs++;
+  }
+
+  integer_t *integer;
+  maybe_resolve_integer_value(v, &integer);
+  if (integer != NULL) {
+    integer->refs++;
+  }
+
+  big_t *big;
+  maybe_resolve_big_value(v, &big);
+  if (big != NULL) {
+    big->refs++;
+  }
+
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(v, &decimal);
+  if (decimal != NULL) {
+    decimal->refs++;
+  }
 }
 
 void dec_string_ref_count(string_source_t *source) {
@@ -153,6 +822,58 @@ void dec_ref_count(value_t v) {
   if (source != NULL) {
     dec_string_ref_count(source);
   }
+
+  record_t *record;
+  maybe_resolve_record_value(v, &record);
+  if (record != NULL) {
+    record->refs--;
+    if (record->refs == 0) {
+      for (int i = 0; i < record->field_count; i++) {
+        dec_ref_count(record->values[i]);
+      }
+      free(record->values);
+      record->values = NULL;
+    }
+  }
+
+  integer_t *integer;
+  maybe_resolve_integer_value(v, &integer);
+  if (integer != NULL) {
+    integer->refs--;
+  }
+
+  big_t *big;
+  maybe_resolve_big_value(v, &big);
+  if (big != NULL) {
+    big->refs--;
+    if (big->refs == 0) {
+      free(big->limbs);
+      free(big->text);
+      big->limbs = NULL;
+      big->text = NULL;
+    }
+  }
+
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(v, &decimal);
+  if (decimal != NULL) {
+    decimal->refs--;
+    if (decimal->refs == 0) {
+      free(decimal->unscaled.limbs);
+      free(decimal->text);
+      decimal->unscaled.limbs = NULL;
+      decimal->text = NULL;
+    }
+  }
+}
+
+// Integers, bignums and decimals are promoted in place, so the slot no longer
+// refers to them
+status_t read_number_slot(value_t *slot, double *target) {
+  checked(resolve_number_value(*slot, target));
+  dec_ref_count(*slot);
+  *slot = *target;
+  return OK;
 }
 
 int strings_equal(string_source_t *first, string_source_t *second) {
@@ -176,7 +897,35 @@ int strings_equal(string_source_t *first, string_source_t *second) {
   return 1;
 }
 
+// Strings, records, booleans and the exact number types are all NaN-boxed
+int is_boxed_value(value_t v) {
+  uint64_t s = *(uint64_t *)&v;
+  return isnan(v) && s >> 48 == 0x7fffULL;
+}
+
 status_t values_equal(value_t first, value_t second, int *result) {
+  decimal_t first_exact;
+  uint32_t first_limbs[2];
+  int first_is_exact = read_exact_decimal(first, &first_exact, first_limbs);
+  decimal_t second_exact;
+  uint32_t second_limbs[2];
+  int second_is_exact = read_exact_decimal(second, &second_exact, second_limbs);
+  if (first_is_exact && second_is_exact) {
+    *result = decimal_compare(&first_exact, &second_exact) == 0;
+    return OK;
+  }
+  if (first_is_exact || second_is_exact) {
+    double first_number;
+    double second_number;
+    checked(resolve_number_value(first, &first_number));
+    checked(resolve_number_value(second, &second_number));
+    *result = first_number == second_number;
+    return OK;
+  }
+  if (is_boxed_value(first) != is_boxed_value(second)) {
+    return TYPE_MISMATCH;
+  }
+
   if (isnan(first)) {
     string_source_t *first_string;
     maybe_resolve_string_value(first, &first_string);
@@ -194,6 +943,27 @@ status_t values_equal(value_t first, value_t second, int *result) {
       return TYPE_MISMATCH;
     }
 
+    record_t *first_record;
+    maybe_resolve_record_value(first, &first_record);
+
+    record_t *second_record;
+    maybe_resolve_record_value(second, &second_record);
+
+    if (first_record != NULL || second_record != NULL) {
+      if (first_record == NULL || second_record == NULL) {
+        return TYPE_MISMATCH;
+      }
+      *result = first_record->namespace == second_record->namespace &&
+                strcmp(first_record->name, second_record->name) == 0;
+      for (int i = 0; *result && i < first_record->field_count; i++) {
+        if (values_equal(first_record->values[i], second_record->values[i],
+                         result) != OK) {
+          *result = 0;
+        }
+      }
+      return OK;
+    }
+
     uint64_t first_bytes = *(uint64_t *)&first;
     uint64_t second_bytes = *(uint64_t *)&second;
 
@@ -219,7 +989,28 @@ int is_truthy(value_t v) {
     if (source != NULL) {
       return source->len > 0;
     }
-    return 0;
+
+    integer_t *integer;
+    maybe_resolve_integer_value(v, &integer);
+    if (integer != NULL) {
+      return integer->value != 0;
+    }
+
+    big_t *big;
+    maybe_resolve_big_value(v, &big);
+    if (big != NULL) {
+      return big->length > 0;
+    }
+
+    decimal_t *decimal;
+    maybe_resolve_decimal_value(v, &decimal);
+    if (decimal != NULL) {
+      return decimal->unscaled.length > 0;
+    }
+
+    record_t *record;
+    maybe_resolve_record_value(v, &record);
+    return record != NULL;
   } else {
     return v != 0l;
   }
@@ -237,26 +1028,226 @@ status_t find_string_source_slot(uint64_t *target) {
   return STRING_MAX;
 }
 
-status_t check_condition(int *truthy) {
-  assert_stack_has(1);
-  stack_read(v, -1);
-  STATE.value_count--;
-  *truthy = is_truthy(v);
-  dec_ref_count(v);
-  return OK;
-}
-
-status_t print_to_string(value_t v, char *scratch_string, int scratch_length,
-                         const char **result_string, int *result_length) {
-  if (isnan(v)) {
-    string_source_t *string_source;
-    maybe_resolve_string_value(v, &string_source);
-    if (string_source != NULL) {
-      *result_string = string_source->data;
-      *result_length = string_source->len;
+status_t find_record_slot(uint64_t *target) {
+  uint64_t max = sizeof(STATE.records) / sizeof(STATE.records[0]);
+  for (uint64_t i = 0; i < max; i++) {
+    if (STATE.records[i].refs == 0) {
+      *target = i;
       return OK;
     }
-
+  }
+  return RECORD_MAX;
+}
+
+status_t find_integer_slot(uint64_t *target) {
+  uint64_t max = sizeof(STATE.integers) / sizeof(STATE.integers[0]);
+  for (uint64_t i = 0; i < max; i++) {
+    if (STATE.integers[i].refs == 0) {
+      *target = i;
+      return OK;
+    }
+  }
+  return INTEGER_MAX;
+}
+
+status_t push_integer(int64_t n) {
+  assert_stack_capacity(1);
+  uint64_t integer_index;
+  checked(find_integer_slot(&integer_index));
+  STATE.integers[integer_index].value = n;
+  STATE.integers[integer_index].refs = 1;
+  stack_at(0) = integer_index_to_value(integer_index);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t find_big_slot(uint64_t *target) {
+  uint64_t max = sizeof(STATE.bigs) / sizeof(STATE.bigs[0]);
+  for (uint64_t i = 0; i < max; i++) {
+    if (STATE.bigs[i].refs == 0) {
+      *target = i;
+      return OK;
+    }
+  }
+  return BIG_MAX;
+}
+
+// Takes ownership of the bignum's limbs
+status_t push_big(big_t big) {
+  uint64_t big_index;
+  if (STATE.value_count >= (int)(sizeof(STATE.values) / sizeof(STATE.values[0]))) {
+    free(big.limbs);
+    return STACK_OVERFLOW;
+  }
+  if (find_big_slot(&big_index) != OK) {
+    free(big.limbs);
+    return BIG_MAX;
+  }
+  big.refs = 1;
+  STATE.bigs[big_index] = big;
+  stack_at(0) = big_index_to_value(big_index);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_big_literal(const char *digits) {
+  return push_big(big_parse(digits));
+}
+
+status_t find_decimal_slot(uint64_t *target) {
+  uint64_t max = sizeof(STATE.decimals) / sizeof(STATE.decimals[0]);
+  for (uint64_t i = 0; i < max; i++) {
+    if (STATE.decimals[i].refs == 0) {
+      *target = i;
+      return OK;
+    }
+  }
+  return DECIMAL_MAX;
+}
+
+// Takes ownership of the decimal's limbs
+status_t push_decimal(decimal_t decimal) {
+  uint64_t decimal_index;
+  if (STATE.value_count >= (int)(sizeof(STATE.values) / sizeof(STATE.values[0]))) {
+    free(decimal.unscaled.limbs);
+    return STACK_OVERFLOW;
+  }
+  if (find_decimal_slot(&decimal_index) != OK) {
+    free(decimal.unscaled.limbs);
+    return DECIMAL_MAX;
+  }
+  decimal.text = NULL;
+  decimal.refs = 1;
+  STATE.decimals[decimal_index] = decimal;
+  stack_at(0) = decimal_index_to_value(decimal_index);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_decimal_literal(const char *text) {
+  return push_decimal(decimal_parse(text));
+}
+
+status_t check_condition(int *truthy) {
+  assert_stack_has(1);
+  stack_read(v, -1);
+  STATE.value_count--;
+  *truthy = is_truthy(v);
+  dec_ref_count(v);
+  return OK;
+}
+
+char *number_to_string(double v, int *len);
+
+#define append_printed(...)                                                    \
+  do {                                                                         \
+    int n = snprintf(scratch_string + *written, scratch_length - *written,     \
+                     __VA_ARGS__);                                             \
+    if (n < 0 || n >= scratch_length - *written) {                             \
+      return STRING_TOO_LONG;                                                  \
+    }                                                                          \
+    *written += n;                                                             \
+  } while (0)
+
+// Writes a record like `point { x: 1, y: "a" }`, nested records are written in
+// place
+status_t print_record(record_t *record, char *scratch_string,
+                      int scratch_length, int *written) {
+  append_printed("%s {", record->name);
+  for (int i = 0; i < record->field_count; i++) {
+    append_printed(i == 0 ? " %s: " : ", %s: ", record->fields[i]);
+    value_t v = record->values[i];
+    string_source_t *string_source;
+    maybe_resolve_string_value(v, &string_source);
+    record_t *nested;
+    maybe_resolve_record_value(v, &nested);
+    integer_t *integer;
+    maybe_resolve_integer_value(v, &integer);
+    big_t *big;
+    maybe_resolve_big_value(v, &big);
+    decimal_t *decimal;
+    maybe_resolve_decimal_value(v, &decimal);
+    uint64_t s = *(uint64_t *)&v;
+    if (string_source != NULL) {
+      append_printed("\"%.*s\"", string_source->len, string_source->data);
+    } else if (integer != NULL) {
+      append_printed("%" PRId64, integer->value);
+    } else if (big != NULL) {
+      append_printed("%s", big_to_string(big));
+    } else if (decimal != NULL) {
+      append_printed("%s", decimal_to_string(decimal));
+    } else if (nested != NULL) {
+      checked(print_record(nested, scratch_string, scratch_length, written));
+    } else if (s == TRUE_BYTES || s == FALSE_BYTES) {
+      append_printed("%s", s == TRUE_BYTES ? "true" : "false");
+    } else {
+      int len;
+      char *number = number_to_string(v, &len);
+      int n = snprintf(scratch_string + *written, scratch_length - *written,
+                       "%s", number);
+      free(number);
+      if (n >= scratch_length - *written) {
+        return STRING_TOO_LONG;
+      }
+      *written += n;
+    }
+  }
+  append_printed("%s", record->field_count > 0 ? " }" : "}");
+  return OK;
+}
+
+status_t print_to_string(value_t v, char *scratch_string, int scratch_length,
+                         const char **result_string, int *result_length) {
+  if (isnan(v)) {
+    record_t *record;
+    maybe_resolve_record_value(v, &record);
+    if (record != NULL) {
+      *result_length = 0;
+      checked(print_record(record, scratch_string, scratch_length,
+                           result_length));
+      *result_string = scratch_string;
+      return OK;
+    }
+
+    string_source_t *string_source;
+    maybe_resolve_string_value(v, &string_source);
+    if (string_source != NULL) {
+      *result_string = string_source->data;
+      *result_length = string_source->len;
+      return OK;
+    }
+
+    integer_t *integer;
+    maybe_resolve_integer_value(v, &integer);
+    if (integer != NULL) {
+      int written_length = snprintf(scratch_string, scratch_length,
+                                    "%" PRId64, integer->value);
+      if (written_length >= scratch_length) {
+        return STRING_TOO_LONG;
+      }
+      *result_length = written_length;
+      *result_string = scratch_string;
+      return OK;
+    }
+
+    // Bignums and decimals keep their own text, which can be longer than the
+    // scratch space
+    big_t *big;
+    maybe_resolve_big_value(v, &big);
+    if (big != NULL) {
+      *result_string = big_to_string(big);
+      *result_length = strlen(*result_string);
+      return OK;
+    }
+
+    decimal_t *decimal;
+    maybe_resolve_decimal_value(v, &decimal);
+    if (decimal != NULL) {
+      *result_string = decimal_to_string(decimal);
+      *result_length = strlen(*result_string);
+      return OK;
+    }
+
     uint64_t s = *(uint64_t *)&v;
     if (s == TRUE_BYTES) {
       *result_length = 4;
@@ -271,12 +1262,17 @@ status_t print_to_string(value_t v, char *scratch_string, int scratch_length,
     }
   }
 
-  int written_length = snprintf(scratch_string, scratch_length, "%.8g", v);
-  if (written_length >= scratch_length) {
+  // Written the same way as to_string, so folded and runtime text match
+  int len;
+  char *number = number_to_string(v, &len);
+  if (len >= scratch_length) {
+    free(number);
     return STRING_TOO_LONG;
   }
+  memcpy(scratch_string, number, len + 1);
+  free(number);
 
-  *result_length = written_length;
+  *result_length = len;
   *result_string = scratch_string;
   return OK;
 }
@@ -359,31 +1355,135 @@ status_t rot(void) {
   return OK;
 }
 
-status_t greater(void) {
+// Arithmetic stays exact when both operands are integers, bignums or decimals,
+// with each widened to the wider of the two when they are mixed. Otherwise both
+// are promoted to floats
+typedef struct {
+  int integers;
+  int bigs;
+  int decimals;
+  int64_t first_integer;
+  int64_t second_integer;
+  big_t first_big;
+  big_t second_big;
+  decimal_t first_decimal;
+  decimal_t second_decimal;
+  uint32_t first_limbs[2];
+  uint32_t second_limbs[2];
+  double first;
+  double second;
+} operands_t;
+
+status_t read_operands(operands_t *operands) {
   assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first > second ? TRUE_V : FALSE_V;
-  STATE.value_count--;
+  operands->integers =
+      resolve_integer_value(stack_at(-2), &operands->first_integer) == OK &&
+      resolve_integer_value(stack_at(-1), &operands->second_integer) == OK;
+  operands->decimals =
+      (is_decimal_value(stack_at(-2)) || is_decimal_value(stack_at(-1))) &&
+      read_exact_decimal(stack_at(-2), &operands->first_decimal,
+                         operands->first_limbs) &&
+      read_exact_decimal(stack_at(-1), &operands->second_decimal,
+                         operands->second_limbs);
+  int exact = operands->decimals ||
+              (read_exact_value(stack_at(-2), &operands->first_big,
+                                operands->first_limbs) &&
+               read_exact_value(stack_at(-1), &operands->second_big,
+                                operands->second_limbs));
+  operands->bigs = exact && !operands->integers && !operands->decimals;
+  if (!exact) {
+    checked(read_number_slot(&stack_at(-2), &operands->first));
+    checked(read_number_slot(&stack_at(-1), &operands->second));
+  }
   return OK;
 }
 
-status_t less(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first < second ? TRUE_V : FALSE_V;
+status_t replace_operands(value_t result) {
+  dec_ref_count(stack_at(-2));
+  dec_ref_count(stack_at(-1));
+  stack_at(-2) = result;
   STATE.value_count--;
   return OK;
 }
 
+status_t replace_operands_with_integer(int64_t result) {
+  dec_ref_count(stack_at(-2));
+  dec_ref_count(stack_at(-1));
+  STATE.value_count -= 2;
+  return push_integer(result);
+}
+
+status_t replace_operands_with_big(big_t result) {
+  dec_ref_count(stack_at(-2));
+  dec_ref_count(stack_at(-1));
+  STATE.value_count -= 2;
+  return push_big(result);
+}
+
+status_t replace_operands_with_decimal(decimal_t result) {
+  dec_ref_count(stack_at(-2));
+  dec_ref_count(stack_at(-1));
+  STATE.value_count -= 2;
+  return push_decimal(result);
+}
+
+// Both parts of the truncated division are computed, only one is kept
+status_t replace_operands_with_division(const operands_t *o, int quotient) {
+  if (o->second_big.length == 0) {
+    return DIVISION_BY_ZERO;
+  }
+  big_t results[2];
+  big_div_rem(&o->first_big, &o->second_big, results, results + 1);
+  free(results[quotient ? 1 : 0].limbs);
+  return replace_operands_with_big(results[quotient ? 0 : 1]);
+}
+
+status_t greater(void) {
+  operands_t o;
+  checked(read_operands(&o));
+  int result =
+      o.integers   ? o.first_integer > o.second_integer
+      : o.bigs     ? big_compare(&o.first_big, &o.second_big) > 0
+      : o.decimals ? decimal_compare(&o.first_decimal, &o.second_decimal) > 0
+                   : o.first > o.second;
+  return replace_operands(result ? TRUE_V : FALSE_V);
+}
+
+status_t less(void) {
+  operands_t o;
+  checked(read_operands(&o));
+  int result =
+      o.integers   ? o.first_integer < o.second_integer
+      : o.bigs     ? big_compare(&o.first_big, &o.second_big) < 0
+      : o.decimals ? decimal_compare(&o.first_decimal, &o.second_decimal) < 0
+                   : o.first < o.second;
+  return replace_operands(result ? TRUE_V : FALSE_V);
+}
+
 status_t modulo(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = fmod(first, second);
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    return replace_operands_with_division(&o, 0);
+  }
+  if (o.decimals) {
+    if (o.second_decimal.unscaled.length == 0) {
+      return DIVISION_BY_ZERO;
+    }
+    return replace_operands_with_decimal(
+        decimal_rem(&o.first_decimal, &o.second_decimal));
+  }
+  if (!o.integers) {
+    return replace_operands(fmod(o.first, o.second));
+  }
+  if (o.second_integer == 0) {
+    return DIVISION_BY_ZERO;
+  }
+  // The smallest integer can't be divided by -1, but nothing remains
+  if (o.second_integer == -1) {
+    return replace_operands_with_integer(0);
+  }
+  return replace_operands_with_integer(o.first_integer % o.second_integer);
 }
 
 status_t not(void) {
@@ -395,48 +1495,133 @@ status_t not(void) {
 }
 
 status_t minus(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first - second;
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    return replace_operands_with_big(
+        big_add(&o.first_big, &o.second_big, !o.second_big.negative));
+  }
+  if (o.decimals) {
+    return replace_operands_with_decimal(
+        decimal_add(&o.first_decimal, &o.second_decimal,
+                    !o.second_decimal.unscaled.negative));
+  }
+  if (!o.integers) {
+    return replace_operands(o.first - o.second);
+  }
+  int64_t result;
+  if (__builtin_sub_overflow(o.first_integer, o.second_integer, &result)) {
+    return INTEGER_OVERFLOW;
+  }
+  return replace_operands_with_integer(result);
 }
 
 status_t plus(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first + second;
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    return replace_operands_with_big(
+        big_add(&o.first_big, &o.second_big, o.second_big.negative));
+  }
+  if (o.decimals) {
+    return replace_operands_with_decimal(
+        decimal_add(&o.first_decimal, &o.second_decimal,
+                    o.second_decimal.unscaled.negative));
+  }
+  if (!o.integers) {
+    return replace_operands(o.first + o.second);
+  }
+  int64_t result;
+  if (__builtin_add_overflow(o.first_integer, o.second_integer, &result)) {
+    return INTEGER_OVERFLOW;
+  }
+  return replace_operands_with_integer(result);
 }
 
 status_t times(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first * second;
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    return replace_operands_with_big(big_mul(&o.first_big, &o.second_big));
+  }
+  if (o.decimals) {
+    return replace_operands_with_decimal(
+        decimal_mul(&o.first_decimal, &o.second_decimal));
+  }
+  if (!o.integers) {
+    return replace_operands(o.first * o.second);
+  }
+  int64_t result;
+  if (__builtin_mul_overflow(o.first_integer, o.second_integer, &result)) {
+    return INTEGER_OVERFLOW;
+  }
+  return replace_operands_with_integer(result);
 }
 
+// Integer division truncates towards zero, and so does decimal division past
+// its last place
 status_t divide(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = first / second;
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    return replace_operands_with_division(&o, 1);
+  }
+  if (o.decimals) {
+    if (o.second_decimal.unscaled.length == 0) {
+      return DIVISION_BY_ZERO;
+    }
+    return replace_operands_with_decimal(
+        decimal_div(&o.first_decimal, &o.second_decimal));
+  }
+  if (!o.integers) {
+    return replace_operands(o.first / o.second);
+  }
+  if (o.second_integer == 0) {
+    return DIVISION_BY_ZERO;
+  }
+  if (o.first_integer == INT64_MIN && o.second_integer == -1) {
+    return INTEGER_OVERFLOW;
+  }
+  return replace_operands_with_integer(o.first_integer / o.second_integer);
 }
 
 status_t pow_i(void) {
-  assert_stack_has(2);
-  stack_read_number(first, -2);
-  stack_read_number(second, -1);
-  stack_at(-2) = powf(first, second);
-  STATE.value_count--;
-  return OK;
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    int64_t exponent;
+    if (o.second_big.negative) {
+      return INVALID_ARGUMENT;
+    }
+    if (!big_to_integer(&o.second_big, &exponent)) {
+      return INTEGER_OVERFLOW;
+    }
+    big_t result;
+    checked(big_pow(&o.first_big, exponent, &result));
+    return replace_operands_with_big(result);
+  }
+  if (o.decimals) {
+    decimal_t result;
+    checked(decimal_pow(&o.first_decimal, &o.second_decimal, &result));
+    return replace_operands_with_decimal(result);
+  }
+  if (!o.integers) {
+    return replace_operands(powf(o.first, o.second));
+  }
+  if (o.second_integer < 0) {
+    return INVALID_ARGUMENT;
+  }
+  int64_t result = 1;
+  int64_t base = o.first_integer;
+  for (int64_t e = o.second_integer; e > 0; e >>= 1) {
+    if ((e & 1) && __builtin_mul_overflow(result, base, &result)) {
+      return INTEGER_OVERFLOW;
+    }
+    if (e > 1 && __builtin_mul_overflow(base, base, &base)) {
+      return INTEGER_OVERFLOW;
+    }
+  }
+  return replace_operands_with_integer(result);
 }
 
 status_t equals(void) {
@@ -445,11 +1630,28 @@ status_t equals(void) {
   stack_read(second, -1);
   int result;
   checked(values_equal(first, second, &result));
+  dec_ref_count(first);
+  dec_ref_count(second);
   stack_at(-2) = result ? TRUE_V : FALSE_V;
   STATE.value_count--;
   return OK;
 }
 
+status_t bind_local(value_t *local) {
+  assert_stack_has(1);
+  STATE.value_count--;
+  *local = stack_at(0);
+  return OK;
+}
+
+status_t push_local(value_t v) {
+  assert_stack_capacity(1);
+  inc_ref_count(v);
+  stack_at(0) = v;
+  STATE.value_count++;
+  return OK;
+}
+
 status_t drop(void) {
   assert_stack_has(1);
   dec_ref_count(stack_at(-1));
@@ -549,77 +1751,469 @@ status_t join(void) {
   return OK;
 }
 
-status_t increment(void) {
+status_t floor_i(void) {
   assert_stack_has(1);
   stack_read_number(v, -1);
-  STATE.values[STATE.value_count - 1] = v + 1;
+  stack_at(-1) = floor(v);
   return OK;
 }
 
-status_t decrement(void) {
+status_t ceil_i(void) {
   assert_stack_has(1);
   stack_read_number(v, -1);
-  STATE.values[STATE.value_count - 1] = v - 1;
+  stack_at(-1) = ceil(v);
   return OK;
 }
 
-status_t assert(void) {
-  assert_stack_has(2);
-  stack_read(v, -2);
-  stack_read_string(string_source, -1);
-  if (!is_truthy(v)) {
-    printf("Assertion failed: %.*s\n", string_source->len, string_source->data);
-    return ASSERT_FAILED;
-  }
-  dec_ref_count(v);
-  dec_string_ref_count(string_source);
-  STATE.value_count -= 2;
+status_t round_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = round(v);
   return OK;
 }
 
-status_t print(void) {
+status_t trunc_i(void) {
   assert_stack_has(1);
-  value_t v = STATE.values[STATE.value_count - 1];
-  const char *str;
-  int len;
-  checked(print_to_string(v, SCRATCH_A, sizeof(SCRATCH_A), &str, &len));
-  printf("%.*s\n", len, str);
-  dec_ref_count(v);
-  STATE.value_count--;
+  stack_read_number(v, -1);
+  stack_at(-1) = trunc(v);
   return OK;
 }
 
-status_t push_number_literal(value_t v) {
-  assert_stack_capacity(1);
-  stack_at(0) = v;
-  STATE.value_count++;
+status_t sqrt_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = sqrt(v);
   return OK;
 }
 
-status_t push_fn_address(operation_t *v) {
-  assert_stack_capacity(1);
-  value_t fn = (value_t)(uint64_t)v;
-  stack_at(0) = fn;
-  STATE.value_count++;
+status_t sin_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = sin(v);
   return OK;
 }
 
-status_t push_true_literal(void) {
-  assert_stack_capacity(1);
-  stack_at(0) = TRUE_V;
-  STATE.value_count++;
+status_t cos_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = cos(v);
   return OK;
 }
 
-status_t push_false_literal(void) {
-  assert_stack_capacity(1);
-  stack_at(0) = FALSE_V;
-  STATE.value_count++;
+status_t tan_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = tan(v);
   return OK;
 }
 
-status_t push_string_literal(const char *data, size_t len) {
-  assert_stack_capacity(1);
+status_t asin_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = asin(v);
+  return OK;
+}
+
+status_t acos_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = acos(v);
+  return OK;
+}
+
+status_t atan_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = atan(v);
+  return OK;
+}
+
+status_t exp_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = exp(v);
+  return OK;
+}
+
+status_t log_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = log(v);
+  return OK;
+}
+
+status_t log10_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = log10(v);
+  return OK;
+}
+
+status_t log2_i(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = log2(v);
+  return OK;
+}
+
+// Replaces the integer on top of the stack, returns 0 when it isn't an integer
+int replace_integer(int64_t (*map)(int64_t, int *), status_t *status) {
+  int64_t v;
+  if (STATE.value_count < 1 || resolve_integer_value(stack_at(-1), &v) != OK) {
+    return 0;
+  }
+  int overflow = 0;
+  int64_t result = map(v, &overflow);
+  if (overflow) {
+    *status = INTEGER_OVERFLOW;
+    return 1;
+  }
+  dec_ref_count(stack_at(-1));
+  STATE.value_count--;
+  *status = push_integer(result);
+  return 1;
+}
+
+int64_t integer_abs(int64_t v, int *overflow) {
+  *overflow = v == INT64_MIN;
+  return v < 0 ? -v : v;
+}
+
+int64_t integer_increment(int64_t v, int *overflow) {
+  *overflow = v == INT64_MAX;
+  return *overflow ? v : v + 1;
+}
+
+int64_t integer_decrement(int64_t v, int *overflow) {
+  *overflow = v == INT64_MIN;
+  return *overflow ? v : v - 1;
+}
+
+// Replaces the bignum on top of the stack, returns 0 when it isn't a bignum
+int replace_big(big_t (*map)(const big_t *), status_t *status) {
+  big_t *big = NULL;
+  if (STATE.value_count > 0) {
+    maybe_resolve_big_value(stack_at(-1), &big);
+  }
+  if (big == NULL) {
+    return 0;
+  }
+  big_t result = map(big);
+  dec_ref_count(stack_at(-1));
+  STATE.value_count--;
+  *status = push_big(result);
+  return 1;
+}
+
+big_t big_abs(const big_t *v) {
+  big_t result = big_copy(v);
+  result.negative = 0;
+  return result;
+}
+
+big_t big_increment(const big_t *v) {
+  uint32_t limbs[2];
+  big_t one = big_from_integer(1, limbs);
+  return big_add(v, &one, 0);
+}
+
+big_t big_decrement(const big_t *v) {
+  uint32_t limbs[2];
+  big_t one = big_from_integer(1, limbs);
+  return big_add(v, &one, 1);
+}
+
+// Replaces the decimal on top of the stack, returns 0 when it isn't a decimal
+int replace_decimal(decimal_t (*map)(const decimal_t *), status_t *status) {
+  decimal_t *decimal = NULL;
+  if (STATE.value_count > 0) {
+    maybe_resolve_decimal_value(stack_at(-1), &decimal);
+  }
+  if (decimal == NULL) {
+    return 0;
+  }
+  decimal_t result = map(decimal);
+  dec_ref_count(stack_at(-1));
+  STATE.value_count--;
+  *status = push_decimal(result);
+  return 1;
+}
+
+decimal_t decimal_abs(const decimal_t *v) {
+  decimal_t result = decimal_copy(v);
+  result.unscaled.negative = 0;
+  return result;
+}
+
+decimal_t decimal_increment(const decimal_t *v) {
+  uint32_t limbs[2];
+  decimal_t one = {big_from_integer(1, limbs), 0, NULL, 0};
+  return decimal_add(v, &one, 0);
+}
+
+decimal_t decimal_decrement(const decimal_t *v) {
+  uint32_t limbs[2];
+  decimal_t one = {big_from_integer(1, limbs), 0, NULL, 0};
+  return decimal_add(v, &one, 1);
+}
+
+status_t abs_i(void) {
+  status_t status;
+  if (replace_integer(integer_abs, &status) ||
+      replace_big(big_abs, &status) || replace_decimal(decimal_abs, &status)) {
+    return status;
+  }
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = fabs(v);
+  return OK;
+}
+
+status_t atan2_i(void) {
+  assert_stack_has(2);
+  stack_read_number(first, -2);
+  stack_read_number(second, -1);
+  stack_at(-2) = atan2(first, second);
+  STATE.value_count--;
+  return OK;
+}
+
+status_t min(void) {
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    int first = big_compare(&o.first_big, &o.second_big) < 0;
+    return replace_operands_with_big(
+        big_copy(first ? &o.first_big : &o.second_big));
+  }
+  // Equal decimals can differ in scale, so ties keep the first like the
+  // interpreter
+  if (o.decimals) {
+    int first = decimal_compare(&o.first_decimal, &o.second_decimal) <= 0;
+    return replace_operands_with_decimal(
+        decimal_copy(first ? &o.first_decimal : &o.second_decimal));
+  }
+  if (!o.integers) {
+    return replace_operands(fmin(o.first, o.second));
+  }
+  return replace_operands_with_integer(o.first_integer < o.second_integer
+                                           ? o.first_integer
+                                           : o.second_integer);
+}
+
+status_t max(void) {
+  operands_t o;
+  checked(read_operands(&o));
+  if (o.bigs) {
+    int first = big_compare(&o.first_big, &o.second_big) > 0;
+    return replace_operands_with_big(
+        big_copy(first ? &o.first_big : &o.second_big));
+  }
+  // Ties keep the second like the interpreter
+  if (o.decimals) {
+    int first = decimal_compare(&o.first_decimal, &o.second_decimal) > 0;
+    return replace_operands_with_decimal(
+        decimal_copy(first ? &o.first_decimal : &o.second_decimal));
+  }
+  if (!o.integers) {
+    return replace_operands(fmax(o.first, o.second));
+  }
+  return replace_operands_with_integer(o.first_integer > o.second_integer
+                                           ? o.first_integer
+                                           : o.second_integer);
+}
+
+status_t pi(void) {
+  assert_stack_capacity(1);
+  stack_at(0) = 3.14159265358979323846;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t increment(void) {
+  status_t status;
+  if (replace_integer(integer_increment, &status) ||
+      replace_big(big_increment, &status) ||
+      replace_decimal(decimal_increment, &status)) {
+    return status;
+  }
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  STATE.values[STATE.value_count - 1] = v + 1;
+  return OK;
+}
+
+status_t decrement(void) {
+  status_t status;
+  if (replace_integer(integer_decrement, &status) ||
+      replace_big(big_decrement, &status) ||
+      replace_decimal(decimal_decrement, &status)) {
+    return status;
+  }
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  STATE.values[STATE.value_count - 1] = v - 1;
+  return OK;
+}
+
+status_t read_integers(int64_t *first, int64_t *second) {
+  assert_stack_has(2);
+  checked(resolve_integer_value(stack_at(-2), first));
+  checked(resolve_integer_value(stack_at(-1), second));
+  return OK;
+}
+
+status_t band(void) {
+  int64_t first, second;
+  checked(read_integers(&first, &second));
+  return replace_operands_with_integer(first & second);
+}
+
+status_t bor(void) {
+  int64_t first, second;
+  checked(read_integers(&first, &second));
+  return replace_operands_with_integer(first | second);
+}
+
+status_t bxor(void) {
+  int64_t first, second;
+  checked(read_integers(&first, &second));
+  return replace_operands_with_integer(first ^ second);
+}
+
+// Bits shifted past either end are lost, shifting right keeps the sign
+status_t shl(void) {
+  int64_t first, second;
+  checked(read_integers(&first, &second));
+  if (second < 0 || second > 63) {
+    return INVALID_ARGUMENT;
+  }
+  return replace_operands_with_integer((int64_t)((uint64_t)first << second));
+}
+
+status_t shr(void) {
+  int64_t first, second;
+  checked(read_integers(&first, &second));
+  if (second < 0 || second > 63) {
+    return INVALID_ARGUMENT;
+  }
+  int64_t shifted = first < 0 ? ~(~first >> second) : first >> second;
+  return replace_operands_with_integer(shifted);
+}
+
+// Floats and decimals are truncated, and must fit in an integer like bignums
+status_t to_int(void) {
+  assert_stack_has(1);
+  int64_t integer;
+  if (resolve_integer_value(stack_at(-1), &integer) == OK) {
+    return OK;
+  }
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(stack_at(-1), &decimal);
+  if (decimal != NULL) {
+    big_t whole = decimal_trunc(decimal, NULL);
+    int fits = big_to_integer(&whole, &integer);
+    free(whole.limbs);
+    if (!fits) {
+      return INVALID_ARGUMENT;
+    }
+    dec_ref_count(stack_at(-1));
+    STATE.value_count--;
+    return push_integer(integer);
+  }
+  big_t *big;
+  maybe_resolve_big_value(stack_at(-1), &big);
+  if (big != NULL) {
+    if (!big_to_integer(big, &integer)) {
+      return INVALID_ARGUMENT;
+    }
+    dec_ref_count(stack_at(-1));
+    STATE.value_count--;
+    return push_integer(integer);
+  }
+  stack_read_number(v, -1);
+  v = trunc(v);
+  if (!(v >= -9223372036854775808.0 && v < 9223372036854775808.0)) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count--;
+  return push_integer((int64_t)v);
+}
+
+status_t to_float(void) {
+  assert_stack_has(1);
+  stack_read_number(v, -1);
+  stack_at(-1) = v;
+  return OK;
+}
+
+status_t print(void) {
+  assert_stack_has(1);
+  value_t v = STATE.values[STATE.value_count - 1];
+  const char *str;
+  int len;
+  checked(print_to_string(v, SCRATCH_A, sizeof(SCRATCH_A), &str, &len));
+  printf("%.*s\n", len, str);
+  dec_ref_count(v);
+  STATE.value_count--;
+  return OK;
+}
+
+status_t eprint(void) {
+  assert_stack_has(1);
+  value_t v = STATE.values[STATE.value_count - 1];
+  const char *str;
+  int len;
+  checked(print_to_string(v, SCRATCH_A, sizeof(SCRATCH_A), &str, &len));
+  fprintf(stderr, "%.*s\n", len, str);
+  dec_ref_count(v);
+  STATE.value_count--;
+  return OK;
+}
+
+status_t write_i(void) {
+  assert_stack_has(1);
+  value_t v = STATE.values[STATE.value_count - 1];
+  const char *str;
+  int len;
+  checked(print_to_string(v, SCRATCH_A, sizeof(SCRATCH_A), &str, &len));
+  printf("%.*s", len, str);
+  dec_ref_count(v);
+  STATE.value_count--;
+  return OK;
+}
+
+status_t push_number_literal(value_t v) {
+  assert_stack_capacity(1);
+  stack_at(0) = v;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_fn_address(operation_t *v) {
+  assert_stack_capacity(1);
+  value_t fn = (value_t)(uint64_t)v;
+  stack_at(0) = fn;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_true_literal(void) {
+  assert_stack_capacity(1);
+  stack_at(0) = TRUE_V;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_false_literal(void) {
+  assert_stack_capacity(1);
+  stack_at(0) = FALSE_V;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t push_string_literal(const char *data, size_t len) {
+  assert_stack_capacity(1);
 
   uint64_t string_index;
   checked(find_string_source_slot(&string_index));
@@ -735,3 +2329,1780 @@ status_t eval_i(void) {
   STATE.value_count--;
   return operation1();
 }
+
+char *string_to_c_string(string_source_t *source) {
+  char *res = malloc(source->len + 1);
+  memcpy(res, source->data, source->len);
+  res[source->len] = '\0';
+  return res;
+}
+
+status_t push_owned_string(char *data, int len) {
+  assert_stack_capacity(1);
+
+  uint64_t string_index;
+  checked(find_string_source_slot(&string_index));
+  string_source_t *res = STATE.strings + string_index;
+
+  res->data = data;
+  res->len = len;
+  res->owned = 1;
+  res->refs = 1;
+
+  stack_at(0) = string_index_to_value(string_index);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t assert(void) {
+  assert_stack_has(2);
+  stack_read(v, -2);
+  stack_read_string(string_source, -1);
+  int truthy = is_truthy(v);
+  int len = string_source->len + 18;
+  char *message = truthy ? NULL : malloc(len);
+  if (!truthy) {
+    memcpy(message, "Assertion failed: ", 18);
+    memcpy(message + 18, string_source->data, string_source->len);
+  }
+  dec_ref_count(v);
+  dec_string_ref_count(string_source);
+  STATE.value_count -= 2;
+  if (truthy) {
+    return OK;
+  }
+  // The message is kept for a catch handler, or reported by main
+  checked(push_owned_string(message, len));
+  STATE.value_count--;
+  STATE.thrown = stack_at(0);
+  return ASSERT_FAILED;
+}
+
+status_t push_failed_string(void) {
+  checked(push_string_literal("", 0));
+  return push_false_literal();
+}
+
+int last_index_of(string_source_t *needle, string_source_t *haystack) {
+  for (int i = haystack->len - needle->len; i >= 0; i--) {
+    if (memcmp(haystack->data + i, needle->data, needle->len) == 0) {
+      return i;
+    }
+  }
+
+  return -1;
+}
+
+status_t last_index(void) {
+  assert_stack_has(2);
+  stack_read_string(needle, -1);
+  stack_read_string(haystack, -2);
+  stack_at(-2) = last_index_of(needle, haystack);
+  STATE.value_count--;
+  dec_string_ref_count(needle);
+  dec_string_ref_count(haystack);
+  return OK;
+}
+
+status_t starts_with(void) {
+  assert_stack_has(2);
+  stack_read_string(prefix, -1);
+  stack_read_string(source, -2);
+  int result = prefix->len <= source->len &&
+               memcmp(source->data, prefix->data, prefix->len) == 0;
+  stack_at(-2) = result ? TRUE_V : FALSE_V;
+  STATE.value_count--;
+  dec_string_ref_count(prefix);
+  dec_string_ref_count(source);
+  return OK;
+}
+
+status_t ends_with(void) {
+  assert_stack_has(2);
+  stack_read_string(suffix, -1);
+  stack_read_string(source, -2);
+  int result =
+      suffix->len <= source->len &&
+      memcmp(source->data + source->len - suffix->len, suffix->data,
+             suffix->len) == 0;
+  stack_at(-2) = result ? TRUE_V : FALSE_V;
+  STATE.value_count--;
+  dec_string_ref_count(suffix);
+  dec_string_ref_count(source);
+  return OK;
+}
+
+status_t push_string_copy(const char *data, int len) {
+  char *copy = malloc(len);
+  memcpy(copy, data, len);
+  return push_owned_string(copy, len);
+}
+
+status_t split(void) {
+  assert_stack_has(2);
+  stack_read_string(separator, -1);
+  stack_read_string(source, -2);
+  STATE.value_count -= 2;
+
+  status_t status = OK;
+  int count = 0;
+  if (separator->len == 0) {
+    for (; count < source->len && status == OK; count++) {
+      status = push_string_copy(source->data + count, 1);
+    }
+  } else {
+    int start = 0;
+    int i = 0;
+    while (i + separator->len <= source->len && status == OK) {
+      if (memcmp(source->data + i, separator->data, separator->len) == 0) {
+        status = push_string_copy(source->data + start, i - start);
+        count++;
+        i += separator->len;
+        start = i;
+      } else {
+        i++;
+      }
+    }
+    if (status == OK) {
+      status = push_string_copy(source->data + start, source->len - start);
+      count++;
+    }
+  }
+
+  dec_string_ref_count(separator);
+  dec_string_ref_count(source);
+  checked(status);
+  assert_stack_capacity(1);
+  stack_at(0) = count;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t replace(void) {
+  assert_stack_has(3);
+  stack_read_string(to, -1);
+  stack_read_string(from, -2);
+  stack_read_string(source, -3);
+  if (from->len == 0) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 3;
+
+  int count = 0;
+  for (int i = 0; i + from->len <= source->len;) {
+    if (memcmp(source->data + i, from->data, from->len) == 0) {
+      count++;
+      i += from->len;
+    } else {
+      i++;
+    }
+  }
+
+  int len = source->len + count * (to->len - from->len);
+  char *data = malloc(len);
+  int written = 0;
+  for (int i = 0; i < source->len;) {
+    if (i + from->len <= source->len &&
+        memcmp(source->data + i, from->data, from->len) == 0) {
+      memcpy(data + written, to->data, to->len);
+      written += to->len;
+      i += from->len;
+    } else {
+      data[written++] = source->data[i++];
+    }
+  }
+
+  dec_string_ref_count(to);
+  dec_string_ref_count(from);
+  dec_string_ref_count(source);
+  return push_owned_string(data, len);
+}
+
+status_t trim(void) {
+  assert_stack_has(1);
+  stack_read_string(source, -1);
+  STATE.value_count--;
+
+  int start = 0;
+  while (start < source->len && isspace((unsigned char)source->data[start])) {
+    start++;
+  }
+  int end = source->len;
+  while (end > start && isspace((unsigned char)source->data[end - 1])) {
+    end--;
+  }
+
+  status_t status = push_string_copy(source->data + start, end - start);
+  dec_string_ref_count(source);
+  return status;
+}
+
+status_t map_string_chars(int (*map)(int)) {
+  assert_stack_has(1);
+  stack_read_string(source, -1);
+  STATE.value_count--;
+
+  char *data = malloc(source->len);
+  for (int i = 0; i < source->len; i++) {
+    data[i] = (char)map((unsigned char)source->data[i]);
+  }
+
+  int len = source->len;
+  dec_string_ref_count(source);
+  return push_owned_string(data, len);
+}
+
+status_t to_upper(void) {
+  return map_string_chars(toupper);
+}
+
+status_t to_lower(void) {
+  return map_string_chars(tolower);
+}
+
+status_t repeat(void) {
+  assert_stack_has(2);
+  stack_read_string(source, -2);
+  stack_read_number(count, -1);
+  if (!(count >= 0 && count * source->len <= INT32_MAX) ||
+      count != (int64_t)count) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+
+  int len = (int)count * source->len;
+  char *data = malloc(len);
+  for (int i = 0; i < (int)count; i++) {
+    memcpy(data + i * source->len, source->data, source->len);
+  }
+
+  dec_string_ref_count(source);
+  return push_owned_string(data, len);
+}
+
+status_t reverse(void) {
+  assert_stack_has(1);
+  stack_read_string(source, -1);
+  STATE.value_count--;
+
+  char *data = malloc(source->len);
+  for (int i = 0; i < source->len; i++) {
+    data[i] = source->data[source->len - 1 - i];
+  }
+
+  int len = source->len;
+  dec_string_ref_count(source);
+  return push_owned_string(data, len);
+}
+
+status_t read_file(void) {
+  assert_stack_has(1);
+  stack_read_string(path_source, -1);
+  char *path = string_to_c_string(path_source);
+  dec_string_ref_count(path_source);
+  STATE.value_count--;
+
+  FILE *file = fopen(path, "rb");
+  free(path);
+  if (file == NULL) {
+    return push_failed_string();
+  }
+
+  char *data = NULL;
+  size_t len = 0;
+  size_t capacity = 0;
+  size_t read;
+  do {
+    if (len == capacity) {
+      capacity = capacity ? capacity * 2 : 4096;
+      data = realloc(data, capacity);
+    }
+    read = fread(data + len, 1, capacity - len, file);
+    len += read;
+  } while (read > 0);
+
+  int failed = ferror(file);
+  fclose(file);
+  if (failed) {
+    free(data);
+    return push_failed_string();
+  }
+
+  checked(push_owned_string(data, len));
+  return push_true_literal();
+}
+
+status_t write_to_file(const char *mode) {
+  assert_stack_has(2);
+  stack_read_string(path_source, -2);
+  stack_read_string(contents, -1);
+
+  char *path = string_to_c_string(path_source);
+  FILE *file = fopen(path, mode);
+  free(path);
+
+  int ok = file != NULL;
+  if (ok) {
+    ok = fwrite(contents->data, 1, contents->len, file) == (size_t)contents->len;
+    ok = fclose(file) == 0 && ok;
+  }
+
+  dec_string_ref_count(path_source);
+  dec_string_ref_count(contents);
+  STATE.value_count -= 2;
+  return ok ? push_true_literal() : push_false_literal();
+}
+
+status_t write_file(void) { return write_to_file("wb"); }
+
+status_t append_file(void) { return write_to_file("ab"); }
+
+status_t file_exists(void) {
+  assert_stack_has(1);
+  stack_read_string(path_source, -1);
+  char *path = string_to_c_string(path_source);
+  struct stat info;
+  int exists = stat(path, &info) == 0;
+  free(path);
+  dec_string_ref_count(path_source);
+  stack_at(-1) = exists ? TRUE_V : FALSE_V;
+  return OK;
+}
+
+int compare_c_strings(const void *first, const void *second) {
+  return strcmp(*(char *const *)first, *(char *const *)second);
+}
+
+status_t list_dir(void) {
+  assert_stack_has(1);
+  stack_read_string(path_source, -1);
+  char *path = string_to_c_string(path_source);
+  dec_string_ref_count(path_source);
+  STATE.value_count--;
+
+  DIR *dir = opendir(path);
+  free(path);
+  if (dir == NULL) {
+    return push_failed_string();
+  }
+
+  char **names = NULL;
+  size_t count = 0;
+  size_t capacity = 0;
+  size_t total = 0;
+  struct dirent *entry;
+  while ((entry = readdir(dir)) != NULL) {
+    if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) {
+      continue;
+    }
+    if (count == capacity) {
+      capacity = capacity ? capacity * 2 : 16;
+      names = realloc(names, capacity * sizeof(char *));
+    }
+    size_t name_len = strlen(entry->d_name);
+    names[count] = malloc(name_len + 1);
+    memcpy(names[count], entry->d_name, name_len + 1);
+    total += name_len + 1;
+    count++;
+  }
+  closedir(dir);
+
+  if (count > 1) {
+    qsort(names, count, sizeof(char *), compare_c_strings);
+  }
+
+  char *data = malloc(total > 0 ? total : 1);
+  size_t len = 0;
+  for (size_t i = 0; i < count; i++) {
+    if (i > 0) {
+      data[len++] = '\n';
+    }
+    size_t name_len = strlen(names[i]);
+    memcpy(data + len, names[i], name_len);
+    len += name_len;
+    free(names[i]);
+  }
+  free(names);
+
+  checked(push_owned_string(data, len));
+  return push_true_literal();
+}
+
+status_t arg_count(void) {
+  assert_stack_capacity(1);
+  stack_at(0) = STATE.argc;
+  STATE.value_count++;
+  return OK;
+}
+
+status_t arg(void) {
+  assert_stack_has(1);
+  stack_read_number(position, -1);
+  STATE.value_count--;
+  if (!(position >= 0 && position < STATE.argc) || position != (int)position) {
+    return push_failed_string();
+  }
+  char *value = STATE.argv[(int)position];
+  checked(push_string_literal(value, strlen(value)));
+  return push_true_literal();
+}
+
+status_t env(void) {
+  assert_stack_has(1);
+  stack_read_string(name_source, -1);
+  char *name = string_to_c_string(name_source);
+  dec_string_ref_count(name_source);
+  STATE.value_count--;
+
+  char *value = getenv(name);
+  free(name);
+  if (value == NULL) {
+    return push_failed_string();
+  }
+  checked(push_string_literal(value, strlen(value)));
+  return push_true_literal();
+}
+
+status_t exit_i(void) {
+  assert_stack_has(1);
+  stack_read_number(code, -1);
+  if (!(code >= 0 && code <= INT32_MAX) || code != (int)code) {
+    return INVALID_EXIT_CODE;
+  }
+  exit((int)code);
+}
+
+// Counts the `{}` placeholders in a format template, -1 for unmatched braces
+int count_format_placeholders(string_source_t *template) {
+  int count = 0;
+  for (int i = 0; i < template->len; i++) {
+    char c = template->data[i];
+    char next = i + 1 < template->len ? template->data[i + 1] : '\0';
+    if ((c == '{' && next == '{') || (c == '}' && next == '}')) {
+      i++;
+    } else if (c == '{' && next == '}') {
+      count++;
+      i++;
+    } else if (c == '{' || c == '}') {
+      return -1;
+    }
+  }
+  return count;
+}
+
+void append_to_buffer(char **data, int *len, int *capacity, const char *str,
+                      int str_len) {
+  if (*len + str_len > *capacity) {
+    while (*len + str_len > *capacity) {
+      *capacity = *capacity ? *capacity * 2 : 64;
+    }
+    *data = realloc(*data, *capacity);
+  }
+  memcpy(*data + *len, str, str_len);
+  *len += str_len;
+}
+
+status_t format(void) {
+  assert_stack_has(1);
+  stack_read_string(template, -1);
+  int count = count_format_placeholders(template);
+  if (count < 0) {
+    return INVALID_FORMAT;
+  }
+  assert_stack_has(count + 1);
+
+  // Allocated up front, so an empty result isn't NULL, which would mark its
+  // string slot as free
+  int capacity = 64;
+  char *data = malloc(capacity);
+  int len = 0;
+  int value_index = 0;
+  for (int i = 0; i < template->len; i++) {
+    char c = template->data[i];
+    char next = i + 1 < template->len ? template->data[i + 1] : '\0';
+    if (c == '{' && next == '}') {
+      const char *str;
+      int str_len;
+      checked(print_to_string(stack_at(value_index - count - 1), SCRATCH_A,
+                              sizeof(SCRATCH_A), &str, &str_len));
+      append_to_buffer(&data, &len, &capacity, str, str_len);
+      value_index++;
+      i++;
+    } else {
+      append_to_buffer(&data, &len, &capacity, &c, 1);
+      if ((c == '{' || c == '}') && next == c) {
+        i++;
+      }
+    }
+  }
+
+  for (int i = 1; i <= count; i++) {
+    dec_ref_count(stack_at(-i - 1));
+  }
+  dec_string_ref_count(template);
+  STATE.value_count -= count + 1;
+  return push_owned_string(data, len);
+}
+
+int is_number_syntax(const char *s) {
+  if (*s == '+' || *s == '-') {
+    s++;
+  }
+  int digits = 0;
+  while (*s >= '0' && *s <= '9') {
+    s++;
+    digits++;
+  }
+  if (*s == '.') {
+    s++;
+    while (*s >= '0' && *s <= '9') {
+      s++;
+      digits++;
+    }
+  }
+  if (digits == 0) {
+    return 0;
+  }
+  if (*s == 'e' || *s == 'E') {
+    s++;
+    if (*s == '+' || *s == '-') {
+      s++;
+    }
+    if (!(*s >= '0' && *s <= '9')) {
+      return 0;
+    }
+    while (*s >= '0' && *s <= '9') {
+      s++;
+    }
+  }
+  return *s == '\0';
+}
+
+status_t parse_number(void) {
+  assert_stack_has(1);
+  stack_read_string(source, -1);
+  char *text = string_to_c_string(source);
+  dec_string_ref_count(source);
+  int ok = is_number_syntax(text);
+  stack_at(-1) = ok ? strtod(text, NULL) : 0;
+  free(text);
+  return ok ? push_true_literal() : push_false_literal();
+}
+
+// Shortest digits that round trip, written without an exponent
+char *number_to_string(double v, int *len) {
+  char *res;
+  if (isnan(v) || isinf(v)) {
+    const char *name = isnan(v) ? "NaN" : v > 0 ? "inf" : "-inf";
+    *len = strlen(name);
+    res = malloc(*len + 1);
+    memcpy(res, name, *len + 1);
+    return res;
+  }
+
+  char buffer[32];
+  for (int precision = 0; precision < 17; precision++) {
+    snprintf(buffer, sizeof(buffer), "%.*e", precision, v);
+    if (strtod(buffer, NULL) == v) {
+      break;
+    }
+  }
+
+  char digits[20];
+  int digit_count = 0;
+  char *c = buffer;
+  if (*c == '-') {
+    c++;
+  }
+  for (; *c != 'e'; c++) {
+    if (*c != '.') {
+      digits[digit_count++] = *c;
+    }
+  }
+  int exponent = atoi(c + 1);
+  while (digit_count > 1 && digits[digit_count - 1] == '0') {
+    digit_count--;
+  }
+
+  int whole = exponent >= 0 ? exponent + 1 : 1;
+  int fraction = digit_count - exponent - 1;
+  if (fraction < 0) {
+    fraction = 0;
+  }
+  res = malloc(whole + fraction + 3);
+  *len = 0;
+  if (signbit(v)) {
+    res[(*len)++] = '-';
+  }
+  for (int i = 0; i < whole; i++) {
+    int index = i - (whole - exponent - 1);
+    res[(*len)++] = index >= 0 && index < digit_count ? digits[index] : '0';
+  }
+  if (fraction > 0) {
+    res[(*len)++] = '.';
+    for (int i = 0; i < fraction; i++) {
+      int index = exponent + 1 + i;
+      res[(*len)++] = index >= 0 && index < digit_count ? digits[index] : '0';
+    }
+  }
+  res[*len] = '\0';
+  return res;
+}
+
+status_t to_string(void) {
+  assert_stack_has(1);
+  stack_read(v, -1);
+  string_source_t *string_source;
+  maybe_resolve_string_value(v, &string_source);
+  if (string_source != NULL) {
+    return OK;
+  }
+
+  record_t *record;
+  maybe_resolve_record_value(v, &record);
+  integer_t *integer;
+  maybe_resolve_integer_value(v, &integer);
+  big_t *big;
+  maybe_resolve_big_value(v, &big);
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(v, &decimal);
+  if (record != NULL || integer != NULL || big != NULL || decimal != NULL) {
+    const char *str;
+    int len;
+    checked(print_to_string(v, SCRATCH_A, sizeof(SCRATCH_A), &str, &len));
+    STATE.value_count--;
+    checked(push_string_copy(str, len));
+    dec_ref_count(v);
+    return OK;
+  }
+
+  uint64_t bytes = *(uint64_t *)&v;
+  STATE.value_count--;
+  if (bytes == TRUE_BYTES) {
+    return push_string_literal("true", 4);
+  }
+  if (bytes == FALSE_BYTES) {
+    return push_string_literal("false", 5);
+  }
+  int len;
+  char *data = number_to_string(v, &len);
+  return push_owned_string(data, len);
+}
+
+status_t to_fixed(void) {
+  assert_stack_has(2);
+  stack_read_number(v, -2);
+  stack_read_number(digits, -1);
+  if (!(digits >= 0 && digits <= 100) || digits != (int)digits) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+  if (isnan(v) || isinf(v)) {
+    int len;
+    return push_owned_string(number_to_string(v, &len), len);
+  }
+  int len = snprintf(NULL, 0, "%.*f", (int)digits, v);
+  char *data = malloc(len + 1);
+  snprintf(data, len + 1, "%.*f", (int)digits, v);
+  return push_owned_string(data, len);
+}
+
+status_t to_radix(void) {
+  assert_stack_has(2);
+  stack_read_number(v, -2);
+  stack_read_number(radix, -1);
+  if (!(radix >= 2 && radix <= 36) || radix != (int)radix) {
+    return INVALID_ARGUMENT;
+  }
+  if (!(fabs(v) <= 9007199254740991.0) || v != (int64_t)v) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+
+  char buffer[70];
+  int len = 0;
+  uint64_t value = (uint64_t)fabs(v);
+  do {
+    buffer[len++] = "0123456789abcdefghijklmnopqrstuvwxyz"[value % (int)radix];
+    value /= (int)radix;
+  } while (value > 0);
+  if (v < 0) {
+    buffer[len++] = '-';
+  }
+
+  char *data = malloc(len);
+  for (int i = 0; i < len; i++) {
+    data[i] = buffer[len - i - 1];
+  }
+  return push_owned_string(data, len);
+}
+
+status_t to_padded(void) {
+  assert_stack_has(2);
+  stack_read_number(v, -2);
+  stack_read_number(width, -1);
+  if (!(width >= 0 && width <= 9007199254740991.0) || width != (int64_t)width) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+
+  int len;
+  char *text = number_to_string(v, &len);
+  int padding = width > len ? (int)width - len : 0;
+  char *data = malloc(len + padding);
+  int sign = !isnan(v) && !isinf(v) && text[0] == '-';
+  char fill = isnan(v) || isinf(v) ? ' ' : '0';
+  memcpy(data, text, sign);
+  memset(data + sign, fill, padding);
+  memcpy(data + sign + padding, text + sign, len - sign);
+  free(text);
+  return push_owned_string(data, len + padding);
+}
+
+// SplitMix64, matching the interpreter so seeded sequences are the same
+uint64_t next_random(void) {
+  STATE.random_state += 0x9E3779B97F4A7C15ULL;
+  uint64_t z = STATE.random_state;
+  z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9ULL;
+  z = (z ^ (z >> 27)) * 0x94D049BB133111EBULL;
+  return z ^ (z >> 31);
+}
+
+status_t random_i(void) {
+  assert_stack_capacity(1);
+  stack_at(0) = (double)(next_random() >> 11) / 9007199254740992.0;
+  STATE.value_count++;
+  return OK;
+}
+
+int is_safe_integer(double v) {
+  return fabs(v) <= 9007199254740991.0 && v == (int64_t)v;
+}
+
+status_t random_int(void) {
+  assert_stack_has(2);
+  stack_read_number(lo, -2);
+  stack_read_number(hi, -1);
+  if (!is_safe_integer(lo) || !is_safe_integer(hi) || !is_safe_integer(hi - lo) ||
+      hi < lo) {
+    return INVALID_ARGUMENT;
+  }
+  uint64_t range = (uint64_t)(hi - lo) + 1;
+  uint64_t threshold = (0 - range) % range;
+  uint64_t z;
+  do {
+    z = next_random();
+  } while (z < threshold);
+  stack_at(-2) = lo + (double)(z % range);
+  STATE.value_count--;
+  return OK;
+}
+
+status_t seed(void) {
+  assert_stack_has(1);
+  stack_read_number(n, -1);
+  if (!is_safe_integer(n)) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.random_state = (uint64_t)(int64_t)n;
+  STATE.value_count--;
+  return OK;
+}
+
+double timespec_to_ms(struct timespec time) {
+  return time.tv_sec * 1000.0 + time.tv_nsec / 1000000.0;
+}
+
+status_t now(void) {
+  assert_stack_capacity(1);
+  struct timespec time;
+  clock_gettime(CLOCK_REALTIME, &time);
+  stack_at(0) = timespec_to_ms(time);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t monotonic(void) {
+  assert_stack_capacity(1);
+  struct timespec time;
+  clock_gettime(CLOCK_MONOTONIC, &time);
+  stack_at(0) = timespec_to_ms(time) - timespec_to_ms(STATE.start);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t sleep_i(void) {
+  assert_stack_has(1);
+  stack_read_number(ms, -1);
+  if (!(ms >= 0 && ms <= 1e15)) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count--;
+  struct timespec duration;
+  duration.tv_sec = (time_t)(ms / 1000);
+  duration.tv_nsec = (long)((ms - duration.tv_sec * 1000.0) * 1000000);
+  while (nanosleep(&duration, &duration) == -1 && errno == EINTR) {
+  }
+  return OK;
+}
+
+// Backtracking regex engine matching the interpreter, operating on bytes
+#define REGEX_MAX_REPEAT 1000
+#define REGEX_MAX_PROGRAM 100000
+// A search gives up after this many steps for each byte of the text, so that
+// patterns which backtrack exponentially, like (a*)*c, fail instead of hanging
+#define REGEX_STEPS_PER_CHAR 1000
+
+typedef enum {
+  REGEX_CLASS,
+  REGEX_START,
+  REGEX_END,
+  REGEX_SPLIT,
+  REGEX_JUMP,
+  REGEX_SAVE,
+  REGEX_RESET,
+  REGEX_PROGRESS,
+  REGEX_MATCH,
+} regex_op_t;
+
+typedef struct {
+  regex_op_t op;
+  int x;
+  int y;
+  unsigned char set[32];
+} regex_inst_t;
+
+typedef struct {
+  regex_inst_t *insts;
+  int len;
+  int capacity;
+} regex_code_t;
+
+typedef struct {
+  const char *pattern;
+  int len;
+  int pos;
+  int group_count;
+  int slot_count;
+} regex_parser_t;
+
+typedef struct {
+  regex_code_t code;
+  int group_count;
+  int slot_count;
+} pattern_t;
+
+typedef struct {
+  int pc;
+  int pos;
+  int slot;
+  int value;
+} regex_backtrack_t;
+
+int regex_emit(regex_code_t *code, regex_op_t op, int x, int y) {
+  if (code->len == code->capacity) {
+    code->capacity = code->capacity ? code->capacity * 2 : 16;
+    code->insts = realloc(code->insts, code->capacity * sizeof(regex_inst_t));
+  }
+  regex_inst_t *inst = code->insts + code->len;
+  inst->op = op;
+  inst->x = x;
+  inst->y = y;
+  memset(inst->set, 0, sizeof(inst->set));
+  return code->len++;
+}
+
+int regex_append(regex_code_t *dest, const regex_code_t *src) {
+  int offset = dest->len;
+  for (int i = 0; i < src->len; i++) {
+    int at = regex_emit(dest, src->insts[i].op, 0, 0);
+    dest->insts[at] = src->insts[i];
+    if (src->insts[i].op == REGEX_SPLIT || src->insts[i].op == REGEX_JUMP) {
+      dest->insts[at].x += offset;
+      dest->insts[at].y += offset;
+    }
+  }
+  return dest->len <= REGEX_MAX_PROGRAM;
+}
+
+void regex_set_range(unsigned char *set, int lo, int hi) {
+  for (int c = lo; c <= hi; c++) {
+    set[c / 8] |= 1 << (c % 8);
+  }
+}
+
+int regex_shorthand_matches(char kind, int c) {
+  switch (kind) {
+  case 'd':
+    return isdigit(c) != 0;
+  case 'D':
+    return !isdigit(c);
+  case 'w':
+    return isalnum(c) || c == '_';
+  case 'W':
+    return !(isalnum(c) || c == '_');
+  case 's':
+    return isspace(c) != 0;
+  default:
+    return !isspace(c);
+  }
+}
+
+int regex_peek(regex_parser_t *p) {
+  return p->pos < p->len ? (unsigned char)p->pattern[p->pos] : -1;
+}
+
+int regex_eat(regex_parser_t *p, char c) {
+  if (regex_peek(p) == c) {
+    p->pos++;
+    return 1;
+  }
+  return 0;
+}
+
+// Returns 0 when invalid, 1 for a single character and 2 for a shorthand
+// class which is added to the set
+int regex_parse_escape(regex_parser_t *p, int in_class, unsigned char *set,
+                       int *c) {
+  int next = regex_peek(p);
+  p->pos++;
+  switch (next) {
+  case 'd':
+  case 'D':
+  case 'w':
+  case 'W':
+  case 's':
+  case 'S':
+    for (int i = 0; i < 256; i++) {
+      if (regex_shorthand_matches(next, i)) {
+        regex_set_range(set, i, i);
+      }
+    }
+    return 2;
+  case 'n':
+    *c = '\n';
+    return 1;
+  case 't':
+    *c = '\t';
+    return 1;
+  case 'r':
+    *c = '\r';
+    return 1;
+  case 'f':
+    *c = '\f';
+    return 1;
+  case 'v':
+    *c = '\v';
+    return 1;
+  case '-':
+    *c = '-';
+    return in_class;
+  default:
+    *c = next;
+    return next > 0 && strchr("^$\\.*+?()[]{}|/", next) != NULL;
+  }
+}
+
+int regex_parse_class_atom(regex_parser_t *p, unsigned char *set, int *c) {
+  int next = regex_peek(p);
+  if (next == -1) {
+    return 0;
+  }
+  p->pos++;
+  if (next == '\\') {
+    return regex_parse_escape(p, 1, set, c);
+  }
+  *c = next;
+  return 1;
+}
+
+int regex_parse_class(regex_parser_t *p, unsigned char *set) {
+  int negated = regex_eat(p, '^');
+  unsigned char items[32] = {0};
+  while (!regex_eat(p, ']')) {
+    int lo;
+    int kind = regex_parse_class_atom(p, items, &lo);
+    int range = regex_peek(p) == '-' && p->pos + 1 < p->len &&
+                p->pattern[p->pos + 1] != ']';
+    if (kind == 0 || (kind == 2 && range)) {
+      return 0;
+    }
+    if (kind == 2) {
+      continue;
+    }
+    int hi = lo;
+    if (range) {
+      p->pos++;
+      unsigned char ignored[32] = {0};
+      if (regex_parse_class_atom(p, ignored, &hi) != 1 || hi < lo) {
+        return 0;
+      }
+    }
+    regex_set_range(items, lo, hi);
+  }
+  for (int i = 0; i < 32; i++) {
+    set[i] = negated ? ~items[i] : items[i];
+  }
+  return 1;
+}
+
+int regex_parse_alternation(regex_parser_t *p, regex_code_t *out);
+
+int regex_parse_atom(regex_parser_t *p, regex_code_t *out, int *is_anchor) {
+  int next = regex_peek(p);
+  p->pos++;
+  switch (next) {
+  case '(': {
+    int index = -1;
+    if (regex_eat(p, '?')) {
+      if (!regex_eat(p, ':')) {
+        return 0;
+      }
+    } else {
+      index = ++p->group_count;
+    }
+    regex_code_t inner = {0};
+    int ok = regex_parse_alternation(p, &inner) && regex_eat(p, ')');
+    if (index != -1) {
+      regex_emit(out, REGEX_SAVE, index * 2, 0);
+    }
+    ok = ok && regex_append(out, &inner);
+    if (index != -1) {
+      regex_emit(out, REGEX_SAVE, index * 2 + 1, 0);
+    }
+    free(inner.insts);
+    return ok;
+  }
+  case '[': {
+    int at = regex_emit(out, REGEX_CLASS, 0, 0);
+    return regex_parse_class(p, out->insts[at].set);
+  }
+  case '.': {
+    int at = regex_emit(out, REGEX_CLASS, 0, 0);
+    regex_set_range(out->insts[at].set, 0, 255);
+    return 1;
+  }
+  case '^':
+    *is_anchor = 1;
+    regex_emit(out, REGEX_START, 0, 0);
+    return 1;
+  case '$':
+    *is_anchor = 1;
+    regex_emit(out, REGEX_END, 0, 0);
+    return 1;
+  case '\\': {
+    int at = regex_emit(out, REGEX_CLASS, 0, 0);
+    int c;
+    int kind = regex_parse_escape(p, 0, out->insts[at].set, &c);
+    if (kind == 1) {
+      regex_set_range(out->insts[at].set, c, c);
+    }
+    return kind != 0;
+  }
+  case '*':
+  case '+':
+  case '?':
+  case '{':
+  case '}':
+  case ']':
+  case ')':
+    return 0;
+  default: {
+    int at = regex_emit(out, REGEX_CLASS, 0, 0);
+    regex_set_range(out->insts[at].set, next, next);
+    return 1;
+  }
+  }
+}
+
+int regex_parse_count(regex_parser_t *p, int *count) {
+  int start = p->pos;
+  *count = 0;
+  while (regex_peek(p) >= '0' && regex_peek(p) <= '9') {
+    *count = *count * 10 + (p->pattern[p->pos] - '0');
+    if (*count > REGEX_MAX_REPEAT) {
+      return 0;
+    }
+    p->pos++;
+  }
+  return p->pos > start;
+}
+
+void regex_set_split(regex_code_t *code, int at, int from, int to, int greedy) {
+  code->insts[at].x = greedy ? from : to;
+  code->insts[at].y = greedy ? to : from;
+}
+
+int regex_parse_quantifier(regex_parser_t *p, regex_code_t *out,
+                           regex_code_t *atom, int is_anchor,
+                           int groups_before) {
+  int min;
+  int max;
+  switch (regex_peek(p)) {
+  case '*':
+    min = 0;
+    max = -1;
+    break;
+  case '+':
+    min = 1;
+    max = -1;
+    break;
+  case '?':
+    min = 0;
+    max = 1;
+    break;
+  case '{':
+    p->pos++;
+    if (!regex_parse_count(p, &min)) {
+      return 0;
+    }
+    max = min;
+    if (regex_eat(p, ',')) {
+      if (regex_peek(p) == '}') {
+        max = -1;
+      } else if (!regex_parse_count(p, &max)) {
+        return 0;
+      }
+    }
+    if (regex_peek(p) != '}' || (max != -1 && max < min)) {
+      return 0;
+    }
+    break;
+  default:
+    return regex_append(out, atom);
+  }
+  p->pos++;
+
+  if (is_anchor) {
+    return 0;
+  }
+  int greedy = !regex_eat(p, '?');
+
+  regex_code_t iteration = {0};
+  if (p->group_count > groups_before) {
+    regex_emit(&iteration, REGEX_RESET, (groups_before + 1) * 2,
+               (p->group_count + 1) * 2);
+  }
+  int ok = regex_append(&iteration, atom);
+
+  for (int i = 0; i < min && ok; i++) {
+    ok = regex_append(out, &iteration);
+  }
+
+  // Iterations past the minimum must consume input, like JavaScript
+  regex_code_t optional = {0};
+  int progress = p->slot_count++;
+  regex_emit(&optional, REGEX_SAVE, progress, 0);
+  ok = ok && regex_append(&optional, &iteration);
+  regex_emit(&optional, REGEX_PROGRESS, progress, 0);
+
+  if (max == -1) {
+    int start = regex_emit(out, REGEX_SPLIT, 0, 0);
+    ok = ok && regex_append(out, &optional);
+    regex_emit(out, REGEX_JUMP, start, 0);
+    regex_set_split(out, start, start + 1, out->len, greedy);
+  } else {
+    int first = out->len;
+    for (int i = min; i < max && ok; i++) {
+      regex_emit(out, REGEX_SPLIT, 0, 0);
+      ok = regex_append(out, &optional);
+    }
+    for (int i = 0; i < max - min && ok; i++) {
+      int at = first + i * (optional.len + 1);
+      regex_set_split(out, at, at + 1, out->len, greedy);
+    }
+  }
+
+  free(optional.insts);
+  free(iteration.insts);
+  return ok;
+}
+
+int regex_parse_concat(regex_parser_t *p, regex_code_t *out) {
+  while (regex_peek(p) != -1 && regex_peek(p) != '|' && regex_peek(p) != ')') {
+    int groups_before = p->group_count;
+    int is_anchor = 0;
+    regex_code_t atom = {0};
+    int ok = regex_parse_atom(p, &atom, &is_anchor) &&
+             regex_parse_quantifier(p, out, &atom, is_anchor, groups_before);
+    free(atom.insts);
+    if (!ok) {
+      return 0;
+    }
+  }
+  return 1;
+}
+
+int regex_parse_alternation(regex_parser_t *p, regex_code_t *out) {
+  regex_code_t branch = {0};
+  int *jumps = NULL;
+  int jump_count = 0;
+  int ok = regex_parse_concat(p, &branch);
+  while (ok && regex_eat(p, '|')) {
+    int split = regex_emit(out, REGEX_SPLIT, 0, 0);
+    ok = regex_append(out, &branch);
+    jumps = realloc(jumps, (jump_count + 1) * sizeof(int));
+    jumps[jump_count++] = regex_emit(out, REGEX_JUMP, 0, 0);
+    regex_set_split(out, split, split + 1, out->len, 1);
+    branch.len = 0;
+    ok = ok && regex_parse_concat(p, &branch);
+  }
+  ok = ok && regex_append(out, &branch);
+  for (int i = 0; i < jump_count; i++) {
+    out->insts[jumps[i]].x = out->len;
+  }
+  free(jumps);
+  free(branch.insts);
+  return ok;
+}
+
+int pattern_compile(string_source_t *source, pattern_t *pattern) {
+  // The first pass counts the groups so that the slots tracking loop progress
+  // can be placed after the captures
+  regex_parser_t counter = {source->data, source->len, 0, 0, 0};
+  regex_code_t body = {0};
+  int ok = regex_parse_alternation(&counter, &body) && counter.pos == counter.len;
+
+  int capture_slots = (counter.group_count + 1) * 2;
+  regex_parser_t p = {source->data, source->len, 0, 0, capture_slots};
+  body.len = 0;
+  ok = ok && regex_parse_alternation(&p, &body);
+
+  pattern->code = (regex_code_t){0};
+  regex_emit(&pattern->code, REGEX_SAVE, 0, 0);
+  ok = ok && regex_append(&pattern->code, &body);
+  regex_emit(&pattern->code, REGEX_SAVE, 1, 0);
+  regex_emit(&pattern->code, REGEX_MATCH, 0, 0);
+  free(body.insts);
+  pattern->group_count = p.group_count;
+  pattern->slot_count = p.slot_count;
+  if (!ok) {
+    free(pattern->code.insts);
+  }
+  return ok;
+}
+
+// Finds the leftmost match starting at or after start, filling slots with the
+// capture boundaries or -1 when a group did not participate. Returns -1 when
+// the search runs out of steps
+int pattern_find(pattern_t *pattern, string_source_t *text, int start,
+                 int *slots) {
+  regex_backtrack_t *stack = NULL;
+  int stack_len = 0;
+  int stack_capacity = 0;
+  int found = 0;
+  int64_t steps = ((int64_t)text->len + 1) * REGEX_STEPS_PER_CHAR;
+
+  for (int i = 0; i < pattern->slot_count; i++) {
+    slots[i] = -1;
+  }
+
+  for (int begin = start; begin <= text->len && !found; begin++) {
+    regex_backtrack_t initial = {0, begin, -1, 0};
+    stack_len = 0;
+    if (stack_capacity == 0) {
+      stack_capacity = 16;
+      stack = malloc(stack_capacity * sizeof(regex_backtrack_t));
+    }
+    stack[stack_len++] = initial;
+
+    while (stack_len > 0 && !found) {
+      regex_backtrack_t entry = stack[--stack_len];
+      if (entry.slot != -1) {
+        slots[entry.slot] = entry.value;
+        continue;
+      }
+
+      int pc = entry.pc;
+      int pos = entry.pos;
+      int alive = 1;
+      while (alive && !found) {
+        if (steps-- == 0) {
+          free(stack);
+          return -1;
+        }
+        regex_inst_t *inst = pattern->code.insts + pc;
+        if (stack_len + (pattern->group_count + 1) * 2 >= stack_capacity) {
+          stack_capacity = stack_capacity * 2 + (pattern->group_count + 1) * 2;
+          stack = realloc(stack, stack_capacity * sizeof(regex_backtrack_t));
+        }
+
+        switch (inst->op) {
+        case REGEX_CLASS: {
+          unsigned char c = pos < text->len ? text->data[pos] : 0;
+          alive = pos < text->len && ((inst->set[c / 8] >> (c % 8)) & 1);
+          pc++;
+          pos++;
+          break;
+        }
+        case REGEX_START:
+          alive = pos == 0;
+          pc++;
+          break;
+        case REGEX_END:
+          alive = pos == text->len;
+          pc++;
+          break;
+        case REGEX_SPLIT: {
+          regex_backtrack_t resume = {inst->y, pos, -1, 0};
+          stack[stack_len++] = resume;
+          pc = inst->x;
+          break;
+        }
+        case REGEX_JUMP:
+          pc = inst->x;
+          break;
+        case REGEX_SAVE: {
+          regex_backtrack_t restore = {0, 0, inst->x, slots[inst->x]};
+          stack[stack_len++] = restore;
+          slots[inst->x] = pos;
+          pc++;
+          break;
+        }
+        case REGEX_RESET:
+          for (int slot = inst->x; slot < inst->y; slot++) {
+            regex_backtrack_t restore = {0, 0, slot, slots[slot]};
+            stack[stack_len++] = restore;
+            slots[slot] = -1;
+          }
+          pc++;
+          break;
+        case REGEX_PROGRESS:
+          alive = slots[inst->x] != pos;
+          pc++;
+          break;
+        case REGEX_MATCH:
+          found = 1;
+          break;
+        }
+      }
+    }
+  }
+
+  free(stack);
+  return found;
+}
+
+status_t push_capture(string_source_t *source, int *slots, int group) {
+  int start = slots[group * 2];
+  int end = slots[group * 2 + 1];
+  if (start == -1 || end == -1) {
+    return push_string_literal("", 0);
+  }
+  return push_string_copy(source->data + start, end - start);
+}
+
+status_t matches(void) {
+  assert_stack_has(2);
+  stack_read_string(pattern_source, -1);
+  stack_read_string(source, -2);
+  pattern_t pattern;
+  if (!pattern_compile(pattern_source, &pattern)) {
+    return INVALID_ARGUMENT;
+  }
+  int *slots = malloc(pattern.slot_count * sizeof(int));
+  int found = pattern_find(&pattern, source, 0, slots);
+  free(slots);
+  free(pattern.code.insts);
+  if (found < 0) {
+    return INVALID_ARGUMENT;
+  }
+
+  stack_at(-2) = found ? TRUE_V : FALSE_V;
+  STATE.value_count--;
+  dec_string_ref_count(pattern_source);
+  dec_string_ref_count(source);
+  return OK;
+}
+
+status_t find_match(void) {
+  assert_stack_has(2);
+  stack_read_string(pattern_source, -1);
+  stack_read_string(source, -2);
+  pattern_t pattern;
+  if (!pattern_compile(pattern_source, &pattern)) {
+    return INVALID_ARGUMENT;
+  }
+  int *slots = malloc(pattern.slot_count * sizeof(int));
+  int found = pattern_find(&pattern, source, 0, slots);
+  free(pattern.code.insts);
+  if (found < 0) {
+    free(slots);
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+
+  status_t status = push_capture(source, slots, 0);
+  if (status == OK) {
+    stack_at(0) = found ? slots[0] : -1;
+    STATE.value_count++;
+  }
+  free(slots);
+  dec_string_ref_count(pattern_source);
+  dec_string_ref_count(source);
+  return status;
+}
+
+void append_replacement(char **data, int *len, int *capacity,
+                        string_source_t *replacement, string_source_t *source,
+                        int *slots, int group_count) {
+  for (int i = 0; i < replacement->len; i++) {
+    char c = replacement->data[i];
+    char next = i + 1 < replacement->len ? replacement->data[i + 1] : '\0';
+    if (c == '$' && next >= '0' && next <= '9' && next - '0' <= group_count) {
+      int group = next - '0';
+      if (slots[group * 2] != -1 && slots[group * 2 + 1] != -1) {
+        append_to_buffer(data, len, capacity, source->data + slots[group * 2],
+                         slots[group * 2 + 1] - slots[group * 2]);
+      }
+      i++;
+    } else if (c == '$' && next == '$') {
+      append_to_buffer(data, len, capacity, "$", 1);
+      i++;
+    } else {
+      append_to_buffer(data, len, capacity, &c, 1);
+    }
+  }
+}
+
+status_t replace_regex(void) {
+  assert_stack_has(3);
+  stack_read_string(replacement, -1);
+  stack_read_string(pattern_source, -2);
+  stack_read_string(source, -3);
+  pattern_t pattern;
+  if (!pattern_compile(pattern_source, &pattern)) {
+    return INVALID_ARGUMENT;
+  }
+  int *slots = malloc(pattern.slot_count * sizeof(int));
+
+  // Allocated up front, so an empty result isn't NULL, which would mark its
+  // string slot as free
+  int capacity = 64;
+  char *data = malloc(capacity);
+  int len = 0;
+  int last = 0;
+  int pos = 0;
+  int found = 0;
+  while (pos <= source->len &&
+         (found = pattern_find(&pattern, source, pos, slots)) > 0) {
+    append_to_buffer(&data, &len, &capacity, source->data + last,
+                     slots[0] - last);
+    append_replacement(&data, &len, &capacity, replacement, source, slots,
+                       pattern.group_count);
+    last = slots[1];
+    pos = slots[1] == slots[0] ? slots[1] + 1 : slots[1];
+  }
+  append_to_buffer(&data, &len, &capacity, source->data + last,
+                   source->len - last);
+
+  free(slots);
+  free(pattern.code.insts);
+  if (found < 0) {
+    free(data);
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 3;
+  dec_string_ref_count(replacement);
+  dec_string_ref_count(pattern_source);
+  dec_string_ref_count(source);
+  return push_owned_string(data, len);
+}
+
+status_t captures(void) {
+  assert_stack_has(2);
+  stack_read_string(pattern_source, -1);
+  stack_read_string(source, -2);
+  pattern_t pattern;
+  if (!pattern_compile(pattern_source, &pattern)) {
+    return INVALID_ARGUMENT;
+  }
+  int *slots = malloc(pattern.slot_count * sizeof(int));
+  int found = pattern_find(&pattern, source, 0, slots);
+  free(pattern.code.insts);
+  if (found < 0) {
+    free(slots);
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count -= 2;
+
+  status_t status = OK;
+  int count = found ? pattern.group_count + 1 : 0;
+  for (int i = 0; i < count && status == OK; i++) {
+    status = push_capture(source, slots, i);
+  }
+
+  free(slots);
+  dec_string_ref_count(pattern_source);
+  dec_string_ref_count(source);
+  checked(status);
+  assert_stack_capacity(1);
+  stack_at(0) = count;
+  STATE.value_count++;
+  return OK;
+}
+
+// The field values move from the stack into the new record
+status_t construct_record(int namespace, const char *name,
+                          const char *const *fields, int field_count) {
+  assert_stack_has(field_count);
+  assert_stack_capacity(1);
+  uint64_t record_index;
+  checked(find_record_slot(&record_index));
+  record_t *record = STATE.records + record_index;
+  record->values = malloc(sizeof(value_t) * (field_count + 1));
+  if (record->values == NULL) {
+    return RECORD_MAX;
+  }
+  memcpy(record->values, &stack_at(-field_count),
+         sizeof(value_t) * field_count);
+  record->namespace = namespace;
+  record->name = name;
+  record->fields = fields;
+  record->field_count = field_count;
+  record->refs = 1;
+  STATE.value_count -= field_count;
+  stack_at(0) = record_index_to_value(record_index);
+  STATE.value_count++;
+  return OK;
+}
+
+status_t record_get(int namespace, const char *name, int field) {
+  assert_stack_has(1);
+  stack_read(v, -1);
+  record_t *record;
+  checked(resolve_record_value(v, namespace, name, &record));
+  value_t field_value = record->values[field];
+  inc_ref_count(field_value);
+  stack_at(-1) = field_value;
+  dec_ref_count(v);
+  return OK;
+}
+
+// Changes the record in place when nothing else refers to it
+status_t record_with(int namespace, const char *name, int field) {
+  assert_stack_has(2);
+  stack_read(target, -2);
+  stack_read(v, -1);
+  record_t *record;
+  checked(resolve_record_value(target, namespace, name, &record));
+  if (record->refs > 1) {
+    uint64_t record_index;
+    checked(find_record_slot(&record_index));
+    record_t *copy = STATE.records + record_index;
+    *copy = *record;
+    copy->values = malloc(sizeof(value_t) * (record->field_count + 1));
+    if (copy->values == NULL) {
+      return RECORD_MAX;
+    }
+    memcpy(copy->values, record->values,
+           sizeof(value_t) * record->field_count);
+    for (int i = 0; i < copy->field_count; i++) {
+      inc_ref_count(copy->values[i]);
+    }
+    copy->refs = 1;
+    record->refs--;
+    record = copy;
+    stack_at(-2) = record_index_to_value(record_index);
+  }
+  dec_ref_count(record->values[field]);
+  record->values[field] = v;
+  STATE.value_count--;
+  return OK;
+}
+
+// Replaces the variant on top with its fields when it is the named one
+status_t match_variant(int namespace, const char *name, int *matched) {
+  assert_stack_has(1);
+  stack_read(v, -1);
+  record_t *record;
+  *matched = resolve_record_value(v, namespace, name, &record) == OK;
+  if (!*matched) {
+    return OK;
+  }
+  assert_stack_capacity(record->field_count);
+  STATE.value_count--;
+  for (int i = 0; i < record->field_count; i++) {
+    inc_ref_count(record->values[i]);
+    stack_at(0) = record->values[i];
+    STATE.value_count++;
+  }
+  dec_ref_count(v);
+  return OK;
+}
+
+// Integers are widened and floats and decimals truncated, floats must be finite
+status_t to_big(void) {
+  assert_stack_has(1);
+  big_t *big;
+  maybe_resolve_big_value(stack_at(-1), &big);
+  if (big != NULL) {
+    return OK;
+  }
+  decimal_t *decimal;
+  maybe_resolve_decimal_value(stack_at(-1), &decimal);
+  if (decimal != NULL) {
+    big_t whole = decimal_trunc(decimal, NULL);
+    dec_ref_count(stack_at(-1));
+    STATE.value_count--;
+    return push_big(whole);
+  }
+  big_t widened;
+  uint32_t limbs[2];
+  if (read_exact_value(stack_at(-1), &widened, limbs)) {
+    dec_ref_count(stack_at(-1));
+    STATE.value_count--;
+    return push_big(big_copy(&widened));
+  }
+  stack_read_number(v, -1);
+  if (!isfinite(v)) {
+    return INVALID_ARGUMENT;
+  }
+  STATE.value_count--;
+  return push_big(big_from_double(v));
+}
+
+// Numbers keep the shortest digits that read back as them, so `0.1 to_dec` is
+// `0.1d`
+status_t to_dec(void) {
+  assert_stack_has(1);
+  if (is_decimal_value(stack_at(-1))) {
+    return OK;
+  }
+  decimal_t widened;
+  uint32_t limbs[2];
+  if (read_exact_decimal(stack_at(-1), &widened, limbs)) {
+    decimal_t result = {big_copy(&widened.unscaled), 0, NULL, 0};
+    dec_ref_count(stack_at(-1));
+    STATE.value_count--;
+    return push_decimal(result);
+  }
+  stack_read_number(v, -1);
+  if (!isfinite(v)) {
+    return INVALID_ARGUMENT;
+  }
+  int len;
+  char *text = number_to_string(v, &len);
+  decimal_t result = decimal_parse(text);
+  free(text);
+  STATE.value_count--;
+  return push_decimal(result);
+}
+
+// A variant of the matched enum without an arm, or a value that isn't one of its variants
+status_t no_match_arm(int namespace, const char *const *variants,
+                      int variant_count) {
+  assert_stack_has(1);
+  stack_read(v, -1);
+  for (int i = 0; i < variant_count; i++) {
+    record_t *record;
+    if (resolve_record_value(v, namespace, variants[i], &record) == OK) {
+      return NO_MATCH;
+    }
+  }
+  return TYPE_MISMATCH;
+}
+
+status_t throw_i(void) {
+  assert_stack_has(1);
+  STATE.value_count--;
+  STATE.thrown = stack_at(0);
+  return THROWN;
+}
+
+const char *status_description(status_t status) {
+  if (status == STACK_UNDERFLOW) {
+    return "Stack empty";
+  } else if (status == STACK_OVERFLOW) {
+    return "Stack overflow";
+  } else if (status == STRING_MAX) {
+    return "Too many strings";
+  } else if (status == RECORD_MAX) {
+    return "Too many records";
+  } else if (status == NO_MATCH) {
+    return "No match arm";
+  } else if (status == INTEGER_MAX) {
+    return "Too many integers";
+  } else if (status == INTEGER_OVERFLOW) {
+    return "Integer overflow";
+  } else if (status == DIVISION_BY_ZERO) {
+    return "Division by zero";
+  } else if (status == BIG_MAX) {
+    return "Too many bignums";
+  } else if (status == DECIMAL_MAX) {
+    return "Too many decimals";
+  } else if (status == TYPE_MISMATCH) {
+    return "Type mismatch";
+  } else if (status == INVALID_EXIT_CODE) {
+    return "Invalid exit code";
+  } else if (status == INVALID_FORMAT) {
+    return "Invalid format";
+  } else if (status == INVALID_ARGUMENT) {
+    return "Invalid argument";
+  } else if (status == STRING_TOO_LONG) {
+    return "String too long";
+  } else if (status == STDIN_FAILED) {
+    return "Failed to read line";
+  }
+  return "Unknown error";
+}
+
+// The snapshot holds its own reference to every value on the stack
+status_t save_stack(stack_snapshot_t *snapshot) {
+  snapshot->value_count = STATE.value_count;
+  snapshot->values = malloc(sizeof(value_t) * (STATE.value_count + 1));
+  if (snapshot->values == NULL) {
+    return STACK_OVERFLOW;
+  }
+  for (int i = 0; i < STATE.value_count; i++) {
+    snapshot->values[i] = STATE.values[i];
+    inc_ref_count(STATE.values[i]);
+  }
+  return OK;
+}
+
+void release_stack(stack_snapshot_t *snapshot) {
+  for (int i = 0; i < snapshot->value_count; i++) {
+    dec_ref_count(snapshot->values[i]);
+  }
+  free(snapshot->values);
+}
+
+// Replaces the stack, the snapshot's references move back onto it
+void restore_stack(stack_snapshot_t *snapshot) {
+  while (STATE.value_count > 0) {
+    STATE.value_count--;
+    dec_ref_count(stack_at(0));
+  }
+  memcpy(STATE.values, snapshot->values,
+         sizeof(value_t) * snapshot->value_count);
+  STATE.value_count = snapshot->value_count;
+  free(snapshot->values);
+}
+
+// Pushes what a catch handler receives for the status that ended its body
+status_t push_caught(void) {
+  if (STATE.status == THROWN || STATE.status == ASSERT_FAILED) {
+    assert_stack_capacity(1);
+    stack_at(0) = STATE.thrown;
+    STATE.value_count++;
+    return OK;
+  }
+  const char *description = status_description(STATE.status);
+  return push_string_literal(description, strlen(description));
+}
+
+status_t report_uncaught(status_t status) {
+  const char *str;
+  int len;
+  if ((status == THROWN || status == ASSERT_FAILED) &&
+      print_to_string(STATE.thrown, SCRATCH_A, sizeof(SCRATCH_A), &str,
+                      &len) == OK) {
+    if (status == THROWN) {
+      fprintf(stderr, "Uncaught throw: %.*s\n", len, str);
+    } else {
+      printf("%.*s\n", len, str);
+    }
+  }
+  return status;
+}
@@ -1,21 +1,44 @@
 use crate::{
-    codegen::{
-        context::{CodegenContext, CodegenResult, CodegenResultG},
-        target::CodegenTarget,
-    },
-    lang::{Block, Function, Loop, Term},
+    codegen::context::{CodegenContext, CodegenResult, CodegenResultG},
+    lang::{Block, Function, Loop, Match, Record, RecordOp, Term, Try},
     program::{NamespaceId, Program},
 };
 
 static DEFS: &str = include_str!("./c.h");
 
+// Failures release what the enclosing blocks hold, then either jump to the handler
+// of the innermost try or leave the function
+fn write_checked(ctx: &mut CodegenContext, call: &str) {
+    let (from, handler) = match ctx.tries.last_mut() {
+        Some(t) => {
+            t.caught = true;
+            (t.cleanup, Some(t.id))
+        }
+        None => (0, None),
+    };
+    if handler.is_none() && ctx.cleanup.is_empty() {
+        ctx.target.write_line(&format!("checked({call});"));
+        return;
+    }
+    ctx.target
+        .write_line(&format!("if ((STATE.status = {call}) != OK) {{"));
+    ctx.target.increase_indent();
+    release_cleanup(ctx, from);
+    match handler {
+        Some(id) => ctx.target.write_line(&format!("goto try_{id}_catch;")),
+        None => ctx.target.write_line("return STATE.status;"),
+    }
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
+}
+
 fn codegen_loop_condition(ctx: &mut CodegenContext, block: Option<&Block>) -> CodegenResult {
     if let Some(e) = block {
         codegen_block(ctx, e)?;
         ctx.target.write_line("{");
         ctx.target.increase_indent();
         ctx.target.write_line("int c;");
-        ctx.target.write_line("checked(check_condition(&c));");
+        write_checked(ctx, "check_condition(&c)");
         ctx.target.write_line("if (!c) {");
         ctx.target.write_line("  break;");
         ctx.target.write_line("}");
@@ -26,45 +49,235 @@ fn codegen_loop_condition(ctx: &mut CodegenContext, block: Option<&Block>) -> Co
 }
 
 fn codegen_loop(ctx: &mut CodegenContext, loop_t: &Loop) -> CodegenResult {
+    let (id, labeled) = {
+        let current = ctx.enter_loop(loop_t);
+        (current.id, current.labeled)
+    };
     ctx.target.write_line("while (1) {");
     ctx.target.increase_indent();
     codegen_loop_condition(ctx, loop_t.pre_condition.as_ref())?;
     codegen_block(ctx, &loop_t.body)?;
+    if labeled {
+        ctx.target.write_line(&format!("loop_{id}_next:;"));
+    }
     codegen_loop_condition(ctx, loop_t.post_condition.as_ref())?;
     ctx.target.decrease_indent();
     ctx.target.write_line("}");
+    ctx.exit_loop();
+    Ok(())
+}
+
+fn release_cleanup(ctx: &mut CodegenContext, from: usize) {
+    for statement in ctx.cleanup[from..].iter().rev() {
+        ctx.target.write_line(statement);
+    }
+}
+
+fn codegen_loop_control(ctx: &mut CodegenContext, is_continue: bool) -> CodegenResult {
+    let (id, labeled, cleanup) = {
+        let current = ctx.current_loop()?;
+        (current.id, current.labeled, current.cleanup)
+    };
+    release_cleanup(ctx, cleanup);
+    if !is_continue {
+        ctx.target.write_line("break;");
+    } else if labeled {
+        ctx.target.write_line(&format!("goto loop_{id}_next;"));
+    } else {
+        ctx.target.write_line("continue;");
+    }
+    Ok(())
+}
+
+// The body runs against a snapshot of the stack, which the handler starts from
+fn codegen_try(ctx: &mut CodegenContext, t: &Try) -> CodegenResult {
+    let id = ctx.try_count;
+    ctx.target.write_line("{");
+    ctx.target.increase_indent();
+    ctx.target
+        .write_line(&format!("stack_snapshot_t try_{id}_stack;"));
+    write_checked(ctx, &format!("save_stack(&try_{id}_stack)"));
+    ctx.cleanup.push(format!("release_stack(&try_{id}_stack);"));
+    ctx.enter_try();
+    codegen_block(ctx, &t.body)?;
+    let current = ctx.exit_try();
+    ctx.cleanup.pop();
+    ctx.target
+        .write_line(&format!("release_stack(&try_{id}_stack);"));
+    if current.caught {
+        ctx.target.write_line(&format!("goto try_{id}_end;"));
+        ctx.target.write_line(&format!("try_{id}_catch:"));
+        ctx.target
+            .write_line(&format!("restore_stack(&try_{id}_stack);"));
+        write_checked(ctx, "push_caught()");
+        ctx.target.write_line("{");
+        ctx.target.increase_indent();
+        codegen_block(ctx, &t.handler)?;
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+        ctx.target.write_line(&format!("try_{id}_end:;"));
+    }
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
+    Ok(())
+}
+
+// Records are declared in the namespace of their functions, which is the one being generated
+fn codegen_record(ctx: &mut CodegenContext, record: &Record, op: RecordOp) {
+    let (namespace, name) = (ctx.namespace, &record.name);
+    match op {
+        RecordOp::Construct => {
+            // The field names are static so that every record can refer to them
+            ctx.target.write_line("{");
+            ctx.target.increase_indent();
+            let fields = if record.fields.is_empty() {
+                "NULL".into()
+            } else {
+                let names = record.fields.iter().map(|f| format!("{f:?}"));
+                ctx.target.write_line(&format!(
+                    "static const char *const fields[] = {{{}}};",
+                    names.collect::<Vec<_>>().join(", ")
+                ));
+                "fields".to_owned()
+            };
+            write_checked(
+                ctx,
+                &format!(
+                    "construct_record({namespace}, {name:?}, {fields}, {})",
+                    record.fields.len()
+                ),
+            );
+            ctx.target.decrease_indent();
+            ctx.target.write_line("}");
+        }
+        RecordOp::Get(i) => {
+            write_checked(ctx, &format!("record_get({namespace}, {name:?}, {i})"));
+        }
+        RecordOp::With(i) => {
+            write_checked(ctx, &format!("record_with({namespace}, {name:?}, {i})"));
+        }
+    }
+}
+
+fn codegen_match(ctx: &mut CodegenContext, m: &Match) -> CodegenResult {
+    ctx.target.write_line("{");
+    ctx.target.increase_indent();
+    ctx.target.write_line("int c;");
+    let mut variants = None;
+    m.arms
+        .iter()
+        .try_for_each(|(name, body)| -> CodegenResult {
+            let (namespace, variant) = ctx.resolve_variant(name)?;
+            variants = variant
+                .variant_of
+                .as_ref()
+                .map(|e| (namespace, &e.variants));
+            write_checked(
+                ctx,
+                &format!("match_variant({namespace}, {:?}, &c)", variant.name),
+            );
+            ctx.target.write_line("if (c) {");
+            ctx.target.increase_indent();
+            codegen_block(ctx, body)?;
+            ctx.target.decrease_indent();
+            ctx.target.write_line("} else {");
+            ctx.target.increase_indent();
+            Ok(())
+        })?;
+    match variants {
+        Some((namespace, variants)) => {
+            let names = variants.iter().map(|v| format!("{v:?}"));
+            ctx.target.write_line(&format!(
+                "static const char *const variants[] = {{{}}};",
+                names.collect::<Vec<_>>().join(", ")
+            ));
+            write_checked(
+                ctx,
+                &format!("no_match_arm({namespace}, variants, {})", variants.len()),
+            );
+        }
+        None => write_checked(ctx, "no_match_arm(0, NULL, 0)"),
+    }
+
+    m.arms.iter().for_each(|_| {
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+    });
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
     Ok(())
 }
 
+fn number_literal(v: f64) -> String {
+    match v {
+        f64::INFINITY => "INFINITY".into(),
+        f64::NEG_INFINITY => "-INFINITY".into(),
+        v if v.is_nan() => "NAN".into(),
+        v => format!("{v:?}"),
+    }
+}
+
+// The smallest integer can't be written as a negated literal in C
+fn integer_literal(v: i64) -> String {
+    if v == i64::MIN {
+        "INT64_MIN".into()
+    } else {
+        format!("INT64_C({v})")
+    }
+}
+
 fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
     match term {
         Term::String(e) => {
             if !e.is_ascii() {
                 return Err("Non-ascii strings are not supported in C".into());
             }
-            ctx.target.write_line(&format!(
-                "checked(push_string_literal({:?}, {}));",
-                e,
-                e.len()
-            ));
+            write_checked(ctx, &format!("push_string_literal({:?}, {})", e, e.len()));
+        }
+        Term::Number(e) => {
+            write_checked(ctx, &format!("push_number_literal({})", number_literal(*e)));
         }
-        Term::Number(e) => ctx
-            .target
-            .write_line(&format!("checked(push_number_literal({}L));", e)),
-        Term::Bool(true) => ctx.target.write_line("checked(push_true_literal());"),
-        Term::Bool(false) => ctx.target.write_line("checked(push_false_literal());"),
-        Term::Name(n, _) => ctx
-            .target
-            .write_line(&format!("checked({}());", ctx.resolve_name(n)?)),
-        Term::Address(n) => ctx.target.write_line(&format!(
-            "checked(push_fn_address(&{}));",
-            ctx.resolve_name(n)?
-        )),
+        Term::Integer(e) => {
+            write_checked(ctx, &format!("push_integer({})", integer_literal(*e)));
+        }
+        Term::Big(e) => write_checked(ctx, &format!("push_big_literal(\"{e}\")")),
+        Term::Decimal(e) => write_checked(ctx, &format!("push_decimal_literal(\"{e}\")")),
+        Term::Bool(true) => write_checked(ctx, "push_true_literal()"),
+        Term::Bool(false) => write_checked(ctx, "push_false_literal()"),
+        Term::Name(n, _) => {
+            let name = ctx.resolve_name(n)?;
+            write_checked(ctx, &format!("{name}()"));
+        }
+        Term::Address(n) => {
+            let name = ctx.resolve_name(n)?;
+            write_checked(ctx, &format!("push_fn_address(&{name})"));
+        }
+        Term::Bind(locals) => {
+            for local in locals.iter().rev() {
+                let name = CodegenContext::local_name(local);
+                ctx.target.write_line(&format!("value_t {name};"));
+                write_checked(ctx, &format!("bind_local(&{name})"));
+                ctx.cleanup.push(format!("dec_ref_count({name});"));
+            }
+        }
+        Term::Break => codegen_loop_control(ctx, false)?,
+        Term::Continue => codegen_loop_control(ctx, true)?,
+        Term::Return => {
+            release_cleanup(ctx, 0);
+            ctx.target.write_line("return OK;");
+        }
+        Term::Local(local) => write_checked(
+            ctx,
+            &format!("push_local({})", CodegenContext::local_name(local)),
+        ),
         Term::Branch(branch) => {
+            // Scoped so that branches in the same block each declare their own condition
+            ctx.target.write_line("{");
+            ctx.target.increase_indent();
             branch.arms.iter().try_for_each(|arm| -> CodegenResult {
                 codegen_block(ctx, &arm.0)?;
                 ctx.target.write_line("int c;");
-                ctx.target.write_line("checked(check_condition(&c));");
+                write_checked(ctx, "check_condition(&c)");
                 ctx.target.write_line("if (c) {");
                 ctx.target.increase_indent();
                 codegen_block(ctx, &arm.1)?;
@@ -78,14 +291,24 @@ fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
                 ctx.target.decrease_indent();
                 ctx.target.write_line("}");
             });
+            ctx.target.decrease_indent();
+            ctx.target.write_line("}");
         }
         Term::Loop(loop_t) => codegen_loop(ctx, loop_t)?,
+        Term::Try(t) => codegen_try(ctx, t)?,
+        Term::Record(record, op) => codegen_record(ctx, record, *op),
+        Term::Match(m) => codegen_match(ctx, m)?,
     }
     Ok(())
 }
 
 fn codegen_block(ctx: &mut CodegenContext, block: &Block) -> CodegenResult {
-    block.terms.iter().try_for_each(|t| codegen_term(ctx, t))
+    let mark = ctx.cleanup.len();
+    block.terms.iter().try_for_each(|t| codegen_term(ctx, t))?;
+    // Locals hold a reference to their value until the end of the block
+    release_cleanup(ctx, mark);
+    ctx.cleanup.truncate(mark);
+    Ok(())
 }
 
 fn codegen_func(ctx: &mut CodegenContext, name: &str, body: &Block) -> CodegenResult {
@@ -116,11 +339,7 @@ pub fn c_codegen_module(
     main_namespace: NamespaceId,
     main: &Block,
 ) -> CodegenResultG<String> {
-    let mut ctx = CodegenContext {
-        namespace: 0,
-        program,
-        target: CodegenTarget::default(),
-    };
+    let mut ctx = CodegenContext::new(program);
 
     ctx.target.write_line(DEFS);
 
@@ -144,8 +363,12 @@ pub fn c_codegen_module(
 
     ctx.target.write_line(
         "
-int main(void) {
-  checked(main_body());
+int main(int argc, char **argv) {
+  STATE.argc = argc - 1;
+  STATE.argv = argv + 1;
+  STATE.random_state = (uint64_t)time(NULL) ^ (uint64_t)clock();
+  clock_gettime(CLOCK_MONOTONIC, &STATE.start);
+  checked(report_uncaught(main_body()));
   checked(print_stack());
 }",
     );
@@ -1,8 +1,9 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt::Write as _};
 
 use crate::{
     codegen::target::CodegenTarget,
     intrinsics::get_intrinsic_codegen_name,
+    lang::{Local, Loop, Record, Term},
     program::{NamespaceId, Program},
 };
 
@@ -10,21 +11,133 @@ pub type CodegenError = Cow<'static, str>;
 pub type CodegenResultG<T> = Result<T, CodegenError>;
 pub type CodegenResult = CodegenResultG<()>;
 
+pub struct LoopContext {
+    pub id: usize,
+    // Set when a continue has to jump over the rest of the body to the post condition
+    pub labeled: bool,
+    // The number of cleanup statements and tries from outside of the loop
+    pub cleanup: usize,
+    pub tries: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TryExit {
+    Break,
+    Continue,
+    Return,
+}
+
+#[derive(Default)]
+pub struct TryContext {
+    pub id: usize,
+    // The number of cleanup statements from outside of the try body
+    pub cleanup: usize,
+    // Set once an error in the body can reach the handler
+    pub caught: bool,
+    // Ways of leaving the body that have to be repeated after it ends
+    pub exits: Vec<TryExit>,
+}
+
+impl TryContext {
+    pub fn leave(&mut self, exit: TryExit) {
+        if !self.exits.contains(&exit) {
+            self.exits.push(exit);
+        }
+    }
+}
+
 pub struct CodegenContext<'a> {
     pub namespace: NamespaceId,
     pub program: &'a Program,
     pub target: CodegenTarget,
+    pub loops: Vec<LoopContext>,
+    pub loop_count: usize,
+    pub tries: Vec<TryContext>,
+    pub try_count: usize,
+    // Statements releasing what the enclosing blocks hold, innermost last
+    pub cleanup: Vec<String>,
 }
 
 impl<'a> CodegenContext<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Self {
+            namespace: 0,
+            program,
+            target: CodegenTarget::default(),
+            loops: vec![],
+            loop_count: 0,
+            tries: vec![],
+            try_count: 0,
+            cleanup: vec![],
+        }
+    }
+
+    pub fn enter_loop(&mut self, loop_t: &Loop) -> &LoopContext {
+        self.loops.push(LoopContext {
+            id: self.loop_count,
+            labeled: loop_t.post_condition.is_some()
+                && loop_t.body.contains_loop_control(&Term::Continue),
+            cleanup: self.cleanup.len(),
+            tries: self.tries.len(),
+        });
+        self.loop_count += 1;
+        self.loops.last().expect("Loop was just entered")
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loops.pop();
+    }
+
+    pub fn current_loop(&self) -> CodegenResultG<&LoopContext> {
+        self.loops
+            .last()
+            .ok_or_else(|| "break and continue must be inside a loop".into())
+    }
+
+    pub fn enter_try(&mut self) -> usize {
+        self.tries.push(TryContext {
+            id: self.try_count,
+            cleanup: self.cleanup.len(),
+            ..TryContext::default()
+        });
+        self.try_count += 1;
+        self.try_count - 1
+    }
+
+    pub fn exit_try(&mut self) -> TryContext {
+        self.tries.pop().expect("Try was entered")
+    }
+
+    // Names like the record getter `point.x` are escaped into valid identifiers
     pub fn scoped_name(namespace: NamespaceId, v: &'a str) -> Cow<'a, str> {
-        Cow::Owned(format!("user_fn_{}_{}", namespace, v))
+        let mut name = format!("user_fn_{}_", namespace);
+        for c in v.chars() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+            } else {
+                write!(name, "_{:x}_", u32::from(c)).expect("Codegen");
+            }
+        }
+        Cow::Owned(name)
     }
 
     pub fn get_scoped_name(&self, v: &'a str) -> Cow<'a, str> {
         Self::scoped_name(self.namespace, v)
     }
 
+    pub fn local_name(local: &Local) -> String {
+        format!("local_{}", local.slot)
+    }
+
+    // Along with the namespace that declares it, which tells it apart from records of the same name
+    pub fn resolve_variant(&self, v: &str) -> CodegenResultG<(NamespaceId, &'a Record)> {
+        let variant = self.program.resolve_variant(self.namespace, v);
+        match (self.program.resolve_function(self.namespace, v), variant) {
+            (Some((namespace, _)), Some(variant)) => Ok((namespace, variant)),
+            _ => Err(format!("Unable to resolve variant: {v}").into()),
+        }
+    }
+
     pub fn resolve_name(&self, v: &'a str) -> CodegenResultG<Cow<'a, str>> {
         if let Some(codegen_name) = get_intrinsic_codegen_name(v) {
             Ok(Cow::Borrowed(codegen_name))
@@ -1,11 +1,67 @@
 /* eslint-disable camelcase, @typescript-eslint/no-unused-vars */
 const STATE = {
   values: [],
+  randomState: BigInt(Date.now()) * 1000000n,
+  start: performance.now(),
 }
 
-const STACK_UNDERFLOW = () => new Error('STACK_UNDERFLOW')
-const TYPE_MISMATCH = () => new Error('TYPE_MISMATCH')
-const ASSERT_FAILED = () => new Error('ASSERT_FAILED')
+// A built-in error, which a handler receives as the description of its kind
+class RuntimeError extends Error {
+  constructor(kind) {
+    super(kind)
+    this.kind = kind
+  }
+}
+
+const STACK_UNDERFLOW = () => new RuntimeError('STACK_UNDERFLOW')
+const TYPE_MISMATCH = () => new RuntimeError('TYPE_MISMATCH')
+const INVALID_EXIT_CODE = () => new RuntimeError('INVALID_EXIT_CODE')
+const INVALID_FORMAT = () => new RuntimeError('INVALID_FORMAT')
+const INVALID_ARGUMENT = () => new RuntimeError('INVALID_ARGUMENT')
+const NO_MATCH = () => new RuntimeError('NO_MATCH')
+const INTEGER_OVERFLOW = () => new RuntimeError('INTEGER_OVERFLOW')
+const DIVISION_BY_ZERO = () => new RuntimeError('DIVISION_BY_ZERO')
+
+class ExitRequest {
+  constructor(code) {
+    this.code = code
+  }
+}
+
+class Thrown extends Error {
+  constructor(value) {
+    super(`Uncaught throw: ${value}`)
+    this.value = value
+  }
+}
+
+const ERROR_DESCRIPTIONS = {
+  STACK_UNDERFLOW: 'Stack empty',
+  TYPE_MISMATCH: 'Type mismatch',
+  INVALID_EXIT_CODE: 'Invalid exit code',
+  INVALID_FORMAT: 'Invalid format',
+  INVALID_ARGUMENT: 'Invalid argument',
+  NO_MATCH: 'No match arm',
+  INTEGER_OVERFLOW: 'Integer overflow',
+  DIVISION_BY_ZERO: 'Division by zero',
+}
+
+class AssertionFailed extends Error {}
+
+// Thrown values and failed assertions reach the handler unchanged, built-in errors as their
+// description. Exiting and failures of the runtime itself are never caught
+function caught(err) {
+  if (err instanceof Thrown) {
+    return err.value
+  }
+  if (err instanceof AssertionFailed) {
+    return err.message
+  }
+  if (err instanceof RuntimeError) {
+    return ERROR_DESCRIPTIONS[err.kind]
+  }
+  throw err
+}
 
 function assertStackHas(x) {
   if (STATE.values.length < x) {
@@ -21,9 +77,128 @@ function storeStack(offset, v) {
   STATE.values[STATE.values.length + offset] = v
 }
 
+// Records share their field names with every other record of the same type, which is told apart
+// by the namespace that declares it as well as by name
+class RecordValue {
+  constructor(namespace, name, fields, values) {
+    this.namespace = namespace
+    this.name = name
+    this.fields = fields
+    this.values = values
+  }
+
+  is(namespace, name) {
+    return this.namespace === namespace && this.name === name
+  }
+
+  // Written like `point { x: 1, y: "a" }`
+  toString() {
+    if (this.fields.length === 0) {
+      return `${this.name} {}`
+    }
+    const fields = this.fields.map((field, i) => {
+      const v = primitive(this.values[i])
+      if (typeof v === 'string') {
+        return `${field}: ${JSON.stringify(v)}`
+      }
+      return `${field}: ${typeof v === 'number' ? numberToString(v) : v}`
+    })
+    return `${this.name} { ${fields.join(', ')} }`
+  }
+
+  [Symbol.for('nodejs.util.inspect.custom')]() {
+    return this.toString()
+  }
+}
+
+// Integers are already BigInts, so bignums wrap one to tell them apart
+class Big {
+  constructor(value) {
+    this.value = value
+  }
+
+  toString() {
+    return `${this.value}`
+  }
+
+  [Symbol.for('nodejs.util.inspect.custom')]() {
+    return this.toString()
+  }
+}
+
+// Exact decimals are `unscaled / 10^scale`, keeping the scale so that `1.50d` prints both places
+class Decimal {
+  constructor(unscaled, scale) {
+    this.unscaled = unscaled
+    this.scale = scale
+  }
+
+  toString() {
+    const sign = this.unscaled < 0n ? '-' : ''
+    const digits = `${this.unscaled < 0n ? -this.unscaled : this.unscaled}`
+    if (this.scale === 0) {
+      return sign + digits
+    }
+    const padded = digits.padStart(this.scale + 1, '0')
+    return `${sign}${padded.slice(0, -this.scale)}.${padded.slice(-this.scale)}`
+  }
+
+  [Symbol.for('nodejs.util.inspect.custom')]() {
+    return this.toString()
+  }
+}
+
+// Quotients that don't end sooner are truncated after this many places
+const DECIMAL_DIVISION_SCALE = 32
+// Values needing more places than this are refused rather than exhausting memory
+const DECIMAL_MAX_SCALE = 1 << 16
+
+// Decimal digits with an optional leading `-` and fraction, like `-1.25`
+function parseDecimal(text) {
+  const [whole, fraction = ''] = text.split('.')
+  return new Decimal(BigInt(whole + fraction), fraction.length)
+}
+
+function toDecimal(v) {
+  return v instanceof Decimal ? v : new Decimal(exactValue(v), 0)
+}
+
+// Both unscaled values at the larger of the two scales, and that scale
+function alignDecimals(first, second) {
+  const scale = Math.max(first.scale, second.scale)
+  return [
+    first.unscaled * 10n ** BigInt(scale - first.scale),
+    second.unscaled * 10n ** BigInt(scale - second.scale),
+    scale,
+  ]
+}
+
+// Drops trailing zeros past `scale` places
+function trimDecimal(v, scale) {
+  let { unscaled, scale: current } = v
+  while (current > scale && unscaled % 10n === 0n) {
+    unscaled /= 10n
+    current -= 1
+  }
+  return new Decimal(unscaled, current)
+}
+
+function truncDecimal(v) {
+  return v.unscaled / 10n ** BigInt(v.scale)
+}
+
+function isTruthy(v) {
+  if (v instanceof Decimal) {
+    return v.unscaled !== 0n
+  }
+  return v instanceof Big ? v.value !== 0n : !!v
+}
+
 function readStackPrimitive(offset) {
-  const v = STATE.values[STATE.values.length + offset]
+  return primitive(STATE.values[STATE.values.length + offset])
+}
 
+function primitive(v) {
   if (typeof v === 'string') {
     return v
   }
@@ -38,14 +213,75 @@ function readStackPrimitive(offset) {
   return v
 }
 
+// Integers are BigInts, promoted wherever a number is expected along with bignums
 function readStackNumber(offset) {
   const v = readStack(offset)
+  if (typeof v === 'bigint') {
+    return Number(v)
+  }
+  if (v instanceof Big) {
+    return Number(v.value)
+  }
+  if (v instanceof Decimal) {
+    return Number(`${v}`)
+  }
   if (typeof v !== 'number') {
     throw TYPE_MISMATCH()
   }
   return v
 }
 
+function readStackInteger(offset) {
+  const v = readStack(offset)
+  if (typeof v !== 'bigint') {
+    throw TYPE_MISMATCH()
+  }
+  return v
+}
+
+function isExact(v) {
+  return typeof v === 'bigint' || v instanceof Big || v instanceof Decimal
+}
+
+function exactValue(v) {
+  return v instanceof Big ? v.value : v
+}
+
+// Arithmetic stays exact when both operands are integers, bignums or decimals, with each promoted
+// to the wider of the two when they are mixed. Anything else is promoted to floats. The third
+// item converts a result back to the operands' type. Decimals are given as their unscaled values
+// at a shared scale, followed by the decimals themselves
+function readOperands() {
+  assertStackHas(2)
+  const first = readStack(-2)
+  const second = readStack(-1)
+  if (typeof first === 'bigint' && typeof second === 'bigint') {
+    return [first, second, checkedInteger]
+  }
+  if (first instanceof Decimal || second instanceof Decimal) {
+    if (isExact(first) && isExact(second)) {
+      const decimals = [toDecimal(first), toDecimal(second)]
+      const [a, b, scale] = alignDecimals(...decimals)
+      return [a, b, (v) => new Decimal(v, scale), decimals]
+    }
+  } else if (isExact(first) && isExact(second)) {
+    return [exactValue(first), exactValue(second), (v) => new Big(v)]
+  }
+  return [readStackNumber(-2), readStackNumber(-1), (v) => v]
+}
+
+function checkedInteger(v) {
+  if (BigInt.asIntN(64, v) !== v) {
+    throw INTEGER_OVERFLOW()
+  }
+  return v
+}
+
+function replaceOperands(v) {
+  storeStack(-2, v)
+  drop()
+}
+
 function readStackString(offset) {
   const v = readStack(offset)
 
@@ -85,6 +321,11 @@ function push(v) {
   STATE.values.push(v)
 }
 
+function take() {
+  assertStackHas(1)
+  return STATE.values.pop()
+}
+
 function swap() {
   assertStackHas(2)
   const temp = readStack(-2)
@@ -93,6 +334,7 @@ function swap() {
 }
 
 function drop() {
+  assertStackHas(1)
   STATE.values.pop()
 }
 
@@ -103,58 +345,230 @@ function join() {
 }
 
 function plus() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) + readStackNumber(-1))
-  drop()
+  const [first, second, exact] = readOperands()
+  replaceOperands(exact(first + second))
 }
 
 function minus() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) - readStackNumber(-1))
-  drop()
+  const [first, second, exact] = readOperands()
+  replaceOperands(exact(first - second))
 }
 
 function greater() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) > readStackNumber(-1))
-  drop()
+  const [first, second] = readOperands()
+  replaceOperands(first > second)
 }
 
 function less() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) < readStackNumber(-1))
-  drop()
+  const [first, second] = readOperands()
+  replaceOperands(first < second)
 }
+// Decimals keep the places of both sides
 function times() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) * readStackNumber(-1))
-  drop()
+  const [first, second, exact, decimals] = readOperands()
+  if (decimals) {
+    const [a, b] = decimals
+    replaceOperands(new Decimal(a.unscaled * b.unscaled, a.scale + b.scale))
+    return
+  }
+  replaceOperands(exact(first * second))
 }
+// BigInt division truncates towards zero, like the other backends. Decimal quotients are exact
+// when they end within DECIMAL_DIVISION_SCALE places or the operands' own
 function divide() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) / readStackNumber(-1))
-  drop()
+  const [first, second, exact, decimals] = readOperands()
+  if (second === 0n) {
+    throw DIVISION_BY_ZERO()
+  }
+  if (decimals) {
+    const kept = Math.max(decimals[0].scale, decimals[1].scale)
+    const scale = Math.max(kept, DECIMAL_DIVISION_SCALE)
+    const quotient = (first * 10n ** BigInt(scale)) / second
+    replaceOperands(trimDecimal(new Decimal(quotient, scale), kept))
+    return
+  }
+  replaceOperands(exact(first / second))
 }
 function modulo() {
-  assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) % readStackNumber(-1))
-  drop()
+  const [first, second, exact] = readOperands()
+  if (second === 0n) {
+    throw DIVISION_BY_ZERO()
+  }
+  replaceOperands(exact(first % second))
 }
+// Bignum powers needing more bits than this are refused rather than exhausting memory
+const BIG_MAX_POW_BITS = 1n << 24n
 function pow_i() {
+  const [first, second, exact, decimals] = readOperands()
+  if (decimals) {
+    powDecimal(...decimals)
+    return
+  }
+  if (typeof second === 'bigint') {
+    if (second < 0n) {
+      throw INVALID_ARGUMENT()
+    }
+    const magnitude = first < 0n ? -first : first
+    const bits = BigInt(magnitude.toString(2).length)
+    // Larger powers of anything but -1, 0 and 1 overflow, and would take too long to compute
+    if (magnitude > 1n && exact === checkedInteger && (bits - 1n) * second >= 64n) {
+      throw INTEGER_OVERFLOW()
+    }
+    if (magnitude > 1n && bits * second > BIG_MAX_POW_BITS) {
+      throw INTEGER_OVERFLOW()
+    }
+  }
+  replaceOperands(exact(first ** second))
+}
+
+function powDecimal(base, exponent) {
+  if (exponent.unscaled < 0n) {
+    throw INVALID_ARGUMENT()
+  }
+  const power = truncDecimal(exponent)
+  if (power * 10n ** BigInt(exponent.scale) !== exponent.unscaled) {
+    throw INVALID_ARGUMENT()
+  }
+  const scale = BigInt(base.scale) * power
+  const magnitude = base.unscaled < 0n ? -base.unscaled : base.unscaled
+  const bits = BigInt(magnitude.toString(2).length)
+  if (scale > BigInt(DECIMAL_MAX_SCALE) || (magnitude > 1n && bits * power > BIG_MAX_POW_BITS)) {
+    throw INTEGER_OVERFLOW()
+  }
+  replaceOperands(new Decimal(base.unscaled ** power, Number(scale)))
+}
+
+function and_i() {
   assertStackHas(2)
-  storeStack(-2, readStackNumber(-2) ** readStackNumber(-1))
+  storeStack(-2, isTruthy(readStack(-2)) ? readStack(-1) : readStack(-2))
   drop()
 }
 
-function and_i() {
+function floor_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.floor(readStackNumber(-1)))
+}
+
+function ceil_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.ceil(readStackNumber(-1)))
+}
+
+// Math.round rounds halfway cases up, the other backends round away from zero
+function round_i() {
+  assertStackHas(1)
+  const v = readStackNumber(-1)
+  storeStack(-1, Math.sign(v) * Math.round(Math.abs(v)))
+}
+
+function trunc_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.trunc(readStackNumber(-1)))
+}
+
+function sqrt_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.sqrt(readStackNumber(-1)))
+}
+
+function sin_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.sin(readStackNumber(-1)))
+}
+
+function cos_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.cos(readStackNumber(-1)))
+}
+
+function tan_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.tan(readStackNumber(-1)))
+}
+
+function asin_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.asin(readStackNumber(-1)))
+}
+
+function acos_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.acos(readStackNumber(-1)))
+}
+
+function atan_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.atan(readStackNumber(-1)))
+}
+
+function exp_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.exp(readStackNumber(-1)))
+}
+
+function log_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.log(readStackNumber(-1)))
+}
+
+function log10_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.log10(readStackNumber(-1)))
+}
+
+function log2_i() {
+  assertStackHas(1)
+  storeStack(-1, Math.log2(readStackNumber(-1)))
+}
+
+function abs_i() {
+  assertStackHas(1)
+  const v = readStack(-1)
+  if (typeof v === 'bigint') {
+    storeStack(-1, checkedInteger(v < 0n ? -v : v))
+    return
+  }
+  if (v instanceof Big) {
+    storeStack(-1, new Big(v.value < 0n ? -v.value : v.value))
+    return
+  }
+  if (v instanceof Decimal) {
+    storeStack(-1, new Decimal(v.unscaled < 0n ? -v.unscaled : v.unscaled, v.scale))
+    return
+  }
+  storeStack(-1, Math.abs(readStackNumber(-1)))
+}
+
+function atan2_i() {
   assertStackHas(2)
-  storeStack(-2, readStack(-2) && readStack(-1))
+  const first = readStackNumber(-2)
+  const second = readStackNumber(-1)
+  storeStack(-2, Math.atan2(first, second))
   drop()
 }
 
+// Math.min and Math.max return NaN when either side is NaN, the other backends ignore it.
+// Decimals are returned as they were, keeping their scale, with min keeping the first of two
+// equal ones and max the second like the interpreter
+function min() {
+  const [first, second, exact, decimals] = readOperands()
+  const pick = Number.isNaN(first) || second < first ? 1 : 0
+  replaceOperands(decimals ? decimals[pick] : exact([first, second][pick]))
+}
+
+function max() {
+  const [first, second, exact, decimals] = readOperands()
+  const pick = Number.isNaN(first) || second > first || (decimals && second === first) ? 1 : 0
+  replaceOperands(decimals ? decimals[pick] : exact([first, second][pick]))
+}
+
+function pi() {
+  push(Math.PI)
+}
+
 function or_i() {
   assertStackHas(2)
-  storeStack(-2, readStack(-2) || readStack(-1))
+  storeStack(-2, isTruthy(readStack(-2)) ? readStack(-2) : readStack(-1))
   drop()
 }
 
@@ -168,11 +582,130 @@ function rot() {
 
 function increment() {
   assertStackHas(1)
-  storeStack(-1, readStackNumber(-1) + 1)
+  const v = readStack(-1)
+  if (v instanceof Big) {
+    storeStack(-1, new Big(v.value + 1n))
+    return
+  }
+  if (v instanceof Decimal) {
+    storeStack(-1, new Decimal(v.unscaled + 10n ** BigInt(v.scale), v.scale))
+    return
+  }
+  storeStack(-1, typeof v === 'bigint' ? checkedInteger(v + 1n) : readStackNumber(-1) + 1)
 }
 function decrement() {
   assertStackHas(1)
-  storeStack(-1, readStackNumber(-1) - 1)
+  const v = readStack(-1)
+  if (v instanceof Big) {
+    storeStack(-1, new Big(v.value - 1n))
+    return
+  }
+  if (v instanceof Decimal) {
+    storeStack(-1, new Decimal(v.unscaled - 10n ** BigInt(v.scale), v.scale))
+    return
+  }
+  storeStack(-1, typeof v === 'bigint' ? checkedInteger(v - 1n) : readStackNumber(-1) - 1)
+}
+
+function band() {
+  assertStackHas(2)
+  storeStack(-2, readStackInteger(-2) & readStackInteger(-1))
+  drop()
+}
+
+function bor() {
+  assertStackHas(2)
+  storeStack(-2, readStackInteger(-2) | readStackInteger(-1))
+  drop()
+}
+
+function bxor() {
+  assertStackHas(2)
+  storeStack(-2, readStackInteger(-2) ^ readStackInteger(-1))
+  drop()
+}
+
+function readShift() {
+  assertStackHas(2)
+  const first = readStackInteger(-2)
+  const second = readStackInteger(-1)
+  if (second < 0n || second > 63n) {
+    throw INVALID_ARGUMENT()
+  }
+  return [first, second]
+}
+
+// Bits shifted past either end are lost, shifting right keeps the sign
+function shl() {
+  const [first, second] = readShift()
+  storeStack(-2, BigInt.asIntN(64, first << second))
+  drop()
+}
+
+function shr() {
+  const [first, second] = readShift()
+  storeStack(-2, first >> second)
+  drop()
+}
+
+// Floats and decimals are truncated, and must fit in an integer like bignums
+function to_int() {
+  assertStackHas(1)
+  const top = readStack(-1)
+  if (typeof top === 'bigint') {
+    return
+  }
+  if (top instanceof Big || top instanceof Decimal) {
+    const v = top instanceof Big ? top.value : truncDecimal(top)
+    if (BigInt.asIntN(64, v) !== v) {
+      throw INVALID_ARGUMENT()
+    }
+    storeStack(-1, v)
+    return
+  }
+  const v = Math.trunc(readStackNumber(-1))
+  if (!(v >= -(2 ** 63) && v < 2 ** 63)) {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-1, BigInt(v))
+}
+
+function to_float() {
+  assertStackHas(1)
+  storeStack(-1, readStackNumber(-1))
+}
+
+function to_big() {
+  assertStackHas(1)
+  const v = readStack(-1)
+  if (v instanceof Decimal) {
+    storeStack(-1, new Big(truncDecimal(v)))
+    return
+  }
+  if (isExact(v)) {
+    storeStack(-1, new Big(exactValue(v)))
+    return
+  }
+  const n = readStackNumber(-1)
+  if (!Number.isFinite(n)) {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-1, new Big(BigInt(Math.trunc(n))))
+}
+
+// Numbers keep the shortest digits that read back as them, so `0.1 to_dec` is `0.1d`
+function to_dec() {
+  assertStackHas(1)
+  const v = readStack(-1)
+  if (isExact(v)) {
+    storeStack(-1, toDecimal(v))
+    return
+  }
+  const n = readStackNumber(-1)
+  if (!Number.isFinite(n)) {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-1, parseDecimal(numberToString(n)))
 }
 
 function substring() {
@@ -195,14 +728,48 @@ function over() {
   storeStack(0, readStack(-2))
 }
 
+function isNumeric(v) {
+  return typeof v === 'number' || isExact(v)
+}
+
+function valuesEqual(left, right) {
+  if (left instanceof RecordValue && right instanceof RecordValue) {
+    return (
+      left.is(right.namespace, right.name) &&
+      left.values.every((v, i) =>
+        valuesEqual(primitive(v), primitive(right.values[i])),
+      )
+    )
+  }
+  // Bignums equal the integers they hold, decimals any exact value they match, and all of them
+  // the floats they promote to
+  if (left instanceof Decimal || right instanceof Decimal) {
+    if (typeof left === 'number' || typeof right === 'number') {
+      return Number(`${left}`) === Number(`${right}`)
+    }
+    const [a, b] = alignDecimals(toDecimal(left), toDecimal(right))
+    return a === b
+  }
+  const first = exactValue(left)
+  const second = exactValue(right)
+  if (typeof first === 'bigint' && typeof second === 'number') {
+    return Number(first) === second
+  }
+  if (typeof first === 'number' && typeof second === 'bigint') {
+    return first === Number(second)
+  }
+  return first === second
+}
+
 function equals() {
   assertStackHas(2)
   const left = readStackPrimitive(-2)
   const right = readStackPrimitive(-1)
-  if (typeof left !== typeof right) {
+  const sameKind = isNumeric(left) ? isNumeric(right) : typeof left === typeof right && !isNumeric(right)
+  if (!sameKind) {
     throw TYPE_MISMATCH()
   }
-  storeStack(-2, left === right)
+  storeStack(-2, valuesEqual(left, right))
   drop()
 }
 
@@ -214,7 +781,31 @@ function dup() {
 function print() {
   assertStackHas(1)
   // eslint-disable-next-line no-console
-  console.log(readStackPrimitive(-1))
+  console.log(printable(readStackPrimitive(-1)))
+  drop()
+}
+
+function eprint() {
+  assertStackHas(1)
+  // eslint-disable-next-line no-console
+  console.error(printable(readStackPrimitive(-1)))
+  drop()
+}
+
+// Logging a BigInt would add its `n` suffix
+function printable(v) {
+  return isExact(v) ? `${v}` : v
+}
+
+function write_i() {
+  assertStackHas(1)
+  const value = `${readStackPrimitive(-1)}`
+  if (typeof process !== 'undefined') {
+    process.stdout.write(value)
+  } else {
+    // eslint-disable-next-line no-console
+    console.log(value)
+  }
   drop()
 }
 
@@ -222,10 +813,8 @@ function assert() {
   assertStackHas(2)
   const value = readStack(-2)
   const message = readStackString(-1)
-  if (!value) {
-    // eslint-disable-next-line no-console
-    console.error('Assertion failed: ', message)
-    throw ASSERT_FAILED()
+  if (!isTruthy(value)) {
+    throw new AssertionFailed(`Assertion failed: ${message}`)
   }
   drop()
   drop()
@@ -233,12 +822,12 @@ function assert() {
 
 function not() {
   assertStackHas(1)
-  storeStack(-1, !readStack(-1))
+  storeStack(-1, !isTruthy(readStack(-1)))
 }
 
 function checkCondition() {
   assertStackHas(1)
-  return !!STATE.values.pop()
+  return isTruthy(STATE.values.pop())
 }
 
 function printStack() {
@@ -252,7 +841,7 @@ function printStack() {
 function to_char() {
   assertStackHas(1)
   const top = readStackChars(-1)
-  if (readStackChars.length !== 1) {
+  if (top.length !== 1) {
     throw TYPE_MISMATCH()
   }
   storeStack(-1, top[0].codePointAt(0))
@@ -260,7 +849,12 @@ function to_char() {
 
 function from_char() {
   assertStackHas(1)
-  storeStack(-1, String.fromCodePoint(readStackNumber(-1)))
+  const code = readStackNumber(-1)
+  const surrogate = code >= 0xd800 && code <= 0xdfff
+  if (!Number.isInteger(code) || code < 0 || code > 0x10ffff || surrogate) {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-1, String.fromCodePoint(code))
 }
 
 function string_index() {
@@ -277,6 +871,175 @@ function string_index() {
   drop()
 }
 
+function last_index() {
+  assertStackHas(2)
+  const needle = readStackString(-1)
+  const haystack = readStackString(-2)
+  const u16_index = haystack.lastIndexOf(needle)
+  if (u16_index === -1) {
+    storeStack(-2, u16_index)
+  } else {
+    const char_index = Array.from(haystack.substring(0, u16_index)).length
+    storeStack(-2, char_index)
+  }
+  drop()
+}
+
+function starts_with() {
+  assertStackHas(2)
+  storeStack(-2, readStackString(-2).startsWith(readStackString(-1)))
+  drop()
+}
+
+function ends_with() {
+  assertStackHas(2)
+  storeStack(-2, readStackString(-2).endsWith(readStackString(-1)))
+  drop()
+}
+
+function split() {
+  assertStackHas(2)
+  const separator = readStackString(-1)
+  const pieces =
+    separator === '' ? readStackChars(-2) : readStackString(-2).split(separator)
+  drop()
+  drop()
+  pieces.forEach(push)
+  push(pieces.length)
+}
+
+function replace() {
+  assertStackHas(3)
+  const to = readStackString(-1)
+  const from = readStackString(-2)
+  if (from === '') {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-3, readStackString(-3).split(from).join(to))
+  drop()
+  drop()
+}
+
+function trim() {
+  assertStackHas(1)
+  storeStack(-1, readStackString(-1).trim())
+}
+
+function to_upper() {
+  assertStackHas(1)
+  storeStack(-1, readStackString(-1).toUpperCase())
+}
+
+function to_lower() {
+  assertStackHas(1)
+  storeStack(-1, readStackString(-1).toLowerCase())
+}
+
+function repeat() {
+  assertStackHas(2)
+  const chars = readStackChars(-2)
+  const count = readStackNumber(-1)
+  if (!Number.isSafeInteger(count) || count < 0 || chars.length * count > 0x7fffffff) {
+    throw INVALID_ARGUMENT()
+  }
+  storeStack(-2, readStackString(-2).repeat(count))
+  drop()
+}
+
+function reverse() {
+  assertStackHas(1)
+  storeStack(-1, ['chars', [...readStackChars(-1)].reverse(), undefined])
+}
+
+const REGEX_ESCAPES = 'dDwWsSntrfv^$\\.*+?()[]{}|/'
+const REGEX_MAX_REPEAT = 1000
+
+// RegExp accepts more than the supported dialect, so reject lookarounds, named
+// groups, backreferences and other escapes before handing the pattern over
+function readStackRegex(offset, flags) {
+  const pattern = readStackString(offset)
+  let inClass = false
+  for (let i = 0; i < pattern.length; i++) {
+    const c = pattern[i]
+    if (c === '\\') {
+      i++
+      if (!REGEX_ESCAPES.includes(pattern[i] ?? '_') && !(inClass && pattern[i] === '-')) {
+        throw INVALID_ARGUMENT()
+      }
+    } else if (inClass) {
+      inClass = c !== ']'
+    } else if (c === '[') {
+      inClass = true
+    } else if (c === '(' && pattern[i + 1] === '?' && pattern[i + 2] !== ':') {
+      throw INVALID_ARGUMENT()
+    } else if (c === '{') {
+      const counts = /^\{(\d*)(?:,(\d*))?\}/.exec(pattern.slice(i)) ?? []
+      if (counts.slice(1).some((e) => Number(e ?? 0) > REGEX_MAX_REPEAT)) {
+        throw INVALID_ARGUMENT()
+      }
+    }
+  }
+  try {
+    return new RegExp(pattern, `su${flags}`)
+  } catch {
+    throw INVALID_ARGUMENT()
+  }
+}
+
+function charIndex(s, u16_index) {
+  return Array.from(s.substring(0, u16_index)).length
+}
+
+function matches() {
+  assertStackHas(2)
+  const regex = readStackRegex(-1, '')
+  storeStack(-2, regex.test(readStackString(-2)))
+  drop()
+}
+
+function find_match() {
+  assertStackHas(2)
+  const regex = readStackRegex(-1, '')
+  const s = readStackString(-2)
+  const found = regex.exec(s)
+  drop()
+  storeStack(-1, found === null ? '' : found[0])
+  push(found === null ? -1 : charIndex(s, found.index))
+}
+
+function replace_regex() {
+  assertStackHas(3)
+  const replacement = readStackString(-1)
+  const regex = readStackRegex(-2, 'g')
+  const s = readStackString(-3)
+  const result = s.replace(regex, (...found) => {
+    const groups = found.slice(0, -2)
+    return replacement.replace(/\$(\d)|\$\$/g, (token, group) => {
+      if (group === undefined) {
+        return '$'
+      }
+      return Number(group) < groups.length ? (groups[group] ?? '') : token
+    })
+  })
+  storeStack(-3, result)
+  drop()
+  drop()
+}
+
+function captures() {
+  assertStackHas(2)
+  const regex = readStackRegex(-1, '')
+  const found = regex.exec(readStackString(-2))
+  drop()
+  drop()
+  if (found === null) {
+    push(0)
+    return
+  }
+  found.forEach((e) => push(e ?? ''))
+  push(found.length)
+}
+
 function eval_i() {
   assertStackHas(1)
   const fn = readStack(-1)
@@ -329,3 +1092,391 @@ function readline() {
     push(true)
   }
 }
+
+function fs_node() {
+  if (typeof require === 'undefined') {
+    return null
+  }
+  // eslint-disable-next-line global-require
+  return require('node:fs')
+}
+
+function read_file() {
+  assertStackHas(1)
+  const path = readStackString(-1)
+  drop()
+  let contents = null
+  try {
+    contents = fs_node().readFileSync(path, 'utf8')
+  } catch {
+    contents = null
+  }
+  push(contents === null ? '' : contents)
+  push(contents !== null)
+}
+
+function write_file() {
+  assertStackHas(2)
+  const path = readStackString(-2)
+  const contents = readStackString(-1)
+  drop()
+  drop()
+  let ok = true
+  try {
+    fs_node().writeFileSync(path, contents)
+  } catch {
+    ok = false
+  }
+  push(ok)
+}
+
+function append_file() {
+  assertStackHas(2)
+  const path = readStackString(-2)
+  const contents = readStackString(-1)
+  drop()
+  drop()
+  let ok = true
+  try {
+    fs_node().appendFileSync(path, contents)
+  } catch {
+    ok = false
+  }
+  push(ok)
+}
+
+function file_exists() {
+  assertStackHas(1)
+  const path = readStackString(-1)
+  const fs = fs_node()
+  storeStack(-1, fs !== null && fs.existsSync(path))
+}
+
+function list_dir() {
+  assertStackHas(1)
+  const path = readStackString(-1)
+  drop()
+  let entries = null
+  try {
+    entries = fs_node().readdirSync(path).sort()
+  } catch {
+    entries = null
+  }
+  push(entries === null ? '' : entries.join('\n'))
+  push(entries !== null)
+}
+
+function script_args() {
+  return typeof process === 'undefined' ? [] : process.argv.slice(2)
+}
+
+function arg_count() {
+  push(script_args().length)
+}
+
+function arg() {
+  assertStackHas(1)
+  const index = readStackNumber(-1)
+  drop()
+  const args = script_args()
+  const found = Number.isInteger(index) && index >= 0 && index < args.length
+  push(found ? args[index] : '')
+  push(found)
+}
+
+function env() {
+  assertStackHas(1)
+  const name = readStackString(-1)
+  drop()
+  const value = typeof process === 'undefined' ? undefined : process.env[name]
+  push(value === undefined ? '' : value)
+  push(value !== undefined)
+}
+
+// The field values move from the stack into the new record
+function construct_record(namespace, name, fields) {
+  assertStackHas(fields.length)
+  const values = STATE.values.splice(STATE.values.length - fields.length)
+  push(new RecordValue(namespace, name, fields, values))
+}
+
+function readStackRecord(offset, namespace, name) {
+  const v = readStack(offset)
+  if (!(v instanceof RecordValue) || !v.is(namespace, name)) {
+    throw TYPE_MISMATCH()
+  }
+  return v
+}
+
+function record_get(namespace, name, field) {
+  assertStackHas(1)
+  storeStack(-1, readStackRecord(-1, namespace, name).values[field])
+}
+
+function record_with(namespace, name, field) {
+  assertStackHas(2)
+  const record = readStackRecord(-2, namespace, name)
+  const values = record.values.slice()
+  values[field] = take()
+  storeStack(-1, new RecordValue(namespace, name, record.fields, values))
+}
+
+// Replaces the variant on top with its fields when it is the named one
+function matchVariant(namespace, name) {
+  assertStackHas(1)
+  const v = readStack(-1)
+  if (!(v instanceof RecordValue) || !v.is(namespace, name)) {
+    return false
+  }
+  STATE.values.pop()
+  STATE.values.push(...v.values)
+  return true
+}
+
+// A variant of the matched enum without an arm, or a value that isn't one of its variants
+function noMatchArm(namespace, variants) {
+  assertStackHas(1)
+  const v = readStack(-1)
+  if (
+    v instanceof RecordValue &&
+    variants.some((variant) => v.is(namespace, variant))
+  ) {
+    throw NO_MATCH()
+  }
+  throw TYPE_MISMATCH()
+}
+
+function throw_i() {
+  throw new Thrown(take())
+}
+
+function exit_i() {
+  assertStackHas(1)
+  const code = readStackNumber(-1)
+  if (!Number.isInteger(code) || code < 0 || code > 2147483647) {
+    throw INVALID_EXIT_CODE()
+  }
+  drop()
+  throw new ExitRequest(code)
+}
+
+function splitFormatTemplate(template) {
+  const pieces = ['']
+  for (let i = 0; i < template.length; i++) {
+    const c = template[i]
+    const next = template[i + 1]
+    if ((c === '{' && next === '{') || (c === '}' && next === '}')) {
+      pieces[pieces.length - 1] += c
+      i++
+    } else if (c === '{' && next === '}') {
+      pieces.push('')
+      i++
+    } else if (c === '{' || c === '}') {
+      return null
+    } else {
+      pieces[pieces.length - 1] += c
+    }
+  }
+  return pieces
+}
+
+function format() {
+  assertStackHas(1)
+  const pieces = splitFormatTemplate(readStackString(-1))
+  if (pieces === null) {
+    throw INVALID_FORMAT()
+  }
+  const count = pieces.length - 1
+  assertStackHas(count + 1)
+  let result = pieces[0]
+  for (let i = 0; i < count; i++) {
+    result += `${readStackPrimitive(i - count - 1)}${pieces[i + 1]}`
+  }
+  for (let i = 0; i <= count; i++) {
+    drop()
+  }
+  push(result)
+}
+
+function parse_number() {
+  assertStackHas(1)
+  const text = readStackString(-1)
+  const ok = /^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$/.test(text)
+  storeStack(-1, ok ? Number(text) : 0)
+  push(ok)
+}
+
+// Shortest digits that round trip, written without an exponent
+function numberToString(v) {
+  if (Number.isNaN(v)) {
+    return 'NaN'
+  }
+  if (!Number.isFinite(v)) {
+    return v > 0 ? 'inf' : '-inf'
+  }
+  const [mantissa, exponentText] = Math.abs(v).toExponential().split('e')
+  const digits = mantissa.replace('.', '')
+  const exponent = Number(exponentText)
+  let text
+  if (exponent < 0) {
+    text = `0.${'0'.repeat(-exponent - 1)}${digits}`
+  } else if (digits.length <= exponent + 1) {
+    text = digits + '0'.repeat(exponent + 1 - digits.length)
+  } else {
+    text = `${digits.slice(0, exponent + 1)}.${digits.slice(exponent + 1)}`
+  }
+  return v < 0 || Object.is(v, -0) ? `-${text}` : text
+}
+
+function to_string() {
+  assertStackHas(1)
+  const v = readStackPrimitive(-1)
+  storeStack(-1, typeof v === 'number' ? numberToString(v) : `${v}`)
+}
+
+// Rounds the exact binary value half to even, matching printf and Rust
+function numberToFixed(v, digits) {
+  if (!Number.isFinite(v)) {
+    return numberToString(v)
+  }
+  const view = new DataView(new ArrayBuffer(8))
+  view.setFloat64(0, v)
+  const bits = view.getBigUint64(0)
+  const biasedExponent = Number((bits >> 52n) & 0x7ffn)
+  let mantissa = bits & 0xfffffffffffffn
+  let exponent = -1074
+  if (biasedExponent !== 0) {
+    mantissa |= 1n << 52n
+    exponent = biasedExponent - 1075
+  }
+
+  const scaled = mantissa * 10n ** BigInt(digits)
+  let quotient = 0n
+  if (exponent >= 0) {
+    quotient = scaled << BigInt(exponent)
+  } else {
+    const shift = BigInt(-exponent)
+    quotient = scaled >> shift
+    const remainder = scaled - (quotient << shift)
+    const half = 1n << (shift - 1n)
+    if (remainder > half || (remainder === half && (quotient & 1n) === 1n)) {
+      quotient += 1n
+    }
+  }
+
+  let text = quotient.toString().padStart(digits + 1, '0')
+  if (digits > 0) {
+    text = `${text.slice(0, -digits)}.${text.slice(-digits)}`
+  }
+  return bits >> 63n ? `-${text}` : text
+}
+
+function to_fixed() {
+  assertStackHas(2)
+  const v = readStackNumber(-2)
+  const digits = readStackNumber(-1)
+  if (!Number.isInteger(digits) || digits < 0 || digits > 100) {
+    throw INVALID_ARGUMENT()
+  }
+  drop()
+  storeStack(-1, numberToFixed(v, digits))
+}
+
+function to_radix() {
+  assertStackHas(2)
+  const v = readStackNumber(-2)
+  const radix = readStackNumber(-1)
+  if (!Number.isInteger(radix) || radix < 2 || radix > 36) {
+    throw INVALID_ARGUMENT()
+  }
+  if (!Number.isSafeInteger(v)) {
+    throw INVALID_ARGUMENT()
+  }
+  drop()
+  storeStack(-1, v.toString(radix))
+}
+
+function to_padded() {
+  assertStackHas(2)
+  const v = readStackNumber(-2)
+  const width = readStackNumber(-1)
+  if (!Number.isSafeInteger(width) || width < 0) {
+    throw INVALID_ARGUMENT()
+  }
+  drop()
+  const text = numberToString(v)
+  if (!Number.isFinite(v)) {
+    storeStack(-1, text.padStart(width, ' '))
+  } else if (text.startsWith('-')) {
+    storeStack(-1, `-${text.slice(1).padStart(width - 1, '0')}`)
+  } else {
+    storeStack(-1, text.padStart(width, '0'))
+  }
+}
+
+const U64_MASK = 0xffffffffffffffffn
+
+// SplitMix64, matching the interpreter so seeded sequences are the same
+function nextRandom() {
+  STATE.randomState = (STATE.randomState + 0x9e3779b97f4a7c15n) & U64_MASK
+  let z = STATE.randomState
+  z = ((z ^ (z >> 30n)) * 0xbf58476d1ce4e5b9n) & U64_MASK
+  z = ((z ^ (z >> 27n)) * 0x94d049bb133111ebn) & U64_MASK
+  return z ^ (z >> 31n)
+}
+
+function random_i() {
+  push(Number(nextRandom() >> 11n) / 2 ** 53)
+}
+
+function random_int() {
+  assertStackHas(2)
+  const lo = readStackNumber(-2)
+  const hi = readStackNumber(-1)
+  if (!Number.isSafeInteger(lo) || !Number.isSafeInteger(hi) || !Number.isSafeInteger(hi - lo) || hi < lo) {
+    throw INVALID_ARGUMENT()
+  }
+  const range = BigInt(hi - lo) + 1n
+  const threshold = ((1n << 64n) - range) % range
+  let z = nextRandom()
+  while (z < threshold) {
+    z = nextRandom()
+  }
+  drop()
+  storeStack(-1, lo + Number(z % range))
+}
+
+function seed() {
+  assertStackHas(1)
+  const n = readStackNumber(-1)
+  if (!Number.isSafeInteger(n)) {
+    throw INVALID_ARGUMENT()
+  }
+  STATE.randomState = BigInt.asUintN(64, BigInt(n))
+  drop()
+}
+
+function now() {
+  push(Date.now())
+}
+
+function monotonic() {
+  push(performance.now() - STATE.start)
+}
+
+function sleep_i() {
+  assertStackHas(1)
+  const ms = readStackNumber(-1)
+  if (!(ms >= 0 && ms <= 1e15)) {
+    throw INVALID_ARGUMENT()
+  }
+  drop()
+  if (typeof SharedArrayBuffer !== 'undefined' && typeof window === 'undefined') {
+    Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, ms)
+  } else {
+    const end = performance.now() + ms
+    while (performance.now() < end) {
+      // Busy wait, the browser main thread cannot block
+    }
+  }
+}
@@ -1,9 +1,6 @@
 use crate::{
-    codegen::{
-        context::{CodegenContext, CodegenResult, CodegenResultG},
-        target::CodegenTarget,
-    },
-    lang::{Block, Loop, Term},
+    codegen::context::{CodegenContext, CodegenResult, CodegenResultG},
+    lang::{BigInt, Block, Loop, Record, RecordOp, Term, Try},
     program::{NamespaceId, Program},
 };
 
@@ -20,20 +17,90 @@ fn codegen_loop_condition(ctx: &mut CodegenContext, block: Option<&Block>) -> Co
 }
 
 fn codegen_loop(ctx: &mut CodegenContext, loop_t: &Loop) -> CodegenResult {
+    let (id, labeled) = {
+        let current = ctx.enter_loop(loop_t);
+        (current.id, current.labeled)
+    };
     ctx.target.write_line("while (1) {");
     ctx.target.increase_indent();
     codegen_loop_condition(ctx, loop_t.pre_condition.as_ref())?;
-    codegen_block(ctx, &loop_t.body)?;
+    if labeled {
+        // A continue leaves this block so the post condition still runs
+        ctx.target.write_line(&format!("loop_{id}: {{"));
+        ctx.target.increase_indent();
+        codegen_block(ctx, &loop_t.body)?;
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+    } else {
+        codegen_block(ctx, &loop_t.body)?;
+    }
     codegen_loop_condition(ctx, loop_t.post_condition.as_ref())?;
     ctx.target.decrease_indent();
     ctx.target.write_line("}");
+    ctx.exit_loop();
     Ok(())
 }
 
+fn codegen_try(ctx: &mut CodegenContext, t: &Try) -> CodegenResult {
+    let id = ctx.enter_try();
+    ctx.target
+        .write_line(&format!("const try_{id} = STATE.values.slice()"));
+    ctx.target.write_line("try {");
+    ctx.target.increase_indent();
+    codegen_block(ctx, &t.body)?;
+    ctx.exit_try();
+    ctx.target.decrease_indent();
+    ctx.target.write_line("} catch (err) {");
+    ctx.target.increase_indent();
+    ctx.target.write_line(&format!("STATE.values = try_{id}"));
+    ctx.target.write_line("push(caught(err))");
+    codegen_block(ctx, &t.handler)?;
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
+    Ok(())
+}
+
+// Records are declared in the namespace of their functions, which is the one being generated
+fn codegen_record(ctx: &mut CodegenContext, record: &Record, op: RecordOp) {
+    let (namespace, name) = (ctx.namespace, &record.name);
+    ctx.target.write_line(&match op {
+        RecordOp::Construct => {
+            format!(
+                "construct_record({namespace}, {name:?}, {:?})",
+                record.fields
+            )
+        }
+        RecordOp::Get(i) => format!("record_get({namespace}, {name:?}, {i})"),
+        RecordOp::With(i) => format!("record_with({namespace}, {name:?}, {i})"),
+    });
+}
+
+fn number_literal(v: f64) -> String {
+    match v {
+        f64::INFINITY => "Infinity".into(),
+        f64::NEG_INFINITY => "-Infinity".into(),
+        v if v.is_nan() => "NaN".into(),
+        v => format!("{v:?}"),
+    }
+}
+
 fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
     match term {
         Term::String(e) => ctx.target.write_line(&format!("push({:?})", e)),
-        Term::Number(e) => ctx.target.write_line(&format!("push({})", e)),
+        Term::Number(e) => ctx
+            .target
+            .write_line(&format!("push({})", number_literal(*e))),
+        Term::Integer(e) => ctx.target.write_line(&format!("push({e}n)")),
+        Term::Big(e) => ctx.target.write_line(&format!("push(new Big({e}n))")),
+        Term::Decimal(e) => {
+            let text = e.to_string();
+            let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+            let scale = fraction.len();
+            // BigInt literals can't start with a zero, so the digits are written out again
+            let unscaled = BigInt::parse(&format!("{whole}{fraction}")).unwrap_or_default();
+            ctx.target
+                .write_line(&format!("push(new Decimal({unscaled}n, {scale}))"));
+        }
         Term::Bool(true) => ctx.target.write_line("push(true)"),
         Term::Bool(false) => ctx.target.write_line("push(false)"),
         Term::Address(name) => ctx
@@ -42,6 +109,31 @@ fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
         Term::Name(n, _) => ctx
             .target
             .write_line(&format!("{}()", ctx.resolve_name(n)?)),
+        Term::Bind(locals) => {
+            for local in locals.iter().rev() {
+                ctx.target.write_line(&format!(
+                    "const {} = take()",
+                    CodegenContext::local_name(local)
+                ));
+            }
+        }
+        Term::Local(local) => ctx
+            .target
+            .write_line(&format!("push({})", CodegenContext::local_name(local))),
+        Term::Break => {
+            ctx.current_loop()?;
+            ctx.target.write_line("break");
+        }
+        Term::Return => ctx.target.write_line("return"),
+        Term::Continue => {
+            let current = ctx.current_loop()?;
+            if current.labeled {
+                let id = current.id;
+                ctx.target.write_line(&format!("break loop_{id}"));
+            } else {
+                ctx.target.write_line("continue");
+            }
+        }
         Term::Branch(branch) => {
             branch.arms.iter().try_for_each(|arm| -> CodegenResult {
                 codegen_block(ctx, &arm.0)?;
@@ -60,6 +152,37 @@ fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
             });
         }
         Term::Loop(loop_t) => codegen_loop(ctx, loop_t)?,
+        Term::Try(t) => codegen_try(ctx, t)?,
+        Term::Record(record, op) => codegen_record(ctx, record, *op),
+        Term::Match(m) => {
+            let (mut declared_in, mut variants) = (ctx.namespace, vec![]);
+            m.arms
+                .iter()
+                .try_for_each(|(name, body)| -> CodegenResult {
+                    let (namespace, variant) = ctx.resolve_variant(name)?;
+                    if let Some(e) = &variant.variant_of {
+                        declared_in = namespace;
+                        variants.clone_from(&e.variants);
+                    }
+                    ctx.target.write_line(&format!(
+                        "if (matchVariant({namespace}, {:?})) {{",
+                        variant.name
+                    ));
+                    ctx.target.increase_indent();
+                    codegen_block(ctx, body)?;
+                    ctx.target.decrease_indent();
+                    ctx.target.write_line("} else {");
+                    ctx.target.increase_indent();
+                    Ok(())
+                })?;
+            ctx.target
+                .write_line(&format!("noMatchArm({declared_in}, {variants:?})"));
+
+            m.arms.iter().for_each(|_| {
+                ctx.target.decrease_indent();
+                ctx.target.write_line("}");
+            });
+        }
     }
     Ok(())
 }
@@ -82,11 +205,7 @@ pub fn js_codegen_module(
     main_namespace: NamespaceId,
     main: &Block,
 ) -> CodegenResultG<String> {
-    let mut ctx = CodegenContext {
-        namespace: 0,
-        program,
-        target: CodegenTarget::default(),
-    };
+    let mut ctx = CodegenContext::new(program);
 
     ctx.target.write_line(DEFS);
 
@@ -106,7 +225,11 @@ pub fn js_codegen_module(
   main_body()
   printStack()
 } catch (err) {
-   console.error(err)
+  if (!(err instanceof ExitRequest)) {
+    console.error(err)
+  } else if (typeof process !== 'undefined') {
+    process.exitCode = err.code
+  }
 }",
     );
 
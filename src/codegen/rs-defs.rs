@@ -14,15 +14,31 @@ rustflags = ["-Aunused"]
 
 use std::borrow::Cow;
 use std::{
+    cmp::Ordering,
     fmt::Write as _,
     fmt::{Debug, Display},
     io::BufRead,
     marker::PhantomData,
-    ops::{Index, Range},
+    ops::{Add, Index, Mul, Neg, Range, Sub},
     rc::Rc,
 };
 
-type InterpreterError = Cow<'static, str>;
+// Exiting ends the process directly, so it isn't an error here
+enum InterpreterError {
+    Failed(ErrorKind, Cow<'static, str>),
+    Reported(Cow<'static, str>),
+    Thrown(Value),
+}
+
+// Reported by `main` when the program fails
+impl Debug for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(_, message) | Self::Reported(message) => Debug::fmt(message, f),
+            Self::Thrown(value) => write!(f, "Uncaught throw: {value}"),
+        }
+    }
+}
 
 type InterpreterValueResult<T> = Result<T, InterpreterError>;
 
@@ -30,12 +46,76 @@ type InterpreterResult = InterpreterValueResult<()>;
 
 type Operation = fn(&mut Interpreter) -> InterpreterResult;
 
+// How a try body, generated as a closure, was left
+enum TryExit {
+    Next,
+    Break,
+    Continue,
+    Return,
+}
+
 #[derive(Clone)]
 enum Value {
     String(Rc<CharString<'static>>),
     Number(f64),
+    Integer(i64),
+    Big(Rc<BigInt>),
+    Decimal(Rc<Decimal>),
     Bool(bool),
     Address(&'static Operation),
+    Record(Rc<RecordValue>),
+}
+
+// Records share their field names with every other record of the same type, which is told apart
+// by the namespace that declares it as well as by name
+struct Record {
+    namespace: usize,
+    name: &'static str,
+    fields: &'static [&'static str],
+}
+
+impl Record {
+    fn is(&self, namespace: usize, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+}
+
+#[derive(Clone)]
+struct RecordValue {
+    record: &'static Record,
+    values: Vec<Value>,
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Integer(a), Self::Number(b)) | (Self::Number(b), Self::Integer(a)) => {
+                *a as f64 == *b
+            }
+            (Self::Big(a), Self::Big(b)) => a == b,
+            (Self::Big(a), Self::Integer(b)) | (Self::Integer(b), Self::Big(a)) => {
+                **a == BigInt::from(*b)
+            }
+            (Self::Big(a), Self::Number(b)) | (Self::Number(b), Self::Big(a)) => a.to_f64() == *b,
+            (Self::Decimal(a), Self::Number(b)) | (Self::Number(b), Self::Decimal(a)) => {
+                a.to_f64() == *b
+            }
+            (Self::Decimal(a), b) | (b, Self::Decimal(a)) => b.to_decimal().is_some_and(|b| *a == b),
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Address(a), Self::Address(b)) => std::ptr::eq(*a, *b),
+            (Self::Record(a), Self::Record(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for RecordValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.record.is(other.record.namespace, other.record.name) && self.values == other.values
+    }
 }
 
 impl Debug for Value {
@@ -43,9 +123,21 @@ impl Debug for Value {
         match self {
             Self::String(s) => Debug::fmt(s, f),
             Self::Number(s) => Display::fmt(s, f),
+            Self::Integer(s) => Display::fmt(s, f),
+            Self::Big(s) => Display::fmt(s, f),
+            Self::Decimal(s) => Display::fmt(s, f),
             Self::Bool(true) => f.write_str("true"),
             Self::Bool(false) => f.write_str("false"),
             Self::Address(a) => f.write_str("Address"),
+            Self::Record(r) => {
+                f.write_str(r.record.name)?;
+                f.write_str(" {")?;
+                for (i, (field, value)) in r.record.fields.iter().zip(&r.values).enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{separator}{field}: {value:?}")?;
+                }
+                f.write_str(if r.values.is_empty() { "}" } else { " }" })
+            }
         }
     }
 }
@@ -60,12 +152,34 @@ impl Display for Value {
 }
 
 impl Value {
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(v) => Some(*v),
+            Value::Integer(v) => Some(*v as f64),
+            Value::Big(v) => Some(v.to_f64()),
+            Value::Decimal(v) => Some(v.to_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn to_decimal(&self) -> Option<Rc<Decimal>> {
+        match self {
+            Value::Integer(v) => Some(Rc::new((*v).into())),
+            Value::Big(v) => Some(Rc::new((**v).clone().into())),
+            Value::Decimal(v) => Some(Rc::clone(v)),
+            _ => None,
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::String(s) => !s.is_empty(),
             Value::Number(v) => !v.is_nan() && *v != 0f64,
+            Value::Integer(v) => *v != 0,
+            Value::Big(v) => !v.is_zero(),
+            Value::Decimal(v) => !v.is_zero(),
             Value::Bool(b) => *b,
-            Value::Address(_) => true,
+            Value::Address(_) | Value::Record(_) => true,
         }
     }
 }
@@ -88,6 +202,24 @@ impl From<bool> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        Self::Big(Rc::new(value))
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(value: Decimal) -> Self {
+        Self::Decimal(Rc::new(value))
+    }
+}
+
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
         Self::Number(value.into())
@@ -102,12 +234,58 @@ impl From<&'static Operation> for Value {
 
 struct Interpreter {
     pub stack: Vec<Value>,
+    args: Vec<String>,
+    pub random_state: u64,
+    start: std::time::Instant,
+}
+
+fn initial_random_state() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Self {
             stack: Vec::with_capacity(1000),
+            args: std::env::args().skip(1).collect(),
+            random_state: initial_random_state(),
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn now(&self) -> f64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0f64, |d| d.as_secs_f64() * 1000f64)
+    }
+
+    pub fn monotonic(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000f64
+    }
+
+    pub fn sleep(&self, ms: f64) {
+        std::thread::sleep(std::time::Duration::from_secs_f64(ms / 1000f64));
+    }
+
+    pub fn exit(&mut self, code: i32) -> InterpreterResult {
+        std::process::exit(code)
+    }
+
+    pub fn throw(&mut self, value: Value) -> InterpreterResult {
+        Err(InterpreterError::Thrown(value))
+    }
+
+    pub fn caught(error: InterpreterError) -> Value {
+        match error {
+            InterpreterError::Failed(kind, _) => kind.description().into(),
+            InterpreterError::Reported(message) => message.into_owned().into(),
+            InterpreterError::Thrown(value) => value,
         }
     }
 
@@ -116,7 +294,7 @@ impl Interpreter {
         let bytes_written = std::io::stdin()
             .lock()
             .read_line(&mut line)
-            .map_err(|_| -> InterpreterError { "Failed to read line".into() })?;
+            .map_err(|_| ErrorKind::InvalidArgument.error("Failed to read line"))?;
         if bytes_written == 0 {
             return Ok(None);
         }
@@ -132,6 +310,60 @@ impl Interpreter {
         Ok(self.take()?.is_truthy())
     }
 
+    pub fn construct_record(&mut self, record: &'static Record) -> InterpreterResult {
+        let Some(start) = self.stack.len().checked_sub(record.fields.len()) else {
+            return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+        };
+        let values = self.stack.split_off(start);
+        self.push(Value::Record(Rc::new(RecordValue { record, values })))
+    }
+
+    pub fn record_get(&mut self, namespace: usize, name: &str, field: usize) -> InterpreterResult {
+        match self.take()? {
+            Value::Record(r) if r.record.is(namespace, name) => self.push(r.values[field].clone()),
+            v => {
+                self.stack.push(v);
+                Err(ErrorKind::TypeMismatch.error(format!("Expected {name} on top of stack")))
+            }
+        }
+    }
+
+    pub fn record_with(&mut self, namespace: usize, name: &str, field: usize) -> InterpreterResult {
+        match self.take2()? {
+            (Value::Record(mut r), value) if r.record.is(namespace, name) => {
+                Rc::make_mut(&mut r).values[field] = value;
+                self.push(Value::Record(r))
+            }
+            (target, value) => {
+                self.push2(target, value)?;
+                Err(ErrorKind::TypeMismatch.error(format!("Expected {name} below the top of stack")))
+            }
+        }
+    }
+
+    // Replaces the variant on top with its fields when it is the named one
+    pub fn match_variant(&mut self, namespace: usize, name: &str) -> InterpreterValueResult<bool> {
+        match self.take()? {
+            Value::Record(r) if r.record.is(namespace, name) => {
+                self.stack.extend(r.values.iter().cloned());
+                Ok(true)
+            }
+            v => {
+                self.stack.push(v);
+                Ok(false)
+            }
+        }
+    }
+
+    pub fn no_match_arm(&self, namespace: usize, expected: &str, variants: &[&str]) -> InterpreterResult {
+        match self.stack.last() {
+            Some(Value::Record(r)) if variants.iter().any(|v| r.record.is(namespace, v)) => {
+                Err(ErrorKind::NoMatch.error(format!("No match arm for {}", r.record.name)))
+            }
+            _ => Err(ErrorKind::TypeMismatch.error(format!("Expected {expected} on top of stack"))),
+        }
+    }
+
     pub fn print(&self) -> InterpreterResult {
         if !self.stack.is_empty() {
             println!("{:?}", self.stack);
@@ -143,6 +375,6 @@ impl Interpreter {
 fn eval_i(i: &mut Interpreter) -> InterpreterResult {
     match i.take()? {
         Value::Address(f) => f(i),
-        _ => Err("Expected function pointer on top of stack".into()),
+        _ => Err(ErrorKind::TypeMismatch.error("Expected function pointer on top of stack")),
     }
 }
@@ -1,9 +1,6 @@
 use crate::{
-    codegen::{
-        context::{CodegenContext, CodegenResult, CodegenResultG},
-        target::CodegenTarget,
-    },
-    lang::{Block, Loop, Term},
+    codegen::context::{CodegenContext, CodegenResult, CodegenResultG, TryExit},
+    lang::{Block, Loop, Match, Record, RecordOp, Term, Try},
     program::{NamespaceId, Program},
 };
 
@@ -20,20 +17,193 @@ fn codegen_loop_condition(ctx: &mut CodegenContext, block: Option<&Block>) -> Co
 }
 
 fn codegen_loop(ctx: &mut CodegenContext, loop_t: &Loop) -> CodegenResult {
-    ctx.target.write_line("loop {");
+    let (id, labeled) = {
+        let current = ctx.enter_loop(loop_t);
+        (current.id, current.labeled)
+    };
+    if labeled {
+        ctx.target.write_line(&format!("'loop_{id}: loop {{"));
+    } else {
+        ctx.target.write_line("loop {");
+    }
     ctx.target.increase_indent();
     codegen_loop_condition(ctx, loop_t.pre_condition.as_ref())?;
-    codegen_block(ctx, &loop_t.body)?;
+    if labeled {
+        // A continue leaves this block so the post condition still runs
+        ctx.target.write_line(&format!("'next_{id}: {{"));
+        ctx.target.increase_indent();
+        codegen_block(ctx, &loop_t.body)?;
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+    } else {
+        codegen_block(ctx, &loop_t.body)?;
+    }
     codegen_loop_condition(ctx, loop_t.post_condition.as_ref())?;
     ctx.target.decrease_indent();
     ctx.target.write_line("}");
+    ctx.exit_loop();
+    Ok(())
+}
+
+fn codegen_loop_control(ctx: &mut CodegenContext, is_continue: bool) -> CodegenResult {
+    let current = ctx.current_loop()?;
+    let (id, labeled) = (current.id, current.labeled);
+    // A try body is a closure, so leaving the loop is repeated once the closure returns
+    if ctx.tries.len() > current.tries
+        && let Some(t) = ctx.tries.last_mut()
+    {
+        let (exit, line) = if is_continue {
+            (TryExit::Continue, "return Ok(TryExit::Continue);")
+        } else {
+            (TryExit::Break, "return Ok(TryExit::Break);")
+        };
+        t.leave(exit);
+        ctx.target.write_line(line);
+        return Ok(());
+    }
+    // Unlabeled breaks aren't allowed inside the labeled body block
+    let line = match (is_continue, labeled) {
+        (true, true) => format!("break 'next_{id};"),
+        (true, false) => "continue;".into(),
+        (false, true) => format!("break 'loop_{id};"),
+        (false, false) => "break;".into(),
+    };
+    ctx.target.write_line(&line);
+    Ok(())
+}
+
+fn codegen_return(ctx: &mut CodegenContext) {
+    if let Some(t) = ctx.tries.last_mut() {
+        t.leave(TryExit::Return);
+        ctx.target.write_line("return Ok(TryExit::Return);");
+    } else {
+        ctx.target.write_line("return Ok(());");
+    }
+}
+
+fn codegen_try(ctx: &mut CodegenContext, t: &Try) -> CodegenResult {
+    let id = ctx.enter_try();
+    ctx.target
+        .write_line(&format!("let try_{id} = c.stack.clone();"));
+    ctx.target.write_line(&format!(
+        "let result_{id} = (|| -> InterpreterValueResult<TryExit> {{"
+    ));
+    ctx.target.increase_indent();
+    codegen_block(ctx, &t.body)?;
+    ctx.target.write_line("Ok(TryExit::Next)");
+    ctx.target.decrease_indent();
+    ctx.target.write_line("})();");
+    let current = ctx.exit_try();
+
+    ctx.target.write_line(&format!("match result_{id} {{"));
+    ctx.target.increase_indent();
+    ctx.target.write_line("Ok(TryExit::Next) => {}");
+    // Leaving the closure is repeated for the enclosing loop or function
+    for exit in &current.exits {
+        match exit {
+            TryExit::Break => {
+                ctx.target.write_line("Ok(TryExit::Break) => {");
+                ctx.target.increase_indent();
+                codegen_loop_control(ctx, false)?;
+            }
+            TryExit::Continue => {
+                ctx.target.write_line("Ok(TryExit::Continue) => {");
+                ctx.target.increase_indent();
+                codegen_loop_control(ctx, true)?;
+            }
+            TryExit::Return => {
+                ctx.target.write_line("Ok(TryExit::Return) => {");
+                ctx.target.increase_indent();
+                codegen_return(ctx);
+            }
+        }
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+    }
+    if current.exits.len() < 3 {
+        ctx.target.write_line("Ok(_) => unreachable!(),");
+    }
+    ctx.target.write_line("Err(e) => {");
+    ctx.target.increase_indent();
+    ctx.target.write_line(&format!("c.stack = try_{id};"));
+    ctx.target
+        .write_line("let caught = Interpreter::caught(e);");
+    ctx.target.write_line("c.push(caught)?;");
+    codegen_block(ctx, &t.handler)?;
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
+    ctx.target.decrease_indent();
+    ctx.target.write_line("}");
+    Ok(())
+}
+
+// Records are declared in the namespace of their functions, which is the one being generated
+fn codegen_record(ctx: &mut CodegenContext, record: &Record, op: RecordOp) {
+    let (namespace, name) = (ctx.namespace, &record.name);
+    ctx.target.write_line(&match op {
+        RecordOp::Construct => format!(
+            "c.construct_record(&Record {{ namespace: {namespace}, name: {name:?}, fields: &{:?} }})?;",
+            record.fields
+        ),
+        RecordOp::Get(i) => format!("c.record_get({namespace}, {name:?}, {i})?;"),
+        RecordOp::With(i) => format!("c.record_with({namespace}, {name:?}, {i})?;"),
+    });
+}
+
+fn codegen_match(ctx: &mut CodegenContext, m: &Match) -> CodegenResult {
+    let mut matched = None;
+    m.arms
+        .iter()
+        .try_for_each(|(name, body)| -> CodegenResult {
+            let (namespace, variant) = ctx.resolve_variant(name)?;
+            matched = variant.variant_of.as_ref().map(|e| (namespace, e));
+            ctx.target.write_line(&format!(
+                "if c.match_variant({namespace}, {:?})? {{",
+                variant.name
+            ));
+            ctx.target.increase_indent();
+            codegen_block(ctx, body)?;
+            ctx.target.decrease_indent();
+            ctx.target.write_line("} else {");
+            ctx.target.increase_indent();
+            Ok(())
+        })?;
+    if let Some((namespace, matched)) = matched {
+        ctx.target.write_line(&format!(
+            "c.no_match_arm({namespace}, {:?}, &{:?})?;",
+            matched.name, matched.variants
+        ));
+    }
+
+    m.arms.iter().for_each(|_| {
+        ctx.target.decrease_indent();
+        ctx.target.write_line("}");
+    });
     Ok(())
 }
 
+fn number_literal(v: f64) -> String {
+    match v {
+        f64::INFINITY => "f64::INFINITY".into(),
+        f64::NEG_INFINITY => "f64::NEG_INFINITY".into(),
+        v if v.is_nan() => "f64::NAN".into(),
+        v => format!("{v:?}f64"),
+    }
+}
+
 fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
     match term {
         Term::String(e) => ctx.target.write_line(&format!("c.push({:?})?;", e)),
-        Term::Number(e) => ctx.target.write_line(&format!("c.push({}f64)?;", e)),
+        Term::Number(e) => ctx
+            .target
+            .write_line(&format!("c.push({})?;", number_literal(*e))),
+        Term::Integer(e) => ctx.target.write_line(&format!("c.push({e}i64)?;")),
+        Term::Big(e) => ctx.target.write_line(&format!(
+            "c.push(BigInt::parse(\"{e}\").unwrap_or_default())?;"
+        )),
+        Term::Decimal(e) => ctx.target.write_line(&format!(
+            "c.push(Decimal::parse(\"{e}\").unwrap_or_default())?;"
+        )),
         Term::Bool(true) => ctx.target.write_line("c.push(true)?;"),
         Term::Bool(false) => ctx.target.write_line("c.push(false)?;"),
         Term::Address(a) => ctx.target.write_line(&format!(
@@ -43,6 +213,21 @@ fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
         Term::Name(n, _) => ctx
             .target
             .write_line(&format!("{}(c)?;", ctx.resolve_name(n)?)),
+        Term::Bind(locals) => {
+            for local in locals.iter().rev() {
+                ctx.target.write_line(&format!(
+                    "let {} = c.take()?;",
+                    CodegenContext::local_name(local)
+                ));
+            }
+        }
+        Term::Local(local) => ctx.target.write_line(&format!(
+            "c.push({}.clone())?;",
+            CodegenContext::local_name(local)
+        )),
+        Term::Break => codegen_loop_control(ctx, false)?,
+        Term::Continue => codegen_loop_control(ctx, true)?,
+        Term::Return => codegen_return(ctx),
         Term::Branch(branch) => {
             branch.arms.iter().try_for_each(|arm| -> CodegenResult {
                 codegen_block(ctx, &arm.0)?;
@@ -61,6 +246,9 @@ fn codegen_term(ctx: &mut CodegenContext, term: &Term) -> CodegenResult {
             });
         }
         Term::Loop(loop_t) => codegen_loop(ctx, loop_t)?,
+        Term::Try(t) => codegen_try(ctx, t)?,
+        Term::Record(record, op) => codegen_record(ctx, record, *op),
+        Term::Match(m) => codegen_match(ctx, m)?,
     }
     Ok(())
 }
@@ -87,11 +275,7 @@ pub fn rs_codegen_module(
     main_namespace: NamespaceId,
     main: &Block,
 ) -> CodegenResultG<String> {
-    let mut ctx = CodegenContext {
-        namespace: 0,
-        program,
-        target: CodegenTarget::default(),
-    };
+    let mut ctx = CodegenContext::new(program);
 
     let definitions = {
         let definition_start = INTERPRETER
@@ -136,8 +320,11 @@ pub fn rs_codegen_module(
 
     let libs = &[
         include_str!("../convert.rs"),
+        include_str!("../lang/big_int.rs"),
+        include_str!("../lang/decimal.rs"),
         include_str!("../lang/string/char_string.rs"),
         include_str!("../lang/string/api.rs"),
+        include_str!("../lang/string/regex.rs"),
     ];
 
     let libs = libs
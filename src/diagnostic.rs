@@ -0,0 +1,177 @@
+use std::{borrow::Cow, fmt::Write as _};
+
+use crate::lang::{OwnedValue, SourceLocation, SourceRange};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiagnosticFrame {
+    pub name: String,
+    pub file: Option<String>,
+    pub range: Option<SourceRange>,
+    pub context: Option<&'static str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub range: Option<SourceRange>,
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: Cow<'static, str>,
+    pub info: Option<Cow<'static, str>>,
+    pub backtrace: Vec<DiagnosticFrame>,
+    pub stack: Option<Vec<OwnedValue>>,
+}
+
+impl Diagnostic {
+    pub fn new<T>(severity: Severity, code: &'static str, message: T) -> Self
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        Self {
+            file: None,
+            range: None,
+            severity,
+            code,
+            message: message.into(),
+            info: None,
+            backtrace: vec![],
+            stack: None,
+        }
+    }
+
+    #[must_use]
+    pub fn at(mut self, file: Option<String>, range: Option<SourceRange>) -> Self {
+        self.file = file;
+        self.range = range;
+        self
+    }
+
+    pub fn write_json(&self, res: &mut String) -> std::fmt::Result {
+        res.push_str("{\"file\":");
+        write_json_optional_string(res, self.file.as_deref())?;
+        res.push_str(",\"range\":");
+        write_json_range(res, self.range.as_ref())?;
+        write!(res, ",\"severity\":\"{}\",\"code\":", self.severity.name())?;
+        write_json_string(res, self.code)?;
+        res.push_str(",\"message\":");
+        write_json_string(res, &self.message)?;
+
+        if let Some(info) = &self.info {
+            res.push_str(",\"info\":");
+            write_json_string(res, info)?;
+        }
+
+        if !self.backtrace.is_empty() {
+            res.push_str(",\"backtrace\":[");
+            for (i, frame) in self.backtrace.iter().enumerate() {
+                if i > 0 {
+                    res.push(',');
+                }
+                res.push_str("{\"name\":");
+                write_json_string(res, &frame.name)?;
+                res.push_str(",\"file\":");
+                write_json_optional_string(res, frame.file.as_deref())?;
+                res.push_str(",\"range\":");
+                write_json_range(res, frame.range.as_ref())?;
+                res.push_str(",\"context\":");
+                write_json_optional_string(res, frame.context)?;
+                res.push('}');
+            }
+            res.push(']');
+        }
+
+        if let Some(stack) = &self.stack {
+            res.push_str(",\"stack\":[");
+            for (i, value) in stack.iter().rev().enumerate() {
+                if i > 0 {
+                    res.push(',');
+                }
+                res.push_str("{\"value\":");
+                write_json_string(res, &format!("{value:?}"))?;
+                write!(res, ",\"type\":\"{}\"}}", value.type_name())?;
+            }
+            res.push(']');
+        }
+
+        res.push('}');
+        Ok(())
+    }
+}
+
+pub fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> Result<String, std::fmt::Error> {
+    let mut res = String::with_capacity(256 * diagnostics.len());
+    res.push('[');
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            res.push(',');
+        }
+        diagnostic.write_json(&mut res)?;
+    }
+    res.push(']');
+    Ok(res)
+}
+
+fn write_json_string(res: &mut String, value: &str) -> std::fmt::Result {
+    res.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if u32::from(c) < 0x20 => write!(res, "\\u{:04x}", u32::from(c))?,
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    Ok(())
+}
+
+fn write_json_optional_string(res: &mut String, value: Option<&str>) -> std::fmt::Result {
+    if let Some(value) = value {
+        write_json_string(res, value)
+    } else {
+        res.push_str("null");
+        Ok(())
+    }
+}
+
+fn write_json_location(res: &mut String, loc: &SourceLocation) -> std::fmt::Result {
+    write!(
+        res,
+        "{{\"line\":{},\"column\":{}}}",
+        loc.line + 1,
+        loc.column + 1
+    )
+}
+
+fn write_json_range(res: &mut String, range: Option<&SourceRange>) -> std::fmt::Result {
+    let Some(range) = range else {
+        res.push_str("null");
+        return Ok(());
+    };
+    res.push_str("{\"start\":");
+    write_json_location(res, &range.start)?;
+    res.push_str(",\"end\":");
+    write_json_location(res, &range.end)?;
+    res.push('}');
+    Ok(())
+}
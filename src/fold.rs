@@ -0,0 +1,296 @@
+use crate::{
+    interpreter::Interpreter,
+    intrinsics::get_intrinsic,
+    lang::{Block, Constant, Module, OwnedValue, Term},
+    program::{FunctionOverwriteStrategy, NamespaceId, Program},
+};
+
+// Folded strings stay within the size of their inputs or this many bytes, so a literal call
+// like `1 1e9 to_padded` is left for the runtime instead of inflating the generated code
+static MAX_FOLDED_GROWTH: usize = 1024;
+
+pub struct Folder<'a> {
+    program: &'a Program,
+    namespace: NamespaceId,
+    // When false only constants are inlined, every intrinsic still runs at runtime
+    fold_intrinsics: bool,
+    // Runs every intrinsic folded by this pass
+    interpreter: Interpreter<'a>,
+}
+
+impl<'a> Folder<'a> {
+    pub fn new(program: &'a Program, namespace: NamespaceId) -> Self {
+        Self {
+            program,
+            namespace,
+            fold_intrinsics: true,
+            interpreter: Interpreter::begin(program),
+        }
+    }
+
+    pub fn with_intrinsics(mut self, fold_intrinsics: bool) -> Self {
+        self.fold_intrinsics = fold_intrinsics;
+        self
+    }
+
+    pub fn fold_block(&mut self, block: &mut Block) {
+        for mut term in std::mem::take(&mut block.terms) {
+            match &mut term {
+                Term::Name(n, _) if get_intrinsic(n).is_none() => {
+                    if let Some(value) = self.program.resolve_constant(self.namespace, n) {
+                        block.terms.push(value.clone());
+                        continue;
+                    }
+                }
+                Term::Name(n, _) => {
+                    if self.fold_intrinsics
+                        && fold_intrinsic(&mut self.interpreter, &mut block.terms, n)
+                    {
+                        continue;
+                    }
+                }
+                Term::Branch(b) => {
+                    for (condition, body) in &mut b.arms {
+                        self.fold_block(condition);
+                        self.fold_block(body);
+                    }
+                }
+                Term::Loop(l) => {
+                    if let Some(condition) = &mut l.pre_condition {
+                        self.fold_block(condition);
+                    }
+                    self.fold_block(&mut l.body);
+                    if let Some(condition) = &mut l.post_condition {
+                        self.fold_block(condition);
+                    }
+                }
+                Term::Try(t) => {
+                    self.fold_block(&mut t.body);
+                    self.fold_block(&mut t.handler);
+                }
+                Term::Match(m) => {
+                    for (_, body) in &mut m.arms {
+                        self.fold_block(body);
+                    }
+                }
+                Term::String(_)
+                | Term::Number(_)
+                | Term::Integer(_)
+                | Term::Big(_)
+                | Term::Decimal(_)
+                | Term::Bool(_)
+                | Term::Address(_)
+                | Term::Bind(_)
+                | Term::Local(_)
+                | Term::Break
+                | Term::Continue
+                | Term::Return
+                | Term::Record(_, _) => {}
+            }
+            block.terms.push(term);
+        }
+    }
+
+    // Constants always fold their whole body, whether or not intrinsics are folded elsewhere
+    pub fn fold_constant(&mut self, constant: &Constant) -> Option<Term> {
+        let mut body = constant.body.clone();
+        let fold_intrinsics = std::mem::replace(&mut self.fold_intrinsics, true);
+        self.fold_block(&mut body);
+        self.fold_intrinsics = fold_intrinsics;
+        match body.terms.as_slice() {
+            [value] if literal_value(value).is_some() => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn literal_value(term: &Term) -> Option<OwnedValue> {
+    match term {
+        Term::String(s) => Some(OwnedValue::String(s.clone())),
+        Term::Number(n) => Some(OwnedValue::Number(*n)),
+        Term::Integer(n) => Some(OwnedValue::Integer(*n)),
+        Term::Big(n) => Some(OwnedValue::Big(n.clone())),
+        Term::Decimal(n) => Some(OwnedValue::Decimal(n.clone())),
+        Term::Bool(b) => Some(OwnedValue::Bool(*b)),
+        Term::Address(_)
+        | Term::Name(_, _)
+        | Term::Bind(_)
+        | Term::Local(_)
+        | Term::Break
+        | Term::Continue
+        | Term::Return
+        | Term::Branch(_)
+        | Term::Loop(_)
+        | Term::Try(_)
+        | Term::Record(_, _)
+        | Term::Match(_) => None,
+    }
+}
+
+fn literal_term(value: OwnedValue) -> Option<Term> {
+    match value {
+        OwnedValue::String(s) => Some(Term::String(s)),
+        // Codegen can write any number, but only finite results are kept to match the source
+        OwnedValue::Number(n) if n.is_finite() => Some(Term::Number(n)),
+        OwnedValue::Integer(n) => Some(Term::Integer(n)),
+        OwnedValue::Big(n) => Some(Term::Big(n)),
+        OwnedValue::Decimal(n) => Some(Term::Decimal(n)),
+        OwnedValue::Bool(b) => Some(Term::Bool(b)),
+        OwnedValue::Number(_) | OwnedValue::Address(_, _) | OwnedValue::Record(_, _) => None,
+    }
+}
+
+fn string_length(value: &OwnedValue) -> usize {
+    match value {
+        OwnedValue::String(s) => s.len(),
+        OwnedValue::Number(_)
+        | OwnedValue::Integer(_)
+        | OwnedValue::Big(_)
+        | OwnedValue::Decimal(_)
+        | OwnedValue::Bool(_)
+        | OwnedValue::Address(_, _)
+        | OwnedValue::Record(_, _) => 0,
+    }
+}
+
+// Bignum and decimal powers are only limited by memory and can take minutes, so only those with
+// results about as small as other folded values are run while compiling
+fn is_small_exact_power(inputs: &[OwnedValue]) -> bool {
+    let digits = |v: &OwnedValue| match v {
+        OwnedValue::Integer(n) => Some(n.to_string().len()),
+        OwnedValue::Big(n) => Some(n.to_string().len()),
+        OwnedValue::Decimal(n) => Some(n.to_string().len()),
+        OwnedValue::Number(_)
+        | OwnedValue::String(_)
+        | OwnedValue::Bool(_)
+        | OwnedValue::Address(_, _)
+        | OwnedValue::Record(_, _) => None,
+    };
+    let exponent = |v: &OwnedValue| match v {
+        OwnedValue::Integer(n) => usize::try_from(*n).ok(),
+        OwnedValue::Big(n) => usize::try_from(n.to_i64()?).ok(),
+        OwnedValue::Decimal(n) => usize::try_from(n.to_whole()?.to_i64()?).ok(),
+        OwnedValue::Number(_)
+        | OwnedValue::String(_)
+        | OwnedValue::Bool(_)
+        | OwnedValue::Address(_, _)
+        | OwnedValue::Record(_, _) => None,
+    };
+    let unbounded = |v: &OwnedValue| matches!(v, OwnedValue::Big(_) | OwnedValue::Decimal(_));
+    match inputs {
+        [base, power] if inputs.iter().any(unbounded) => match (digits(base), exponent(power)) {
+            (Some(digits), Some(power)) => digits.saturating_mul(power) <= MAX_FOLDED_GROWTH,
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+// Replaces the literals at the end of `terms` with the result of calling `name` on them.
+// Anything that fails is left for the runtime to report.
+fn fold_intrinsic(interpreter: &mut Interpreter, terms: &mut Vec<Term>, name: &str) -> bool {
+    let Some(intrinsic) = get_intrinsic(name).filter(|e| e.foldable) else {
+        return false;
+    };
+    let (pops, _) = intrinsic.arity.size();
+    let Some(start) = terms.len().checked_sub(pops) else {
+        return false;
+    };
+    let Some(inputs) = terms[start..]
+        .iter()
+        .map(literal_value)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    if name == "**" && !is_small_exact_power(&inputs) {
+        return false;
+    }
+    let max_length = inputs.iter().map(string_length).sum::<usize>() + MAX_FOLDED_GROWTH;
+
+    interpreter.stack = inputs.into_iter().map(Into::into).collect();
+    if (intrinsic.func)(interpreter).is_err() {
+        return false;
+    }
+    let Some(results) = std::mem::take(&mut interpreter.stack)
+        .into_iter()
+        .map(|v| {
+            Some(v.into())
+                .filter(|v| string_length(v) <= max_length)
+                .and_then(literal_term)
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+
+    terms.truncate(start);
+    terms.extend(results);
+    true
+}
+
+// Reports a constant that fails on its own before any that are only waiting on others
+fn unresolved_constant_error(pending: &[&Constant]) -> String {
+    let waiting_on = |constant: &Constant| {
+        constant.body.terms.iter().find_map(|t| {
+            if let Term::Name(n, _) = t
+                && pending.iter().any(|c| &c.name == n)
+            {
+                return Some(n.clone());
+            }
+            None
+        })
+    };
+    if let Some(constant) = pending.iter().find(|c| waiting_on(c).is_none()) {
+        return format!("Constant {} does not fold to a single value", constant.name);
+    }
+    let constant = pending[0];
+    match waiting_on(constant) {
+        Some(other) if other != constant.name => format!(
+            "Constant {} refers to constant {other}, which depends on it",
+            constant.name
+        ),
+        _ => format!("Constant {} refers to itself", constant.name),
+    }
+}
+
+// Adds the module's constants to the namespace and inlines them, along with any folded
+// intrinsics, into the function bodies and main body
+pub fn fold_module(
+    program: &mut Program,
+    namespace: NamespaceId,
+    module: &mut Module,
+    overwrite_strategy: FunctionOverwriteStrategy,
+    fold_intrinsics: bool,
+) -> Result<(), String> {
+    // Constants can refer to ones defined after them, so each pass adds the constants whose
+    // references were all added by an earlier one
+    let mut pending: Vec<&Constant> = module.constants.iter().collect();
+    while !pending.is_empty() {
+        let mut folder = Folder::new(program, namespace);
+        let mut resolved = vec![];
+        let mut remaining = vec![];
+        for constant in &pending {
+            match folder.fold_constant(constant) {
+                Some(value) => resolved.push((*constant, value)),
+                None => remaining.push(*constant),
+            }
+        }
+        for (constant, value) in resolved {
+            program
+                .add_constant(namespace, &constant.name, value, overwrite_strategy)
+                .map_err(|e| format!("Constant redefinition error: {e}"))?;
+        }
+        if remaining.len() == pending.len() {
+            return Err(unresolved_constant_error(&remaining));
+        }
+        pending = remaining;
+    }
+
+    let mut folder = Folder::new(program, namespace).with_intrinsics(fold_intrinsics);
+    for function in &mut module.functions {
+        folder.fold_block(&mut function.body);
+    }
+    folder.fold_block(&mut module.body);
+    Ok(())
+}
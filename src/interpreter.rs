@@ -1,16 +1,51 @@
 use std::{
     borrow::Cow,
+    fmt::{Display, Formatter},
     io::{BufRead as _, StdinLock},
     rc::Rc,
 };
 
 use crate::{
-    intrinsics::{IntrinsicData, get_intrinsic},
-    lang::{Block, Branch, Loop, OwnedValue, Term, Value, string::CharString},
+    clock::{Clock, SystemClock},
+    intrinsics::{ErrorKind, IntrinsicData, get_intrinsic},
+    lang::{
+        Block, Branch, Local, Loop, Match, OwnedValue, Record, RecordOp, RecordValue, Term, Try,
+        Value, string::CharString,
+    },
     program::{NamespaceId, Program},
 };
 
-pub type InterpreterError = Cow<'static, str>;
+/// Why evaluation stopped early
+#[derive(Debug, PartialEq)]
+pub enum InterpreterError {
+    /// A built-in error, which a handler receives as the description of its kind
+    Failed(ErrorKind, Cow<'static, str>),
+    /// A failed assertion or unknown name, which a handler receives as the message itself
+    Reported(Cow<'static, str>),
+    /// A thrown value, which a handler receives unchanged
+    Thrown(OwnedValue),
+    /// Unwinds the whole program without being caught
+    Exited(i32),
+}
+
+impl InterpreterError {
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::Exited(code) => Some(*code),
+            Self::Failed(..) | Self::Reported(_) | Self::Thrown(_) => None,
+        }
+    }
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(_, message) | Self::Reported(message) => f.write_str(message),
+            Self::Thrown(value) => write!(f, "Uncaught throw: {}", Value::from(value)),
+            Self::Exited(code) => write!(f, "Program exited with code {code}"),
+        }
+    }
+}
 
 pub type InterpreterValueResult<T> = Result<T, InterpreterError>;
 
@@ -18,18 +53,48 @@ pub type InterpreterResult = InterpreterValueResult<()>;
 
 pub type BacktraceItem<'a> = (NamespaceId, &'a Term);
 
+// How evaluation leaves a block, loops consume `Break` and `Continue`, and
+// function calls consume `Return`
+#[derive(Clone, Copy, PartialEq)]
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return,
+}
+
 pub struct Interpreter<'a> {
     pub stack: Vec<Value<'a>>,
     pub base_namespace: NamespaceId,
     pub namespace_stack: Vec<NamespaceId>,
     pub backtrace: Vec<BacktraceItem<'a>>,
     pub program: &'a Program,
+    locals: Vec<Value<'a>>,
+    locals_base: usize,
+    args: &'a [String],
+    pub random_state: u64,
+    clock: &'a dyn Clock,
     input: StdinLock<'static>,
 }
 
 #[derive(Default, Debug, PartialEq)]
 pub struct InterpreterSnapshot {
     pub stack: Vec<OwnedValue>,
+    pub random_state: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InterpreterFailure<'a> {
+    pub error: InterpreterError,
+    pub backtrace: Vec<BacktraceItem<'a>>,
+    pub stack: Vec<OwnedValue>,
+}
+
+fn initial_random_state() -> u64 {
+    #[expect(clippy::cast_possible_truncation, reason = "only the low bits vary")]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
 }
 
 impl<'a> Interpreter<'a> {
@@ -45,28 +110,46 @@ impl<'a> Interpreter<'a> {
             namespace_stack: vec![],
             program,
             backtrace: Vec::with_capacity(64),
+            locals: vec![],
+            locals_base: 0,
+            args: &[],
+            random_state: snapshot.random_state.unwrap_or_else(initial_random_state),
+            clock: &SystemClock,
             input: std::io::stdin().lock(),
         }
     }
 
+    pub fn with_args(mut self, args: &'a [String]) -> Self {
+        self.args = args;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_clock(mut self, clock: &'a dyn Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn execute(
         mut self,
         base_namespace: NamespaceId,
         block: &'a Block,
-    ) -> Result<InterpreterSnapshot, (InterpreterError, Vec<BacktraceItem<'a>>)> {
+    ) -> Result<InterpreterSnapshot, InterpreterFailure<'a>> {
         self.base_namespace = base_namespace;
-        let res = self.evaluate_block(block);
-        res.map_err(|e| {
-            let mut backtrace = Vec::new();
-            std::mem::swap(&mut backtrace, &mut self.backtrace);
-            (e, backtrace)
-        })?;
+        if let Err(error) = self.evaluate_block(block) {
+            return Err(InterpreterFailure {
+                error,
+                backtrace: self.backtrace,
+                stack: self.stack.into_iter().map(Into::into).collect(),
+            });
+        }
         assert!(
             self.backtrace.is_empty(),
             "Backtrace should be empty after successful execution"
         );
         Ok(InterpreterSnapshot {
             stack: self.stack.into_iter().map(Into::into).collect(),
+            random_state: Some(self.random_state),
         })
     }
 
@@ -75,7 +158,7 @@ impl<'a> Interpreter<'a> {
         let bytes_written = self
             .input
             .read_line(&mut line)
-            .map_err(|_| "read_line failed")?;
+            .map_err(|_| ErrorKind::InvalidArgument.error("read_line failed"))?;
         if bytes_written == 0 {
             return Ok(None);
         }
@@ -85,27 +168,74 @@ impl<'a> Interpreter<'a> {
         Ok(Some(line))
     }
 
+    pub fn args(&self) -> &[String] {
+        self.args
+    }
+
+    pub fn now(&self) -> f64 {
+        self.clock.now()
+    }
+
+    pub fn monotonic(&self) -> f64 {
+        self.clock.monotonic()
+    }
+
+    pub fn sleep(&self, ms: f64) {
+        self.clock.sleep(ms);
+    }
+
+    /// Unwinds the whole program, the exit code is reported through `InterpreterFailure`
+    #[expect(
+        clippy::unused_self,
+        reason = "the generated Rust exits from here instead"
+    )]
+    pub fn exit(&mut self, code: i32) -> InterpreterResult {
+        Err(InterpreterError::Exited(code))
+    }
+
+    /// Unwinds to the innermost try, which receives `value` instead of an error description
+    #[expect(
+        clippy::unused_self,
+        reason = "intrinsics throw through the interpreter"
+    )]
+    pub fn throw(&mut self, value: Value<'a>) -> InterpreterResult {
+        Err(InterpreterError::Thrown(value.into()))
+    }
+
+    /// What a handler receives for an error other than exiting
+    pub fn caught(error: InterpreterError) -> Value<'a> {
+        match error {
+            InterpreterError::Failed(kind, _) => kind.description().into(),
+            InterpreterError::Reported(message) => message.into_owned().into(),
+            InterpreterError::Thrown(value) => value.into(),
+            InterpreterError::Exited(_) => unreachable!("exiting is never caught"),
+        }
+    }
+
     // Codegen Interpreter Start
     pub fn take(&mut self) -> InterpreterValueResult<Value<'a>> {
         match self.stack.pop() {
             Some(a) => Ok(a),
-            None => Err("Stack empty".into()),
+            None => Err(ErrorKind::StackUnderflow.error("Stack empty")),
         }
     }
 
     pub fn take_number(&mut self) -> InterpreterValueResult<f64> {
-        if let Value::Number(v) = self.take()? {
-            Ok(v)
-        } else {
-            Err("Expected number on top of stack".into())
+        let v = self.take()?;
+        if let Some(n) = v.to_number() {
+            return Ok(n);
         }
+        self.stack.push(v);
+        Err(ErrorKind::TypeMismatch.error("Expected number on top of stack"))
     }
 
     pub fn take_string(&mut self) -> InterpreterValueResult<Rc<CharString<'a>>> {
-        if let Value::String(v) = self.take()? {
+        let v = self.take()?;
+        if let Value::String(v) = v {
             Ok(v)
         } else {
-            Err("Expected string on top of stack".into())
+            self.stack.push(v);
+            Err(ErrorKind::TypeMismatch.error("Expected string on top of stack"))
         }
     }
 
@@ -143,12 +273,18 @@ impl<'a> Interpreter<'a> {
     }
 
     pub fn take2(&mut self) -> InterpreterValueResult<(Value<'a>, Value<'a>)> {
+        if self.stack.len() < 2 {
+            return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+        }
         let top = self.take()?;
         let second = self.take()?;
         Ok((second, top))
     }
 
     pub fn take3(&mut self) -> InterpreterValueResult<(Value<'a>, Value<'a>, Value<'a>)> {
+        if self.stack.len() < 3 {
+            return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+        }
         let c = self.take()?;
         let b = self.take()?;
         let a = self.take()?;
@@ -156,9 +292,23 @@ impl<'a> Interpreter<'a> {
     }
 
     pub fn take2_numbers(&mut self) -> InterpreterValueResult<(f64, f64)> {
+        let (a, b) = self.take2()?;
+        if let (Some(a), Some(b)) = (a.to_number(), b.to_number()) {
+            return Ok((a, b));
+        }
+        self.stack.push(a);
+        self.stack.push(b);
+        Err(ErrorKind::TypeMismatch.error("Expected two numbers on top of stack"))
+    }
+
+    pub fn take2_integers(&mut self) -> InterpreterValueResult<(i64, i64)> {
         match self.take2()? {
-            (Value::Number(a), Value::Number(b)) => Ok((a, b)),
-            _ => Err("Expected two numbers on top of stack".into()),
+            (Value::Integer(a), Value::Integer(b)) => Ok((a, b)),
+            (a, b) => {
+                self.stack.push(a);
+                self.stack.push(b);
+                Err(ErrorKind::TypeMismatch.error("Expected two integers on top of stack"))
+            }
         }
     }
 
@@ -170,22 +320,27 @@ impl<'a> Interpreter<'a> {
             .map_or(self.base_namespace, std::borrow::ToOwned::to_owned)
     }
 
-    fn evaluate_block(&mut self, block: &'a Block) -> InterpreterResult {
+    fn evaluate_block(&mut self, block: &'a Block) -> InterpreterValueResult<Flow> {
         for term in &block.terms {
-            self.evaluate_term(term)?;
+            let flow = self.evaluate_term(term)?;
+            if flow != Flow::Next {
+                return Ok(flow);
+            }
         }
-        Ok(())
+        Ok(Flow::Next)
     }
 
-    fn evaluate_branch(&mut self, b: &'a Branch) -> InterpreterResult {
+    fn evaluate_branch(&mut self, b: &'a Branch) -> InterpreterValueResult<Flow> {
         for arm in &b.arms {
-            self.evaluate_block(&arm.0)?;
+            let flow = self.evaluate_block(&arm.0)?;
+            if flow != Flow::Next {
+                return Ok(flow);
+            }
             if self.take()?.is_truthy() {
-                self.evaluate_block(&arm.1)?;
-                return Ok(());
+                return self.evaluate_block(&arm.1);
             }
         }
-        Ok(())
+        Ok(Flow::Next)
     }
 
     pub fn evaluate_name(&mut self, current_namespace: usize, name: &str) -> InterpreterResult {
@@ -196,39 +351,150 @@ impl<'a> Interpreter<'a> {
         let Some((resolved_namespace, resolved_name)) =
             self.program.resolve_function(current_namespace, name)
         else {
-            return Err(format!("Unknown function name: {name}").into());
+            return Err(InterpreterError::Reported(
+                format!("Unknown function name: {name}").into(),
+            ));
         };
 
         let function = &self.program.namespaces[resolved_namespace].functions[resolved_name];
         self.namespace_stack.push(resolved_namespace);
+        let caller_base = std::mem::replace(&mut self.locals_base, self.locals.len());
         self.evaluate_block(&function.body)?;
+        self.locals.truncate(self.locals_base);
+        self.locals_base = caller_base;
         self.namespace_stack.pop();
 
         Ok(())
     }
 
-    fn evaluate_loop(&mut self, l: &'a Loop) -> InterpreterResult {
+    // A falsy condition breaks out of the loop
+    fn evaluate_loop_condition(
+        &mut self,
+        condition: Option<&'a Block>,
+    ) -> InterpreterValueResult<Flow> {
+        let Some(b) = condition else {
+            return Ok(Flow::Next);
+        };
+        let flow = self.evaluate_block(b)?;
+        if flow != Flow::Next {
+            return Ok(flow);
+        }
+        Ok(if self.take()?.is_truthy() {
+            Flow::Next
+        } else {
+            Flow::Break
+        })
+    }
+
+    fn evaluate_loop(&mut self, l: &'a Loop) -> InterpreterValueResult<Flow> {
+        let exit = |flow| match flow {
+            Flow::Next | Flow::Continue => None,
+            Flow::Break => Some(Flow::Next),
+            Flow::Return => Some(Flow::Return),
+        };
         loop {
-            match &l.pre_condition {
-                None => {}
-                Some(b) => {
-                    self.evaluate_block(b)?;
-                    if !self.take()?.is_truthy() {
-                        return Ok(());
-                    }
+            if let Some(flow) = exit(self.evaluate_loop_condition(l.pre_condition.as_ref())?) {
+                return Ok(flow);
+            }
+            // A continue still runs the post condition
+            if let Some(flow) = exit(self.evaluate_block(&l.body)?) {
+                return Ok(flow);
+            }
+            if let Some(flow) = exit(self.evaluate_loop_condition(l.post_condition.as_ref())?) {
+                return Ok(flow);
+            }
+        }
+    }
+
+    // Errors other than exiting restore the stack from before the body and run the handler
+    fn evaluate_try(&mut self, t: &'a Try) -> InterpreterValueResult<Flow> {
+        let stack = self.stack.clone();
+        let backtrace = self.backtrace.len();
+        let namespaces = self.namespace_stack.len();
+        let (locals, locals_base) = (self.locals.len(), self.locals_base);
+        match self.evaluate_block(&t.body) {
+            Err(error) if error.exit_code().is_none() => {
+                self.stack = stack;
+                self.backtrace.truncate(backtrace);
+                self.namespace_stack.truncate(namespaces);
+                self.locals.truncate(locals);
+                self.locals_base = locals_base;
+                let value = Self::caught(error);
+                self.push(value)?;
+                self.evaluate_block(&t.handler)
+            }
+            result => result,
+        }
+    }
+
+    // The constructor takes the fields in declaration order, so the last field is on top
+    fn evaluate_record(&mut self, record: &Rc<Record>, op: RecordOp) -> InterpreterResult {
+        match op {
+            RecordOp::Construct => {
+                let Some(start) = self.stack.len().checked_sub(record.fields.len()) else {
+                    return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+                };
+                let values = self.stack.split_off(start);
+                self.push(Value::Record(Rc::new(RecordValue {
+                    record: Rc::clone(record),
+                    values,
+                })))
+            }
+            RecordOp::Get(i) => {
+                let v = self.take()?;
+                if let Value::Record(r) = &v
+                    && Rc::ptr_eq(&r.record, record)
+                {
+                    return self.push(r.values[i].clone());
                 }
+                self.stack.push(v);
+                Err(ErrorKind::TypeMismatch
+                    .error(format!("Expected {} on top of stack", record.name)))
             }
-            self.evaluate_block(&l.body)?;
-            match &l.post_condition {
-                None => {}
-                Some(b) => {
-                    self.evaluate_block(b)?;
-                    if !self.take()?.is_truthy() {
-                        return Ok(());
-                    }
+            RecordOp::With(i) => match self.take2()? {
+                (Value::Record(mut r), value) if Rc::ptr_eq(&r.record, record) => {
+                    Rc::make_mut(&mut r).values[i] = value;
+                    self.push(Value::Record(r))
+                }
+                (target, value) => {
+                    self.push2(target, value)?;
+                    Err(ErrorKind::TypeMismatch
+                        .error(format!("Expected {} below the top of stack", record.name)))
                 }
+            },
+        }
+    }
+
+    // The matched value is replaced by the fields of its variant before the arm runs
+    fn evaluate_match(&mut self, m: &'a Match) -> InterpreterValueResult<Flow> {
+        let namespace = self.get_current_namespace();
+        let value = self.take()?;
+        let mut expected = "";
+        for (name, body) in &m.arms {
+            let Some(variant) = self.program.resolve_variant(namespace, name) else {
+                self.stack.push(value);
+                return Err(InterpreterError::Reported(
+                    format!("Unknown variant: {name}").into(),
+                ));
+            };
+            if let Value::Record(r) = &value
+                && Rc::ptr_eq(&r.record, variant)
+            {
+                self.stack.extend(r.values.iter().cloned());
+                return self.evaluate_block(body);
             }
+            expected = variant.type_name();
         }
+
+        let error = if let Value::Record(r) = &value
+            && r.record.type_name() == expected
+        {
+            ErrorKind::NoMatch.error(format!("No match arm for {}", r.record.name))
+        } else {
+            ErrorKind::TypeMismatch.error(format!("Expected {expected} on top of stack"))
+        };
+        self.stack.push(value);
+        Err(error)
     }
 
     fn store_address(&mut self, name: &'a str) -> InterpreterResult {
@@ -236,21 +502,51 @@ impl<'a> Interpreter<'a> {
         self.push(Value::Address(current_namespace, name.into()))
     }
 
-    fn evaluate_term(&mut self, term: &'a Term) -> InterpreterResult {
+    // The last name is bound to the top of the stack
+    fn bind_locals(&mut self, locals: &[Local]) -> InterpreterResult {
+        if self.stack.len() < locals.len() {
+            return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+        }
+        for local in locals.iter().rev() {
+            let value = self.take()?;
+            let i = self.locals_base + local.slot;
+            if self.locals.len() <= i {
+                self.locals.resize(i + 1, Value::Bool(false));
+            }
+            self.locals[i] = value;
+        }
+        Ok(())
+    }
+
+    fn evaluate_term(&mut self, term: &'a Term) -> InterpreterValueResult<Flow> {
         match term {
-            Term::String(l) => self.push(Value::String(Rc::new(l.as_str().into()))),
-            Term::Number(l) => self.push(Value::Number(*l)),
-            Term::Bool(l) => self.push(Value::Bool(*l)),
+            Term::String(l) => self.push(Value::String(Rc::new(l.as_str().into())))?,
+            Term::Number(l) => self.push(Value::Number(*l))?,
+            Term::Integer(l) => self.push(Value::Integer(*l))?,
+            Term::Big(l) => self.push(l.clone())?,
+            Term::Decimal(l) => self.push(l.clone())?,
+            Term::Bool(l) => self.push(Value::Bool(*l))?,
             Term::Name(name, _) => {
                 let current_namespace = self.get_current_namespace();
                 self.backtrace.push((current_namespace, term));
                 self.evaluate_name(current_namespace, name)?;
                 self.backtrace.pop();
-                Ok(())
             }
-            Term::Branch(b) => self.evaluate_branch(b),
-            Term::Loop(l) => self.evaluate_loop(l),
-            Term::Address(s) => self.store_address(s),
+            Term::Bind(locals) => self.bind_locals(locals)?,
+            Term::Local(local) => {
+                let value = self.locals[self.locals_base + local.slot].clone();
+                self.push(value)?;
+            }
+            Term::Break => return Ok(Flow::Break),
+            Term::Continue => return Ok(Flow::Continue),
+            Term::Return => return Ok(Flow::Return),
+            Term::Branch(b) => return self.evaluate_branch(b),
+            Term::Loop(l) => return self.evaluate_loop(l),
+            Term::Try(t) => return self.evaluate_try(t),
+            Term::Address(s) => self.store_address(s)?,
+            Term::Record(record, op) => self.evaluate_record(record, *op)?,
+            Term::Match(m) => return self.evaluate_match(m),
         }
+        Ok(Flow::Next)
     }
 }
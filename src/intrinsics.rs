@@ -1,46 +1,277 @@
-use std::{rc::Rc, sync::OnceLock};
+use std::{borrow::Cow, ops::Range, rc::Rc, sync::OnceLock};
 
 use crate::{
     analyze::AnalysisError,
     convert::{f64_to_char, f64_to_usize, usize_to_f64},
-    interpreter::{Interpreter, InterpreterResult},
+    interpreter::{Interpreter, InterpreterError, InterpreterResult, InterpreterValueResult},
     lang::{
-        Arity, Type, Value,
-        string::{CharString, StringApi as _},
+        Arity, BigInt, Decimal, Type, Value,
+        string::{CharString, Regex, RegexCaptures, StringApi as _},
     },
 };
 
 type Intrinsic = fn(&mut Interpreter) -> InterpreterResult;
 
 // Codegen Intrinsics Start
+// Arithmetic stays exact when both operands are integers, bignums or decimals, with each promoted
+// to the wider of the two when they are mixed. Anything else is promoted to floats
+enum Operands {
+    Integers(i64, i64),
+    Bigs(Rc<BigInt>, Rc<BigInt>),
+    Decimals(Rc<Decimal>, Rc<Decimal>),
+    Floats(f64, f64),
+}
+
+fn take_operands(i: &mut Interpreter) -> InterpreterValueResult<Operands> {
+    match i.take2()? {
+        (Value::Integer(a), Value::Integer(b)) => Ok(Operands::Integers(a, b)),
+        (Value::Big(a), Value::Big(b)) => Ok(Operands::Bigs(a, b)),
+        (Value::Big(a), Value::Integer(b)) => Ok(Operands::Bigs(a, Rc::new(b.into()))),
+        (Value::Integer(a), Value::Big(b)) => Ok(Operands::Bigs(Rc::new(a.into()), b)),
+        (a, b) => {
+            if let (Some(a), Some(b)) = (a.to_decimal(), b.to_decimal()) {
+                return Ok(Operands::Decimals(a, b));
+            }
+            i.push2(a, b)?;
+            let (a, b) = i.take2_numbers()?;
+            Ok(Operands::Floats(a, b))
+        }
+    }
+}
+
+// Runs an intrinsic on the top `N` values, which are left on the stack when it fails so that the
+// error shows what it failed on. A shorter stack is kept whole
+fn keeping_operands<const N: usize>(
+    i: &mut Interpreter,
+    intrinsic: impl FnOnce(&mut Interpreter) -> InterpreterResult,
+) -> InterpreterResult {
+    let base = i.stack.len().saturating_sub(N);
+    let operands: [Option<Value>; N] = std::array::from_fn(|n| i.stack.get(base + n).cloned());
+    let result = intrinsic(i);
+    if result.is_err() {
+        i.stack.truncate(base);
+        i.stack.extend(operands.into_iter().flatten());
+    }
+    result
+}
+
+fn checked_integer<T>(v: Option<T>) -> InterpreterValueResult<T> {
+    v.ok_or_else(|| ErrorKind::IntegerOverflow.error("Integer overflow"))
+}
+
+fn big_div_rem(a: &BigInt, b: &BigInt) -> InterpreterValueResult<(BigInt, BigInt)> {
+    a.checked_div_rem(b)
+        .ok_or_else(|| ErrorKind::DivisionByZero.error("Division by zero"))
+}
+
+fn checked_division<T>(v: Option<T>) -> InterpreterValueResult<T> {
+    v.ok_or_else(|| ErrorKind::DivisionByZero.error("Division by zero"))
+}
+
 fn plus(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a + b)
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(checked_integer(a.checked_add(b))?),
+        Operands::Bigs(a, b) => i.push(&*a + &*b),
+        Operands::Decimals(a, b) => i.push(&*a + &*b),
+        Operands::Floats(a, b) => i.push(a + b),
+    })
 }
 
 fn minus(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a - b)
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(checked_integer(a.checked_sub(b))?),
+        Operands::Bigs(a, b) => i.push(&*a - &*b),
+        Operands::Decimals(a, b) => i.push(&*a - &*b),
+        Operands::Floats(a, b) => i.push(a - b),
+    })
 }
 
 fn times(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a * b)
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(checked_integer(a.checked_mul(b))?),
+        Operands::Bigs(a, b) => i.push(&*a * &*b),
+        Operands::Decimals(a, b) => i.push(&*a * &*b),
+        Operands::Floats(a, b) => i.push(a * b),
+    })
 }
 
+// Integer division truncates towards zero, and so does decimal division past its last place
 fn divide(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a / b)
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(_, 0) => Err(ErrorKind::DivisionByZero.error("Division by zero")),
+        Operands::Integers(a, b) => i.push(checked_integer(a.checked_div(b))?),
+        Operands::Bigs(a, b) => i.push(big_div_rem(&a, &b)?.0),
+        Operands::Decimals(a, b) => i.push(checked_division(a.checked_div(&b))?),
+        Operands::Floats(a, b) => i.push(a / b),
+    })
 }
 
 fn modulo(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a % b)
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(_, 0) => Err(ErrorKind::DivisionByZero.error("Division by zero")),
+        Operands::Integers(a, b) => i.push(a.wrapping_rem(b)),
+        Operands::Bigs(a, b) => i.push(big_div_rem(&a, &b)?.1),
+        Operands::Decimals(a, b) => i.push(checked_division(a.checked_rem(&b))?),
+        Operands::Floats(a, b) => i.push(a % b),
+    })
 }
 
 fn pow_i(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| match take_operands(i)? {
+        Operands::Integers(_, b) if b < 0 => {
+            Err(ErrorKind::InvalidArgument.error("Negative exponent"))
+        }
+        Operands::Integers(a, b) => {
+            let power = u32::try_from(b).ok().and_then(|b| a.checked_pow(b));
+            i.push(checked_integer(power)?)
+        }
+        Operands::Bigs(_, b) if b.is_negative() => {
+            Err(ErrorKind::InvalidArgument.error("Negative exponent"))
+        }
+        Operands::Bigs(a, b) => {
+            let power = b
+                .to_i64()
+                .and_then(|b| u64::try_from(b).ok())
+                .and_then(|b| a.checked_pow(b));
+            i.push(checked_integer(power)?)
+        }
+        Operands::Decimals(_, b) if b.is_negative() => {
+            Err(ErrorKind::InvalidArgument.error("Negative exponent"))
+        }
+        Operands::Decimals(a, b) => {
+            let Some(b) = b.to_whole() else {
+                return Err(ErrorKind::InvalidArgument.error("Exponent must be a whole number"));
+            };
+            let power = b
+                .to_i64()
+                .and_then(|b| u64::try_from(b).ok())
+                .and_then(|b| a.checked_pow(b));
+            i.push(checked_integer(power)?)
+        }
+        Operands::Floats(a, b) => i.push(a.powf(b)),
+    })
+}
+
+fn floor_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.floor())
+}
+
+fn ceil_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.ceil())
+}
+
+fn round_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.round())
+}
+
+fn trunc_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.trunc())
+}
+
+fn sqrt_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.sqrt())
+}
+
+fn sin_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.sin())
+}
+
+fn cos_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.cos())
+}
+
+fn tan_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.tan())
+}
+
+fn asin_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.asin())
+}
+
+fn acos_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.acos())
+}
+
+fn atan_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.atan())
+}
+
+fn exp_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.exp())
+}
+
+fn log_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.ln())
+}
+
+fn log10_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.log10())
+}
+
+fn log2_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v.log2())
+}
+
+fn abs_i(i: &mut Interpreter) -> InterpreterResult {
+    if let Some(Value::Integer(v)) = i.stack.last() {
+        let v = checked_integer(v.checked_abs())?;
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Big(v)) = i.stack.last() {
+        let v = v.abs();
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Decimal(v)) = i.stack.last() {
+        let v = v.abs();
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    let v = i.take_number()?;
+    i.push(v.abs())
+}
+
+fn atan2_i(i: &mut Interpreter) -> InterpreterResult {
     let (a, b) = i.take2_numbers()?;
-    i.push(a.powf(b))
+    i.push(a.atan2(b))
+}
+
+fn min(i: &mut Interpreter) -> InterpreterResult {
+    match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(a.min(b)),
+        Operands::Bigs(a, b) => i.push(Value::Big(a.min(b))),
+        Operands::Decimals(a, b) => i.push(Value::Decimal(a.min(b))),
+        Operands::Floats(a, b) => i.push(a.min(b)),
+    }
+}
+
+fn max(i: &mut Interpreter) -> InterpreterResult {
+    match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(a.max(b)),
+        Operands::Bigs(a, b) => i.push(Value::Big(a.max(b))),
+        Operands::Decimals(a, b) => i.push(Value::Decimal(a.max(b))),
+        Operands::Floats(a, b) => i.push(a.max(b)),
+    }
+}
+
+fn pi(i: &mut Interpreter) -> InterpreterResult {
+    i.push(std::f64::consts::PI)
 }
 
 fn or_i(i: &mut Interpreter) -> InterpreterResult {
@@ -79,13 +310,21 @@ fn drop(i: &mut Interpreter) -> InterpreterResult {
 }
 
 fn greater(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a > b)
+    match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(a > b),
+        Operands::Bigs(a, b) => i.push(a > b),
+        Operands::Decimals(a, b) => i.push(a > b),
+        Operands::Floats(a, b) => i.push(a > b),
+    }
 }
 
 fn less(i: &mut Interpreter) -> InterpreterResult {
-    let (a, b) = i.take2_numbers()?;
-    i.push(a < b)
+    match take_operands(i)? {
+        Operands::Integers(a, b) => i.push(a < b),
+        Operands::Bigs(a, b) => i.push(a < b),
+        Operands::Decimals(a, b) => i.push(a < b),
+        Operands::Floats(a, b) => i.push(a < b),
+    }
 }
 
 fn not(i: &mut Interpreter) -> InterpreterResult {
@@ -94,26 +333,166 @@ fn not(i: &mut Interpreter) -> InterpreterResult {
 }
 
 fn decrement(i: &mut Interpreter) -> InterpreterResult {
+    if let Some(Value::Integer(v)) = i.stack.last() {
+        let v = checked_integer(v.checked_sub(1))?;
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Big(v)) = i.stack.last() {
+        let v = &**v - &BigInt::from(1);
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Decimal(v)) = i.stack.last() {
+        let v = &**v - &Decimal::from(1);
+        let _ = i.take()?;
+        return i.push(v);
+    }
     let v = i.take_number()?;
     i.push(v - 1f64)
 }
 
 fn increment(i: &mut Interpreter) -> InterpreterResult {
+    if let Some(Value::Integer(v)) = i.stack.last() {
+        let v = checked_integer(v.checked_add(1))?;
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Big(v)) = i.stack.last() {
+        let v = &**v + &BigInt::from(1);
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    if let Some(Value::Decimal(v)) = i.stack.last() {
+        let v = &**v + &Decimal::from(1);
+        let _ = i.take()?;
+        return i.push(v);
+    }
     let v = i.take_number()?;
     i.push(v + 1f64)
 }
 
-fn substring(i: &mut Interpreter) -> InterpreterResult {
-    let Some(end) = f64_to_usize(i.take_number()?) else {
-        return Err("Invalid substring end index".into());
-    };
-    let Some(start) = f64_to_usize(i.take_number()?) else {
-        return Err("Invalid substring start index".into());
+fn band(i: &mut Interpreter) -> InterpreterResult {
+    let (a, b) = i.take2_integers()?;
+    i.push(a & b)
+}
+
+fn bor(i: &mut Interpreter) -> InterpreterResult {
+    let (a, b) = i.take2_integers()?;
+    i.push(a | b)
+}
+
+fn bxor(i: &mut Interpreter) -> InterpreterResult {
+    let (a, b) = i.take2_integers()?;
+    i.push(a ^ b)
+}
+
+fn take_shift(i: &mut Interpreter) -> InterpreterValueResult<(i64, u32)> {
+    let (a, b) = i.take2_integers()?;
+    match u32::try_from(b) {
+        Ok(b) if b < 64 => Ok((a, b)),
+        _ => Err(ErrorKind::InvalidArgument.error("Invalid shift")),
+    }
+}
+
+// Bits shifted past either end are lost, shifting right keeps the sign
+fn shl(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let (a, b) = take_shift(i)?;
+        i.push(a << b)
+    })
+}
+
+fn shr(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let (a, b) = take_shift(i)?;
+        i.push(a >> b)
+    })
+}
+
+// Floats and decimals are truncated, and must fit in an integer like bignums
+fn to_int(i: &mut Interpreter) -> InterpreterResult {
+    if let Some(Value::Integer(_)) = i.stack.last() {
+        return Ok(());
+    }
+    let exact = match i.stack.last() {
+        Some(Value::Big(v)) => Some(Rc::clone(v)),
+        Some(Value::Decimal(v)) => Some(Rc::new(v.trunc())),
+        _ => None,
     };
-    let original = i.take_string()?;
-    let start = start.min(original.len());
-    let end = end.min(original.len()).max(start);
-    i.push(Value::String(Rc::new(original.substring(start..end))))
+    if let Some(v) = exact {
+        let Some(v) = v.to_i64() else {
+            return Err(ErrorKind::InvalidArgument.error("Number out of integer range"));
+        };
+        let _ = i.take()?;
+        return i.push(v);
+    }
+    let v = i.take_number()?;
+    if !(-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&v.trunc()) {
+        i.push(v)?;
+        return Err(ErrorKind::InvalidArgument.error("Number out of integer range"));
+    }
+    #[expect(clippy::cast_possible_truncation)]
+    i.push(v.trunc() as i64)
+}
+
+fn to_float(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take_number()?;
+    i.push(v)
+}
+
+fn to_big(i: &mut Interpreter) -> InterpreterResult {
+    match i.take()? {
+        v @ Value::Big(_) => i.push(v),
+        Value::Integer(v) => i.push(BigInt::from(v)),
+        Value::Decimal(v) => i.push(v.trunc()),
+        Value::Number(v) => {
+            if let Some(big) = BigInt::from_f64(v) {
+                return i.push(big);
+            }
+            i.push(v)?;
+            Err(ErrorKind::InvalidArgument.error("Expected a finite number"))
+        }
+        v @ (Value::String(_) | Value::Bool(_) | Value::Address(..) | Value::Record(_)) => {
+            i.stack.push(v);
+            Err(ErrorKind::TypeMismatch.error("Expected number on top of stack"))
+        }
+    }
+}
+
+// Numbers keep the shortest digits that read back as them, so `0.1 to_dec` is `0.1d`
+fn to_dec(i: &mut Interpreter) -> InterpreterResult {
+    match i.take()? {
+        v @ Value::Decimal(_) => i.push(v),
+        Value::Integer(v) => i.push(Decimal::from(v)),
+        Value::Big(v) => i.push(Decimal::from(Rc::unwrap_or_clone(v))),
+        Value::Number(v) => {
+            if let Some(decimal) = Decimal::from_f64(v) {
+                return i.push(decimal);
+            }
+            i.push(v)?;
+            Err(ErrorKind::InvalidArgument.error("Expected a finite number"))
+        }
+        v @ (Value::String(_) | Value::Bool(_) | Value::Address(..) | Value::Record(_)) => {
+            i.stack.push(v);
+            Err(ErrorKind::TypeMismatch.error("Expected number on top of stack"))
+        }
+    }
+}
+
+fn substring(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<3>(i, |i| {
+        let Some(end) = f64_to_usize(i.take_number()?) else {
+            return Err(ErrorKind::InvalidArgument.error("Invalid substring end index"));
+        };
+        let Some(start) = f64_to_usize(i.take_number()?) else {
+            return Err(ErrorKind::InvalidArgument.error("Invalid substring start index"));
+        };
+        let original = i.take_string()?;
+        let start = start.min(original.len());
+        let end = end.min(original.len()).max(start);
+        i.push(Value::String(Rc::new(original.substring(start..end))))
+    })
 }
 
 fn join(i: &mut Interpreter) -> InterpreterResult {
@@ -122,50 +501,280 @@ fn join(i: &mut Interpreter) -> InterpreterResult {
 }
 
 fn length(i: &mut Interpreter) -> InterpreterResult {
-    let Some(len) = usize_to_f64(i.take_string()?.len()) else {
-        return Err("String length is out of range".into());
-    };
-    i.push(len)
+    keeping_operands::<1>(i, |i| {
+        let Some(len) = usize_to_f64(i.take_string()?.len()) else {
+            return Err(ErrorKind::InvalidArgument.error("String length is out of range"));
+        };
+        i.push(len)
+    })
 }
 
 fn to_char(i: &mut Interpreter) -> InterpreterResult {
-    let s = i.take_string()?;
-    if s.len() != 1 {
-        return Err("to_ascii only works on strings with length: 1".into());
-    }
-    let code = s[0] as u32;
-    i.push(f64::from(code))
+    keeping_operands::<1>(i, |i| {
+        let s = i.take_string()?;
+        if s.len() != 1 {
+            return Err(
+                ErrorKind::TypeMismatch.error("to_ascii only works on strings with length: 1")
+            );
+        }
+        let code = s[0] as u32;
+        i.push(f64::from(code))
+    })
 }
 
 fn from_char(i: &mut Interpreter) -> InterpreterResult {
-    let s = i.take_number()?;
-    let Some(char) = f64_to_char(s) else {
-        return Err("from_char only works with valid unicode codepoints".into());
-    };
-    i.push(Value::String(Rc::new(CharString::from(char))))
+    keeping_operands::<1>(i, |i| {
+        let s = i.take_number()?;
+        let Some(char) = f64_to_char(s) else {
+            return Err(ErrorKind::InvalidArgument
+                .error("from_char only works with valid unicode codepoints"));
+        };
+        i.push(Value::String(Rc::new(CharString::from(char))))
+    })
 }
 
 fn string_index(i: &mut Interpreter) -> InterpreterResult {
-    let needle = i.take_string()?;
-    let haystack = i.take_string()?;
-    let location = match haystack.find(&needle) {
-        Some(e) => match usize_to_f64(e) {
-            Some(e) => e,
-            None => return Err("String index cannot be converted to number".into()),
-        },
-        None => -1f64,
-    };
-    i.push(location)
+    keeping_operands::<2>(i, |i| {
+        let needle = i.take_string()?;
+        let haystack = i.take_string()?;
+        let location = match haystack.find(&needle) {
+            Some(e) => match usize_to_f64(e) {
+                Some(e) => e,
+                None => {
+                    return Err(ErrorKind::InvalidArgument
+                        .error("String index cannot be converted to number"));
+                }
+            },
+            None => -1f64,
+        };
+        i.push(location)
+    })
+}
+
+fn last_index(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let needle = i.take_string()?;
+        let haystack = i.take_string()?;
+        let location = match haystack.rfind(&needle) {
+            Some(e) => match usize_to_f64(e) {
+                Some(e) => e,
+                None => {
+                    return Err(ErrorKind::InvalidArgument
+                        .error("String index cannot be converted to number"));
+                }
+            },
+            None => -1f64,
+        };
+        i.push(location)
+    })
+}
+
+fn starts_with(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let prefix = i.take_string()?;
+        let s = i.take_string()?;
+        i.push(s.starts_with(&prefix))
+    })
+}
+
+fn ends_with(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let suffix = i.take_string()?;
+        let s = i.take_string()?;
+        i.push(s.ends_with(&suffix))
+    })
+}
+
+fn split(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let separator = i.take_string()?;
+        let s = i.take_string()?;
+        let pieces = s.split(&separator);
+        let Some(count) = usize_to_f64(pieces.len()) else {
+            return Err(ErrorKind::InvalidArgument.error("Split count is out of range"));
+        };
+        for piece in pieces {
+            i.push(Value::String(Rc::new(piece)))?;
+        }
+        i.push(count)
+    })
+}
+
+fn replace(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<3>(i, |i| {
+        let to = i.take_string()?;
+        let from = i.take_string()?;
+        let s = i.take_string()?;
+        if from.is_empty() {
+            return Err(ErrorKind::InvalidArgument.error("Cannot replace an empty string"));
+        }
+        i.push(Value::String(Rc::new(s.replace(&from, &to))))
+    })
+}
+
+fn trim(i: &mut Interpreter) -> InterpreterResult {
+    let s = i.take_string()?;
+    i.push(Value::String(Rc::new(s.trim())))
+}
+
+fn to_upper(i: &mut Interpreter) -> InterpreterResult {
+    let s = i.take_string()?;
+    i.push(Value::String(Rc::new(s.to_uppercase())))
+}
+
+fn to_lower(i: &mut Interpreter) -> InterpreterResult {
+    let s = i.take_string()?;
+    i.push(Value::String(Rc::new(s.to_lowercase())))
+}
+
+fn repeat(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let count = i.take_number()?;
+        let s = i.take_string()?;
+        let Some(count) = f64_to_usize(count).filter(|&c| s.len().saturating_mul(c) <= 0x7FFF_FFFF)
+        else {
+            return Err(ErrorKind::InvalidArgument.error("Invalid repeat count"));
+        };
+        i.push(Value::String(Rc::new(s.repeat(count))))
+    })
+}
+
+fn reverse(i: &mut Interpreter) -> InterpreterResult {
+    let s = i.take_string()?;
+    i.push(Value::String(Rc::new(s.reverse())))
+}
+
+fn take_regex(i: &mut Interpreter) -> InterpreterValueResult<Regex> {
+    let pattern = i.take_string()?;
+    Regex::new(&pattern.to_string())
+        .ok_or_else(|| ErrorKind::InvalidArgument.error("Invalid regex"))
+}
+
+fn find_regex(
+    regex: &Regex,
+    s: &CharString,
+    start: usize,
+) -> InterpreterValueResult<Option<RegexCaptures>> {
+    regex
+        .find_at(s, start)
+        .map_err(|_| ErrorKind::InvalidArgument.error("Regex took too many steps"))
+}
+
+fn capture_substring<'a>(s: &CharString<'a>, capture: Option<&Range<usize>>) -> CharString<'a> {
+    s.substring(capture.cloned().unwrap_or_default())
+}
+
+fn matches(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let regex = take_regex(i)?;
+        let s = i.take_string()?;
+        i.push(find_regex(&regex, &s, 0)?.is_some())
+    })
+}
+
+fn find_match(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let regex = take_regex(i)?;
+        let s = i.take_string()?;
+        let Some(captures) = find_regex(&regex, &s, 0)? else {
+            return i.push2("", -1f64);
+        };
+        let Some(index) = captures[0].as_ref().and_then(|e| usize_to_f64(e.start)) else {
+            return Err(
+                ErrorKind::InvalidArgument.error("String index cannot be converted to number")
+            );
+        };
+        i.push2(
+            Value::String(Rc::new(capture_substring(&s, captures[0].as_ref()))),
+            index,
+        )
+    })
+}
+
+fn expand_replacement(replacement: &str, s: &CharString, captures: &RegexCaptures) -> String {
+    let mut res = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        let group = chars
+            .peek()
+            .and_then(|e| e.to_digit(10))
+            .and_then(|e| usize::try_from(e).ok())
+            .filter(|&e| e < captures.len());
+        match (c, chars.peek(), group) {
+            ('$', _, Some(group)) => {
+                chars.next();
+                res.push_str(&capture_substring(s, captures[group].as_ref()).to_string());
+            }
+            ('$', Some('$'), _) => {
+                chars.next();
+                res.push('$');
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+fn replace_regex(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<3>(i, |i| {
+        let replacement = i.take_string()?.to_string();
+        let regex = take_regex(i)?;
+        let s = i.take_string()?;
+        let mut res = String::new();
+        let mut last = 0;
+        let mut pos = 0;
+        while pos <= s.len() {
+            let Some(captures) = find_regex(&regex, &s, pos)? else {
+                break;
+            };
+            let Some(found) = captures[0].clone() else {
+                break;
+            };
+            res.push_str(&s.substring(last..found.start).to_string());
+            res.push_str(&expand_replacement(&replacement, &s, &captures));
+            last = found.end;
+            pos = if found.is_empty() {
+                found.end + 1
+            } else {
+                found.end
+            };
+        }
+        res.push_str(&s.substring(last..s.len()).to_string());
+        i.push(res)
+    })
+}
+
+fn captures(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let regex = take_regex(i)?;
+        let s = i.take_string()?;
+        let Some(captures) = find_regex(&regex, &s, 0)? else {
+            return i.push(0f64);
+        };
+        let Some(count) = usize_to_f64(captures.len()) else {
+            return Err(ErrorKind::InvalidArgument.error("Capture count is out of range"));
+        };
+        for capture in &captures {
+            i.push(Value::String(Rc::new(capture_substring(
+                &s,
+                capture.as_ref(),
+            ))))?;
+        }
+        i.push(count)
+    })
 }
 
 fn equals(i: &mut Interpreter) -> InterpreterResult {
-    match i.take2()? {
-        #[expect(clippy::float_cmp)]
-        (Value::Number(a), Value::Number(b)) => i.push(a == b),
+    keeping_operands::<2>(i, |i| match i.take2()? {
+        (
+            a @ (Value::Number(_) | Value::Integer(_) | Value::Big(_) | Value::Decimal(_)),
+            b @ (Value::Number(_) | Value::Integer(_) | Value::Big(_) | Value::Decimal(_)),
+        ) => i.push(a == b),
         (Value::String(a), Value::String(b)) => i.push(a == b),
         (Value::Bool(a), Value::Bool(b)) => i.push(a == b),
-        _ => Err("Mismatched types cannot be compared with ==".into()),
-    }
+        (Value::Record(a), Value::Record(b)) => i.push(a == b),
+        _ => Err(ErrorKind::TypeMismatch.error("Mismatched types cannot be compared with ==")),
+    })
 }
 
 fn print(i: &mut Interpreter) -> InterpreterResult {
@@ -184,30 +793,431 @@ fn readline(i: &mut Interpreter) -> InterpreterResult {
     }
 }
 
-fn assert(i: &mut Interpreter) -> InterpreterResult {
-    let message = i.take_string()?;
-    if i.take()?.is_truthy() {
-        Ok(())
+fn read_file(i: &mut Interpreter) -> InterpreterResult {
+    let path = i.take_string()?.to_string();
+    match std::fs::read_to_string(path) {
+        Ok(contents) => i.push2(contents, true),
+        Err(_) => i.push2("", false),
+    }
+}
+
+fn write_file(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let contents = i.take_string()?.to_string();
+        let path = i.take_string()?.to_string();
+        i.push(std::fs::write(path, contents).is_ok())
+    })
+}
+
+fn append_file(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        use std::io::Write as _;
+        let contents = i.take_string()?.to_string();
+        let path = i.take_string()?.to_string();
+        let result = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut f| f.write_all(contents.as_bytes()));
+        i.push(result.is_ok())
+    })
+}
+
+fn file_exists(i: &mut Interpreter) -> InterpreterResult {
+    let path = i.take_string()?.to_string();
+    i.push(std::path::Path::new(&path).exists())
+}
+
+fn list_dir(i: &mut Interpreter) -> InterpreterResult {
+    let path = i.take_string()?.to_string();
+    let entries = std::fs::read_dir(path).and_then(|entries| {
+        entries
+            .map(|e| e.map(|e| e.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    match entries {
+        Ok(mut entries) => {
+            entries.sort();
+            i.push2(entries.join("\n"), true)
+        }
+        Err(_) => i.push2("", false),
+    }
+}
+
+fn eprint(i: &mut Interpreter) -> InterpreterResult {
+    #![expect(clippy::print_stderr, reason = "eprint intrinsic")]
+    eprintln!("{}", i.take()?);
+    Ok(())
+}
+
+fn write_i(i: &mut Interpreter) -> InterpreterResult {
+    #![expect(clippy::print_stdout, reason = "write intrinsic")]
+    print!("{}", i.take()?);
+    Ok(())
+}
+
+/// Splits a template on its `{}` placeholders, `{{` and `}}` escape literal braces
+pub fn split_format_template(template: &str) -> Option<Vec<String>> {
+    let mut pieces = vec![String::new()];
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                pieces.last_mut()?.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                pieces.push(String::new());
+            }
+            ('{' | '}', _) => return None,
+            (c, _) => pieces.last_mut()?.push(c),
+        }
+    }
+    Some(pieces)
+}
+
+fn format(i: &mut Interpreter) -> InterpreterResult {
+    use std::fmt::Write as _;
+    let template = i.take_string()?;
+    let Some(pieces) = split_format_template(&template.to_string()) else {
+        i.push(Value::String(template))?;
+        return Err(ErrorKind::InvalidFormat.error("Invalid format string"));
+    };
+    let count = pieces.len() - 1;
+    if i.stack.len() < count {
+        i.push(Value::String(template))?;
+        return Err(ErrorKind::StackUnderflow.error("Stack empty"));
+    }
+    let values = i.stack.split_off(i.stack.len() - count);
+    let mut result = String::new();
+    for (piece, value) in pieces.iter().zip(values.iter().map(Some).chain([None])) {
+        result.push_str(piece);
+        if let Some(value) = value {
+            let _ = write!(result, "{value}");
+        }
+    }
+    i.push(result)
+}
+
+/// Accepts `[+-]digits[.digits][(e|E)[+-]digits]`, digits may be omitted on one side of the `.`
+fn is_number_syntax(s: &str) -> bool {
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(split) => (&s[..split], Some(&s[split + 1..])),
+        None => (s, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+    let exponent_valid = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && all_digits(e)
+    });
+    !(whole.is_empty() && fraction.is_empty())
+        && all_digits(whole)
+        && all_digits(fraction)
+        && exponent_valid
+}
+
+fn parse_number(i: &mut Interpreter) -> InterpreterResult {
+    let s = i.take_string()?.to_string();
+    match s.parse::<f64>() {
+        Ok(n) if is_number_syntax(&s) => i.push2(n, true),
+        _ => i.push2(0f64, false),
+    }
+}
+
+fn to_string(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take()?;
+    i.push(v.to_string())
+}
+
+// Takes a number and the option it is formatted with, which are left on the stack when either is
+// invalid. Messages from `parse` are invalid argument errors
+fn take_formatting<T>(
+    i: &mut Interpreter,
+    parse: impl FnOnce(f64, f64) -> Result<T, &'static str>,
+) -> InterpreterValueResult<T> {
+    let (n, option) = i.take2()?;
+    let result = match (n.to_number(), option.to_number()) {
+        (Some(n), Some(option)) => {
+            parse(n, option).map_err(|message| ErrorKind::InvalidArgument.error(message))
+        }
+        _ => Err(ErrorKind::TypeMismatch.error("Expected two numbers on top of stack")),
+    };
+    if result.is_err() {
+        i.stack.push(n);
+        i.stack.push(option);
+    }
+    result
+}
+
+fn to_fixed(i: &mut Interpreter) -> InterpreterResult {
+    let (n, digits) = take_formatting(i, |n, digits| {
+        match f64_to_usize(digits).filter(|d| *d <= 100) {
+            Some(digits) => Ok((n, digits)),
+            None => Err("Invalid precision"),
+        }
+    })?;
+    i.push(format!("{n:.digits$}"))
+}
+
+fn to_radix(i: &mut Interpreter) -> InterpreterResult {
+    let (n, mut value, radix) = take_formatting(i, |n, radix| {
+        let Some(radix) = f64_to_usize(radix)
+            .and_then(|r| u32::try_from(r).ok())
+            .filter(|r| (2..=36).contains(r))
+        else {
+            return Err("Invalid radix");
+        };
+        match f64_to_usize(n.abs()) {
+            Some(value) => Ok((n, value, radix)),
+            None => Err("to_radix only works on integers"),
+        }
+    })?;
+    let mut digits = vec![];
+    loop {
+        let digit = u32::try_from(value % radix as usize).unwrap_or_default();
+        digits.push(char::from_digit(digit, radix).unwrap_or_default());
+        value /= radix as usize;
+        if value == 0 {
+            break;
+        }
+    }
+    if n < 0f64 {
+        digits.push('-');
+    }
+    i.push(digits.iter().rev().collect::<String>())
+}
+
+fn to_padded(i: &mut Interpreter) -> InterpreterResult {
+    let (n, width) = take_formatting(i, |n, width| match f64_to_usize(width) {
+        Some(width) => Ok((n, width)),
+        None => Err("Invalid width"),
+    })?;
+    let s = n.to_string();
+    let padding = width.saturating_sub(s.chars().count());
+    let padded = if !n.is_finite() {
+        format!("{}{s}", " ".repeat(padding))
+    } else if let Some(digits) = s.strip_prefix('-') {
+        format!("-{}{digits}", "0".repeat(padding))
     } else {
-        Err(format!("Assertion failed: {}", message).into())
+        format!("{}{s}", "0".repeat(padding))
+    };
+    i.push(padded)
+}
+
+// SplitMix64, the C and JS runtimes use the same steps so seeded sequences match
+fn next_random(i: &mut Interpreter) -> u64 {
+    i.random_state = i.random_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = i.random_state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn random_i(i: &mut Interpreter) -> InterpreterResult {
+    #![expect(clippy::cast_precision_loss, reason = "53 bits fit in the mantissa")]
+    let bits = next_random(i) >> 11;
+    i.push(bits as f64 / (1u64 << 53) as f64)
+}
+
+fn random_int(i: &mut Interpreter) -> InterpreterResult {
+    #![expect(clippy::cast_precision_loss, reason = "offset is below the range")]
+    keeping_operands::<2>(i, |i| {
+        let (lo, hi) = i.take2_numbers()?;
+        let range = f64_to_usize(lo.abs())
+            .and(f64_to_usize(hi.abs()))
+            .and(f64_to_usize(hi - lo))
+            .and_then(|r| u64::try_from(r).ok());
+        let Some(range) = range.map(|r| r + 1) else {
+            return Err(ErrorKind::InvalidArgument.error("Invalid random range"));
+        };
+        // Rejecting the lowest values removes the modulo bias
+        let threshold = range.wrapping_neg() % range;
+        loop {
+            let z = next_random(i);
+            if z >= threshold {
+                return i.push(lo + (z % range) as f64);
+            }
+        }
+    })
+}
+
+fn seed(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<1>(i, |i| {
+        let n = i.take_number()?;
+        let Some(state) = f64_to_usize(n.abs()).and_then(|s| u64::try_from(s).ok()) else {
+            return Err(ErrorKind::InvalidArgument.error("Invalid seed"));
+        };
+        i.random_state = if n < 0f64 {
+            state.wrapping_neg()
+        } else {
+            state
+        };
+        Ok(())
+    })
+}
+
+fn now(i: &mut Interpreter) -> InterpreterResult {
+    let ms = i.now();
+    i.push(ms)
+}
+
+fn monotonic(i: &mut Interpreter) -> InterpreterResult {
+    let ms = i.monotonic();
+    i.push(ms)
+}
+
+fn sleep_i(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<1>(i, |i| {
+        let ms = i.take_number()?;
+        // Capped like the C backend, well beyond any useful duration
+        if !(0f64..=1e15).contains(&ms) {
+            return Err(ErrorKind::InvalidArgument.error("Invalid sleep duration"));
+        }
+        i.sleep(ms);
+        Ok(())
+    })
+}
+
+fn arg_count(i: &mut Interpreter) -> InterpreterResult {
+    let Some(count) = usize_to_f64(i.args().len()) else {
+        return Err(ErrorKind::InvalidArgument.error("Argument count is out of range"));
+    };
+    i.push(count)
+}
+
+fn arg(i: &mut Interpreter) -> InterpreterResult {
+    let index = i.take_number()?;
+    let value = f64_to_usize(index).and_then(|index| i.args().get(index).cloned());
+    match value {
+        Some(value) => i.push2(value, true),
+        None => i.push2("", false),
+    }
+}
+
+fn env(i: &mut Interpreter) -> InterpreterResult {
+    let name = i.take_string()?.to_string();
+    match std::env::var(name) {
+        Ok(value) => i.push2(value, true),
+        Err(_) => i.push2("", false),
+    }
+}
+
+fn exit_i(i: &mut Interpreter) -> InterpreterResult {
+    let v = i.take()?;
+    let error = match v.to_number() {
+        Some(code) => match f64_to_usize(code).and_then(|c| i32::try_from(c).ok()) {
+            Some(code) => return i.exit(code),
+            None => ErrorKind::InvalidExitCode.error("Invalid exit code"),
+        },
+        None => ErrorKind::TypeMismatch.error("Expected number on top of stack"),
+    };
+    i.stack.push(v);
+    Err(error)
+}
+
+fn throw_i(i: &mut Interpreter) -> InterpreterResult {
+    let value = i.take()?;
+    i.throw(value)
+}
+
+fn assert(i: &mut Interpreter) -> InterpreterResult {
+    keeping_operands::<2>(i, |i| {
+        let message = i.take_string()?;
+        if i.take()?.is_truthy() {
+            Ok(())
+        } else {
+            Err(InterpreterError::Reported(
+                format!("Assertion failed: {}", message).into(),
+            ))
+        }
+    })
+}
+
+/// The kinds of built-in error. A handler only sees the description of the kind, so that what
+/// it catches is the same in every backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    StackUnderflow,
+    TypeMismatch,
+    InvalidExitCode,
+    InvalidFormat,
+    InvalidArgument,
+    NoMatch,
+    IntegerOverflow,
+    DivisionByZero,
+}
+
+/// Each kind with its name in the generated C and JavaScript, which keep their own copy of the
+/// descriptions that the codegen tests compare against this one
+pub static ERROR_DESCRIPTIONS: [(ErrorKind, &str, &str); 8] = [
+    (ErrorKind::StackUnderflow, "STACK_UNDERFLOW", "Stack empty"),
+    (ErrorKind::TypeMismatch, "TYPE_MISMATCH", "Type mismatch"),
+    (
+        ErrorKind::InvalidExitCode,
+        "INVALID_EXIT_CODE",
+        "Invalid exit code",
+    ),
+    (ErrorKind::InvalidFormat, "INVALID_FORMAT", "Invalid format"),
+    (
+        ErrorKind::InvalidArgument,
+        "INVALID_ARGUMENT",
+        "Invalid argument",
+    ),
+    (ErrorKind::NoMatch, "NO_MATCH", "No match arm"),
+    (
+        ErrorKind::IntegerOverflow,
+        "INTEGER_OVERFLOW",
+        "Integer overflow",
+    ),
+    (
+        ErrorKind::DivisionByZero,
+        "DIVISION_BY_ZERO",
+        "Division by zero",
+    ),
+];
+
+impl ErrorKind {
+    /// An error of this kind, which is reported with `message` when uncaught
+    pub(crate) fn error(self, message: impl Into<Cow<'static, str>>) -> InterpreterError {
+        InterpreterError::Failed(self, message.into())
+    }
+
+    pub fn description(self) -> &'static str {
+        ERROR_DESCRIPTIONS
+            .iter()
+            .find(|(kind, _, _)| *kind == self)
+            .map_or("", |(_, _, description)| description)
     }
 }
 // Codegen Intrinsics End
 
 fn eval_i(i: &mut Interpreter) -> InterpreterResult {
-    if let Value::Address(namespace, name) = i.take()? {
-        i.evaluate_name(namespace, &name)
-    } else {
-        Err("Expected function pointer on top of stack".into())
+    match i.take()? {
+        Value::Address(namespace, name) => i.evaluate_name(namespace, &name),
+        v @ (Value::String(_)
+        | Value::Number(_)
+        | Value::Integer(_)
+        | Value::Big(_)
+        | Value::Decimal(_)
+        | Value::Bool(_)
+        | Value::Record(_)) => {
+            i.stack.push(v);
+            Err(ErrorKind::TypeMismatch.error("Expected function pointer on top of stack"))
+        }
     }
 }
 
-type RawIntrinsic = (&'static str, Arity, Intrinsic);
+type RawIntrinsic = (&'static str, Arity, Intrinsic, bool);
 
 pub struct IntrinsicData {
     pub name: &'static str,
     pub arity: Arity,
     pub func: Intrinsic,
+    /// Pure and quick to run, so calls on literals can be folded at compile time
+    pub foldable: bool,
 }
 
 impl From<RawIntrinsic> for IntrinsicData {
@@ -216,53 +1226,158 @@ impl From<RawIntrinsic> for IntrinsicData {
             name: value.0,
             arity: value.1,
             func: value.2,
+            foldable: value.3,
         }
     }
 }
 
-static N: Type = Type::Number;
-static S: Type = Type::String;
-static B: Type = Type::Bool;
-static U: Type = Type::Unknown;
+const N: Type = Type::Number;
+const I: Type = Type::Integer;
+const S: Type = Type::String;
+const B: Type = Type::Bool;
+const U: Type = Type::Unknown;
+
+// Regexes and repeat are pure, but can take too long or grow too large to run while compiling
+static FOLD: bool = true;
+static RUN: bool = false;
 
 fn get_intrinsic_data() -> IntrinsicsData {
     let i: [RawIntrinsic; _] = [
-        ("+", Arity::number_binary(), plus),
-        ("-", Arity::number_binary(), minus),
-        ("*", Arity::number_binary(), times),
-        ("/", Arity::number_binary(), divide),
-        ("%", Arity::number_binary(), modulo),
-        ("**", Arity::number_binary(), pow_i),
-        ("||", Arity::generic_1(2, (0, 1)), or_i),
-        ("&&", Arity::generic_1(2, (0, 1)), and_i),
-        ("swap", Arity::generic_2(2, 0, 1), swap),
-        ("dup", Arity::generic_2(1, 0, 0), dup),
-        ("over", Arity::generic_3(2, 1, 0, 1), over),
-        ("rot", Arity::generic_3(3, 1, 0, 2), rot),
-        ("drop", (vec![Type::Unknown], vec![]).into(), drop),
-        ("print", (vec![Type::Unknown], vec![]).into(), print),
-        ("readline", Arity::push_two(S, B), readline),
-        ("substring", (vec![N, N, S], vec![S]).into(), substring),
-        ("to_char", Arity::unary(S, N), to_char),
-        ("from_char", Arity::unary(N, S), from_char),
-        ("index", Arity::binary(S, S, N), string_index),
-        ("join", Arity::binary(U, U, S), join),
-        ("length", Arity::unary(S, N), length),
-        ("assert", Arity::pop_two(U, S), assert),
-        ("eval", Arity::noop(), eval_i),
-        (">", Arity::binary(N, N, B), greater),
-        ("<", Arity::binary(N, N, B), less),
-        ("!", Arity::unary(U, B), not),
-        ("--", Arity::number_unary(), decrement),
-        ("++", Arity::number_unary(), increment),
-        ("==", Arity::binary(U, U, B), equals),
+        ("+", Arity::number_binary(), plus, FOLD),
+        ("-", Arity::number_binary(), minus, FOLD),
+        ("*", Arity::number_binary(), times, FOLD),
+        ("/", Arity::number_binary(), divide, FOLD),
+        ("%", Arity::number_binary(), modulo, FOLD),
+        ("**", Arity::number_binary(), pow_i, FOLD),
+        ("floor", Arity::number_unary(), floor_i, FOLD),
+        ("ceil", Arity::number_unary(), ceil_i, FOLD),
+        ("round", Arity::number_unary(), round_i, FOLD),
+        ("trunc", Arity::number_unary(), trunc_i, FOLD),
+        ("sqrt", Arity::number_unary(), sqrt_i, FOLD),
+        ("sin", Arity::number_unary(), sin_i, FOLD),
+        ("cos", Arity::number_unary(), cos_i, FOLD),
+        ("tan", Arity::number_unary(), tan_i, FOLD),
+        ("asin", Arity::number_unary(), asin_i, FOLD),
+        ("acos", Arity::number_unary(), acos_i, FOLD),
+        ("atan", Arity::number_unary(), atan_i, FOLD),
+        ("exp", Arity::number_unary(), exp_i, FOLD),
+        ("log", Arity::number_unary(), log_i, FOLD),
+        ("log10", Arity::number_unary(), log10_i, FOLD),
+        ("log2", Arity::number_unary(), log2_i, FOLD),
+        ("abs", Arity::number_unary(), abs_i, FOLD),
+        ("atan2", Arity::number_binary(), atan2_i, FOLD),
+        ("min", Arity::number_binary(), min, FOLD),
+        ("max", Arity::number_binary(), max, FOLD),
+        ("pi", (vec![], vec![N]).into(), pi, FOLD),
+        ("||", Arity::generic_1(2, (0, 1)), or_i, FOLD),
+        ("&&", Arity::generic_1(2, (0, 1)), and_i, FOLD),
+        ("swap", Arity::generic_2(2, 0, 1), swap, FOLD),
+        ("dup", Arity::generic_2(1, 0, 0), dup, FOLD),
+        ("over", Arity::generic_3(2, 1, 0, 1), over, FOLD),
+        ("rot", Arity::generic_3(3, 1, 0, 2), rot, FOLD),
+        ("drop", (vec![Type::Unknown], vec![]).into(), drop, FOLD),
+        ("print", (vec![Type::Unknown], vec![]).into(), print, RUN),
+        ("eprint", (vec![Type::Unknown], vec![]).into(), eprint, RUN),
+        ("write", (vec![Type::Unknown], vec![]).into(), write_i, RUN),
+        ("format", Arity::unary(S, S), format, RUN),
+        (
+            "parse_number",
+            (vec![S], vec![N, B]).into(),
+            parse_number,
+            FOLD,
+        ),
+        ("to_string", Arity::unary(U, S), to_string, FOLD),
+        ("to_fixed", Arity::binary(N, N, S), to_fixed, FOLD),
+        ("to_radix", Arity::binary(N, N, S), to_radix, FOLD),
+        ("to_padded", Arity::binary(N, N, S), to_padded, FOLD),
+        ("readline", Arity::push_two(S, B), readline, RUN),
+        (
+            "substring",
+            (vec![N, N, S], vec![S]).into(),
+            substring,
+            FOLD,
+        ),
+        ("to_char", Arity::unary(S, N), to_char, FOLD),
+        ("from_char", Arity::unary(N, S), from_char, FOLD),
+        ("index", Arity::binary(S, S, N), string_index, FOLD),
+        ("join", Arity::binary(U, U, S), join, FOLD),
+        ("length", Arity::unary(S, N), length, FOLD),
+        ("last_index", Arity::binary(S, S, N), last_index, FOLD),
+        ("starts_with", Arity::binary(S, S, B), starts_with, FOLD),
+        ("ends_with", Arity::binary(S, S, B), ends_with, FOLD),
+        ("split", Arity::noop(), split, RUN),
+        ("replace", (vec![S, S, S], vec![S]).into(), replace, FOLD),
+        ("trim", Arity::unary(S, S), trim, FOLD),
+        ("to_upper", Arity::unary(S, S), to_upper, FOLD),
+        ("to_lower", Arity::unary(S, S), to_lower, FOLD),
+        ("repeat", Arity::binary(N, S, S), repeat, RUN),
+        ("reverse", Arity::unary(S, S), reverse, FOLD),
+        ("matches", Arity::binary(S, S, B), matches, RUN),
+        (
+            "find_match",
+            (vec![S, S], vec![S, N]).into(),
+            find_match,
+            RUN,
+        ),
+        (
+            "replace_regex",
+            (vec![S, S, S], vec![S]).into(),
+            replace_regex,
+            RUN,
+        ),
+        ("captures", Arity::noop(), captures, RUN),
+        ("assert", Arity::pop_two(U, S), assert, RUN),
+        ("eval", Arity::noop(), eval_i, RUN),
+        (">", Arity::binary(N, N, B), greater, FOLD),
+        ("<", Arity::binary(N, N, B), less, FOLD),
+        ("!", Arity::unary(U, B), not, FOLD),
+        ("--", Arity::number_unary(), decrement, FOLD),
+        ("++", Arity::number_unary(), increment, FOLD),
+        ("==", Arity::binary(U, U, B), equals, FOLD),
     ];
 
     i.into_iter()
+        .chain(get_system_intrinsics())
+        .chain(get_integer_intrinsics())
         .map(|e| From::<RawIntrinsic>::from(e))
         .collect()
 }
 
+fn get_system_intrinsics() -> [RawIntrinsic; 16] {
+    [
+        ("read_file", (vec![S], vec![S, B]).into(), read_file, RUN),
+        ("write_file", Arity::binary(S, S, B), write_file, RUN),
+        ("append_file", Arity::binary(S, S, B), append_file, RUN),
+        ("file_exists", Arity::unary(S, B), file_exists, RUN),
+        ("list_dir", (vec![S], vec![S, B]).into(), list_dir, RUN),
+        ("random", (vec![], vec![N]).into(), random_i, RUN),
+        ("random_int", Arity::number_binary(), random_int, RUN),
+        ("seed", (vec![N], vec![]).into(), seed, RUN),
+        ("now", (vec![], vec![N]).into(), now, RUN),
+        ("monotonic", (vec![], vec![N]).into(), monotonic, RUN),
+        ("sleep", (vec![N], vec![]).into(), sleep_i, RUN),
+        ("arg_count", (vec![], vec![N]).into(), arg_count, RUN),
+        ("arg", (vec![N], vec![S, B]).into(), arg, RUN),
+        ("env", (vec![S], vec![S, B]).into(), env, RUN),
+        ("exit", (vec![N], vec![]).into(), exit_i, RUN),
+        ("throw", (vec![U], vec![]).into(), throw_i, RUN),
+    ]
+}
+
+fn get_integer_intrinsics() -> [RawIntrinsic; 9] {
+    [
+        ("band", Arity::binary(I, I, I), band, FOLD),
+        ("bor", Arity::binary(I, I, I), bor, FOLD),
+        ("bxor", Arity::binary(I, I, I), bxor, FOLD),
+        ("shl", Arity::binary(I, I, I), shl, FOLD),
+        ("shr", Arity::binary(I, I, I), shr, FOLD),
+        ("to_int", Arity::unary(N, I), to_int, FOLD),
+        ("to_float", Arity::number_unary(), to_float, FOLD),
+        ("to_big", Arity::unary(N, Type::Big), to_big, FOLD),
+        ("to_dec", Arity::unary(N, Type::Decimal), to_dec, FOLD),
+    ]
+}
+
 type IntrinsicsData = Vec<IntrinsicData>;
 
 static INTRINSICS_DATA: OnceLock<IntrinsicsData> = OnceLock::new();
@@ -271,14 +1386,13 @@ pub fn get_intrinsics() -> &'static IntrinsicsData {
     INTRINSICS_DATA.get_or_init(get_intrinsic_data)
 }
 
-static LOOKUP_TABLE_SIZE: usize = 256;
+static LOOKUP_TABLE_SIZE: usize = 1024;
 
+// Gives every intrinsic its own slot, pick another multiplier if a new name collides
 fn hash_name(name: &str) -> usize {
-    let mut b = name.bytes();
-    let f1 = b.next().unwrap_or_default();
-    let f2 = b.next().unwrap_or_default();
-
-    (f1 as usize * 300 + f2 as usize) % LOOKUP_TABLE_SIZE
+    name.bytes().fold(0usize, |h, b| {
+        h.wrapping_mul(359).wrapping_add(usize::from(b))
+    }) % LOOKUP_TABLE_SIZE
 }
 
 fn create_lookup_table() -> Vec<Option<&'static IntrinsicData>> {
@@ -325,11 +1439,33 @@ pub fn get_intrinsic_codegen_name(name: &str) -> Option<&'static str> {
         "+" => "plus",
         "index" => "string_index",
         "eval" => "eval_i",
+        "exit" => "exit_i",
+        "throw" => "throw_i",
+        "random" => "random_i",
+        "sleep" => "sleep_i",
+        "write" => "write_i",
         "-" => "minus",
         "*" => "times",
         "/" => "divide",
         "%" => "modulo",
         "**" => "pow_i",
+        "floor" => "floor_i",
+        "ceil" => "ceil_i",
+        "round" => "round_i",
+        "trunc" => "trunc_i",
+        "sqrt" => "sqrt_i",
+        "sin" => "sin_i",
+        "cos" => "cos_i",
+        "tan" => "tan_i",
+        "asin" => "asin_i",
+        "acos" => "acos_i",
+        "atan" => "atan_i",
+        "exp" => "exp_i",
+        "log" => "log_i",
+        "log10" => "log10_i",
+        "log2" => "log2_i",
+        "abs" => "abs_i",
+        "atan2" => "atan2_i",
         "||" => "or_i",
         "&&" => "and_i",
         ">" => "greater",
@@ -342,8 +1478,20 @@ pub fn get_intrinsic_codegen_name(name: &str) -> Option<&'static str> {
     })
 }
 
+// Arithmetic stays exact, so these push an integer, bignum or decimal of type `t` when every
+// operand is one
+pub fn get_exact_arity(name: &str, t: Type) -> Option<Arity> {
+    match name {
+        "+" | "-" | "*" | "/" | "%" | "**" | "min" | "max" => {
+            Some(Arity::binary(t.clone(), t.clone(), t))
+        }
+        "abs" | "++" | "--" => Some(Arity::unary(t.clone(), t)),
+        _ => None,
+    }
+}
+
 pub fn get_intrinsic_arity(name: &str) -> Result<Option<&'static Arity>, AnalysisError> {
-    if name == "eval" {
+    if matches!(name, "eval" | "format" | "split" | "captures") {
         return Err(AnalysisError::IndefiniteSize);
     }
 
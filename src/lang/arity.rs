@@ -138,7 +138,7 @@ impl ResultantType {
 
     pub fn union(&self, other: &Self) -> Self {
         match (self, other) {
-            (ResultantType::Normal(a), ResultantType::Normal(b)) => a.union(*b).into(),
+            (ResultantType::Normal(a), ResultantType::Normal(b)) => a.union(b).into(),
             (ResultantType::Dependent(_), ResultantType::Normal(n))
             | (ResultantType::Normal(n), ResultantType::Dependent(_)) => n.to_owned().into(),
             (ResultantType::Dependent(s), ResultantType::Dependent(other)) => {
@@ -267,9 +267,11 @@ impl Arity {
     }
 
     pub fn attempt_pop(&mut self, term: Type) -> Result<ResultantType, ArityCombineError> {
-        match (self.pushes.pop(), term) {
+        match (self.pushes.pop(), &term) {
+            (Some(ResultantType::Normal(Type::Field)), Type::Unknown) => Ok(Type::Field.into()),
+            (Some(ResultantType::Normal(Type::Field)), _) => Ok(term.into()),
             (Some(ResultantType::Normal(t)), _) => {
-                if !t.assignable_to(term) {
+                if !t.assignable_to(&term) {
                     return Err(ArityCombineError::IncompatibleTypes);
                 }
                 Ok(t.into())
@@ -281,13 +283,13 @@ impl Arity {
             (Some(ResultantType::Dependent(i)), Type::Unknown) => Ok(ResultantType::Dependent(i)),
             (Some(ResultantType::Dependent(i)), _) => {
                 for x in i.iter() {
-                    if term.assignable_to(self.pops[x]) {
+                    if term.assignable_to(&self.pops[x]) {
                         for push in &mut self.pushes {
                             if push.references(x) {
-                                *push = ResultantType::Normal(term);
+                                *push = ResultantType::Normal(term.clone());
                             }
                         }
-                        self.pops[x] = term;
+                        self.pops[x] = term.clone();
                     } else {
                         return Err(ArityCombineError::IncompatibleTypes);
                     }
@@ -297,6 +299,18 @@ impl Arity {
         }
     }
 
+    // Locals hold a value taken from the stack, typed by the pops it depends on
+    pub fn bind_local(&mut self) -> ResultantType {
+        self.attempt_pop(Type::Unknown)
+            .expect("Any value can be bound to a local")
+    }
+
+    pub fn push_local(&mut self, local: &ResultantType) {
+        let mut pushes = vec![local.clone()];
+        Self::resolve_dependents(&mut pushes, &self.pops);
+        self.pushes.extend(pushes);
+    }
+
     pub fn push<T>(&mut self, term: T)
     where
         T: Into<ResultantType>,
@@ -312,7 +326,7 @@ impl Arity {
         })?;
 
         second.pushes.iter().for_each(|f| match f {
-            ResultantType::Normal(t) => running.push(*t),
+            ResultantType::Normal(t) => running.push(t.clone()),
             ResultantType::Dependent(x) => {
                 let mut first = resolved_pop_types[x.el].clone();
                 let others = x.iter_rest().map(|f| &resolved_pop_types[f]);
@@ -362,8 +376,8 @@ impl Arity {
                     for index in multi_index.iter() {
                         if pops[index] != Type::Unknown {
                             resolved_type = match resolved_type {
-                                Some(t) => t.inter(pops[index]),
-                                None => Some(pops[index]),
+                                Some(t) => t.inter(&pops[index]),
+                                None => Some(pops[index].clone()),
                             }
                         }
                     }
@@ -395,7 +409,7 @@ impl Arity {
         let mut res = Arity::noop();
 
         for (i, t) in left.pops.iter().enumerate() {
-            let Some(expected_type) = right.pops[i].inter(*t) else {
+            let Some(expected_type) = right.pops[i].inter(t) else {
                 return Err(ArityCombineError::IncompatibleTypes);
             };
             res.pops.push(expected_type);
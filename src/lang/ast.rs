@@ -1,26 +1,50 @@
-use crate::lang::SourceRange;
+use std::rc::Rc;
+
+use crate::lang::{BigInt, Decimal, SourceRange};
 
 #[derive(Clone, Debug)]
 pub enum Term {
     String(String),
     Number(f64),
+    Integer(i64),
+    Big(BigInt),
+    Decimal(Decimal),
     Bool(bool),
     Address(String),
     Name(String, SourceRange),
+    Bind(Vec<Local>),
+    Local(Local),
+    Break,
+    Continue,
+    Return,
     Branch(Branch),
     Loop(Loop),
+    Try(Try),
+    Record(Rc<Record>, RecordOp),
+    Match(Match),
 }
 
 impl PartialEq for Term {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Number(l0), Self::Number(r0)) => l0 == r0,
+            (Self::Integer(l0), Self::Integer(r0)) => l0 == r0,
+            (Self::Big(l0), Self::Big(r0)) => l0 == r0,
+            (Self::Decimal(l0), Self::Decimal(r0)) => l0 == r0,
             (Self::Bool(l0), Self::Bool(r0)) => l0 == r0,
             (Self::String(l0), Self::String(r0))
             | (Self::Address(l0), Self::Address(r0))
             | (Self::Name(l0, _), Self::Name(r0, _)) => l0 == r0,
+            (Self::Bind(l0), Self::Bind(r0)) => l0 == r0,
+            (Self::Local(l0), Self::Local(r0)) => l0 == r0,
             (Self::Branch(l0), Self::Branch(r0)) => l0 == r0,
             (Self::Loop(l0), Self::Loop(r0)) => l0 == r0,
+            (Self::Try(l0), Self::Try(r0)) => l0 == r0,
+            (Self::Record(l0, l1), Self::Record(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::Match(l0), Self::Match(r0)) => l0 == r0,
+            (Self::Break, Self::Break)
+            | (Self::Continue, Self::Continue)
+            | (Self::Return, Self::Return) => true,
             _ => false,
         }
     }
@@ -32,6 +56,24 @@ impl From<i32> for Term {
     }
 }
 
+impl From<i64> for Term {
+    fn from(value: i64) -> Self {
+        Term::Integer(value)
+    }
+}
+
+impl From<BigInt> for Term {
+    fn from(value: BigInt) -> Self {
+        Term::Big(value)
+    }
+}
+
+impl From<Decimal> for Term {
+    fn from(value: Decimal) -> Self {
+        Term::Decimal(value)
+    }
+}
+
 impl From<f64> for Term {
     fn from(value: f64) -> Self {
         Term::Number(value)
@@ -44,6 +86,13 @@ impl From<bool> for Term {
     }
 }
 
+// Slots are numbered per function, so every binding site gets its own slot
+#[derive(Clone, Debug, PartialEq)]
+pub struct Local {
+    pub name: String,
+    pub slot: usize,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Branch {
     pub arms: Vec<(Block, Block)>,
@@ -56,17 +105,173 @@ pub struct Loop {
     pub post_condition: Option<Block>,
 }
 
+// The handler starts from the stack as it was before the body, plus the caught value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Try {
+    pub body: Block,
+    pub handler: Block,
+}
+
+// Arms are named by a variant, like `match { (some) ... (none) ... }`, and start
+// with the variant's fields on the stack in place of the matched value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub arms: Vec<(String, Block)>,
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Block {
     pub terms: Vec<Term>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TermContext {
+    Block,
+    BranchCondition,
+    LoopPreCondition,
+    LoopBody,
+    LoopPostCondition,
+    TryBody,
+    TryHandler,
+    MatchArm,
+}
+
+impl Block {
+    // Whether `control` (a break or continue) applies to the loop running this block
+    pub fn contains_loop_control(&self, control: &Term) -> bool {
+        self.terms.iter().any(|term| match term {
+            Term::Branch(b) => b.arms.iter().any(|(condition, body)| {
+                condition.contains_loop_control(control) || body.contains_loop_control(control)
+            }),
+            Term::Try(t) => {
+                t.body.contains_loop_control(control) || t.handler.contains_loop_control(control)
+            }
+            Term::Match(m) => m
+                .arms
+                .iter()
+                .any(|(_, body)| body.contains_loop_control(control)),
+            Term::String(_)
+            | Term::Number(_)
+            | Term::Integer(_)
+            | Term::Big(_)
+            | Term::Decimal(_)
+            | Term::Bool(_)
+            | Term::Address(_)
+            | Term::Name(_, _)
+            | Term::Bind(_)
+            | Term::Local(_)
+            | Term::Break
+            | Term::Continue
+            | Term::Return
+            | Term::Loop(_)
+            | Term::Record(_, _) => term == control,
+        })
+    }
+
+    // Whether a return can leave the function from anywhere within this block
+    pub fn contains_return(&self) -> bool {
+        self.terms.iter().any(|term| match term {
+            Term::Return => true,
+            Term::Branch(b) => b
+                .arms
+                .iter()
+                .any(|(condition, body)| condition.contains_return() || body.contains_return()),
+            Term::Loop(l) => [
+                l.pre_condition.as_ref(),
+                Some(&l.body),
+                l.post_condition.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(Block::contains_return),
+            Term::Try(t) => t.body.contains_return() || t.handler.contains_return(),
+            Term::Match(m) => m.arms.iter().any(|(_, body)| body.contains_return()),
+            Term::String(_)
+            | Term::Number(_)
+            | Term::Integer(_)
+            | Term::Big(_)
+            | Term::Decimal(_)
+            | Term::Bool(_)
+            | Term::Address(_)
+            | Term::Name(_, _)
+            | Term::Bind(_)
+            | Term::Local(_)
+            | Term::Break
+            | Term::Continue
+            | Term::Record(_, _) => false,
+        })
+    }
+
+    // Finds `target` by identity, reporting the innermost branch condition or loop section around it
+    pub fn locate(&self, target: &Term) -> Option<TermContext> {
+        self.locate_in(target, TermContext::Block)
+    }
+
+    fn locate_in(&self, target: &Term, context: TermContext) -> Option<TermContext> {
+        for term in &self.terms {
+            if std::ptr::eq(term, target) {
+                return Some(context);
+            }
+            let found = match term {
+                Term::Branch(b) => b.arms.iter().find_map(|(condition, body)| {
+                    condition
+                        .locate_in(target, TermContext::BranchCondition)
+                        .or_else(|| body.locate_in(target, context))
+                }),
+                Term::Loop(l) => l
+                    .pre_condition
+                    .as_ref()
+                    .and_then(|b| b.locate_in(target, TermContext::LoopPreCondition))
+                    .or_else(|| l.body.locate_in(target, TermContext::LoopBody))
+                    .or_else(|| {
+                        l.post_condition
+                            .as_ref()?
+                            .locate_in(target, TermContext::LoopPostCondition)
+                    }),
+                Term::Try(t) => t
+                    .body
+                    .locate_in(target, TermContext::TryBody)
+                    .or_else(|| t.handler.locate_in(target, TermContext::TryHandler)),
+                Term::Match(m) => m
+                    .arms
+                    .iter()
+                    .find_map(|(_, body)| body.locate_in(target, TermContext::MatchArm)),
+                Term::String(_)
+                | Term::Number(_)
+                | Term::Integer(_)
+                | Term::Big(_)
+                | Term::Decimal(_)
+                | Term::Bool(_)
+                | Term::Address(_)
+                | Term::Name(_, _)
+                | Term::Bind(_)
+                | Term::Local(_)
+                | Term::Break
+                | Term::Continue
+                | Term::Return
+                | Term::Record(_, _) => None,
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Function {
     pub name: String,
+    pub loc: SourceRange,
     pub body: Block,
 }
 
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.body == other.body
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ImportNaming {
     Wildcard,
@@ -77,6 +282,7 @@ pub enum ImportNaming {
 #[derive(Clone, Debug, PartialEq)]
 pub enum ImportLocation {
     Relative(String),
+    Search(String),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -85,9 +291,71 @@ pub struct Import {
     pub location: ImportLocation,
 }
 
+// The body is folded down to a single literal when the module is loaded
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constant {
+    pub name: String,
+    pub body: Block,
+}
+
+// Declared like `record point { x y }`, values hold one value per field
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<String>,
+    // Set for the variants of an enum, which are only constructed and matched
+    pub variant_of: Option<Rc<Enum>>,
+}
+
+// Declared like `enum option { some { value } none }`, each variant is a record
+#[derive(Debug, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordOp {
+    Construct,
+    Get(usize),
+    With(usize),
+}
+
+impl Record {
+    // Variants are typed as their enum
+    pub fn type_name(&self) -> &str {
+        self.variant_of.as_ref().map_or(&self.name, |e| &e.name)
+    }
+
+    // The constructor takes the fields in order, like `1 2 point`, getters are named
+    // like `point.x` and setters return a copy, like `p 3 point.with_x`
+    pub fn functions(self: &Rc<Self>, loc: SourceRange) -> Vec<Function> {
+        let function = |name: String, op: RecordOp| Function {
+            name,
+            loc,
+            body: Block {
+                terms: vec![Term::Record(Rc::clone(self), op)],
+            },
+        };
+        let mut functions = vec![function(self.name.clone(), RecordOp::Construct)];
+        if self.variant_of.is_some() {
+            return functions;
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            functions.push(function(format!("{}.{field}", self.name), RecordOp::Get(i)));
+            functions.push(function(
+                format!("{}.with_{field}", self.name),
+                RecordOp::With(i),
+            ));
+        }
+        functions
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Module {
     pub imports: Vec<Import>,
+    pub constants: Vec<Constant>,
     pub functions: Vec<Function>,
     pub body: Block,
 }
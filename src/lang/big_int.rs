@@ -0,0 +1,342 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+// Codegen Imports End
+
+// Arbitrary precision integers, shared with the generated Rust code. Limbs are base 2^32 and
+// little endian, without trailing zeros so every value has a single representation
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+// Powers that would need more bits than this are refused rather than exhausting memory
+static BIG_INT_MAX_POW_BITS: u64 = 1 << 24;
+
+fn trim_limbs(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn compare_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, l) in long.iter().enumerate() {
+        let sum = u64::from(*l) + u64::from(short.get(i).copied().unwrap_or(0)) + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// Expects `a` to be at least as large as `b`
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0u32;
+    for (i, l) in a.iter().enumerate() {
+        let (difference, first) = l.overflowing_sub(b.get(i).copied().unwrap_or(0));
+        let (difference, second) = difference.overflowing_sub(borrow);
+        borrow = u32::from(first || second);
+        result.push(difference);
+    }
+    trim_limbs(&mut result);
+    result
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(result[i + j]) + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim_limbs(&mut result);
+    result
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn mul_small_limbs(limbs: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = u64::from(addend);
+    for l in limbs.iter_mut() {
+        let product = u64::from(*l) * u64::from(factor) + carry;
+        *l = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+// Divides in place and returns the remainder
+#[expect(clippy::cast_possible_truncation)]
+fn div_small_limbs(limbs: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for l in limbs.iter_mut().rev() {
+        let current = (remainder << 32) | u64::from(*l);
+        *l = (current / u64::from(divisor)) as u32;
+        remainder = current % u64::from(divisor);
+    }
+    trim_limbs(limbs);
+    remainder as u32
+}
+
+// Long division one bit at a time, `b` must not be zero
+fn div_rem_limbs(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = b {
+        let mut quotient = a.to_vec();
+        let remainder = div_small_limbs(&mut quotient, *divisor);
+        let mut remainder = vec![remainder];
+        trim_limbs(&mut remainder);
+        return (quotient, remainder);
+    }
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = vec![];
+    for bit in (0..a.len() * 32).rev() {
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for l in &mut remainder {
+            let next = *l >> 31;
+            *l = (*l << 1) | carry;
+            carry = next;
+        }
+        if carry > 0 {
+            remainder.push(carry);
+        }
+        if compare_limbs(&remainder, b) != Ordering::Less {
+            remainder = sub_limbs(&remainder, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim_limbs(&mut quotient);
+    (quotient, remainder)
+}
+
+impl BigInt {
+    fn new(negative: bool, mut limbs: Vec<u32>) -> Self {
+        trim_limbs(&mut limbs);
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    // Decimal digits with an optional leading `-`
+    pub fn parse(source: &str) -> Option<Self> {
+        Self::parse_radix(source, 10)
+    }
+
+    // Digits in any radix up to 36, with an optional leading `-`
+    pub fn parse_radix(source: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match source.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, source),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        let mut limbs = vec![];
+        for c in digits.chars() {
+            mul_small_limbs(&mut limbs, radix, c.to_digit(radix)?);
+        }
+        Some(Self::new(negative, limbs))
+    }
+
+    // Drops the fractional part, non-finite numbers have no integer value
+    pub fn from_f64(v: f64) -> Option<Self> {
+        if !v.is_finite() {
+            return None;
+        }
+        Self::parse(&format!("{:.0}", v.trunc()))
+    }
+
+    // Rounds to the nearest float, like parsing the decimal digits would
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or_default()
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        let magnitude = match self.limbs.as_slice() {
+            [] => 0,
+            [low] => u64::from(*low),
+            [low, high] => u64::from(*low) | (u64::from(*high) << 32),
+            _ => return None,
+        };
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(false, self.limbs.clone())
+    }
+
+    fn bits(&self) -> u64 {
+        let Some(high) = self.limbs.last() else {
+            return 0;
+        };
+        (self.limbs.len() as u64 - 1) * 32 + u64::from(32 - high.leading_zeros())
+    }
+
+    // Truncates towards zero, so the remainder has the sign of `self`
+    pub fn checked_div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_limbs(&self.limbs, &divisor.limbs);
+        Some((
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    pub fn checked_pow(&self, exponent: u64) -> Option<Self> {
+        if self.bits() <= 1 {
+            let negative = self.negative && exponent % 2 == 1;
+            return Some(if exponent == 0 {
+                Self::from(1)
+            } else {
+                Self::new(negative, self.limbs.clone())
+            });
+        }
+        if self.bits().saturating_mul(exponent) > BIG_INT_MAX_POW_BITS {
+            return None;
+        }
+        let mut result = Self::from(1);
+        let mut base = self.clone();
+        let mut exponent = exponent;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        Some(result)
+    }
+}
+
+impl From<i64> for BigInt {
+    #[expect(clippy::cast_possible_truncation)]
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_limbs(&self.limbs, &other.limbs),
+            (true, true) => compare_limbs(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: Self) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_limbs(&self.limbs, &other.limbs));
+        }
+        match compare_limbs(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_limbs(&other.limbs, &self.limbs)),
+            Ordering::Equal | Ordering::Greater => {
+                BigInt::new(self.negative, sub_limbs(&self.limbs, &other.limbs))
+            }
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: Self) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: Self) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_limbs(&self.limbs, &other.limbs),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Nine decimal digits at a time, least significant first
+        let mut limbs = self.limbs.clone();
+        let mut chunks = vec![];
+        while !limbs.is_empty() {
+            chunks.push(div_small_limbs(&mut limbs, 1_000_000_000));
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().copied().unwrap_or(0))?;
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}n")
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::lang::BigInt;
+
+// Codegen Imports End
+
+// Exact decimals, shared with the generated Rust code. The value is `unscaled / 10^scale`, and
+// the scale is kept so that `1.50d` prints both of its places
+#[derive(Clone, Default)]
+pub struct Decimal {
+    unscaled: BigInt,
+    scale: u32,
+}
+
+// Quotients that don't end sooner are truncated after this many places
+static DECIMAL_DIVISION_SCALE: u32 = 32;
+
+// Values needing more places than this are refused rather than exhausting memory
+static DECIMAL_MAX_SCALE: u32 = 1 << 16;
+
+fn power_of_ten(exponent: u32) -> BigInt {
+    BigInt::from(10)
+        .checked_pow(u64::from(exponent))
+        .unwrap_or_default()
+}
+
+impl Decimal {
+    // Places past the written scale are multiplied out, so `1.5e2` has none
+    fn new(unscaled: BigInt, scale: i64) -> Option<Self> {
+        if scale.unsigned_abs() > u64::from(DECIMAL_MAX_SCALE) {
+            return None;
+        }
+        Some(match u32::try_from(scale) {
+            Ok(scale) => Self { unscaled, scale },
+            Err(_) => Self {
+                unscaled: &unscaled * &power_of_ten(u32::try_from(-scale).ok()?),
+                scale: 0,
+            },
+        })
+    }
+
+    // Decimal digits with an optional leading `-`, fraction and exponent, like `-1.25e3`
+    pub fn parse(source: &str) -> Option<Self> {
+        let (mantissa, exponent) = match source.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (source, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let unscaled = BigInt::parse(&format!("{whole}{fraction}"))?;
+        Self::new(unscaled, i64::try_from(fraction.len()).ok()? - exponent)
+    }
+
+    // The shortest digits that read back as `v`, non-finite numbers have no decimal value
+    pub fn from_f64(v: f64) -> Option<Self> {
+        if !v.is_finite() {
+            return None;
+        }
+        Self::parse(&v.to_string())
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or_default()
+    }
+
+    // Drops the fractional part
+    pub fn trunc(&self) -> BigInt {
+        self.unscaled
+            .checked_div_rem(&power_of_ten(self.scale))
+            .map(|(whole, _)| whole)
+            .unwrap_or_default()
+    }
+
+    // The integer value, when there is no fractional part
+    pub fn to_whole(&self) -> Option<BigInt> {
+        let (whole, fraction) = self.unscaled.checked_div_rem(&power_of_ten(self.scale))?;
+        fraction.is_zero().then_some(whole)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.unscaled.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.unscaled.is_negative()
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            unscaled: self.unscaled.abs(),
+            scale: self.scale,
+        }
+    }
+
+    // Expects `scale` to be at least the current one
+    fn rescaled(&self, scale: u32) -> BigInt {
+        &self.unscaled * &power_of_ten(scale - self.scale)
+    }
+
+    // Drops trailing zeros past `scale` places
+    fn trimmed(mut self, scale: u32) -> Self {
+        let ten = BigInt::from(10);
+        while self.scale > scale {
+            match self.unscaled.checked_div_rem(&ten) {
+                Some((shorter, digit)) if digit.is_zero() => {
+                    self.unscaled = shorter;
+                    self.scale -= 1;
+                }
+                _ => break,
+            }
+        }
+        self
+    }
+
+    // Exact when the quotient ends within `DECIMAL_DIVISION_SCALE` places or the operands' own,
+    // otherwise truncated towards zero there like integer division
+    pub fn checked_div(&self, divisor: &Self) -> Option<Self> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let kept = self.scale.max(divisor.scale);
+        let scale = kept.max(DECIMAL_DIVISION_SCALE);
+        let dividend = &self.unscaled * &power_of_ten(scale + divisor.scale - self.scale);
+        let (quotient, _) = dividend.checked_div_rem(&divisor.unscaled)?;
+        Some(
+            Self {
+                unscaled: quotient,
+                scale,
+            }
+            .trimmed(kept),
+        )
+    }
+
+    // Has the sign of `self`, like integer remainders
+    pub fn checked_rem(&self, divisor: &Self) -> Option<Self> {
+        let scale = self.scale.max(divisor.scale);
+        let (_, remainder) = self
+            .rescaled(scale)
+            .checked_div_rem(&divisor.rescaled(scale))?;
+        Some(Self {
+            unscaled: remainder,
+            scale,
+        })
+    }
+
+    pub fn checked_pow(&self, exponent: u64) -> Option<Self> {
+        let scale = u64::from(self.scale).checked_mul(exponent)?;
+        Self::new(
+            self.unscaled.checked_pow(exponent)?,
+            i64::try_from(scale).ok()?,
+        )
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(value: BigInt) -> Self {
+        Self {
+            unscaled: value,
+            scale: 0,
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        BigInt::from(value).into()
+    }
+}
+
+// Compared by value, so `1.5d` equals `1.50d`
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.rescaled(scale).cmp(&other.rescaled(scale))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Self) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal {
+            unscaled: &self.rescaled(scale) + &other.rescaled(scale),
+            scale,
+        }
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Self) -> Decimal {
+        self + &-other
+    }
+}
+
+// Keeps the places of both sides, so `1.5d 1.5d *` is `2.25d`
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Self) -> Decimal {
+        Decimal {
+            unscaled: &self.unscaled * &other.unscaled,
+            scale: self.scale + other.scale,
+        }
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            unscaled: -&self.unscaled,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_negative() {
+            f.write_str("-")?;
+        }
+        let digits = self.unscaled.abs().to_string();
+        let Ok(scale) = usize::try_from(self.scale) else {
+            return f.write_str(&digits);
+        };
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{whole}.{fraction}")
+    }
+}
+
+impl Debug for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}d")
+    }
+}
@@ -1,5 +1,7 @@
 mod arity;
 mod ast;
+mod big_int;
+mod decimal;
 mod owned_value;
 mod source_location;
 pub mod string;
@@ -10,6 +12,8 @@ mod value;
 
 pub use arity::*;
 pub use ast::*;
+pub use big_int::*;
+pub use decimal::*;
 pub use owned_value::*;
 pub use source_location::*;
 pub use symbol::*;
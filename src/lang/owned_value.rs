@@ -1,13 +1,32 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, rc::Rc};
 
-use crate::lang::Value;
+use crate::lang::{BigInt, Decimal, Record, RecordValue, Value};
 
 #[derive(Clone, PartialEq)]
 pub enum OwnedValue {
     String(String),
     Number(f64),
+    Integer(i64),
+    Big(BigInt),
+    Decimal(Decimal),
     Bool(bool),
     Address(usize, String),
+    Record(Rc<Record>, Vec<OwnedValue>),
+}
+
+impl OwnedValue {
+    pub fn type_name(&self) -> &str {
+        match self {
+            OwnedValue::String(_) => "string",
+            OwnedValue::Number(_) => "number",
+            OwnedValue::Integer(_) => "integer",
+            OwnedValue::Big(_) => "big",
+            OwnedValue::Decimal(_) => "decimal",
+            OwnedValue::Bool(_) => "bool",
+            OwnedValue::Address(_, _) => "address",
+            OwnedValue::Record(r, _) => r.type_name(),
+        }
+    }
 }
 
 impl From<Value<'_>> for OwnedValue {
@@ -15,8 +34,15 @@ impl From<Value<'_>> for OwnedValue {
         match value {
             Value::String(v) => OwnedValue::String(v.to_string()),
             Value::Number(v) => OwnedValue::Number(v),
+            Value::Integer(v) => OwnedValue::Integer(v),
+            Value::Big(v) => OwnedValue::Big(Rc::unwrap_or_clone(v)),
+            Value::Decimal(v) => OwnedValue::Decimal(Rc::unwrap_or_clone(v)),
             Value::Bool(v) => OwnedValue::Bool(v),
             Value::Address(n, name) => OwnedValue::Address(n, name),
+            Value::Record(r) => OwnedValue::Record(
+                Rc::clone(&r.record),
+                r.values.iter().cloned().map(Into::into).collect(),
+            ),
         }
     }
 }
@@ -26,8 +52,15 @@ impl From<&OwnedValue> for Value<'_> {
         match value {
             OwnedValue::String(v) => v.as_str().into(),
             OwnedValue::Number(v) => Value::Number(*v),
+            OwnedValue::Integer(v) => Value::Integer(*v),
+            OwnedValue::Big(v) => v.clone().into(),
+            OwnedValue::Decimal(v) => v.clone().into(),
             OwnedValue::Bool(v) => Value::Bool(*v),
             OwnedValue::Address(n, name) => Value::Address(*n, name.clone()),
+            OwnedValue::Record(record, values) => Value::Record(Rc::new(RecordValue {
+                record: Rc::clone(record),
+                values: values.iter().map(Into::into).collect(),
+            })),
         }
     }
 }
@@ -37,8 +70,15 @@ impl<'a> From<OwnedValue> for Value<'a> {
         match v {
             OwnedValue::String(v) => v.into(),
             OwnedValue::Number(v) => v.into(),
+            OwnedValue::Integer(v) => v.into(),
+            OwnedValue::Big(v) => v.into(),
+            OwnedValue::Decimal(v) => v.into(),
             OwnedValue::Bool(v) => v.into(),
             OwnedValue::Address(n, name) => Value::Address(n, name),
+            OwnedValue::Record(record, values) => Value::Record(Rc::new(RecordValue {
+                record,
+                values: values.into_iter().map(Into::into).collect(),
+            })),
         }
     }
 }
@@ -56,6 +96,24 @@ impl From<i32> for OwnedValue {
     }
 }
 
+impl From<i64> for OwnedValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<BigInt> for OwnedValue {
+    fn from(value: BigInt) -> Self {
+        Self::Big(value)
+    }
+}
+
+impl From<Decimal> for OwnedValue {
+    fn from(value: Decimal) -> Self {
+        Self::Decimal(value)
+    }
+}
+
 impl From<bool> for OwnedValue {
     fn from(value: bool) -> Self {
         Self::Bool(value)
@@ -1,6 +1,6 @@
 use std::{fmt::Debug, iter::Peekable, str::Chars};
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Default)]
 pub struct SourceLocation {
     pub character: usize,
     pub line: usize,
@@ -39,7 +39,7 @@ impl Debug for SourceLocation {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Default)]
 pub struct SourceRange {
     pub start: SourceLocation,
     pub end: SourceLocation,
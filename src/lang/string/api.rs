@@ -19,7 +19,27 @@ where
 
     fn find(&self, other: &Self) -> Option<usize>;
 
+    fn rfind(&self, other: &Self) -> Option<usize>;
+
+    fn starts_with(&self, prefix: &Self) -> bool;
+
+    fn ends_with(&self, suffix: &Self) -> bool;
+
     fn is_empty(&self) -> bool;
 
     fn substring(&self, range: Range<usize>) -> Self;
+
+    fn split(&self, separator: &Self) -> Vec<Self>;
+
+    fn replace(&self, from: &Self, to: &Self) -> Self;
+
+    fn trim(&self) -> Self;
+
+    fn to_uppercase(&self) -> Self;
+
+    fn to_lowercase(&self) -> Self;
+
+    fn repeat(&self, count: usize) -> Self;
+
+    fn reverse(&self) -> Self;
 }
@@ -14,6 +14,19 @@ pub struct CharString<'a> {
     source: Vec<char>,
 }
 
+impl CharString<'_> {
+    fn from_chars(source: Vec<char>) -> Self {
+        Self {
+            p: PhantomData,
+            source,
+        }
+    }
+
+    fn matches_at(&self, other: &Self, index: usize) -> bool {
+        self.source[index..].starts_with(&other.source)
+    }
+}
+
 impl StringApi<'_> for CharString<'_> {
     fn len(&self) -> usize {
         self.source.len()
@@ -30,6 +43,19 @@ impl StringApi<'_> for CharString<'_> {
         None
     }
 
+    fn rfind(&self, other: &Self) -> Option<usize> {
+        let max_index = self.len().checked_sub(other.len())?;
+        (0..=max_index).rev().find(|&i| self.matches_at(other, i))
+    }
+
+    fn starts_with(&self, prefix: &Self) -> bool {
+        self.source.starts_with(&prefix.source)
+    }
+
+    fn ends_with(&self, suffix: &Self) -> bool {
+        self.source.ends_with(&suffix.source)
+    }
+
     fn is_empty(&self) -> bool {
         self.source.is_empty()
     }
@@ -40,14 +66,81 @@ impl StringApi<'_> for CharString<'_> {
             source: self.source[range].to_owned(),
         }
     }
+
+    fn split(&self, separator: &Self) -> Vec<Self> {
+        if separator.is_empty() {
+            return self.source.iter().map(|&c| c.into()).collect();
+        }
+
+        let mut res = vec![];
+        let mut start = 0;
+        let mut i = 0;
+        while i + separator.len() <= self.len() {
+            if self.matches_at(separator, i) {
+                res.push(self.substring(start..i));
+                i += separator.len();
+                start = i;
+            } else {
+                i += 1;
+            }
+        }
+        res.push(self.substring(start..self.len()));
+        res
+    }
+
+    fn replace(&self, from: &Self, to: &Self) -> Self {
+        if from.is_empty() {
+            return self.clone();
+        }
+
+        let mut source = Vec::with_capacity(self.len());
+        let mut i = 0;
+        while i < self.len() {
+            if self.matches_at(from, i) {
+                source.extend_from_slice(&to.source);
+                i += from.len();
+            } else {
+                source.push(self.source[i]);
+                i += 1;
+            }
+        }
+        Self::from_chars(source)
+    }
+
+    fn trim(&self) -> Self {
+        let start = self
+            .source
+            .iter()
+            .position(|c| !c.is_whitespace())
+            .unwrap_or(self.len());
+        let end = self
+            .source
+            .iter()
+            .rposition(|c| !c.is_whitespace())
+            .map_or(start, |e| e + 1);
+        self.substring(start..end)
+    }
+
+    fn to_uppercase(&self) -> Self {
+        self.to_string().to_uppercase().into()
+    }
+
+    fn to_lowercase(&self) -> Self {
+        self.to_string().to_lowercase().into()
+    }
+
+    fn repeat(&self, count: usize) -> Self {
+        Self::from_chars(self.source.repeat(count))
+    }
+
+    fn reverse(&self) -> Self {
+        Self::from_chars(self.source.iter().rev().copied().collect())
+    }
 }
 
 impl From<&str> for CharString<'_> {
     fn from(value: &str) -> Self {
-        Self {
-            p: PhantomData,
-            source: value.chars().collect(),
-        }
+        Self::from_chars(value.chars().collect())
     }
 }
 
@@ -59,10 +152,7 @@ impl From<String> for CharString<'_> {
 
 impl From<char> for CharString<'_> {
     fn from(value: char) -> Self {
-        Self {
-            p: PhantomData,
-            source: vec![value],
-        }
+        Self::from_chars(vec![value])
     }
 }
 
@@ -1,5 +1,7 @@
 mod api;
 mod char_string;
+mod regex;
 
 pub use api::*;
 pub use char_string::*;
+pub use regex::*;
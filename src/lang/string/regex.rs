@@ -0,0 +1,540 @@
+use std::ops::Range;
+
+use crate::lang::string::{CharString, StringApi as _};
+
+// Codegen Imports End
+
+// Backtracking regex engine shared with the C backend, see the README for the supported dialect
+
+static REGEX_MAX_REPEAT: usize = 1000;
+static REGEX_MAX_PROGRAM: usize = 100_000;
+
+#[derive(Clone, Copy)]
+enum RegexClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl RegexClassItem {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Self::Range(lo, hi) => lo <= c && c <= hi,
+            Self::Digit(negated) => c.is_ascii_digit() != negated,
+            Self::Word(negated) => (c.is_ascii_alphanumeric() || c == '_') != negated,
+            // Same set as \s in JavaScript
+            Self::Space(negated) => {
+                (c == '\u{feff}' || (c.is_whitespace() && c != '\u{85}')) != negated
+            }
+        }
+    }
+}
+
+enum RegexAtom {
+    Char(char),
+    Shorthand(RegexClassItem),
+}
+
+enum RegexNode {
+    Char(char),
+    Any,
+    Class(Vec<RegexClassItem>, bool),
+    Start,
+    End,
+    Group(Box<RegexNode>, Option<usize>),
+    Concat(Vec<RegexNode>),
+    Alternate(Vec<RegexNode>),
+    Repeat {
+        node: Box<RegexNode>,
+        groups: Range<usize>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+#[derive(Clone)]
+enum RegexInst {
+    Char(char),
+    Any,
+    Class(Vec<RegexClassItem>, bool),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Reset(Range<usize>),
+    Progress(usize),
+    Match,
+}
+
+struct RegexParser {
+    pattern: Vec<char>,
+    pos: usize,
+    group_count: usize,
+}
+
+impl RegexParser {
+    fn peek(&self) -> Option<char> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn parse_alternation(&mut self) -> Option<RegexNode> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+        if branches.len() == 1 {
+            return branches.pop();
+        }
+        Some(RegexNode::Alternate(branches))
+    }
+
+    fn parse_concat(&mut self) -> Option<RegexNode> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let groups_before = self.group_count;
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom, groups_before)?);
+        }
+        Some(RegexNode::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Option<RegexNode> {
+        Some(match self.next()? {
+            '(' => {
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        return None;
+                    }
+                    None
+                } else {
+                    self.group_count += 1;
+                    Some(self.group_count)
+                };
+                let inner = self.parse_alternation()?;
+                if !self.eat(')') {
+                    return None;
+                }
+                RegexNode::Group(Box::new(inner), index)
+            }
+            '[' => self.parse_class()?,
+            '.' => RegexNode::Any,
+            '^' => RegexNode::Start,
+            '$' => RegexNode::End,
+            '\\' => match self.parse_escape(false)? {
+                RegexAtom::Char(c) => RegexNode::Char(c),
+                RegexAtom::Shorthand(item) => RegexNode::Class(vec![item], false),
+            },
+            '*' | '+' | '?' | '{' | '}' | ']' | ')' => return None,
+            c => RegexNode::Char(c),
+        })
+    }
+
+    fn parse_escape(&mut self, in_class: bool) -> Option<RegexAtom> {
+        Some(match self.next()? {
+            'd' => RegexAtom::Shorthand(RegexClassItem::Digit(false)),
+            'D' => RegexAtom::Shorthand(RegexClassItem::Digit(true)),
+            'w' => RegexAtom::Shorthand(RegexClassItem::Word(false)),
+            'W' => RegexAtom::Shorthand(RegexClassItem::Word(true)),
+            's' => RegexAtom::Shorthand(RegexClassItem::Space(false)),
+            'S' => RegexAtom::Shorthand(RegexClassItem::Space(true)),
+            'n' => RegexAtom::Char('\n'),
+            't' => RegexAtom::Char('\t'),
+            'r' => RegexAtom::Char('\r'),
+            'f' => RegexAtom::Char('\x0c'),
+            'v' => RegexAtom::Char('\x0b'),
+            '-' if in_class => RegexAtom::Char('-'),
+            c if "^$\\.*+?()[]{}|/".contains(c) => RegexAtom::Char(c),
+            _ => return None,
+        })
+    }
+
+    fn parse_class_atom(&mut self) -> Option<RegexAtom> {
+        match self.next()? {
+            '\\' => self.parse_escape(true),
+            c => Some(RegexAtom::Char(c)),
+        }
+    }
+
+    fn parse_class(&mut self) -> Option<RegexNode> {
+        let negated = self.eat('^');
+        let mut items = vec![];
+        while !self.eat(']') {
+            let item = match self.parse_class_atom()? {
+                RegexAtom::Char(lo) => {
+                    if self.peek() == Some('-')
+                        && !matches!(self.pattern.get(self.pos + 1), Some(']') | None)
+                    {
+                        self.pos += 1;
+                        let RegexAtom::Char(hi) = self.parse_class_atom()? else {
+                            return None;
+                        };
+                        if hi < lo {
+                            return None;
+                        }
+                        RegexClassItem::Range(lo, hi)
+                    } else {
+                        RegexClassItem::Range(lo, lo)
+                    }
+                }
+                RegexAtom::Shorthand(item) => {
+                    if self.peek() == Some('-')
+                        && !matches!(self.pattern.get(self.pos + 1), Some(']') | None)
+                    {
+                        return None;
+                    }
+                    item
+                }
+            };
+            items.push(item);
+        }
+        Some(RegexNode::Class(items, negated))
+    }
+
+    fn parse_count(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.pattern[start..self.pos].iter().collect();
+        digits
+            .parse()
+            .ok()
+            .filter(|&count| count <= REGEX_MAX_REPEAT)
+    }
+
+    fn parse_quantifier(&mut self, atom: RegexNode, groups_before: usize) -> Option<RegexNode> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.parse_count()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') {
+                        None
+                    } else {
+                        Some(self.parse_count()?)
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') || max.is_some_and(|max| max < min) {
+                    return None;
+                }
+                (min, max)
+            }
+            _ => return Some(atom),
+        };
+        self.pos += 1;
+
+        if matches!(atom, RegexNode::Start | RegexNode::End) {
+            return None;
+        }
+
+        Some(RegexNode::Repeat {
+            node: Box::new(atom),
+            groups: groups_before + 1..self.group_count + 1,
+            min,
+            max,
+            greedy: !self.eat('?'),
+        })
+    }
+}
+
+// A search gives up after this many steps for each character of the text, so that patterns which
+// backtrack exponentially, like `(a*)*c`, fail instead of hanging
+const REGEX_STEPS_PER_CHAR: usize = 1000;
+
+// Returned by a search that ran out of steps
+#[derive(Debug, PartialEq)]
+pub struct RegexStepLimit;
+
+pub struct Regex {
+    program: Vec<RegexInst>,
+    group_count: usize,
+    slot_count: usize,
+}
+
+pub type RegexCaptures = Vec<Option<Range<usize>>>;
+
+impl Regex {
+    pub fn new(pattern: &str) -> Option<Self> {
+        let mut parser = RegexParser {
+            pattern: pattern.chars().collect(),
+            pos: 0,
+            group_count: 0,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.peek().is_some() {
+            return None;
+        }
+
+        let capture_slots = (parser.group_count + 1) * 2;
+        let mut compiler = RegexCompiler {
+            program: vec![RegexInst::Save(0)],
+            slot_count: capture_slots,
+        };
+        compiler.compile(&node)?;
+        compiler.program.push(RegexInst::Save(1));
+        compiler.program.push(RegexInst::Match);
+
+        Some(Self {
+            program: compiler.program,
+            group_count: parser.group_count,
+            slot_count: compiler.slot_count,
+        })
+    }
+
+    // Finds the leftmost match starting at or after `start`, the first capture is the whole match
+    pub fn find_at(
+        &self,
+        text: &CharString,
+        start: usize,
+    ) -> Result<Option<RegexCaptures>, RegexStepLimit> {
+        enum Backtrack {
+            Resume(usize, usize),
+            Restore(usize, Option<usize>),
+        }
+
+        let len = text.len();
+        let mut slots = vec![None; self.slot_count];
+        let mut stack = vec![];
+        let mut steps = (len + 1).saturating_mul(REGEX_STEPS_PER_CHAR);
+
+        for begin in start..=len {
+            stack.push(Backtrack::Resume(0, begin));
+            while let Some(entry) = stack.pop() {
+                let (mut pc, mut pos) = match entry {
+                    Backtrack::Resume(pc, pos) => (pc, pos),
+                    Backtrack::Restore(slot, value) => {
+                        slots[slot] = value;
+                        continue;
+                    }
+                };
+
+                loop {
+                    steps = steps.checked_sub(1).ok_or(RegexStepLimit)?;
+                    match &self.program[pc] {
+                        RegexInst::Char(c) => {
+                            if pos >= len || text[pos] != *c {
+                                break;
+                            }
+                            pc += 1;
+                            pos += 1;
+                        }
+                        RegexInst::Any => {
+                            if pos >= len {
+                                break;
+                            }
+                            pc += 1;
+                            pos += 1;
+                        }
+                        RegexInst::Class(items, negated) => {
+                            if pos >= len || items.iter().any(|e| e.matches(text[pos])) == *negated
+                            {
+                                break;
+                            }
+                            pc += 1;
+                            pos += 1;
+                        }
+                        RegexInst::Start => {
+                            if pos != 0 {
+                                break;
+                            }
+                            pc += 1;
+                        }
+                        RegexInst::End => {
+                            if pos != len {
+                                break;
+                            }
+                            pc += 1;
+                        }
+                        RegexInst::Split(first, second) => {
+                            stack.push(Backtrack::Resume(*second, pos));
+                            pc = *first;
+                        }
+                        RegexInst::Jump(target) => pc = *target,
+                        RegexInst::Save(slot) => {
+                            stack.push(Backtrack::Restore(*slot, slots[*slot]));
+                            slots[*slot] = Some(pos);
+                            pc += 1;
+                        }
+                        RegexInst::Reset(range) => {
+                            for slot in range.clone() {
+                                stack.push(Backtrack::Restore(slot, slots[slot]));
+                                slots[slot] = None;
+                            }
+                            pc += 1;
+                        }
+                        RegexInst::Progress(slot) => {
+                            if slots[*slot] == Some(pos) {
+                                break;
+                            }
+                            pc += 1;
+                        }
+                        RegexInst::Match => {
+                            return Ok(Some(
+                                slots[..(self.group_count + 1) * 2]
+                                    .chunks(2)
+                                    .map(|e| Some(e[0]?..e[1]?))
+                                    .collect(),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+struct RegexCompiler {
+    program: Vec<RegexInst>,
+    slot_count: usize,
+}
+
+impl RegexCompiler {
+    fn compile(&mut self, node: &RegexNode) -> Option<()> {
+        if self.program.len() > REGEX_MAX_PROGRAM {
+            return None;
+        }
+
+        match node {
+            RegexNode::Char(c) => self.program.push(RegexInst::Char(*c)),
+            RegexNode::Any => self.program.push(RegexInst::Any),
+            RegexNode::Class(items, negated) => {
+                self.program.push(RegexInst::Class(items.clone(), *negated));
+            }
+            RegexNode::Start => self.program.push(RegexInst::Start),
+            RegexNode::End => self.program.push(RegexInst::End),
+            RegexNode::Group(inner, index) => {
+                if let Some(index) = index {
+                    self.program.push(RegexInst::Save(index * 2));
+                    self.compile(inner)?;
+                    self.program.push(RegexInst::Save(index * 2 + 1));
+                } else {
+                    self.compile(inner)?;
+                }
+            }
+            RegexNode::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            RegexNode::Alternate(branches) => {
+                let mut jumps = vec![];
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.compile(branch)?;
+                    } else {
+                        let split = self.program.len();
+                        self.program.push(RegexInst::Jump(0));
+                        self.compile(branch)?;
+                        jumps.push(self.program.len());
+                        self.program.push(RegexInst::Jump(0));
+                        self.program[split] = RegexInst::Split(split + 1, self.program.len());
+                    }
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = RegexInst::Jump(end);
+                }
+            }
+            RegexNode::Repeat {
+                node,
+                groups,
+                min,
+                max,
+                greedy,
+            } => self.compile_repeat(node, groups, *min, *max, *greedy)?,
+        }
+
+        Some(())
+    }
+
+    // Iterations past the minimum must consume input, like JavaScript, which also keeps loops finite
+    fn compile_repeat(
+        &mut self,
+        node: &RegexNode,
+        groups: &Range<usize>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    ) -> Option<()> {
+        let split = |from: usize, to: usize| {
+            if greedy {
+                RegexInst::Split(from, to)
+            } else {
+                RegexInst::Split(to, from)
+            }
+        };
+
+        for _ in 0..min {
+            self.compile_iteration(node, groups)?;
+        }
+
+        if max == Some(min) {
+            return Some(());
+        }
+
+        let progress = self.slot_count;
+        self.slot_count += 1;
+        let optional = |compiler: &mut Self| {
+            compiler.program.push(RegexInst::Save(progress));
+            compiler.compile_iteration(node, groups)?;
+            compiler.program.push(RegexInst::Progress(progress));
+            Some(())
+        };
+
+        if let Some(max) = max {
+            let mut splits = vec![];
+            for _ in min..max {
+                splits.push(self.program.len());
+                self.program.push(RegexInst::Jump(0));
+                optional(self)?;
+            }
+            let end = self.program.len();
+            for i in splits {
+                self.program[i] = split(i + 1, end);
+            }
+        } else {
+            let start = self.program.len();
+            self.program.push(RegexInst::Jump(0));
+            optional(self)?;
+            self.program.push(RegexInst::Jump(start));
+            self.program[start] = split(start + 1, self.program.len());
+        }
+
+        Some(())
+    }
+
+    fn compile_iteration(&mut self, node: &RegexNode, groups: &Range<usize>) -> Option<()> {
+        if !groups.is_empty() {
+            self.program
+                .push(RegexInst::Reset(groups.start * 2..groups.end * 2));
+        }
+        self.compile(node)
+    }
+}
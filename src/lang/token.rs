@@ -1,11 +1,14 @@
 use std::fmt::Debug;
 
-use crate::lang::{SourceLocation, SourceRange, Symbol};
+use crate::lang::{BigInt, Decimal, SourceLocation, SourceRange, Symbol};
 
 #[derive(Clone, PartialEq)]
 pub enum Token {
     String(String),
     Number(f64),
+    Integer(i64),
+    Big(BigInt),
+    Decimal(Decimal),
     Bool(bool),
     Name(String),
     Symbol(Symbol),
@@ -17,6 +20,9 @@ impl Debug for Token {
             Self::Name(s) => f.write_str(s),
             Self::String(s) => s.fmt(f),
             Self::Number(s) => s.fmt(f),
+            Self::Integer(s) => write!(f, "{s}i"),
+            Self::Big(s) => s.fmt(f),
+            Self::Decimal(s) => s.fmt(f),
             Self::Bool(s) => s.fmt(f),
             Self::Symbol(s) => s.fmt(f),
         }
@@ -1,36 +1,76 @@
-#[derive(Copy, Clone, PartialEq, Debug)]
+use std::sync::Arc;
+
+use crate::program::NamespaceId;
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum Type {
     Bool,
     Number,
+    Integer,
+    Big,
+    Decimal,
     String,
     Address,
+    Record(RecordType),
     Unknown,
+    // Read from a record field, which can hold anything, so it is narrowed to whatever its use
+    // requires instead of being checked
+    Field,
+}
+
+// Records are told apart by the namespace that declares them as well as by name, so ones with the
+// same name from different files are different types. Variants are typed as their enum
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecordType {
+    pub namespace: NamespaceId,
+    pub name: Arc<str>,
 }
 
 impl Type {
-    pub fn assignable_to(self, other: Self) -> bool {
-        if other == Self::Unknown {
+    pub fn record(namespace: NamespaceId, name: &str) -> Self {
+        Self::Record(RecordType {
+            namespace,
+            name: name.into(),
+        })
+    }
+
+    pub fn assignable_to(&self, other: &Self) -> bool {
+        if *other == Self::Unknown {
             return true;
         }
         if self == other {
             return true;
         }
-        false
+        // Integers are promoted wherever a bignum, decimal or number is expected, bignums where a
+        // decimal or number is, and decimals where a number is
+        matches!(
+            (self, other),
+            (Self::Integer, Self::Big | Self::Decimal | Self::Number)
+                | (Self::Big, Self::Decimal | Self::Number)
+                | (Self::Decimal, Self::Number)
+        )
     }
 
-    pub fn stringify(self) -> &'static str {
+    pub fn stringify(&self) -> &str {
         match self {
             Type::Bool => "b",
             Type::Number => "n",
+            Type::Integer => "i",
+            Type::Big => "big",
+            Type::Decimal => "dec",
             Type::String => "s",
-            Type::Unknown => "u",
+            Type::Unknown | Type::Field => "u",
             Type::Address => "a",
+            Type::Record(record) => &record.name,
         }
     }
 
     pub fn parse_raw(source: &str) -> Option<Self> {
         Some(match source {
             "n" => Self::Number,
+            "i" => Self::Integer,
+            "big" => Self::Big,
+            "dec" => Self::Decimal,
             "a" => Self::Address,
             "s" => Self::String,
             "u" => Self::Unknown,
@@ -52,23 +92,21 @@ impl Type {
         })
     }
 
-    pub fn union(self, other: Self) -> Self {
+    pub fn union(&self, other: &Self) -> Self {
         if self.assignable_to(other) {
-            other
+            other.clone()
         } else if other.assignable_to(self) {
-            self
+            self.clone()
         } else {
             Self::Unknown
         }
     }
 
-    pub fn inter(self, other: Self) -> Option<Self> {
-        if self == other {
-            Some(self)
-        } else if self == Self::Unknown {
-            Some(other)
-        } else if other == Self::Unknown {
-            Some(self)
+    pub fn inter(&self, other: &Self) -> Option<Self> {
+        if self.assignable_to(other) {
+            Some(self.clone())
+        } else if other.assignable_to(self) {
+            Some(other.clone())
         } else {
             None
         }
@@ -81,38 +119,86 @@ mod tests {
 
     #[test]
     fn assignable_to() {
-        assert!(Type::Bool.assignable_to(Type::Bool));
-        assert!(!Type::Bool.assignable_to(Type::Number));
-        assert!(!Type::Bool.assignable_to(Type::String));
-        assert!(Type::Bool.assignable_to(Type::Unknown));
-
-        assert!(!Type::Unknown.assignable_to(Type::Bool));
-        assert!(!Type::Unknown.assignable_to(Type::Number));
-        assert!(!Type::Unknown.assignable_to(Type::String));
-        assert!(Type::Unknown.assignable_to(Type::Unknown));
+        assert!(Type::Bool.assignable_to(&Type::Bool));
+        assert!(!Type::Bool.assignable_to(&Type::Number));
+        assert!(!Type::Bool.assignable_to(&Type::String));
+        assert!(Type::Bool.assignable_to(&Type::Unknown));
+
+        assert!(!Type::Unknown.assignable_to(&Type::Bool));
+        assert!(!Type::Unknown.assignable_to(&Type::Number));
+        assert!(!Type::Unknown.assignable_to(&Type::String));
+        assert!(Type::Unknown.assignable_to(&Type::Unknown));
     }
 
     #[test]
     fn union() {
-        assert_eq!(Type::Bool.union(Type::Bool), Type::Bool);
-        assert_eq!(Type::Number.union(Type::Number), Type::Number);
-        assert_eq!(Type::String.union(Type::String), Type::String);
-        assert_eq!(Type::Unknown.union(Type::Unknown), Type::Unknown);
-
-        assert_eq!(Type::Bool.union(Type::Unknown), Type::Unknown);
-        assert_eq!(Type::Bool.union(Type::Number), Type::Unknown);
-        assert_eq!(Type::Unknown.union(Type::String), Type::Unknown);
+        assert_eq!(Type::Bool.union(&Type::Bool), Type::Bool);
+        assert_eq!(Type::Number.union(&Type::Number), Type::Number);
+        assert_eq!(Type::String.union(&Type::String), Type::String);
+        assert_eq!(Type::Unknown.union(&Type::Unknown), Type::Unknown);
+
+        assert_eq!(Type::Bool.union(&Type::Unknown), Type::Unknown);
+        assert_eq!(Type::Bool.union(&Type::Number), Type::Unknown);
+        assert_eq!(Type::Unknown.union(&Type::String), Type::Unknown);
     }
 
     #[test]
     fn inter() {
-        assert_eq!(Type::Bool.inter(Type::Bool), Some(Type::Bool));
-        assert_eq!(Type::Number.inter(Type::Number), Some(Type::Number));
-        assert_eq!(Type::String.inter(Type::String), Some(Type::String));
-        assert_eq!(Type::Unknown.inter(Type::Unknown), Some(Type::Unknown));
-
-        assert_eq!(Type::Bool.inter(Type::Unknown), Some(Type::Bool));
-        assert_eq!(Type::Bool.inter(Type::Number), None);
-        assert_eq!(Type::Unknown.inter(Type::String), Some(Type::String));
+        assert_eq!(Type::Bool.inter(&Type::Bool), Some(Type::Bool));
+        assert_eq!(Type::Number.inter(&Type::Number), Some(Type::Number));
+        assert_eq!(Type::String.inter(&Type::String), Some(Type::String));
+        assert_eq!(Type::Unknown.inter(&Type::Unknown), Some(Type::Unknown));
+
+        assert_eq!(Type::Bool.inter(&Type::Unknown), Some(Type::Bool));
+        assert_eq!(Type::Bool.inter(&Type::Number), None);
+        assert_eq!(Type::Unknown.inter(&Type::String), Some(Type::String));
+    }
+
+    #[test]
+    fn records() {
+        let point = Type::record(0, "point");
+        assert_eq!(point, Type::record(0, "point"));
+        assert_ne!(point, Type::record(0, "size"));
+        assert_ne!(point, Type::record(1, "point"));
+        assert_eq!(point.stringify(), "point");
+
+        assert!(point.assignable_to(&Type::Unknown));
+        assert!(!point.assignable_to(&Type::record(0, "size")));
+        assert!(!point.assignable_to(&Type::record(1, "point")));
+        assert_eq!(point.union(&Type::record(0, "size")), Type::Unknown);
+        assert_eq!(point.inter(&Type::Unknown), Some(point.clone()));
+        assert_eq!(point.inter(&Type::Number), None);
+    }
+
+    #[test]
+    fn integers() {
+        assert!(Type::Integer.assignable_to(&Type::Number));
+        assert!(!Type::Number.assignable_to(&Type::Integer));
+        assert_eq!(Type::Integer.union(&Type::Number), Type::Number);
+        assert_eq!(Type::Number.union(&Type::Integer), Type::Number);
+        assert_eq!(Type::Integer.inter(&Type::Number), Some(Type::Integer));
+        assert_eq!(Type::Integer.inter(&Type::String), None);
+        assert_eq!(Type::parse_raw("i"), Some(Type::Integer));
+    }
+
+    #[test]
+    fn bignums() {
+        assert!(Type::Integer.assignable_to(&Type::Big));
+        assert!(Type::Big.assignable_to(&Type::Number));
+        assert!(!Type::Big.assignable_to(&Type::Integer));
+        assert_eq!(Type::Integer.union(&Type::Big), Type::Big);
+        assert_eq!(Type::Big.union(&Type::Number), Type::Number);
+        assert_eq!(Type::Big.inter(&Type::Number), Some(Type::Big));
+        assert_eq!(Type::parse_raw("big"), Some(Type::Big));
+    }
+
+    #[test]
+    fn decimals() {
+        assert!(Type::Big.assignable_to(&Type::Decimal));
+        assert!(Type::Decimal.assignable_to(&Type::Number));
+        assert!(!Type::Decimal.assignable_to(&Type::Big));
+        assert_eq!(Type::Integer.union(&Type::Decimal), Type::Decimal);
+        assert_eq!(Type::Decimal.union(&Type::Number), Type::Number);
+        assert_eq!(Type::parse_raw("dec"), Some(Type::Decimal));
     }
 }
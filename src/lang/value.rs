@@ -3,23 +3,95 @@ use std::{
     rc::Rc,
 };
 
-use crate::lang::string::{CharString, StringApi as _};
+use crate::lang::{
+    BigInt, Decimal, Record,
+    string::{CharString, StringApi as _},
+};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub enum Value<'a> {
     String(Rc<CharString<'a>>),
     Number(f64),
+    Integer(i64),
+    Big(Rc<BigInt>),
+    Decimal(Rc<Decimal>),
     Bool(bool),
     Address(usize, String),
+    Record(Rc<RecordValue<'a>>),
+}
+
+#[derive(Clone)]
+pub struct RecordValue<'a> {
+    pub record: Rc<Record>,
+    pub values: Vec<Value<'a>>,
+}
+
+impl PartialEq for Value<'_> {
+    // Integers, bignums and decimals equal the values they promote to, also when held in records
+    #[expect(clippy::cast_precision_loss)]
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Integer(a), Self::Number(b)) | (Self::Number(b), Self::Integer(a)) => {
+                *a as f64 == *b
+            }
+            (Self::Big(a), Self::Big(b)) => a == b,
+            (Self::Big(a), Self::Integer(b)) | (Self::Integer(b), Self::Big(a)) => {
+                **a == BigInt::from(*b)
+            }
+            (Self::Big(a), Self::Number(b)) | (Self::Number(b), Self::Big(a)) => a.to_f64() == *b,
+            (Self::Decimal(a), Self::Number(b)) | (Self::Number(b), Self::Decimal(a)) => {
+                a.to_f64() == *b
+            }
+            (Self::Decimal(a), b) | (b, Self::Decimal(a)) => {
+                b.to_decimal().is_some_and(|b| *a == b)
+            }
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Address(a0, a1), Self::Address(b0, b1)) => a0 == b0 && a1 == b1,
+            (Self::Record(a), Self::Record(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
 impl Value<'_> {
+    // Integers, bignums and decimals are promoted, so intrinsics working on floats accept them too
+    #[expect(clippy::cast_precision_loss)]
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(v) => Some(*v),
+            Value::Integer(v) => Some(*v as f64),
+            Value::Big(v) => Some(v.to_f64()),
+            Value::Decimal(v) => Some(v.to_f64()),
+            Value::String(_) | Value::Bool(_) | Value::Address(_, _) | Value::Record(_) => None,
+        }
+    }
+
+    // Integers and bignums are exact too, so they are promoted where a decimal is expected
+    pub fn to_decimal(&self) -> Option<Rc<Decimal>> {
+        match self {
+            Value::Integer(v) => Some(Rc::new((*v).into())),
+            Value::Big(v) => Some(Rc::new((**v).clone().into())),
+            Value::Decimal(v) => Some(Rc::clone(v)),
+            Value::String(_)
+            | Value::Number(_)
+            | Value::Bool(_)
+            | Value::Address(_, _)
+            | Value::Record(_) => None,
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::String(s) => !s.is_empty(),
             Value::Number(v) => !v.is_nan() && *v != 0f64,
+            Value::Integer(v) => *v != 0,
+            Value::Big(v) => !v.is_zero(),
+            Value::Decimal(v) => !v.is_zero(),
             Value::Bool(b) => *b,
-            Value::Address(_, _) => true,
+            Value::Address(_, _) | Value::Record(_) => true,
         }
     }
 }
@@ -30,6 +102,24 @@ impl From<i32> for Value<'_> {
     }
 }
 
+impl From<i64> for Value<'_> {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<BigInt> for Value<'_> {
+    fn from(value: BigInt) -> Self {
+        Value::Big(Rc::new(value))
+    }
+}
+
+impl From<Decimal> for Value<'_> {
+    fn from(value: Decimal) -> Self {
+        Value::Decimal(Rc::new(value))
+    }
+}
+
 impl From<bool> for Value<'_> {
     fn from(value: bool) -> Self {
         Value::Bool(value)
@@ -59,10 +149,34 @@ impl Debug for Value<'_> {
         match self {
             Self::String(s) => Debug::fmt(s, f),
             Self::Number(s) => Display::fmt(s, f),
+            Self::Integer(s) => Display::fmt(s, f),
+            Self::Big(s) => Display::fmt(s, f),
+            Self::Decimal(s) => Display::fmt(s, f),
             Self::Bool(true) => f.write_str("true"),
             Self::Bool(false) => f.write_str("false"),
             Self::Address(ns, name) => write!(f, "Fn[{ns}, {name}]"),
+            Self::Record(r) => Debug::fmt(r, f),
+        }
+    }
+}
+
+impl PartialEq for RecordValue<'_> {
+    // Records of the same name declared in different files are different types
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.record, &other.record) && self.values == other.values
+    }
+}
+
+impl Debug for RecordValue<'_> {
+    // Written like `point { x: 1, y: "a" }`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.record.name)?;
+        f.write_str(" {")?;
+        for (i, (field, value)) in self.record.fields.iter().zip(&self.values).enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{field}: {value:?}")?;
         }
+        f.write_str(if self.values.is_empty() { "}" } else { " }" })
     }
 }
 
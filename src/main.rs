@@ -1,6 +1,9 @@
 mod analyze;
+mod clock;
 mod codegen;
 mod convert;
+mod diagnostic;
+mod fold;
 mod interpreter;
 mod intrinsics;
 mod lang;
@@ -9,23 +12,38 @@ mod parser;
 mod path;
 mod program;
 mod repl;
+mod stdlib;
 mod test_analyze;
 mod test_arity;
 mod test_convert;
 mod test_e2e;
+mod test_fold;
 mod test_interpreter;
 mod test_parser;
+mod test_regex;
+mod test_repl;
 mod test_tokenizer;
 mod test_values;
 mod tokenizer;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-use crate::repl::Repl;
+use crate::repl::{Repl, ReplError};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
 
 #[derive(Parser, Debug)]
 pub struct ReplArgs {
+    /// Files to run in sequence
     pub files: Vec<String>,
 
+    /// Arguments passed to the script, after a `--` separator
+    #[arg(last = true)]
+    pub script_args: Vec<String>,
+
     /// Analyze code instead of type checking
     #[arg(short, long, default_value_t = false)]
     pub analyze: bool,
@@ -33,6 +51,18 @@ pub struct ReplArgs {
     /// Generate code for the provided file
     #[arg(short, long)]
     pub generate: Option<String>,
+
+    /// Directory searched for non-relative imports, may be repeated
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    pub include: Vec<String>,
+
+    /// Format used for errors and analysis results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Leave literal-only sequences to run at runtime, constants are still inlined
+    #[arg(long, default_value_t = false)]
+    pub no_fold: bool,
 }
 
 fn main() {
@@ -53,8 +83,12 @@ fn main() {
         args,
         std::env::current_dir().expect("Could not get current directory"),
     );
-    if let Err(e) = repl.run() {
-        eprintln!("{e}");
-        std::process::exit(1);
+    match repl.run() {
+        Ok(()) => {}
+        Err(ReplError::Exit(code)) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
     }
 }
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use crate::{
     lang::{SourceLocation, SourceRange, Symbol},
-    tokenizer::{EscapeSequenceError, TokenizeError},
+    tokenizer::{EscapeSequenceError, NumberError, TokenizeError},
 };
 
 #[derive(Debug, PartialEq)]
@@ -11,6 +11,9 @@ pub enum WrappedExpression {
     Branch,
     Function,
     Loop,
+    Try,
+    Record,
+    Enum,
     ImportNameList,
 }
 
@@ -19,12 +22,18 @@ pub enum ParseSection {
     Condition,
     Branch,
     Loop,
+    Try,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum ReasonExpectingMore {
     Address,
+    Binding,
     Branch,
+    Try,
+    Record,
+    Enum,
+    Match,
     ImportName,
     ImportPath,
 }
@@ -39,6 +48,16 @@ pub enum EndOfFileError {
 pub enum UnexpectedContext {
     FirstInBranch,
     Address,
+    Binding,
+    BindingOutsideFunction,
+    LoopControl,
+    ReturnOutsideFunction,
+    Try,
+    Constant,
+    ConstantIntrinsicName,
+    Record,
+    Enum,
+    Match,
     AfterPostCondition,
     ImportNameList,
     ImportNaming,
@@ -114,6 +133,17 @@ pub fn cannot_use_in<T>(
     }))
 }
 
+const TRY_INFO: &str = "A try needs a body and a handler, like: try { ... } catch { ... }";
+
+const RECORD_INFO: &str =
+    "A record needs a name and distinct field names, like: record point { x y }";
+
+const ENUM_INFO: &str =
+    "An enum needs a name and distinct variant names, like: enum option { some { value } none }";
+
+const MATCH_INFO: &str =
+    "Each arm of a match names a different variant, like: match { (some) ... (none) ... }";
+
 type ErrorDetails = (Cow<'static, str>, SourceRange, Option<Cow<'static, str>>);
 
 struct Details {
@@ -171,10 +201,19 @@ impl Details {
 }
 
 impl TokenizeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnboundedString(_) => "unclosed-string",
+            Self::InvalidEscape(..) => "invalid-escape",
+            Self::UnboundedComment(_) => "unclosed-comment",
+            Self::InvalidNumber(..) => "invalid-number",
+        }
+    }
+
     pub fn is_early_eof(&self) -> bool {
         match self {
             TokenizeError::UnboundedString(_source_location) => true,
-            TokenizeError::InvalidEscape(..) => false,
+            TokenizeError::InvalidEscape(..) | TokenizeError::InvalidNumber(..) => false,
             TokenizeError::UnboundedComment(_source_location) => false,
         }
     }
@@ -219,11 +258,43 @@ impl TokenizeError {
                 loc,
                 "Multiline comments are closed with: */",
             ),
+            Self::InvalidNumber(NumberError::MissingDigits, range) => Details::full(
+                "Expected digits in number literal",
+                range,
+                "Prefixes like 0x, decimal points and exponents must be followed by digits",
+            ),
+            Self::InvalidNumber(NumberError::InvalidDigit, range) => Details::full(
+                "Unexpected character in number literal",
+                range,
+                "Numbers look like: 42, -1.5e3, 0xff, 0b1010, and 0o17, with an i or n suffix for integers and bignums",
+            ),
+            Self::InvalidNumber(NumberError::MisplacedSeparator, range) => Details::full(
+                "Misplaced digit separator in number literal",
+                range,
+                "Underscores can only sit between two digits, like: 1_000_000",
+            ),
+            Self::InvalidNumber(NumberError::NotAnInteger, range) => Details::full(
+                "Integer literal with a fraction or exponent",
+                range,
+                "Use to_int or to_big to drop the fractional part of a number",
+            ),
+            Self::InvalidNumber(NumberError::OutOfRange, range) => Details::full(
+                "Integer literal out of range",
+                range,
+                "Integers are 64 bit, bignums like 9223372036854775808n have no limit",
+            ),
         }
     }
 }
 
 impl EndOfFileError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnclosedExpression(..) => "unclosed-expression",
+            Self::ExpectedMoreAfter(..) => "incomplete-expression",
+        }
+    }
+
     pub fn into_details(self) -> ErrorDetails {
         match self {
             Self::UnclosedExpression(expression, loc) => {
@@ -236,6 +307,11 @@ impl EndOfFileError {
                         ("function", "Function bodies are closed using: }")
                     }
                     WrappedExpression::Loop => ("loop", "Loops are closed using ]"),
+                    WrappedExpression::Try => {
+                        ("try block", "Try and catch blocks are closed using: }")
+                    }
+                    WrappedExpression::Record => ("record", "Record fields are closed using: }"),
+                    WrappedExpression::Enum => ("enum", "Enum variants are closed using: }"),
                     WrappedExpression::ImportNameList => (
                         "import name list",
                         "Import name lists are closed using }, like: # {f1 f2} \"./file.sl\"",
@@ -253,11 +329,28 @@ impl EndOfFileError {
                     loc,
                     "A function pointer (@) must be followed directly by a function name",
                 ),
+                ReasonExpectingMore::Binding => Details::full(
+                    "Incomplete local binding",
+                    loc,
+                    "A local binding (->) must be followed by the names to bind, like: -> x y",
+                ),
                 ReasonExpectingMore::Branch => Details::full(
                     "Incomplete branch expression",
                     loc,
                     "End a branch expression with: }",
                 ),
+                ReasonExpectingMore::Try => {
+                    Details::full("Incomplete try statement", loc, TRY_INFO)
+                }
+                ReasonExpectingMore::Record => {
+                    Details::full("Incomplete record definition", loc, RECORD_INFO)
+                }
+                ReasonExpectingMore::Enum => {
+                    Details::full("Incomplete enum definition", loc, ENUM_INFO)
+                }
+                ReasonExpectingMore::Match => {
+                    Details::full("Incomplete match statement", loc, MATCH_INFO)
+                }
                 ReasonExpectingMore::ImportName => Details::full(
                     "Incomplete import statement",
                     loc,
@@ -266,7 +359,7 @@ impl EndOfFileError {
                 ReasonExpectingMore::ImportPath => Details::full(
                     "Incomplete import statement",
                     loc,
-                    "An import (#) must specify a relative path: # * \"./file.sl\"",
+                    "An import (#) must specify a path, either relative like: # * \"./file.sl\" or on the search path like: # * \"std/math\"",
                 ),
             },
         }
@@ -274,6 +367,14 @@ impl EndOfFileError {
 }
 
 impl UnexpectedError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InContext { .. } => "unexpected-expression",
+            Self::SymbolInSection { .. } | Self::GeneralSymbol(..) => "unexpected-symbol",
+        }
+    }
+
+    #[expect(clippy::too_many_lines, reason = "one message per syntax error")]
     pub fn into_details(self) -> ErrorDetails {
         match self {
             Self::InContext {
@@ -291,6 +392,50 @@ impl UnexpectedError {
                     context_start,
                     "A function pointer (@) must be followed directly by a function name",
                 ),
+                UnexpectedContext::Binding => Details::full(
+                    "Invalid local binding",
+                    (context_start, loc),
+                    "A local binding (->) must be followed only by names, up to the end of the line",
+                ),
+                UnexpectedContext::BindingOutsideFunction => Details::full(
+                    "Local bindings can only be used inside functions",
+                    context_start,
+                    "Move this code into a function body, like: fn: { -> x y ... }",
+                ),
+                UnexpectedContext::LoopControl => Details::full(
+                    "break and continue can only be used inside a loop body",
+                    context_start,
+                    "Use them between the conditions of a loop, like: [(condition) ... break ... (condition)]",
+                ),
+                UnexpectedContext::ReturnOutsideFunction => Details::full(
+                    "return can only be used inside functions",
+                    context_start,
+                    "Move this code into a function body, like: fn: { {(condition) return} ... }",
+                ),
+                UnexpectedContext::Try => {
+                    Details::full("Invalid try statement", (context_start, loc), TRY_INFO)
+                }
+                UnexpectedContext::Constant => Details::full(
+                    "Invalid constant definition",
+                    (context_start, loc),
+                    "A constant needs a name and a value on the same line, like: const LIMIT: 100",
+                ),
+                UnexpectedContext::ConstantIntrinsicName => Details::full(
+                    "Constants cannot be named after an intrinsic",
+                    loc,
+                    "Choose a name that isn't built in, like: const MAX_SIZE: 100",
+                ),
+                UnexpectedContext::Record => Details::full(
+                    "Invalid record definition",
+                    (context_start, loc),
+                    RECORD_INFO,
+                ),
+                UnexpectedContext::Enum => {
+                    Details::full("Invalid enum definition", (context_start, loc), ENUM_INFO)
+                }
+                UnexpectedContext::Match => {
+                    Details::full("Invalid match statement", (context_start, loc), MATCH_INFO)
+                }
                 UnexpectedContext::AfterPostCondition => Details::full(
                     "Unexpected expression after loop's post condition",
                     (context_start, loc),
@@ -309,7 +454,7 @@ impl UnexpectedError {
                 UnexpectedContext::ImportPath => Details::full(
                     "Invalid import path",
                     (context_start, loc),
-                    "The second expression after # must be a relative path like: # file1 \"./file1.sl\"",
+                    "The second expression after # must be a path string, either relative like: # file1 \"./file1.sl\" or on the search path like: # math \"std/math\"",
                 ),
             },
             Self::SymbolInSection {
@@ -322,6 +467,7 @@ impl UnexpectedError {
                     ParseSection::Condition => "condition",
                     ParseSection::Branch => "branch",
                     ParseSection::Loop => "loop",
+                    ParseSection::Try => "try block",
                 };
 
                 let message = format!("Unexpected {:?} in {section_name}", symbol);
@@ -350,6 +496,14 @@ impl UnexpectedError {
 }
 
 impl ParseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Tokenization(e) => e.code(),
+            Self::EndOfFile(e) => e.code(),
+            Self::Unexpected(e) => e.code(),
+        }
+    }
+
     pub fn is_early_eof(&self) -> bool {
         match self {
             Self::EndOfFile(_) => true,
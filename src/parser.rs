@@ -1,9 +1,10 @@
-use std::{iter::Peekable, vec::IntoIter};
+use std::{iter::Peekable, rc::Rc, vec::IntoIter};
 
 use crate::{
+    intrinsics::get_intrinsic,
     lang::{
-        Block, Branch, Function, Import, ImportLocation, ImportNaming, Loop, Module, ParsedToken,
-        SourceLocation, SourceRange, Symbol, Term, Token,
+        Block, Branch, Constant, Enum, Function, Import, ImportLocation, ImportNaming, Local, Loop,
+        Match, Module, ParsedToken, Record, SourceLocation, SourceRange, Symbol, Term, Token, Try,
     },
     parse_error::{
         EndOfFileError, ParseError, ParseSection, ReasonExpectingMore, UnexpectedContext,
@@ -13,7 +14,70 @@ use crate::{
     tokenizer::tokenize,
 };
 
-type Tokens = Peekable<IntoIter<ParsedToken>>;
+#[derive(Default)]
+struct LocalScope {
+    visible: Vec<Local>,
+    slot_count: usize,
+}
+
+struct Tokens {
+    inner: Peekable<IntoIter<ParsedToken>>,
+    open: Vec<Symbol>,
+    // Only present while parsing a function body
+    locals: Option<LocalScope>,
+    // Counts the enclosing loop bodies that break and continue apply to
+    loop_depth: usize,
+}
+
+impl Tokens {
+    fn new(tokens: Vec<ParsedToken>) -> Self {
+        Self {
+            inner: tokens.into_iter().peekable(),
+            open: vec![],
+            locals: None,
+            loop_depth: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&ParsedToken> {
+        self.inner.peek()
+    }
+
+    fn name_term(&self, name: String, loc: SourceRange) -> Term {
+        let local = self
+            .locals
+            .as_ref()
+            .and_then(|scope| scope.visible.iter().rev().find(|l| l.name == name));
+        match local {
+            Some(local) => Term::Local(local.clone()),
+            None => Term::Name(name, loc),
+        }
+    }
+}
+
+impl Iterator for Tokens {
+    type Item = ParsedToken;
+
+    fn next(&mut self) -> Option<ParsedToken> {
+        let token = self.inner.next()?;
+        if let Token::Symbol(s) = token.value {
+            let opening = match s {
+                Symbol::CurlyOpen | Symbol::ParenOpen | Symbol::SquareOpen => {
+                    self.open.push(s);
+                    None
+                }
+                Symbol::CurlyClose => Some(Symbol::CurlyOpen),
+                Symbol::ParenClose => Some(Symbol::ParenOpen),
+                Symbol::SquareClose => Some(Symbol::SquareOpen),
+                Symbol::Colon | Symbol::Hash | Symbol::At | Symbol::LineEnd => None,
+            };
+            if opening.is_some() && self.open.last().copied() == opening {
+                self.open.pop();
+            }
+        }
+        Some(token)
+    }
+}
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
@@ -58,16 +122,97 @@ fn maybe_consume_next_symbol(symbol: Symbol, tokens: &mut Tokens) -> Option<Pars
     }
 }
 
+fn is_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "->" | "break" | "continue" | "return" | "try" | "catch" | "match"
+    )
+}
+
+fn parse_keyword(tokens: &mut Tokens, name: &str, start: &SourceLocation) -> ParseResult<Term> {
+    match name {
+        "->" => parse_bind(tokens, start),
+        "try" => Ok(Term::Try(parse_try(tokens, start)?)),
+        "match" => Ok(Term::Match(parse_match(tokens, start)?)),
+        "catch" => cannot_use_in(UnexpectedContext::Try, *start, *start),
+        "return" if tokens.locals.is_none() => {
+            cannot_use_in(UnexpectedContext::ReturnOutsideFunction, *start, *start)
+        }
+        "return" => Ok(Term::Return),
+        _ if tokens.loop_depth == 0 => {
+            cannot_use_in(UnexpectedContext::LoopControl, *start, *start)
+        }
+        "break" => Ok(Term::Break),
+        _ => Ok(Term::Continue),
+    }
+}
+
+// Parses a local binding such as `-> x y`, the names run until the line ends or a bracket
+fn parse_bind(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Term> {
+    let Some(scope) = tokens.locals.as_mut() else {
+        return cannot_use_in(UnexpectedContext::BindingOutsideFunction, *start, *start);
+    };
+
+    let mut locals = vec![];
+    loop {
+        match tokens.inner.peek() {
+            Some(ParsedToken {
+                value: Token::Name(n),
+                ..
+            }) if !is_keyword(n) => {
+                locals.push(Local {
+                    name: n.clone(),
+                    slot: scope.slot_count,
+                });
+                scope.slot_count += 1;
+                tokens.inner.next();
+            }
+            Some(ParsedToken {
+                value: Token::Symbol(_),
+                ..
+            })
+            | None
+                if !locals.is_empty() =>
+            {
+                break;
+            }
+            Some(ParsedToken { loc, .. }) => {
+                return cannot_use_in(UnexpectedContext::Binding, *start, loc.start);
+            }
+            None => return need_more(ReasonExpectingMore::Binding, *start),
+        }
+    }
+
+    scope.visible.extend(locals.iter().cloned());
+    Ok(Term::Bind(locals))
+}
+
 fn consume_block_terms(
     target: &mut Vec<Term>,
     tokens: &mut Tokens,
+) -> ParseResult<Option<(BlockEndSymbol, SourceRange)>> {
+    let visible = tokens.locals.as_ref().map(|scope| scope.visible.len());
+    let end = consume_scoped_terms(target, tokens);
+    if let (Some(scope), Some(visible)) = (tokens.locals.as_mut(), visible) {
+        scope.visible.truncate(visible);
+    }
+    end
+}
+
+fn consume_scoped_terms(
+    target: &mut Vec<Term>,
+    tokens: &mut Tokens,
 ) -> ParseResult<Option<(BlockEndSymbol, SourceRange)>> {
     while let Some(ParsedToken { value: token, loc }) = tokens.next() {
         match token {
             Token::String(l) => target.push(Term::String(l)),
             Token::Number(l) => target.push(Term::Number(l)),
+            Token::Integer(l) => target.push(Term::Integer(l)),
+            Token::Big(l) => target.push(Term::Big(l)),
+            Token::Decimal(l) => target.push(Term::Decimal(l)),
             Token::Bool(l) => target.push(Term::Bool(l)),
-            Token::Name(l) => target.push(Term::Name(l, loc)),
+            Token::Name(l) if is_keyword(&l) => target.push(parse_keyword(tokens, &l, &loc.start)?),
+            Token::Name(l) => target.push(tokens.name_term(l, loc)),
             Token::Symbol(s) => match s {
                 Symbol::LineEnd => {}
                 Symbol::Hash | Symbol::Colon => {
@@ -107,6 +252,13 @@ fn consume_block_terms(
     Ok(None)
 }
 
+fn parse_loop_condition(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Block> {
+    let loop_depth = std::mem::take(&mut tokens.loop_depth);
+    let condition = parse_condition(tokens, start);
+    tokens.loop_depth = loop_depth;
+    condition
+}
+
 fn parse_condition(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Block> {
     let mut condition = Block { terms: vec![] };
     let section = ParseSection::Condition;
@@ -189,11 +341,14 @@ fn parse_loop(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Loop>
     };
 
     if let Some(t) = maybe_consume_next_symbol(Symbol::ParenOpen, tokens) {
-        loop_v.pre_condition = Some(parse_condition(tokens, &t.loc.start)?);
+        loop_v.pre_condition = Some(parse_loop_condition(tokens, &t.loc.start)?);
     }
 
     let section = ParseSection::Loop;
-    match consume_block_terms(&mut loop_v.body.terms, tokens)? {
+    tokens.loop_depth += 1;
+    let body_end = consume_block_terms(&mut loop_v.body.terms, tokens);
+    tokens.loop_depth -= 1;
+    match body_end? {
         Some((BlockEndSymbol::ParenClose, loc)) => {
             unexpected_symbol_in(Symbol::ParenClose, loc.start, section, *start)
         }
@@ -201,7 +356,7 @@ fn parse_loop(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Loop>
             unexpected_symbol_in(Symbol::CurlyClose, loc.start, section, *start)
         }
         Some((BlockEndSymbol::ParenOpen, loc)) => {
-            loop_v.post_condition = Some(parse_condition(tokens, &loc.start)?);
+            loop_v.post_condition = Some(parse_loop_condition(tokens, &loc.start)?);
             assert_next_symbol(
                 tokens,
                 Symbol::SquareClose,
@@ -219,6 +374,79 @@ fn parse_loop(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Loop>
     }
 }
 
+fn parse_try_block(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Block> {
+    let open = assert_next_symbol(
+        tokens,
+        Symbol::CurlyOpen,
+        |t| UnexpectedError::InContext {
+            context: UnexpectedContext::Try,
+            context_start: *start,
+            loc: t.loc.start,
+        },
+        EndOfFileError::ExpectedMoreAfter(ReasonExpectingMore::Try, *start),
+    )?;
+
+    let mut block = Block { terms: vec![] };
+    let section = ParseSection::Try;
+    match consume_block_terms(&mut block.terms, tokens)? {
+        Some((BlockEndSymbol::CurlyClose, _)) => Ok(block),
+        Some((BlockEndSymbol::ParenOpen, loc)) => {
+            unexpected_symbol_in(Symbol::ParenOpen, loc.start, section, open.loc.start)
+        }
+        Some((BlockEndSymbol::ParenClose, loc)) => {
+            unexpected_symbol_in(Symbol::ParenClose, loc.start, section, open.loc.start)
+        }
+        Some((BlockEndSymbol::SquareClose, loc)) => {
+            unexpected_symbol_in(Symbol::SquareClose, loc.start, section, open.loc.start)
+        }
+        None => unclosed(open.loc.start, WrappedExpression::Try),
+    }
+}
+
+// Parses `try { body } catch { handler }`
+fn parse_try(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Try> {
+    let body = parse_try_block(tokens, start)?;
+    ignore_whitespace(tokens);
+    match tokens.next() {
+        Some(ParsedToken {
+            value: Token::Name(n),
+            ..
+        }) if n == "catch" => {}
+        Some(t) => return cannot_use_in(UnexpectedContext::Try, *start, t.loc.start),
+        None => return need_more(ReasonExpectingMore::Try, *start),
+    }
+    let handler = parse_try_block(tokens, start)?;
+    Ok(Try { body, handler })
+}
+
+// Parses `match { (some) ... (none) ... }`, each condition holds only a variant name
+fn parse_match(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Match> {
+    let open = assert_next_symbol(
+        tokens,
+        Symbol::CurlyOpen,
+        |t| UnexpectedError::InContext {
+            context: UnexpectedContext::Match,
+            context_start: *start,
+            loc: t.loc.start,
+        },
+        EndOfFileError::ExpectedMoreAfter(ReasonExpectingMore::Match, *start),
+    )?;
+
+    let mut arms: Vec<(String, Block)> = vec![];
+    for (condition, body) in parse_branch(tokens, &open.loc.start)?.arms {
+        match condition.terms.as_slice() {
+            [Term::Name(n, _)] if !arms.iter().any(|(variant, _)| variant == n) => {
+                arms.push((n.clone(), body));
+            }
+            [Term::Name(_, loc), ..] => {
+                return cannot_use_in(UnexpectedContext::Match, *start, loc.start);
+            }
+            _ => return cannot_use_in(UnexpectedContext::Match, *start, *start),
+        }
+    }
+    Ok(Match { arms })
+}
+
 fn parse_function_body(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Block> {
     let mut body = Block { terms: vec![] };
 
@@ -240,8 +468,12 @@ fn parse_single_line(tokens: &mut Tokens) -> ParseResult<Block> {
         match t {
             Token::String(l) => target.push(Term::String(l)),
             Token::Number(l) => target.push(Term::Number(l)),
+            Token::Integer(l) => target.push(Term::Integer(l)),
+            Token::Big(l) => target.push(Term::Big(l)),
+            Token::Decimal(l) => target.push(Term::Decimal(l)),
             Token::Bool(l) => target.push(Term::Bool(l)),
-            Token::Name(l) => target.push(Term::Name(l, loc)),
+            Token::Name(l) if is_keyword(&l) => target.push(parse_keyword(tokens, &l, &loc.start)?),
+            Token::Name(l) => target.push(tokens.name_term(l, loc)),
             Token::Symbol(s) => match s {
                 Symbol::LineEnd => break,
                 Symbol::CurlyOpen => target.push(Term::Branch(parse_branch(tokens, &loc.start)?)),
@@ -282,7 +514,7 @@ fn parse_single_line(tokens: &mut Tokens) -> ParseResult<Block> {
     Ok(Block { terms: target })
 }
 
-fn parse_function(name: String, tokens: &mut Tokens) -> ParseResult<Function> {
+fn parse_function(name: String, loc: SourceRange, tokens: &mut Tokens) -> ParseResult<Function> {
     let multiline_start = match tokens.peek() {
         Some(ParsedToken {
             value: Token::Symbol(Symbol::LineEnd),
@@ -291,6 +523,7 @@ fn parse_function(name: String, tokens: &mut Tokens) -> ParseResult<Function> {
         | None => {
             return Ok(Function {
                 name,
+                loc,
                 body: Block { terms: vec![] },
             });
         }
@@ -305,16 +538,185 @@ fn parse_function(name: String, tokens: &mut Tokens) -> ParseResult<Function> {
         _ => None,
     };
 
-    if let Some(start) = multiline_start {
-        Ok(Function {
-            name,
-            body: parse_function_body(tokens, &start)?,
+    tokens.locals = Some(LocalScope::default());
+    let body = match multiline_start {
+        Some(start) => parse_function_body(tokens, &start),
+        None => parse_single_line(tokens),
+    };
+    tokens.locals = None;
+
+    Ok(Function {
+        name,
+        loc,
+        body: body?,
+    })
+}
+
+// Tokens are only peeked, so a line end is left for error recovery. Constants never
+// continue onto the next line, so running out of input is not treated as incomplete.
+fn constant_error<T>(token: Option<&ParsedToken>, start: &SourceLocation) -> ParseResult<T> {
+    let loc = token.map_or(*start, |t| t.loc.start);
+    cannot_use_in(UnexpectedContext::Constant, *start, loc)
+}
+
+// Parses `const NAME: value`, the value is the rest of the line
+fn parse_constant(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Constant> {
+    let name = match tokens.peek() {
+        // Intrinsics take precedence over constants, which would never be used
+        Some(ParsedToken {
+            value: Token::Name(n),
+            loc,
+        }) if get_intrinsic(n).is_some() => {
+            return cannot_use_in(UnexpectedContext::ConstantIntrinsicName, *start, loc.start);
+        }
+        Some(ParsedToken {
+            value: Token::Name(n),
+            ..
+        }) if !is_keyword(n) => n.clone(),
+        t => return constant_error(t, start),
+    };
+    tokens.next();
+
+    match tokens.peek() {
+        Some(ParsedToken {
+            value: Token::Symbol(Symbol::Colon),
+            ..
+        }) => tokens.next(),
+        t => return constant_error(t, start),
+    };
+
+    match tokens.peek() {
+        Some(ParsedToken {
+            value: Token::Symbol(Symbol::LineEnd),
+            ..
         })
-    } else {
-        Ok(Function {
+        | None => constant_error(tokens.peek(), start),
+        Some(_) => Ok(Constant {
             name,
             body: parse_single_line(tokens)?,
-        })
+        }),
+    }
+}
+
+// Parses `record point { x y }` into the functions it generates, the fields can span lines
+fn parse_record(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Vec<Function>> {
+    let (name, loc) = parse_declared_name(tokens, start, UnexpectedContext::Record)?;
+    let open = assert_next_symbol(
+        tokens,
+        Symbol::CurlyOpen,
+        |t| UnexpectedError::InContext {
+            context: UnexpectedContext::Record,
+            context_start: *start,
+            loc: t.loc.start,
+        },
+        EndOfFileError::ExpectedMoreAfter(ReasonExpectingMore::Record, *start),
+    )?;
+    let fields = parse_field_names(tokens, start, &open.loc.start, UnexpectedContext::Record)?;
+
+    let record = Record {
+        name,
+        fields,
+        variant_of: None,
+    };
+    Ok(Rc::new(record).functions(loc))
+}
+
+// Parses `enum option { some { value } none }`, only variants with fields list them
+fn parse_enum(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Vec<Function>> {
+    let (name, loc) = parse_declared_name(tokens, start, UnexpectedContext::Enum)?;
+    let open = assert_next_symbol(
+        tokens,
+        Symbol::CurlyOpen,
+        |t| UnexpectedError::InContext {
+            context: UnexpectedContext::Enum,
+            context_start: *start,
+            loc: t.loc.start,
+        },
+        EndOfFileError::ExpectedMoreAfter(ReasonExpectingMore::Enum, *start),
+    )?;
+
+    let mut variants: Vec<(String, Vec<String>)> = vec![];
+    loop {
+        ignore_whitespace(tokens);
+        match tokens.next() {
+            Some(ParsedToken {
+                value: Token::Symbol(Symbol::CurlyClose),
+                ..
+            }) => break,
+            Some(ParsedToken {
+                value: Token::Name(n),
+                ..
+            }) if !is_keyword(&n) && !variants.iter().any(|(v, _)| *v == n) => {
+                let fields = match maybe_consume_next_symbol(Symbol::CurlyOpen, tokens) {
+                    Some(t) => {
+                        parse_field_names(tokens, start, &t.loc.start, UnexpectedContext::Enum)?
+                    }
+                    None => vec![],
+                };
+                variants.push((n, fields));
+            }
+            Some(t) => return cannot_use_in(UnexpectedContext::Enum, *start, t.loc.start),
+            None => return unclosed(open.loc.start, WrappedExpression::Enum),
+        }
+    }
+
+    let declared = Rc::new(Enum {
+        name,
+        variants: variants.iter().map(|(v, _)| v.clone()).collect(),
+    });
+    let mut functions = vec![];
+    for (name, fields) in variants {
+        let variant = Record {
+            name,
+            fields,
+            variant_of: Some(Rc::clone(&declared)),
+        };
+        functions.extend(Rc::new(variant).functions(loc));
+    }
+    Ok(functions)
+}
+
+fn parse_declared_name(
+    tokens: &mut Tokens,
+    start: &SourceLocation,
+    context: UnexpectedContext,
+) -> ParseResult<(String, SourceRange)> {
+    match tokens.next() {
+        Some(ParsedToken {
+            value: Token::Name(n),
+            loc,
+        }) if !is_keyword(&n) => Ok((n, loc)),
+        Some(t) => cannot_use_in(context, *start, t.loc.start),
+        None if context == UnexpectedContext::Enum => need_more(ReasonExpectingMore::Enum, *start),
+        None => need_more(ReasonExpectingMore::Record, *start),
+    }
+}
+
+// Reads distinct field names up to the closing }, which may be on a later line
+fn parse_field_names(
+    tokens: &mut Tokens,
+    start: &SourceLocation,
+    open: &SourceLocation,
+    context: UnexpectedContext,
+) -> ParseResult<Vec<String>> {
+    let mut fields: Vec<String> = vec![];
+    loop {
+        ignore_whitespace(tokens);
+        match tokens.next() {
+            Some(ParsedToken {
+                value: Token::Symbol(Symbol::CurlyClose),
+                ..
+            }) => return Ok(fields),
+            Some(ParsedToken {
+                value: Token::Name(n),
+                ..
+            }) if !is_keyword(&n) && !fields.contains(&n) => fields.push(n),
+            Some(t) => return cannot_use_in(context, *start, t.loc.start),
+            None if context == UnexpectedContext::Enum => {
+                return unclosed(*open, WrappedExpression::Enum);
+            }
+            None => return unclosed(*open, WrappedExpression::Record),
+        }
     }
 }
 
@@ -358,7 +760,13 @@ fn parse_import(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Impo
             }
             ImportNaming::Named(names)
         }
-        Token::String(_) | Token::Number(_) | Token::Bool(_) | Token::Symbol(_) => {
+        Token::String(_)
+        | Token::Number(_)
+        | Token::Integer(_)
+        | Token::Big(_)
+        | Token::Decimal(_)
+        | Token::Bool(_)
+        | Token::Symbol(_) => {
             return cannot_use_in(UnexpectedContext::ImportNaming, *start, first_loc.start);
         }
     };
@@ -369,7 +777,11 @@ fn parse_import(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Impo
             ..
         }) => Ok(Import {
             naming,
-            location: ImportLocation::Relative(s),
+            location: if s.starts_with("./") || s.starts_with("../") {
+                ImportLocation::Relative(s)
+            } else {
+                ImportLocation::Search(s)
+            },
         }),
         Some(ParsedToken { loc, .. }) => {
             cannot_use_in(UnexpectedContext::ImportPath, *start, loc.start)
@@ -378,74 +790,166 @@ fn parse_import(tokens: &mut Tokens, start: &SourceLocation) -> ParseResult<Impo
     }
 }
 
-fn parse_module(tokens: &mut Tokens) -> Result<Module, ParseError> {
+fn parse_module_item(
+    module: &mut Module,
+    ParsedToken { value: token, loc }: ParsedToken,
+    tokens: &mut Tokens,
+) -> ParseResult<()> {
+    match token {
+        Token::String(l) => module.body.terms.push(Term::String(l)),
+        Token::Number(l) => module.body.terms.push(Term::Number(l)),
+        Token::Integer(l) => module.body.terms.push(Term::Integer(l)),
+        Token::Big(l) => module.body.terms.push(Term::Big(l)),
+        Token::Decimal(l) => module.body.terms.push(Term::Decimal(l)),
+        Token::Bool(l) => module.body.terms.push(Term::Bool(l)),
+        Token::Name(s) => {
+            if maybe_consume_next_symbol(Symbol::Colon, tokens).is_some() {
+                module.functions.push(parse_function(s, loc, tokens)?);
+            } else if s == "const" {
+                module.constants.push(parse_constant(tokens, &loc.start)?);
+            } else if s == "record" {
+                module.functions.extend(parse_record(tokens, &loc.start)?);
+            } else if s == "enum" {
+                module.functions.extend(parse_enum(tokens, &loc.start)?);
+            } else {
+                module.body.terms.push(if is_keyword(&s) {
+                    parse_keyword(tokens, &s, &loc.start)?
+                } else {
+                    Term::Name(s, loc)
+                });
+            }
+        }
+        Token::Symbol(s) => match s {
+            Symbol::LineEnd => {}
+            Symbol::At => match tokens.peek() {
+                Some(ParsedToken {
+                    value: Token::Name(n),
+                    ..
+                }) => {
+                    module.body.terms.push(Term::Address(n.clone()));
+                    tokens.next();
+                }
+                Some(ParsedToken {
+                    loc: unexpected_loc,
+                    ..
+                }) => {
+                    return cannot_use_in(
+                        UnexpectedContext::Address,
+                        loc.start,
+                        unexpected_loc.start,
+                    );
+                }
+                None => {
+                    return need_more(ReasonExpectingMore::Address, loc.start);
+                }
+            },
+            Symbol::Hash => module.imports.push(parse_import(tokens, &loc.start)?),
+            Symbol::Colon
+            | Symbol::ParenClose
+            | Symbol::ParenOpen
+            | Symbol::SquareClose
+            | Symbol::CurlyClose => return unexpected_symbol(s, loc.start),
+            Symbol::CurlyOpen => module
+                .body
+                .terms
+                .push(Term::Branch(parse_branch(tokens, &loc.start)?)),
+            Symbol::SquareOpen => module
+                .body
+                .terms
+                .push(Term::Loop(parse_loop(tokens, &loc.start)?)),
+        },
+    }
+    Ok(())
+}
+
+// Skips the remainder of a failed top-level item. Resumes once the brackets that
+// were open at the error are closed, at the end of the line when none were open,
+// or at the next line starting with an import or a declaration.
+fn synchronize(tokens: &mut Tokens) -> Option<ParsedToken> {
+    while let Some(token) = tokens.next() {
+        if tokens.open.is_empty() {
+            if token.value == Token::Symbol(Symbol::LineEnd) || is_closing(&token) {
+                return None;
+            }
+            continue;
+        }
+
+        if token.value != Token::Symbol(Symbol::LineEnd) {
+            continue;
+        }
+
+        ignore_whitespace(tokens);
+        match tokens.peek() {
+            Some(ParsedToken {
+                value: Token::Symbol(Symbol::Hash),
+                ..
+            }) => {
+                tokens.open.clear();
+                return None;
+            }
+            Some(ParsedToken {
+                value: Token::Name(_),
+                ..
+            }) => {
+                let name = tokens.next();
+                let is_declaration = name.as_ref().is_some_and(
+                    |t| matches!(&t.value, Token::Name(n) if matches!(n.as_str(), "const" | "record" | "enum")),
+                );
+                if is_declaration
+                    || tokens
+                        .peek()
+                        .is_some_and(|t| t.value == Token::Symbol(Symbol::Colon))
+                {
+                    tokens.open.clear();
+                    return name;
+                }
+            }
+            Some(_) | None => {}
+        }
+    }
+    None
+}
+
+fn is_closing(token: &ParsedToken) -> bool {
+    matches!(
+        token.value,
+        Token::Symbol(Symbol::CurlyClose | Symbol::ParenClose | Symbol::SquareClose)
+    )
+}
+
+fn parse_module(tokens: &mut Tokens) -> (Module, Vec<ParseError>) {
     let mut module = Module {
         imports: vec![],
+        constants: vec![],
         functions: vec![],
         body: Block { terms: vec![] },
     };
+    let mut errors = vec![];
+    let mut pending = None;
 
-    while let Some(ParsedToken { value: token, loc }) = tokens.next() {
-        match token {
-            Token::String(l) => module.body.terms.push(Term::String(l)),
-            Token::Number(l) => module.body.terms.push(Term::Number(l)),
-            Token::Bool(l) => module.body.terms.push(Term::Bool(l)),
-            Token::Name(s) => {
-                if maybe_consume_next_symbol(Symbol::Colon, tokens).is_some() {
-                    module.functions.push(parse_function(s, tokens)?);
-                } else {
-                    module.body.terms.push(Term::Name(s, loc));
-                }
-            }
-            Token::Symbol(s) => match s {
-                Symbol::LineEnd => {}
-                Symbol::At => match tokens.peek() {
-                    Some(ParsedToken {
-                        value: Token::Name(n),
-                        ..
-                    }) => {
-                        module.body.terms.push(Term::Address(n.clone()));
-                        tokens.next();
-                    }
-                    Some(ParsedToken {
-                        loc: unexpected_loc,
-                        ..
-                    }) => {
-                        return cannot_use_in(
-                            UnexpectedContext::Address,
-                            loc.start,
-                            unexpected_loc.start,
-                        );
-                    }
-                    None => {
-                        return need_more(ReasonExpectingMore::Address, loc.start);
-                    }
-                },
-                Symbol::Hash => module.imports.push(parse_import(tokens, &loc.start)?),
-                Symbol::Colon
-                | Symbol::ParenClose
-                | Symbol::ParenOpen
-                | Symbol::SquareClose
-                | Symbol::CurlyClose => return unexpected_symbol(s, loc.start),
-                Symbol::CurlyOpen => module
-                    .body
-                    .terms
-                    .push(Term::Branch(parse_branch(tokens, &loc.start)?)),
-                Symbol::SquareOpen => module
-                    .body
-                    .terms
-                    .push(Term::Loop(parse_loop(tokens, &loc.start)?)),
-            },
+    while let Some(token) = pending.take().or_else(|| tokens.next()) {
+        if let Err(e) = parse_module_item(&mut module, token, tokens) {
+            errors.push(e);
+            pending = synchronize(tokens);
         }
     }
 
-    Ok(module)
+    (module, errors)
+}
+
+pub fn parse_recovering(source: &str) -> (Module, Vec<ParseError>) {
+    match tokenize(source) {
+        Ok(tokens) => parse_module(&mut Tokens::new(tokens)),
+        Err(e) => (Module::default(), vec![ParseError::Tokenization(e)]),
+    }
 }
 
+// Only the first error, for tests that expect a source to parse
+#[cfg(test)]
 pub fn parse(source: &str) -> ParseResult<Module> {
-    let mut tokens = tokenize(source)
-        .map_err(ParseError::Tokenization)?
-        .into_iter()
-        .peekable();
-    parse_module(&mut tokens)
+    let (module, errors) = parse_recovering(source);
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(module),
+    }
 }
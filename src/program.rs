@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
-    lang::{Function, ImportNaming, Module},
+    lang::{Function, ImportNaming, Module, Record, RecordOp, Term},
     path::CanonicalPathBuf,
 };
 
@@ -14,8 +14,12 @@ pub struct NamespaceImport {
 #[derive(Default, Debug)]
 pub struct Namespace {
     pub path: Option<CanonicalPathBuf>,
+    pub library: Option<&'static str>,
     pub imports: Vec<NamespaceImport>,
     pub functions: HashMap<String, Function>,
+    pub function_sources: HashMap<String, Rc<str>>,
+    // Folded literal values, inlined wherever they are referenced
+    pub constants: HashMap<String, Term>,
 }
 
 pub type NamespaceId = usize;
@@ -78,10 +82,12 @@ impl Program {
         let namespace = &mut self.namespaces[namespace];
 
         for f in functions {
-            if namespace
+            let replaced_constant = namespace.constants.remove(&f.name).is_some();
+            if (namespace
                 .functions
                 .insert(f.name.clone(), f.clone())
                 .is_some()
+                || replaced_constant)
                 && overwrite_strategy == FunctionOverwriteStrategy::FailOnDuplicate
             {
                 return Err(f.name.as_str());
@@ -90,6 +96,37 @@ impl Program {
         Ok(())
     }
 
+    pub fn add_constant<'a>(
+        &mut self,
+        namespace: NamespaceId,
+        name: &'a str,
+        value: Term,
+        overwrite_strategy: FunctionOverwriteStrategy,
+    ) -> Result<(), &'a str> {
+        let namespace = &mut self.namespaces[namespace];
+        let replaced_function = namespace.functions.remove(name).is_some();
+        if (namespace.constants.insert(name.to_owned(), value).is_some() || replaced_function)
+            && overwrite_strategy == FunctionOverwriteStrategy::FailOnDuplicate
+        {
+            return Err(name);
+        }
+        Ok(())
+    }
+
+    pub fn add_function_sources(
+        &mut self,
+        namespace: NamespaceId,
+        functions: &[Function],
+        source: &Rc<str>,
+    ) {
+        let namespace = &mut self.namespaces[namespace];
+        for f in functions {
+            namespace
+                .function_sources
+                .insert(f.name.clone(), Rc::clone(source));
+        }
+    }
+
     pub fn add_imports(&mut self, namespace: NamespaceId, imports: Vec<NamespaceImport>) {
         let namespace = &mut self.namespaces[namespace];
         namespace.imports.extend(imports);
@@ -111,36 +148,64 @@ impl Program {
         current_id: NamespaceId,
         name: &str,
     ) -> Option<(NamespaceId, &str)> {
+        self.resolve(current_id, name, |id, name| {
+            self.resolve_function_in_namespace(id, name)
+        })
+    }
+
+    // A function found before the constant hides it, the same as any other name
+    pub fn resolve_constant(&self, current_id: NamespaceId, name: &str) -> Option<&Term> {
+        self.resolve(current_id, name, |id, name| {
+            let namespace = &self.namespaces[id];
+            match namespace.constants.get(name) {
+                Some(constant) => Some(Some(constant)),
+                None => namespace.functions.contains_key(name).then_some(None),
+            }
+        })
+        .flatten()
+    }
+
+    // Match arms name a variant by its constructor, so they resolve like function names
+    pub fn resolve_variant(&self, current_id: NamespaceId, name: &str) -> Option<&Rc<Record>> {
+        let (id, name) = self.resolve_function(current_id, name)?;
+        match self.namespaces[id].functions[name].body.terms.as_slice() {
+            [Term::Record(record, RecordOp::Construct)] if record.variant_of.is_some() => {
+                Some(record)
+            }
+            _ => None,
+        }
+    }
+
+    // Looks in the current namespace, then through its imports in order
+    fn resolve<T>(
+        &self,
+        current_id: NamespaceId,
+        name: &str,
+        find: impl Fn(NamespaceId, &str) -> Option<T>,
+    ) -> Option<T> {
         let current = &self.namespaces[current_id];
 
-        if let Some(same_namespace) = self.resolve_function_in_namespace(current_id, name) {
+        if let Some(same_namespace) = find(current_id, name) {
             return Some(same_namespace);
         }
 
         for import in &current.imports {
             match &import.naming {
                 ImportNaming::Wildcard => {
-                    if let Some(other_namespace) =
-                        self.resolve_function_in_namespace(import.id, name)
-                    {
+                    if let Some(other_namespace) = find(import.id, name) {
                         return Some(other_namespace);
                     }
                 }
                 ImportNaming::Named(names) => {
                     let is_named = names.iter().any(|f| f == name);
-                    if is_named
-                        && let Some(other_namespace) =
-                            self.resolve_function_in_namespace(import.id, name)
-                    {
+                    if is_named && let Some(other_namespace) = find(import.id, name) {
                         return Some(other_namespace);
                     }
                 }
                 ImportNaming::Scoped(prefix) => {
                     if name.starts_with(prefix) && name[prefix.len()..].starts_with('.') {
                         let trailing = &name[(prefix.len() + 1)..];
-                        if let Some(other_namespace) =
-                            self.resolve_function_in_namespace(import.id, trailing)
-                        {
+                        if let Some(other_namespace) = find(import.id, trailing) {
                             return Some(other_namespace);
                         }
                     }
@@ -4,27 +4,32 @@ use std::{
     fmt::Write as _,
     io::{IsTerminal as _, StdoutLock, Write as _, stdin},
     path::{Path, PathBuf},
+    rc::Rc,
 };
 
 use crate::{
-    ReplArgs,
+    OutputFormat, ReplArgs,
     analyze::{AnalysisError, BlockAnalysisResult, analyze_block_in_namespace, analyze_program},
     codegen::{c::c_codegen_module, js::js_codegen_module, rs::rs_codegen_module},
-    interpreter::{BacktraceItem, Interpreter, InterpreterError, InterpreterSnapshot},
-    intrinsics::{IntrinsicData, get_intrinsics},
-    lang::{ImportLocation, ImportNaming, Module, Term},
+    diagnostic::{Diagnostic, DiagnosticFrame, Severity, diagnostics_to_json},
+    fold::fold_module,
+    interpreter::{BacktraceItem, Interpreter, InterpreterFailure, InterpreterSnapshot},
+    intrinsics::{IntrinsicData, get_intrinsic, get_intrinsics},
+    lang::{Block, ImportLocation, ImportNaming, Module, OwnedValue, Term, TermContext},
     parse_error::ParseError,
-    parser::parse,
+    parser::parse_recovering,
     path::CanonicalPathBuf,
     program::{FunctionOverwriteStrategy, NamespaceId, NamespaceImport, Program},
+    stdlib::get_stdlib_module,
 };
 
-fn report_arity_inner(result: Option<&BlockAnalysisResult>) -> Cow<'static, str> {
+pub(crate) fn report_arity_inner(result: Option<&BlockAnalysisResult>) -> Cow<'static, str> {
     match result {
         Some(Ok(arity)) => return arity.stringify().into(),
         Some(Err(AnalysisError::IndefiniteSize)) => "unbounded",
         Some(Err(AnalysisError::Pending)) | None => "not resolved",
         Some(Err(AnalysisError::IncompatibleTypes)) => "incompatible types",
+        Some(Err(AnalysisError::NonExhaustive)) => "non-exhaustive match",
     }
     .into()
 }
@@ -34,17 +39,90 @@ fn report_arity(label: &str, result: Option<&BlockAnalysisResult>) {
     println!("{}: {}", label, report_arity_inner(result));
 }
 
+fn arity_diagnostic(label: &str, result: Option<&BlockAnalysisResult>) -> Diagnostic {
+    let (severity, code) = match result {
+        Some(Ok(_)) => (Severity::Info, "arity"),
+        Some(Err(AnalysisError::IndefiniteSize)) => (Severity::Warning, "unbounded-arity"),
+        Some(Err(AnalysisError::Pending)) | None => (Severity::Warning, "unresolved-arity"),
+        Some(Err(AnalysisError::IncompatibleTypes)) => (Severity::Error, "incompatible-types"),
+        Some(Err(AnalysisError::NonExhaustive)) => (Severity::Error, "non-exhaustive-match"),
+    };
+    Diagnostic::new(
+        severity,
+        code,
+        format!("{}: {}", label, report_arity_inner(result)),
+    )
+}
+
+fn term_context_name(context: Option<TermContext>) -> Option<&'static str> {
+    match context? {
+        TermContext::BranchCondition => Some("branch condition"),
+        TermContext::LoopPreCondition => Some("loop pre-condition"),
+        TermContext::LoopBody => Some("loop body"),
+        TermContext::LoopPostCondition => Some("loop post-condition"),
+        TermContext::TryBody => Some("try body"),
+        TermContext::TryHandler => Some("catch handler"),
+        TermContext::MatchArm => Some("match arm"),
+        TermContext::Block => None,
+    }
+}
+
+static SEARCH_PATH_VAR: &str = "SCATTER_PATH";
+static STDLIB_PREFIX: &str = "std/";
+static STDLIB_DIR: &str = "std";
+
+static STACK_DUMP_LIMIT: usize = 8;
+static STACK_DUMP_VALUE_WIDTH: usize = 32;
+
+type FrameContainer<'a> = Option<(&'a Block, Option<&'a str>)>;
+
 pub struct Repl {
     args: ReplArgs,
-    program: Program,
+    pub(crate) program: Program,
     snapshot: InterpreterSnapshot,
     base_path: PathBuf,
+    search_paths: Vec<PathBuf>,
+    stdlib_path: Option<PathBuf>,
     loaded_paths: HashMap<CanonicalPathBuf, NamespaceId>,
+    loaded_stdlib: HashMap<&'static str, NamespaceId>,
     pending_code: String,
     is_terminal: bool,
 }
 
-pub type ReplError = Cow<'static, str>;
+#[derive(Debug)]
+pub enum ReplError {
+    Message(Cow<'static, str>),
+    Report(String),
+    Exit(i32),
+}
+
+impl std::fmt::Display for ReplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Message(m) => f.write_str(m),
+            Self::Report(r) => f.write_str(r),
+            Self::Exit(code) => write!(f, "Exited with code {code}"),
+        }
+    }
+}
+
+impl From<&'static str> for ReplError {
+    fn from(value: &'static str) -> Self {
+        Self::Message(value.into())
+    }
+}
+
+impl From<String> for ReplError {
+    fn from(value: String) -> Self {
+        Self::Message(value.into())
+    }
+}
+
+impl From<Cow<'static, str>> for ReplError {
+    fn from(value: Cow<'static, str>) -> Self {
+        Self::Message(value)
+    }
+}
 
 type ReplResult<T> = Result<T, ReplError>;
 
@@ -68,12 +146,30 @@ fn stringify_absolute_path(path: Option<&Path>) -> String {
 
 impl Repl {
     pub fn new(args: ReplArgs, base_path: PathBuf) -> Self {
+        let env_paths = std::env::var_os(SEARCH_PATH_VAR)
+            .map(|e| std::env::split_paths(&e).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let search_paths = args
+            .include
+            .iter()
+            .map(PathBuf::from)
+            .chain(env_paths)
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| base_path.join(p))
+            .collect();
+        let stdlib_path = std::env::current_exe()
+            .ok()
+            .and_then(|e| Some(e.parent()?.join(STDLIB_DIR)));
+
         Self {
             args,
             snapshot: InterpreterSnapshot::default(),
             program: Program::new(),
             base_path,
+            search_paths,
+            stdlib_path,
             loaded_paths: HashMap::default(),
+            loaded_stdlib: HashMap::default(),
             pending_code: String::new(),
             is_terminal: stdin().lock().is_terminal(),
         }
@@ -81,37 +177,131 @@ impl Repl {
 
     pub fn prepare_code(
         &mut self,
-        ast: &Module,
+        ast: &mut Module,
         id: NamespaceId,
         context: &Path,
+        source: &Rc<str>,
         function_overwrite_strategy: FunctionOverwriteStrategy,
     ) -> ReplResult<()> {
         let mut imports = vec![];
         for import in &ast.imports {
-            match &import.location {
+            let dependency_id = match &import.location {
                 ImportLocation::Relative(path) => {
-                    if !path.starts_with("./") && !path.starts_with("../") {
-                        return Err(format!("Invalid import: {}", path).into());
-                    }
                     let file_path = CanonicalPathBuf::try_from_path(&context.join(path))
                         .map_err(|e| Cow::Owned(e.to_string()))?;
-                    let dependency_id = self.prepare_dependency(&file_path)?;
-                    imports.push(NamespaceImport {
-                        id: dependency_id,
-                        naming: import.naming.clone(),
-                    });
+                    self.prepare_dependency(&file_path)?
                 }
-            }
+                ImportLocation::Search(path) => {
+                    if let Some(module) =
+                        path.strip_prefix(STDLIB_PREFIX).and_then(get_stdlib_module)
+                    {
+                        self.prepare_stdlib_dependency(module)?
+                    } else {
+                        let file_path = self.find_import(path)?;
+                        self.prepare_dependency(&file_path)?
+                    }
+                }
+            };
+            imports.push(NamespaceImport {
+                id: dependency_id,
+                naming: import.naming.clone(),
+            });
         }
+        self.program.add_imports(id, imports);
+
+        fold_module(
+            &mut self.program,
+            id,
+            ast,
+            function_overwrite_strategy,
+            !self.args.no_fold,
+        )?;
         self.program
             .add_functions(id, &ast.functions, function_overwrite_strategy)
             .map_err(|e| format!("Function redefinition error: {}", e))?;
-
-        self.program.add_imports(id, imports);
+        self.program
+            .add_function_sources(id, &ast.functions, source);
 
         Ok(())
     }
 
+    fn find_import(&self, path: &str) -> ReplResult<CanonicalPathBuf> {
+        if path.is_empty() || Path::new(path).has_root() {
+            return Err(format!("Invalid import: {}", path).into());
+        }
+
+        let (roots, relative) = match path.strip_prefix(STDLIB_PREFIX) {
+            Some(rest) => (self.stdlib_path.as_slice(), rest),
+            None => (self.search_paths.as_slice(), path),
+        };
+
+        for root in roots {
+            for candidate in [root.join(relative), root.join(format!("{relative}.sl"))] {
+                if candidate.is_file() {
+                    return CanonicalPathBuf::try_from_path(&candidate)
+                        .map_err(|e| e.to_string().into());
+                }
+            }
+        }
+
+        Err(format!("Could not find import \"{path}\" in the search path").into())
+    }
+
+    fn prepare_stdlib_dependency(
+        &mut self,
+        (name, source): (&'static str, &'static str),
+    ) -> ReplResult<NamespaceId> {
+        if let Some(id) = self.loaded_stdlib.get(name) {
+            return Ok(*id);
+        }
+
+        let id = self.program.allocate_namespace();
+        self.prepare_stdlib_namespace(id, name, source)
+            .inspect_err(|_| self.discard_namespaces(id))?;
+        self.loaded_stdlib.insert(name, id);
+        Ok(id)
+    }
+
+    fn prepare_stdlib_namespace(
+        &mut self,
+        id: NamespaceId,
+        name: &'static str,
+        source: &'static str,
+    ) -> ReplResult<()> {
+        self.program.get_namespace_mut(id).library = Some(name);
+
+        let source: Rc<str> = source.into();
+        let (mut ast, errors) = parse_recovering(&source);
+        if !errors.is_empty() {
+            return Err(format!("Failed to parse library: {STDLIB_PREFIX}{name}").into());
+        }
+        let base = self.base_path.clone();
+        self.prepare_code(
+            &mut ast,
+            id,
+            &base,
+            &source,
+            FunctionOverwriteStrategy::FailOnDuplicate,
+        )
+    }
+
+    // Drops the namespace of a failed load, along with any dependencies it loaded, so a
+    // later import of the same module is prepared again rather than reusing a partial one
+    fn discard_namespaces(&mut self, first: NamespaceId) {
+        self.program.namespaces.truncate(first);
+        self.loaded_paths.retain(|_, id| *id < first);
+        self.loaded_stdlib.retain(|_, id| *id < first);
+    }
+
+    fn namespace_label(&self, id: NamespaceId) -> Option<String> {
+        let namespace = self.program.get_namespace(id);
+        match (&namespace.path, namespace.library) {
+            (Some(path), _) => Some(stringify_absolute_path(Some(path.as_path()))),
+            (None, Some(library)) => Some(format!("{STDLIB_PREFIX}{library}")),
+            (None, None) => None,
+        }
+    }
+
     pub fn prepare_dependency(&mut self, path: &CanonicalPathBuf) -> ReplResult<NamespaceId> {
         match self.loaded_paths.get(path) {
             Some(e) => Ok(*e),
@@ -119,25 +309,43 @@ impl Repl {
         }
     }
 
-    pub fn prepare_file(&mut self, path: &CanonicalPathBuf) -> ReplResult<(NamespaceId, Module)> {
+    pub fn prepare_file(
+        &mut self,
+        path: &CanonicalPathBuf,
+    ) -> ReplResult<(NamespaceId, Module, Rc<str>)> {
         let id = self.program.allocate_namespace();
         self.loaded_paths.insert(path.clone(), id);
+        let (ast, source) = self
+            .prepare_file_namespace(id, path)
+            .inspect_err(|_| self.discard_namespaces(id))?;
+        Ok((id, ast, source))
+    }
 
-        let source = std::fs::read_to_string(path).map_err(|_| "Failed to read file")?;
-        let ast = parse(&source)
-            .map_err(|e| Self::try_stringify_parse_error(Some(path.as_path()), e, &source))?;
+    fn prepare_file_namespace(
+        &mut self,
+        id: NamespaceId,
+        path: &CanonicalPathBuf,
+    ) -> ReplResult<(Module, Rc<str>)> {
+        let source: Rc<str> = std::fs::read_to_string(path)
+            .map_err(|_| "Failed to read file")?
+            .into();
+        let (mut ast, errors) = parse_recovering(&source);
+        if !errors.is_empty() {
+            return Err(self.try_stringify_parse_errors(Some(path.as_path()), errors, &source));
+        }
         let Some(context) = path.as_path().parent() else {
             return Err("Unable to resolve file path context".into());
         };
 
         self.prepare_code(
-            &ast,
+            &mut ast,
             id,
             context,
+            &source,
             FunctionOverwriteStrategy::FailOnDuplicate,
         )?;
         self.program.get_namespace_mut(id).path = Some(path.to_owned());
-        Ok((id, ast))
+        Ok((ast, source))
     }
 
     pub fn load_code(&mut self, id: NamespaceId, source: &str) -> ReplResult<()> {
@@ -148,21 +356,31 @@ impl Repl {
         let base = self.base_path.clone();
         let mut full_source = String::new();
         std::mem::swap(&mut full_source, &mut self.pending_code);
-        let ast = match parse(&full_source) {
-            Ok(e) => e,
-            Err(e) => {
-                if e.is_early_eof() {
-                    std::mem::swap(&mut full_source, &mut self.pending_code);
-                    return Ok(());
-                }
-                return Err(Self::try_stringify_parse_error(None, e, &full_source));
+        let (mut ast, errors) = parse_recovering(&full_source);
+        if let Some(first) = errors.first() {
+            if first.is_early_eof() {
+                std::mem::swap(&mut full_source, &mut self.pending_code);
+                return Ok(());
             }
-        };
-        self.prepare_code(&ast, id, base.as_path(), FunctionOverwriteStrategy::Replace)?;
-        self.consume_ast(id, &ast)
+            return Err(self.try_stringify_parse_errors(None, errors, &full_source));
+        }
+        let source: Rc<str> = full_source.into();
+        self.prepare_code(
+            &mut ast,
+            id,
+            base.as_path(),
+            &source,
+            FunctionOverwriteStrategy::Replace,
+        )?;
+        self.consume_ast(id, &ast, &source)
     }
 
-    fn consume_ast(&mut self, namespace: NamespaceId, ast: &Module) -> ReplResult<()> {
+    fn consume_ast(
+        &mut self,
+        namespace: NamespaceId,
+        ast: &Module,
+        source: &str,
+    ) -> ReplResult<()> {
         if let Some(lang) = &self.args.generate {
             let str = match lang.as_str() {
                 "c" => c_codegen_module(&self.program, namespace, &ast.body),
@@ -176,7 +394,37 @@ impl Repl {
             }
             return Ok(());
         }
-        if self.args.analyze {
+        if self.args.analyze && self.args.format == OutputFormat::Json {
+            let arities = analyze_program(&self.program);
+            let file = self.namespace_label(namespace);
+            let mut diagnostics = vec![];
+            for func in &ast.functions {
+                diagnostics.push(
+                    arity_diagnostic(&func.name, arities[namespace].get(&func.name))
+                        .at(file.clone(), Some(func.loc)),
+                );
+            }
+            if !ast.body.terms.is_empty() {
+                diagnostics.push(
+                    arity_diagnostic(
+                        "<body>",
+                        Some(&analyze_block_in_namespace(
+                            &arities,
+                            namespace,
+                            &ast.body,
+                            &self.program,
+                        )),
+                    )
+                    .at(file, None),
+                );
+            }
+            let json = diagnostics_to_json(&diagnostics).map_err(|e| e.to_string())?;
+            {
+                #![expect(clippy::print_stdout, reason = "reporting arity")]
+                println!("{json}");
+            }
+            Ok(())
+        } else if self.args.analyze {
             let arities = analyze_program(&self.program);
             for func in &ast.functions {
                 report_arity(&func.name, arities[namespace].get(&func.name));
@@ -196,10 +444,14 @@ impl Repl {
         } else {
             let mut snap = InterpreterSnapshot::default();
             std::mem::swap(&mut snap, &mut self.snapshot);
-            let interpreter = Interpreter::from_snapshot(snap, &self.program);
-            self.snapshot = interpreter
-                .execute(namespace, &ast.body)
-                .map_err(|e| self.try_stringify_backtrace(e.0, &e.1))?;
+            let interpreter =
+                Interpreter::from_snapshot(snap, &self.program).with_args(&self.args.script_args);
+            self.snapshot = interpreter.execute(namespace, &ast.body).map_err(|e| {
+                match e.error.exit_code() {
+                    Some(code) => ReplError::Exit(code),
+                    None => self.try_stringify_backtrace(&e, &ast.body, source),
+                }
+            })?;
             Ok(())
         }
     }
@@ -207,8 +459,8 @@ impl Repl {
     pub fn load_file(&mut self, path: &str) -> ReplResult<()> {
         let file_path = CanonicalPathBuf::try_from_path(&self.base_path.join(path))
             .map_err(|e| Cow::Owned(e.to_string()))?;
-        let (namespace_id, ast) = self.prepare_file(&file_path)?;
-        self.consume_ast(namespace_id, &ast)
+        let (namespace_id, ast, source) = self.prepare_file(&file_path)?;
+        self.consume_ast(namespace_id, &ast, &source)
     }
 
     pub fn list(&mut self, user_namespace: usize) {
@@ -265,15 +517,11 @@ impl Repl {
             );
         }
         for import in &self.program.namespaces[user_namespace].imports {
-            let title = stringify_absolute_path(
-                self.program
-                    .get_namespace(import.id)
-                    .path
-                    .as_ref()
-                    .map(super::path::CanonicalPathBuf::as_path),
-            );
+            let title = self
+                .namespace_label(import.id)
+                .unwrap_or_else(|| stringify_absolute_path(None));
 
-            println!("\n  ╒{:═<20} Imported from: {title}", "",);
+            println!("\n  ╒{:═<20} Imported from: {title}", "");
             match &import.naming {
                 ImportNaming::Wildcard => {
                     let column_width = column_width.saturating_sub(2);
@@ -359,7 +607,23 @@ impl Repl {
         }
     }
 
+    fn finish_error(&self, err: ReplError) -> ReplError {
+        match (self.args.format, err) {
+            (OutputFormat::Json, ReplError::Message(message)) => {
+                match diagnostics_to_json(&[Diagnostic::new(Severity::Error, "error", message)]) {
+                    Ok(json) => ReplError::Report(json),
+                    Err(e) => e.to_string().into(),
+                }
+            }
+            (_, err) => err,
+        }
+    }
+
     pub fn run(mut self) -> ReplResult<()> {
+        self.run_inner().map_err(|e| self.finish_error(e))
+    }
+
+    fn run_inner(&mut self) -> ReplResult<()> {
         if !self.args.files.is_empty() {
             for path in &self.args.files.clone() {
                 self.load_file(path)?;
@@ -394,10 +658,11 @@ impl Repl {
                     "clear" => self.snapshot.stack.clear(),
                     c => match (self.is_terminal, self.load_code(user_namespace, c)) {
                         (_, Ok(())) => {}
+                        (_, Err(ReplError::Exit(code))) => return Err(ReplError::Exit(code)),
                         (true, Err(e)) => {
                             {
                                 #![expect(clippy::print_stderr, reason = "print and stay running")]
-                                eprintln!("{e}");
+                                eprintln!("{}", self.finish_error(e));
                             }
                         }
                         (false, Err(e)) => return Err(e),
@@ -409,21 +674,154 @@ impl Repl {
 
     fn try_stringify_backtrace(
         &self,
-        err: InterpreterError,
-        backtrace: &Vec<BacktraceItem>,
+        failure: &InterpreterFailure,
+        body: &Block,
+        body_source: &str,
     ) -> ReplError {
-        match self.stringify_backtrace(err, backtrace) {
-            Ok(e) => e,
+        let res = match self.args.format {
+            OutputFormat::Text => self.stringify_backtrace(failure, body, body_source),
+            OutputFormat::Json => self.backtrace_to_json(failure, body, body_source),
+        };
+        match res {
+            Ok(e) => ReplError::Report(e),
             Err(e) => e.to_string().into(),
         }
     }
 
+    fn backtrace_to_json(
+        &self,
+        failure: &InterpreterFailure,
+        body: &Block,
+        body_source: &str,
+    ) -> Result<String, std::fmt::Error> {
+        let containers = self.backtrace_containers(&failure.backtrace, body, body_source);
+        let backtrace: Vec<DiagnosticFrame> = failure
+            .backtrace
+            .iter()
+            .zip(containers)
+            .rev()
+            .map(|((namespace, term), container)| DiagnosticFrame {
+                name: if let Term::Name(name, _) = term {
+                    name.clone()
+                } else {
+                    "Unknown".into()
+                },
+                file: self.namespace_label(*namespace),
+                range: if let Term::Name(_, loc) = term {
+                    Some(*loc)
+                } else {
+                    None
+                },
+                context: term_context_name(container.and_then(|(block, _)| block.locate(term))),
+            })
+            .collect();
+
+        let (file, range) = backtrace
+            .first()
+            .map(|f| (f.file.clone(), f.range))
+            .unwrap_or_default();
+
+        let mut diagnostic =
+            Diagnostic::new(Severity::Error, "runtime-error", failure.error.to_string())
+                .at(file, range);
+        diagnostic.backtrace = backtrace;
+        diagnostic.stack = Some(failure.stack.clone());
+
+        diagnostics_to_json(&[diagnostic])
+    }
+
+    fn backtrace_containers<'a>(
+        &'a self,
+        backtrace: &[BacktraceItem],
+        body: &'a Block,
+        body_source: &'a str,
+    ) -> Vec<FrameContainer<'a>> {
+        let mut res = Vec::with_capacity(backtrace.len());
+        let mut container = Some((body, Some(body_source)));
+        for (namespace, term) in backtrace {
+            res.push(container);
+            container = match term {
+                Term::Name(name, _) if get_intrinsic(name).is_none() => self
+                    .program
+                    .resolve_function(*namespace, name)
+                    .map(|(namespace, name)| {
+                        let ns = self.program.get_namespace(namespace);
+                        (
+                            &ns.functions[name].body,
+                            ns.function_sources.get(name).map(AsRef::as_ref),
+                        )
+                    }),
+                Term::Name(_, _)
+                | Term::String(_)
+                | Term::Number(_)
+                | Term::Integer(_)
+                | Term::Big(_)
+                | Term::Decimal(_)
+                | Term::Bool(_)
+                | Term::Address(_)
+                | Term::Bind(_)
+                | Term::Local(_)
+                | Term::Break
+                | Term::Continue
+                | Term::Return
+                | Term::Branch(_)
+                | Term::Loop(_)
+                | Term::Try(_)
+                | Term::Record(_, _)
+                | Term::Match(_) => None,
+            };
+        }
+        res
+    }
+
+    fn write_stack_dump(res: &mut String, stack: &[OwnedValue]) -> std::fmt::Result {
+        if stack.is_empty() {
+            res.push_str("│\n│  Data stack: empty\n");
+            return Ok(());
+        }
+
+        let values: Vec<String> = stack
+            .iter()
+            .rev()
+            .take(STACK_DUMP_LIMIT)
+            .map(|v| {
+                let debug = format!("{v:?}");
+                if debug.chars().count() > STACK_DUMP_VALUE_WIDTH {
+                    let mut truncated: String =
+                        debug.chars().take(STACK_DUMP_VALUE_WIDTH - 1).collect();
+                    truncated.push('…');
+                    truncated
+                } else {
+                    debug
+                }
+            })
+            .collect();
+
+        let width = values
+            .iter()
+            .map(|v| v.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        res.push_str("│\n│  Data stack (top first):\n");
+        for (value, original) in values.iter().zip(stack.iter().rev()) {
+            writeln!(res, "│    {value:width$}  {}", original.type_name())?;
+        }
+        if stack.len() > STACK_DUMP_LIMIT {
+            writeln!(res, "│    … {} more", stack.len() - STACK_DUMP_LIMIT)?;
+        }
+        Ok(())
+    }
+
     fn stringify_backtrace(
         &self,
-        err: InterpreterError,
-        backtrace: &Vec<BacktraceItem>,
-    ) -> Result<InterpreterError, std::fmt::Error> {
+        failure: &InterpreterFailure,
+        body: &Block,
+        body_source: &str,
+    ) -> Result<String, std::fmt::Error> {
+        let backtrace = &failure.backtrace;
         let unknown = "Unknown";
+        let line_number_width = 6;
         let mut has_name = false;
         let max_name_width = backtrace
             .iter()
@@ -441,17 +839,22 @@ impl Repl {
             + 4;
 
         if !has_name {
-            return Ok(err);
+            return Ok(failure.error.to_string());
         }
 
+        let containers = self.backtrace_containers(backtrace, body, body_source);
+
         let mut res = String::with_capacity(1000);
         {
             res.push_str("\n╒═════════════════════════════ Runtime Error\n│\n│  ");
-            res.push_str(&err);
+            res.push_str(&failure.error.to_string());
             res.push('\n');
 
-            for (i, (namespace, term)) in backtrace.iter().rev().enumerate() {
-                let ns = self.program.get_namespace(*namespace);
+            Self::write_stack_dump(&mut res, &failure.stack)?;
+
+            for (i, ((namespace, term), container)) in
+                backtrace.iter().zip(containers).rev().enumerate()
+            {
                 let prefix = if i == 0 {
                     "│\n└─ at:  "
                 } else {
@@ -467,33 +870,86 @@ impl Repl {
                     } else {
                         unknown
                     },
-                    stringify_absolute_path(
-                        ns.path.as_ref().map(super::path::CanonicalPathBuf::as_path)
-                    ),
+                    self.namespace_label(*namespace)
+                        .unwrap_or_else(|| stringify_absolute_path(None)),
                 )?;
 
                 if let Term::Name(_, loc) = term {
                     write!(res, ":{:?}", loc.start)?;
                 }
 
+                if let Some(context) =
+                    term_context_name(container.and_then(|(block, _)| block.locate(term)))
+                {
+                    write!(res, " (in {context})")?;
+                }
+
                 res.push('\n');
+
+                if let (Term::Name(_, loc), Some((_, Some(source)))) = (term, container)
+                    && let Some(line) = loc.start.extract(source)
+                {
+                    let width = if loc.start.line == loc.end.line {
+                        loc.end.column.saturating_sub(loc.start.column) + 1
+                    } else {
+                        1
+                    };
+                    writeln!(
+                        res,
+                        "        {: >line_number_width$} │ {}",
+                        loc.start.line + 1,
+                        line
+                    )?;
+                    writeln!(
+                        res,
+                        "        {: >line_number_width$} │ {: >2$}{3:^>width$}",
+                        "", "", loc.start.column, ""
+                    )?;
+                }
             }
         }
 
-        Ok(res.into())
+        Ok(res)
     }
 
-    fn try_stringify_parse_error(
+    fn try_stringify_parse_errors(
+        &self,
         path: Option<&Path>,
-        err: ParseError,
+        errors: Vec<ParseError>,
         source_code: &str,
     ) -> ReplError {
-        match Self::stringify_parse_error(path, err, source_code) {
-            Ok(e) => e.into(),
+        let res = match self.args.format {
+            OutputFormat::Text => errors.into_iter().try_fold(String::new(), |mut res, err| {
+                res.push_str(&Self::stringify_parse_error(path, err, source_code)?);
+                Ok(res)
+            }),
+            OutputFormat::Json => Self::parse_errors_to_json(path, errors),
+        };
+        match res {
+            Ok(e) => ReplError::Report(e),
             Err(e) => e.to_string().into(),
         }
     }
 
+    fn parse_errors_to_json(
+        path: Option<&Path>,
+        errors: Vec<ParseError>,
+    ) -> Result<String, std::fmt::Error> {
+        let file = path.map(|e| stringify_absolute_path(Some(e)));
+        let diagnostics: Vec<Diagnostic> = errors
+            .into_iter()
+            .map(|err| {
+                let code = err.code();
+                let (message, loc, info) = err.into_details();
+                let mut diagnostic =
+                    Diagnostic::new(Severity::Error, code, message).at(file.clone(), Some(loc));
+                diagnostic.info = info;
+                diagnostic
+            })
+            .collect();
+        diagnostics_to_json(&diagnostics)
+    }
+
     fn stringify_parse_error(
         path: Option<&Path>,
         err: ParseError,
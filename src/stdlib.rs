@@ -0,0 +1,17 @@
+static MODULES: [(&str, &str); 5] = [
+    ("math", include_str!("../std/math.sl")),
+    ("option", include_str!("../std/option.sl")),
+    ("stack", include_str!("../std/stack.sl")),
+    ("strings", include_str!("../std/strings.sl")),
+    ("testing", include_str!("../std/testing.sl")),
+];
+
+pub fn get_stdlib_module(name: &str) -> Option<(&'static str, &'static str)> {
+    let name = name.strip_suffix(".sl").unwrap_or(name);
+    MODULES.iter().find(|(n, _)| *n == name).copied()
+}
+
+#[allow(dead_code)]
+pub fn get_stdlib_modules() -> &'static [(&'static str, &'static str)] {
+    &MODULES
+}
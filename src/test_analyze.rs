@@ -8,6 +8,8 @@ mod tests {
     use crate::lang::Module;
     use crate::parser::parse;
     use crate::program::{FunctionOverwriteStrategy, NamespaceImport, Program};
+    use crate::repl::report_arity_inner;
+    use crate::stdlib::get_stdlib_modules;
 
     struct SimpleAnalysis {
         arities: NamespaceArities,
@@ -674,4 +676,260 @@ fn2: "fn2"
         let actual = analyze_block_in_namespace(&analysis, 1, &ast.body, &program);
         assert_eq!(&actual, &Err(AnalysisError::Pending));
     }
+
+    #[test]
+    fn records_from_different_namespaces() {
+        let mut program = Program::new();
+        let geometry = program.allocate_namespace();
+        let record = parse("record point { x y }").unwrap();
+        program
+            .add_functions(
+                geometry,
+                &record.functions,
+                FunctionOverwriteStrategy::FailOnDuplicate,
+            )
+            .unwrap();
+        let other = program.allocate_namespace();
+        let record = parse("record point { x }").unwrap();
+        program
+            .add_functions(
+                other,
+                &record.functions,
+                FunctionOverwriteStrategy::FailOnDuplicate,
+            )
+            .unwrap();
+        program.add_imports(
+            other,
+            vec![NamespaceImport {
+                id: geometry,
+                naming: crate::lang::ImportNaming::Scoped("geometry".into()),
+            }],
+        );
+        let analysis = analyze_program(&program);
+
+        let ast = parse("1 point point.x").unwrap();
+        let actual = analyze_block_in_namespace(&analysis, other, &ast.body, &program);
+        assert_eq!(&actual.unwrap().stringify(), "- u");
+
+        let ast = parse("1 point geometry.point.x").unwrap();
+        let actual = analyze_block_in_namespace(&analysis, other, &ast.body, &program);
+        assert_eq!(&actual, &Err(AnalysisError::IncompatibleTypes));
+    }
+
+    #[test]
+    fn format_literal_template() {
+        assert_fn_arity(r#"fn: {"{} and {}" format}"#, "u u - s");
+        assert_fn_arity(r#"fn: {1 "{{}} {}" format}"#, "- s");
+    }
+
+    #[test]
+    fn format_dynamic_template() {
+        assert_fn_err("fn: {format}", AnalysisError::IndefiniteSize);
+        assert_fn_err(r#"fn: {"{" format}"#, AnalysisError::IndefiniteSize);
+    }
+
+    #[test]
+    fn local_bindings() {
+        assert_fn_arity("fn: {\n-> a b\nb a\n}", "1 0 - 0 1");
+        assert_fn_arity("fn: {\n-> a\na 1 + a\n}", "n - n n");
+        assert_fn_arity("fn: {\n-> a b\n{(b) a 1 + (1) a}\n}", "n u - n");
+        assert_fn_arity("fn: {\n-> s\n0 [(dup 3 <) 1 + s length +]\n}", "s - n");
+    }
+
+    #[test]
+    fn local_bindings_check_types() {
+        assert_fn_err(
+            "fn: {\n-> a\na 1 + a length\n}",
+            AnalysisError::IncompatibleTypes,
+        );
+    }
+
+    #[test]
+    fn loop_control() {
+        assert_fn_arity("fn: [ {(dup 10 >) break} 2 * ]", "n - n");
+        assert_fn_arity("fn: [ {(dup) 1 break} ]", "0 - 0 n");
+        assert_fn_arity("fn: [ 1 + {(dup 3 >) \"x\" break} ]", "n - n s");
+        assert_fn_arity("fn: [(dup) {(dup 5 >) 1 - continue} 2 - ]", "n - n");
+        assert_fn_arity("fn: [ 1 + {(dup 3 <) continue} 1 + (dup 9 <) ]", "n - n");
+        assert_fn_err("fn: [ \"a\" continue ]", AnalysisError::IndefiniteSize);
+        assert_fn_err(
+            "fn: [ {(dup) 1 1 break} {(dup) break} ]",
+            AnalysisError::IndefiniteSize,
+        );
+    }
+
+    #[test]
+    fn early_return() {
+        assert_fn_arity("fn: {\n{(dup) 1 return} 2\n}", "0 - 0 n");
+        assert_fn_arity("fn: {\n{(dup) \"a\" return} 2\n}", "0 - 0 u");
+        assert_fn_arity("fn: [ 1 + {(dup 9 >) return} ]", "n - n");
+        assert_fn_arity("fn: [(return) 1]", "-");
+        assert_fn_arity("fn: [ [ {(dup) return} 1 - ] ]", "n - n");
+        assert_fn_err(
+            "fn: {\n{(dup) 1 1 return} 2\n}",
+            AnalysisError::IndefiniteSize,
+        );
+    }
+
+    #[test]
+    fn try_catch() {
+        assert_fn_arity("fn: { try { 1 } catch { drop 2 } }", "- n");
+        assert_fn_arity("fn: { try { / } catch { drop drop drop 0 } }", "n n - n");
+        assert_fn_arity("fn: {\n{(dup) throw} 1 +\n}", "n - n");
+        assert_fn_arity("fn: { \"always\" throw }", "-");
+        assert_fn_arity(
+            "fn: [ try { {(dup) break} 1 - } catch { drop drop 0 } ]",
+            "n - n",
+        );
+        assert_fn_err(
+            "fn: { try { 1 + } catch { } }",
+            AnalysisError::IndefiniteSize,
+        );
+        assert_fn_arity("fn: { try { 1 2 throw } catch { drop \"h\" throw } }", "-");
+    }
+
+    #[test]
+    fn exit() {
+        assert_fn_arity("fn: { {(dup) 1 exit (1) 5} }", "0 - 0 n");
+        assert_fn_arity("fn: { 0 exit }", "-");
+        assert_fn_arity("fn: { try { 1 2 exit } catch { drop 3 } }", "- n");
+    }
+
+    #[test]
+    fn records() {
+        let ast = parse("record point { x y }\nfn: { 1 2 point 3 point.with_x }").unwrap();
+        let a = analyze(&ast);
+        let arity = |name: &str| a.arities[name].as_ref().unwrap().stringify();
+        assert_eq!(arity("point"), "u u - point");
+        assert_eq!(arity("point.y"), "point - u");
+        assert_eq!(arity("point.with_y"), "point u - point");
+        assert_eq!(arity("fn"), "- point");
+        assert_fn_arity("record point { x y }\nfn: { point.x 2 * }", "point - n");
+        assert_fn_arity("record point { x y }\nfn: { 1 2 point point.x 2 * }", "- n");
+        // Only field values are narrowed, other unknown values still have to fit their use
+        assert_fn_err("fn: { 1 + ! 3 && 2 * }", AnalysisError::IncompatibleTypes);
+        assert_fn_err(
+            "record point { x }\nfn: { 1 point.x }",
+            AnalysisError::IncompatibleTypes,
+        );
+    }
+
+    #[test]
+    fn match_arms() {
+        let enums = "enum option { some { value } none }\nenum unit { unit }\n";
+        assert_fn_arity(
+            &format!("{enums}fn: {{ match {{ (some) drop 1 (none) 0 }} }}"),
+            "option - n",
+        );
+        assert_fn_arity(
+            &format!("{enums}fn: {{ match {{ (some) (none) \"\" }} }}"),
+            "option - u",
+        );
+        assert_fn_err(
+            &format!("{enums}fn: {{ match {{ (some) }} }}"),
+            AnalysisError::NonExhaustive,
+        );
+        assert_fn_err(
+            &format!("{enums}fn: {{ match {{ (some) (unit) }} }}"),
+            AnalysisError::IncompatibleTypes,
+        );
+        assert_fn_err(
+            &format!("{enums}fn: {{ 1 match {{ (unit) }} }}"),
+            AnalysisError::IncompatibleTypes,
+        );
+        assert_fn_unset("fn: { match { (some) } }");
+    }
+
+    #[test]
+    fn match_payloads() {
+        let shape = "enum shape { circle { r } rect { w h } dot }\n";
+        assert_fn_arity(
+            &format!("{shape}fn: {{ match {{ (circle) 1 + (rect) * (dot) 0 }} }}"),
+            "shape - n",
+        );
+        assert_fn_err(
+            &format!("{shape}fn: {{ match {{ (circle) 1 + (rect) * length (dot) 0 }} }}"),
+            AnalysisError::IncompatibleTypes,
+        );
+
+        let ast = parse(&format!("{shape}fn: {{ match {{ (circle) 1 (rect) 2 }} }}")).unwrap();
+        let a = analyze(&ast);
+        assert_eq!(
+            report_arity_inner(a.arities.get("fn")),
+            "non-exhaustive match"
+        );
+    }
+
+    #[test]
+    fn integers() {
+        assert_fn_arity("fn: {1i 2i +}", "- i");
+        assert_fn_arity("fn: {1i 2 +}", "- n");
+        assert_fn_arity("fn: {1i +}", "n - n");
+        assert_fn_arity("fn: {-3i abs ++ 2i **}", "- i");
+        assert_fn_arity("fn: {3i band}", "i - i");
+        assert_fn_arity("fn: {2.5 to_int 1i shl}", "- i");
+        assert_fn_err("fn: {2.5 band}", AnalysisError::IncompatibleTypes);
+    }
+
+    #[test]
+    fn bigs() {
+        assert_fn_arity("fn: {1n 2i +}", "- big");
+        assert_fn_arity("fn: {1n 2 +}", "- n");
+        assert_fn_arity("fn: {2i to_big 10n **}", "- big");
+        assert_fn_arity("fn: {1n to_int}", "- i");
+        assert_fn_err("fn: {1n band}", AnalysisError::IncompatibleTypes);
+    }
+
+    #[test]
+    fn decimals() {
+        assert_fn_arity("fn: {1.5d 2n +}", "- dec");
+        assert_fn_arity("fn: {1.5d 2i *}", "- dec");
+        assert_fn_arity("fn: {1.5d 2 +}", "- n");
+        assert_fn_arity("fn: {0.1 to_dec 2i **}", "- dec");
+        assert_fn_arity("fn: {1.5d to_int}", "- i");
+        assert_fn_err("fn: {1.5d band}", AnalysisError::IncompatibleTypes);
+    }
+
+    #[test]
+    fn stdlib_arities() {
+        let expected = [
+            ("abs", "n - n"),
+            ("min", "n n - n"),
+            ("max", "n n - n"),
+            ("gcd", "n n - n"),
+            ("lcm", "n n - n"),
+            ("sqrt", "n - n"),
+            ("nip", "u 0 - 0"),
+            ("tuck", "1 0 - 0 1 0"),
+            ("2dup", "1 0 - 1 0 1 0"),
+            ("2drop", "u u -"),
+            ("-rot", "2 1 0 - 0 2 1"),
+            ("repeat", "u n - s"),
+            ("pad_left", "s n - s"),
+            ("pad_right", "s n - s"),
+            ("trim", "s - s"),
+            ("split_once", "s s - s s"),
+            ("reverse", "s - s"),
+            ("to_option", "u u - option"),
+            ("unwrap_or", "option u - u"),
+            ("is_some", "option - b"),
+            ("start_suite", "u - n s"),
+            ("end_suite", "u u -"),
+            ("should_equal", "n u u u - n s"),
+        ];
+
+        let mut arities = NamespaceArities::new();
+        for (name, source) in get_stdlib_modules() {
+            let ast = parse(source).unwrap();
+            let module_arities = analyze(&ast).arities;
+            for (function, arity) in &module_arities {
+                assert!(arity.is_ok(), "std/{name} {function}: {arity:?}");
+            }
+            arities.extend(module_arities);
+        }
+
+        for (function, arity) in expected {
+            assert_eq!(arities[function].as_ref().unwrap().stringify(), arity);
+        }
+    }
 }
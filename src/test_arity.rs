@@ -94,6 +94,7 @@ mod tests {
     #[test]
     fn serial_error_1() {
         check_serial_err("n - s", "n - s", &ArityCombineError::IncompatibleTypes);
+        check_serial_err("s - u", "n - n", &ArityCombineError::IncompatibleTypes);
     }
 
     #[test]
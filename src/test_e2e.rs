@@ -1,18 +1,26 @@
 #[cfg(test)]
 mod tests {
     use crate::codegen;
-    use crate::intrinsics::{IntrinsicData, get_intrinsic_codegen_name, get_intrinsics};
+    use crate::fold::fold_module;
+    use crate::intrinsics::{
+        ERROR_DESCRIPTIONS, IntrinsicData, get_intrinsic_codegen_name, get_intrinsics,
+    };
     use crate::lang::{ImportNaming, Module};
     use crate::program::{FunctionOverwriteStrategy, NamespaceImport, Program};
     use crate::{interpreter::Interpreter, parser::parse};
 
-    static TEST_HELPERS: &str = include_str!("../examples/test.sl");
+    static TEST_HELPERS: &str = include_str!("../std/testing.sl");
     static E2E_TESTS: &str = include_str!("../examples/e2e.sl");
+    static E2E_IMPORTS: [(&str, &str); 2] = [
+        ("a", include_str!("../examples/e2e/a.sl")),
+        ("b", include_str!("../examples/e2e/b.sl")),
+    ];
 
     fn get_e2e_program() -> (Module, Program) {
         let helpers_ast = parse(TEST_HELPERS).unwrap();
-        let ast = parse(E2E_TESTS).unwrap();
-        let mut program = Program::new_from_module(&ast);
+        let mut ast = parse(E2E_TESTS).unwrap();
+        let mut program = Program::new();
+        let id = program.allocate_namespace();
         let helpers_namespace = program.allocate_namespace();
         program
             .add_functions(
@@ -21,13 +29,29 @@ mod tests {
                 FunctionOverwriteStrategy::FailOnDuplicate,
             )
             .unwrap();
-        program.add_imports(
-            0,
-            vec![NamespaceImport {
-                id: helpers_namespace,
-                naming: ImportNaming::Wildcard,
-            }],
-        );
+        let mut imports = vec![NamespaceImport {
+            id: helpers_namespace,
+            naming: ImportNaming::Wildcard,
+        }];
+        for (scope, source) in E2E_IMPORTS {
+            let namespace = program.allocate_namespace();
+            program
+                .add_functions(
+                    namespace,
+                    &parse(source).unwrap().functions,
+                    FunctionOverwriteStrategy::FailOnDuplicate,
+                )
+                .unwrap();
+            imports.push(NamespaceImport {
+                id: namespace,
+                naming: ImportNaming::Scoped(scope.into()),
+            });
+        }
+        program.add_imports(id, imports);
+        // Intrinsics are left unfolded so every backend still runs them
+        let strategy = FunctionOverwriteStrategy::FailOnDuplicate;
+        fold_module(&mut program, id, &mut ast, strategy, false).unwrap();
+        program.add_functions(id, &ast.functions, strategy).unwrap();
         (ast, program)
     }
 
@@ -38,7 +62,8 @@ mod tests {
         assert_eq!(ctx.execute(0, &ast.body).unwrap().stack, vec![]);
     }
 
-    static SKIPPED_INTRINSICS: [&str; 3] = ["assert", "print", "readline"];
+    static SKIPPED_INTRINSICS: [&str; 6] =
+        ["assert", "eprint", "exit", "print", "readline", "write"];
 
     #[test]
     fn exhaustive() {
@@ -126,4 +151,23 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn error_descriptions_codegen_test() {
+        static C_DEFINITIONS: &str = include_str!("./codegen/c.h");
+        static JS_DEFINITIONS: &str = include_str!("./codegen/js.js");
+
+        for (_, kind, description) in ERROR_DESCRIPTIONS {
+            assert!(
+                C_DEFINITIONS.contains(&format!(
+                    "status == {kind}) {{\n    return \"{description}\";"
+                )),
+                "described by c: {kind}",
+            );
+            assert!(
+                JS_DEFINITIONS.contains(&format!("  {kind}: '{description}',")),
+                "described by js: {kind}",
+            );
+        }
+    }
 }
@@ -0,0 +1,245 @@
+#[cfg(test)]
+mod tests {
+    use crate::fold::fold_module;
+    use crate::lang::{
+        BigInt, Block, Decimal, ImportNaming, Local, Loop, Module, SourceRange, Term,
+    };
+    use crate::parser::parse;
+    use crate::program::{FunctionOverwriteStrategy, NamespaceImport, Program};
+
+    fn name(n: &str) -> Term {
+        Term::Name(n.into(), SourceRange::default())
+    }
+
+    fn string(s: &str) -> Term {
+        Term::String(s.into())
+    }
+
+    fn fold_with(source: &str, fold_intrinsics: bool) -> Result<Module, String> {
+        let mut ast = parse(source).unwrap();
+        let mut program = Program::new();
+        let id = program.allocate_namespace();
+        fold_module(
+            &mut program,
+            id,
+            &mut ast,
+            FunctionOverwriteStrategy::FailOnDuplicate,
+            fold_intrinsics,
+        )?;
+        Ok(ast)
+    }
+
+    fn fold(source: &str) -> Vec<Term> {
+        fold_with(source, true).unwrap().body.terms
+    }
+
+    #[test]
+    fn literal_sequences() {
+        assert_eq!(fold("3 4 + 2 *"), vec![14.into()]);
+        assert_eq!(fold("1 2 swap"), vec![2.into(), 1.into()]);
+        assert_eq!(fold("\"ab\" length 1 + 3 =="), vec![true.into()]);
+        assert_eq!(fold("\"a\" \"b\" join"), vec![string("ab")]);
+        assert_eq!(fold("x 1 2 +"), vec![name("x"), 3.into()]);
+        assert_eq!(fold("1 2 + +"), vec![3.into(), name("+")]);
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(fold("7i 2i / 1i 3i shl"), vec![3i64.into(), 8i64.into()]);
+        assert_eq!(fold("1i 0.5 +"), vec![1.5.into()]);
+        assert_eq!(
+            fold("9223372036854775807i 1i +"),
+            vec![i64::MAX.into(), 1i64.into(), name("+")]
+        );
+    }
+
+    #[test]
+    fn bigs() {
+        let big = |s: &str| Term::Big(BigInt::parse(s).unwrap());
+        assert_eq!(fold("2n 64n ** 1i +"), vec![big("18446744073709551617")]);
+        assert_eq!(fold("1n 0n /"), vec![big("1"), big("0"), name("/")]);
+        assert_eq!(
+            fold("3n 1000000n **"),
+            vec![big("3"), big("1000000"), name("**")]
+        );
+    }
+
+    #[test]
+    fn decimals() {
+        let dec = |s: &str| Term::Decimal(Decimal::parse(s).unwrap());
+        assert_eq!(fold("0.1d 0.2d + 1i -"), vec![dec("-0.7")]);
+        assert_eq!(fold("1d 0d /"), vec![dec("1"), dec("0"), name("/")]);
+        assert_eq!(
+            fold("1.5d 1000000n **"),
+            vec![dec("1.5"), Term::Big(BigInt::from(1_000_000)), name("**")]
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        assert_eq!(
+            fold("[(dup 5 2 * <) 1 1 + +]"),
+            vec![Term::Loop(Loop {
+                pre_condition: Some(Block {
+                    terms: vec![name("dup"), 10.into(), name("<")],
+                }),
+                body: Block {
+                    terms: vec![2.into(), name("+")],
+                },
+                post_condition: None,
+            })]
+        );
+    }
+
+    #[test]
+    fn failures_left_for_runtime() {
+        assert_eq!(fold("\"a\" 1 +"), vec![string("a"), 1.into(), name("+")]);
+        assert_eq!(fold("1 0 /"), vec![1.into(), 0.into(), name("/")]);
+        assert_eq!(fold("1 print"), vec![1.into(), name("print")]);
+        assert_eq!(
+            fold("1 6 random_int"),
+            vec![1.into(), 6.into(), name("random_int")]
+        );
+    }
+
+    #[test]
+    fn expensive_calls_left_for_runtime() {
+        assert_eq!(
+            fold("\"aaaa\" \"(a+)+b\" matches"),
+            vec![string("aaaa"), string("(a+)+b"), name("matches")]
+        );
+        assert_eq!(
+            fold("\"ab\" 3 repeat"),
+            vec![string("ab"), 3.into(), name("repeat")]
+        );
+        assert_eq!(
+            fold("1 100000 to_padded"),
+            vec![1.into(), 100_000.into(), name("to_padded")]
+        );
+        assert_eq!(fold("1 5 to_padded"), vec![string("00001")]);
+    }
+
+    #[test]
+    fn constants() {
+        let ast = fold_with("const A: 2\nconst B: A A *\nfn: { B 1 + }\nB", true).unwrap();
+        assert_eq!(ast.functions[0].body.terms, vec![5.into()]);
+        assert_eq!(ast.body.terms, vec![4.into()]);
+
+        let local = Local {
+            name: "A".into(),
+            slot: 0,
+        };
+        let ast = fold_with("const A: 1\nfn: {\n-> A\nA\n}", true).unwrap();
+        assert_eq!(
+            ast.functions[0].body.terms,
+            vec![Term::Bind(vec![local.clone()]), Term::Local(local)]
+        );
+    }
+
+    #[test]
+    fn constants_without_folding() {
+        let ast = fold_with("const A: 2 3 +\nA 1 +", false).unwrap();
+        assert_eq!(ast.body.terms, vec![5.into(), 1.into(), name("+")]);
+    }
+
+    #[test]
+    fn constant_errors() {
+        assert_eq!(
+            fold_with("const A: 1 print", true),
+            Err("Constant A does not fold to a single value".into())
+        );
+        assert_eq!(
+            fold_with("const A: 1 2", true),
+            Err("Constant A does not fold to a single value".into())
+        );
+        assert_eq!(
+            fold_with("const A: 1\nconst A: 2", true),
+            Err("Constant redefinition error: A".into())
+        );
+        assert_eq!(
+            fold_with("const A: B 1 +\nconst B: 1 print", true),
+            Err("Constant B does not fold to a single value".into())
+        );
+        assert_eq!(
+            fold_with("const A: B 1 +\nconst B: A", true),
+            Err("Constant A refers to constant B, which depends on it".into())
+        );
+        assert_eq!(
+            fold_with("const A: A", true),
+            Err("Constant A refers to itself".into())
+        );
+    }
+
+    #[test]
+    fn constant_forward_references() {
+        let ast = fold_with("const A: B 1 +\nconst B: C 2 *\nconst C: 3\nA", true).unwrap();
+        assert_eq!(ast.body.terms, vec![7.into()]);
+    }
+
+    #[test]
+    fn imported_constants() {
+        let mut program = Program::new();
+        let main = program.allocate_namespace();
+        let library = program.allocate_namespace();
+        let mut library_ast = parse("const WIDTH: 4").unwrap();
+        fold_module(
+            &mut program,
+            library,
+            &mut library_ast,
+            FunctionOverwriteStrategy::FailOnDuplicate,
+            true,
+        )
+        .unwrap();
+        program.add_imports(
+            main,
+            vec![NamespaceImport {
+                id: library,
+                naming: ImportNaming::Scoped("m".into()),
+            }],
+        );
+
+        let mut ast = parse("m.WIDTH 2 * WIDTH").unwrap();
+        fold_module(
+            &mut program,
+            main,
+            &mut ast,
+            FunctionOverwriteStrategy::FailOnDuplicate,
+            true,
+        )
+        .unwrap();
+        assert_eq!(ast.body.terms, vec![8.into(), name("WIDTH")]);
+    }
+
+    #[test]
+    fn constants_before_imported_functions() {
+        let mut program = Program::new();
+        let main = program.allocate_namespace();
+        let library = program.allocate_namespace();
+        let library_ast = parse("WIDTH: { 8 }").unwrap();
+        program
+            .add_functions(
+                library,
+                &library_ast.functions,
+                FunctionOverwriteStrategy::FailOnDuplicate,
+            )
+            .unwrap();
+        program.add_imports(
+            main,
+            vec![NamespaceImport {
+                id: library,
+                naming: ImportNaming::Wildcard,
+            }],
+        );
+
+        let mut ast = parse("const WIDTH: 4\nWIDTH").unwrap();
+        fold_module(
+            &mut program,
+            main,
+            &mut ast,
+            FunctionOverwriteStrategy::FailOnDuplicate,
+            true,
+        )
+        .unwrap();
+        assert_eq!(ast.body.terms, vec![4.into()]);
+    }
+}
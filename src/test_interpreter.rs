@@ -1,11 +1,13 @@
 #[cfg(test)]
 mod tests {
-    use std::borrow::Cow;
+    use std::cell::Cell;
 
-    use crate::interpreter::Interpreter;
+    use crate::clock::Clock;
+    use crate::interpreter::{Interpreter, InterpreterFailure};
+    use crate::intrinsics::ErrorKind;
     use crate::lang::{
-        Block, Branch, Function, ImportNaming, Loop, Module, OwnedValue, SourceLocation,
-        SourceRange, Term,
+        BigInt, Block, Branch, Decimal, Function, ImportNaming, Loop, Module, OwnedValue,
+        SourceLocation, SourceRange, Term,
     };
     use crate::parser::parse;
     use crate::program::{FunctionOverwriteStrategy, NamespaceImport, Program};
@@ -172,6 +174,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "generate".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![36.into(), 6.into(), name("+")],
                 },
@@ -192,6 +195,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "rfib".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![Term::Branch(Branch {
                         arms: vec![(
@@ -231,6 +235,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "ifib".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![
                         0.into(),
@@ -358,10 +363,637 @@ fn",
                 end: SourceLocation::start(),
             },
         );
-        let expected = Err((
-            Cow::Borrowed("Stack empty"),
-            vec![(0usize, &t1), (0usize, &t2)],
-        ));
+        let expected = Err(InterpreterFailure {
+            error: ErrorKind::StackUnderflow.error("Stack empty"),
+            backtrace: vec![(0usize, &t1), (0usize, &t2)],
+            stack: vec![],
+        });
         assert_eq!(actual, expected,);
     }
+
+    #[test]
+    fn runtime_error_keeps_operands() {
+        let actual = interpret_failing(r#"1 "two" +"#);
+
+        assert_eq!(actual.error, "Expected two numbers on top of stack");
+        assert_eq!(
+            actual.stack,
+            vec![1.into(), OwnedValue::String("two".into())]
+        );
+    }
+
+    #[test]
+    fn exit_stops_execution() {
+        let actual = interpret_failing("1 2 exit 3");
+
+        assert_eq!(actual.exit_code, Some(2));
+        assert_eq!(actual.stack, vec![1.into()]);
+    }
+
+    #[test]
+    fn exit_rejects_invalid_codes() {
+        for code in ["-1", "1.5", "nan"] {
+            let actual = interpret_failing(&format!("{code} exit"));
+            assert_eq!(actual.error, "Invalid exit code");
+            assert_eq!(actual.exit_code, None);
+        }
+    }
+
+    #[test]
+    fn number_formatting_keeps_operands_on_error() {
+        for (source, error) in [
+            ("1.5 101 to_fixed", "Invalid precision"),
+            ("255 37 to_radix", "Invalid radix"),
+            ("1.5 16 to_radix", "to_radix only works on integers"),
+            ("7 -1 to_padded", "Invalid width"),
+        ] {
+            let actual = interpret_failing(source);
+            assert_eq!(actual.error, error);
+            assert_eq!(actual.stack.len(), 2, "{source}");
+        }
+    }
+
+    #[test]
+    fn intrinsics_keep_operands_on_error() {
+        for (source, error, depth) in [
+            (r#""abc" 1 -1 substring"#, "Invalid substring end index", 3),
+            (
+                r#""abc" "" "x" replace"#,
+                "Cannot replace an empty string",
+                3,
+            ),
+            (r#""abc" -1 repeat"#, "Invalid repeat count", 2),
+            ("1i 0i /", "Division by zero", 2),
+            ("1i 0i %", "Division by zero", 2),
+            ("2i -1i **", "Negative exponent", 2),
+            ("1i 64i shl", "Invalid shift", 2),
+            (r#"1 "a" starts_with"#, "Expected string on top of stack", 2),
+            (r#"1 "a" ends_with"#, "Expected string on top of stack", 2),
+            (r#"1 "a" index"#, "Expected string on top of stack", 2),
+            ("1.5 exit", "Invalid exit code", 1),
+            ("-1 sleep", "Invalid sleep duration", 1),
+            ("nan seed", "Invalid seed", 1),
+        ] {
+            let actual = interpret_failing(&format!("0 {source}"));
+            assert_eq!(actual.error, error, "{source}");
+            assert_eq!(actual.stack.len(), depth + 1, "{source}");
+        }
+    }
+
+    fn interpret_source(source: &str) -> Vec<OwnedValue> {
+        interpret(&parse(source).unwrap())
+    }
+
+    // The error a source fails with, along with the stack and exit code it leaves
+    struct Failure {
+        error: String,
+        stack: Vec<OwnedValue>,
+        exit_code: Option<i32>,
+    }
+
+    fn interpret_failing(source: &str) -> Failure {
+        let ast = parse(source).unwrap();
+        let program = Program::new_from_module(&ast);
+        let failure = Interpreter::begin(&program)
+            .execute(0, &ast.body)
+            .unwrap_err();
+        Failure {
+            error: failure.error.to_string(),
+            stack: failure.stack,
+            exit_code: failure.error.exit_code(),
+        }
+    }
+
+    fn interpret_failure(source: &str) -> String {
+        interpret_failing(source).error
+    }
+
+    #[test]
+    fn math_rounding() {
+        assert_eq!(
+            interpret_source("2.5 floor 2.5 ceil 2.5 round -2.5 round -2.7 trunc"),
+            vec![2.into(), 3.into(), 3.into(), (-3).into(), (-2).into()]
+        );
+    }
+
+    #[test]
+    fn math_functions() {
+        assert_eq!(
+            interpret_source("16 sqrt -3 abs 0 exp 1000 log10 1024 log2 1 log"),
+            vec![4.into(), 3.into(), 1.into(), 3.into(), 10.into(), 0.into()]
+        );
+    }
+
+    #[test]
+    fn math_trigonometry() {
+        assert_eq!(
+            interpret_source("0 sin 0 cos pi cos 1 1 atan2 pi 4 / =="),
+            vec![0.into(), 1.into(), (-1).into(), true.into()]
+        );
+    }
+
+    #[test]
+    fn math_min_max() {
+        assert_eq!(
+            interpret_source("1 2 min 1 2 max nan 1 min 1 nan max"),
+            vec![1.into(), 2.into(), 1.into(), 1.into()]
+        );
+    }
+
+    #[test]
+    fn math_constants() {
+        let result = interpret_source("pi inf -inf nan");
+        assert_eq!(
+            result[..3],
+            [
+                std::f64::consts::PI.into(),
+                f64::INFINITY.into(),
+                f64::NEG_INFINITY.into()
+            ]
+        );
+        assert!(matches!(result[3], OwnedValue::Number(v) if v.is_nan()));
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        assert_eq!(
+            interpret_source("7i 2i / -7i 2i % 2i 10i ** -3i abs ++ 2i 3i max"),
+            vec![
+                OwnedValue::Integer(3),
+                OwnedValue::Integer(-1),
+                OwnedValue::Integer(1024),
+                OwnedValue::Integer(4),
+                OwnedValue::Integer(3),
+            ]
+        );
+        assert_eq!(
+            interpret_source("7i 2 / 1i 0.5 + 2i 2 == 2i sqrt 4i to_float"),
+            vec![
+                3.5.into(),
+                1.5.into(),
+                true.into(),
+                std::f64::consts::SQRT_2.into(),
+                4.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_bitwise() {
+        assert_eq!(
+            interpret_source(
+                "12i 10i band 12i 10i bor 12i 10i bxor 1i 4i shl -8i 1i shr 2.7 to_int"
+            ),
+            vec![
+                OwnedValue::Integer(8),
+                OwnedValue::Integer(14),
+                OwnedValue::Integer(6),
+                OwnedValue::Integer(16),
+                OwnedValue::Integer(-4),
+                OwnedValue::Integer(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_errors() {
+        assert_eq!(
+            interpret_failure("9223372036854775807i 1i +"),
+            "Integer overflow"
+        );
+        assert_eq!(interpret_failure("1i 0i %"), "Division by zero");
+        assert_eq!(interpret_failure("2i -1i **"), "Negative exponent");
+        assert_eq!(interpret_failure("1i 64i shl"), "Invalid shift");
+        assert_eq!(
+            interpret_failure("1e19 to_int"),
+            "Number out of integer range"
+        );
+
+        let actual = interpret_failing("1.5 2i band");
+        assert_eq!(actual.error, "Expected two integers on top of stack");
+        assert_eq!(actual.stack, vec![1.5.into(), OwnedValue::Integer(2)]);
+    }
+
+    #[test]
+    fn big_arithmetic() {
+        let big = |s: &str| OwnedValue::Big(BigInt::parse(s).unwrap());
+        assert_eq!(
+            interpret_source(
+                "2n 100n ** 9223372036854775807i 1n + -7n 2n / -7n 2n % 3n 2i min -2n abs ++"
+            ),
+            vec![
+                big("1267650600228229401496703205376"),
+                big("9223372036854775808"),
+                big("-3"),
+                big("-1"),
+                big("2"),
+                big("3"),
+            ]
+        );
+        assert_eq!(
+            interpret_source("1n 0.5 + 5n 5i == 5n 5 == 2.9 to_big -12n to_int"),
+            vec![
+                1.5.into(),
+                true.into(),
+                true.into(),
+                big("2"),
+                OwnedValue::Integer(-12),
+            ]
+        );
+        assert_eq!(
+            interpret_source("6n to_int 3i band"),
+            vec![OwnedValue::Integer(2)]
+        );
+    }
+
+    #[test]
+    fn big_errors() {
+        assert_eq!(interpret_failure("1n 0n /"), "Division by zero");
+        assert_eq!(interpret_failure("2n -1n **"), "Negative exponent");
+        assert_eq!(interpret_failure("3n 100000000n **"), "Integer overflow");
+        assert_eq!(
+            interpret_failure("1e400 to_big"),
+            "Expected a finite number"
+        );
+        assert_eq!(
+            interpret_failure("99999999999999999999n to_int"),
+            "Number out of integer range"
+        );
+    }
+
+    #[test]
+    fn decimal_arithmetic() {
+        let dec = |s: &str| OwnedValue::Decimal(Decimal::parse(s).unwrap());
+        assert_eq!(
+            interpret_source("0.1d 0.2d + 1.5d 1.5d * 1d 8d / 3.00d 2d / -7.5d 2d % 1.1d 2i **"),
+            vec![
+                dec("0.3"),
+                dec("2.25"),
+                dec("0.125"),
+                dec("1.50"),
+                dec("-1.5"),
+                dec("1.21"),
+            ]
+        );
+        assert_eq!(
+            interpret_source("0.5d 0.25 + 2n 0.5d * 1.5d 1.50d == 0.1 to_dec -2.75d to_int"),
+            vec![
+                0.75.into(),
+                dec("1.0"),
+                true.into(),
+                dec("0.1"),
+                OwnedValue::Integer(-2),
+            ]
+        );
+        assert_eq!(interpret_failure("1d 0d /"), "Division by zero");
+        assert_eq!(
+            interpret_failure("1.5d 0.5d **"),
+            "Exponent must be a whole number"
+        );
+        assert_eq!(interpret_failure("inf to_dec"), "Expected a finite number");
+    }
+
+    #[test]
+    fn seeded_random_is_reproducible() {
+        let first = interpret_source("5 seed random 1 100 random_int random");
+        let second = interpret_source("5 seed random 1 100 random_int random");
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[2]);
+    }
+
+    #[test]
+    fn random_state_carries_across_snapshots() {
+        let ast = parse("random").unwrap();
+        let program = Program::new_from_module(&ast);
+        let seeded = parse("9 seed").unwrap();
+        let snapshot = Interpreter::begin(&program)
+            .execute(0, &seeded.body)
+            .unwrap();
+        let continued = Interpreter::from_snapshot(snapshot, &program)
+            .execute(0, &ast.body)
+            .unwrap();
+        assert_eq!(continued.stack, interpret_source("9 seed random"));
+    }
+
+    #[test]
+    fn random_int_rejects_invalid_ranges() {
+        for range in ["2 1", "0.5 2", "0 inf", "nan 1"] {
+            assert_eq!(
+                interpret_failure(&format!("{range} random_int")),
+                "Invalid random range"
+            );
+        }
+    }
+
+    struct MockClock {
+        elapsed: Cell<f64>,
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> f64 {
+            1_000_000f64 + self.elapsed.get()
+        }
+
+        fn monotonic(&self) -> f64 {
+            self.elapsed.get()
+        }
+
+        fn sleep(&self, ms: f64) {
+            self.elapsed.set(self.elapsed.get() + ms);
+        }
+    }
+
+    #[test]
+    fn mocked_clock() {
+        let ast = parse("now monotonic 250 sleep monotonic now").unwrap();
+        let program = Program::new_from_module(&ast);
+        let clock = MockClock {
+            elapsed: Cell::new(0f64),
+        };
+        let result = Interpreter::begin(&program)
+            .with_clock(&clock)
+            .execute(0, &ast.body)
+            .unwrap();
+        assert_eq!(
+            result.stack,
+            vec![1_000_000.into(), 0.into(), 250.into(), 1_000_250.into()]
+        );
+    }
+
+    #[test]
+    fn sleep_rejects_invalid_durations() {
+        for duration in ["-1", "nan", "inf", "1e300"] {
+            assert_eq!(
+                interpret_failure(&format!("{duration} sleep")),
+                "Invalid sleep duration"
+            );
+        }
+    }
+
+    #[test]
+    fn string_operations_reject_invalid_arguments() {
+        for (source, error) in [
+            (r#""abc" "" "x" replace"#, "Cannot replace an empty string"),
+            (r#""ab" -1 repeat"#, "Invalid repeat count"),
+            (r#""ab" 1.5 repeat"#, "Invalid repeat count"),
+            (
+                r#""aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab" "(a*)*c" matches"#,
+                "Regex took too many steps",
+            ),
+        ] {
+            assert_eq!(interpret_failure(source), error, "{source}");
+        }
+    }
+
+    #[test]
+    fn local_bindings() {
+        let source = "
+distance: {
+  -> x1 y1 x2 y2
+  x2 x1 - dup *
+  y2 y1 - dup * + sqrt
+}
+shadow: {
+  -> x
+  {(x)
+    -> x
+    x
+  }
+  x
+}
+3 0 0 4 distance
+\"inner\" true shadow";
+        assert_eq!(
+            interpret_source(source),
+            vec![5.into(), OwnedValue::String("inner".into()), true.into()]
+        );
+    }
+
+    #[test]
+    fn local_bindings_are_per_call() {
+        let source = "
+fact: {
+  -> n
+  {(n 1 >) n 1 - fact n * (1) 1}
+}
+5 fact";
+        assert_eq!(interpret_source(source), vec![120.into()]);
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let source = "
+sum_odd: {
+  -> n
+  0 0
+  [(dup n <)
+    1 +
+    {(dup 2 % 0 ==) continue}
+    swap over + swap
+  ]
+  drop
+}
+10 sum_odd
+1 [ {(dup 100 >) break} 2 * ]
+0 [ 1 + {(dup 3 <) continue} \"x\" swap (dup 4 <) ]";
+        assert_eq!(
+            interpret_source(source),
+            vec![
+                25.into(),
+                128.into(),
+                OwnedValue::String("x".into()),
+                OwnedValue::String("x".into()),
+                4.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn early_return() {
+        let source = "
+sign: {
+  -> n
+  {(n 0 <) \"-\" return}
+  {(n 0 ==) \"0\" return}
+  \"+\"
+}
+first_multiple: {
+  -> n m
+  n [
+    -> i
+    {(i m % 0 ==) i return}
+    i 1 +
+  ]
+}
+-2 sign 0 sign 3 sign
+10 7 first_multiple";
+        assert_eq!(
+            interpret_source(source),
+            vec![
+                OwnedValue::String("-".into()),
+                OwnedValue::String("0".into()),
+                OwnedValue::String("+".into()),
+                14.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn try_catch() {
+        let source = "
+1 2 try { 3 drop drop drop drop } catch { }
+safe_divide: {
+  -> a b
+  try {
+    {(b 0 ==) \"divide by zero\" throw}
+    a b /
+  } catch { }
+}
+6 3 safe_divide 6 0 safe_divide
+try { 0 \"checked\" assert } catch { }
+try { try { 5 throw } catch { 1 + throw } } catch { }";
+        assert_eq!(
+            interpret_source(source),
+            vec![
+                1.into(),
+                2.into(),
+                OwnedValue::String("Stack empty".into()),
+                2.into(),
+                OwnedValue::String("divide by zero".into()),
+                OwnedValue::String("Assertion failed: checked".into()),
+                6.into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_catch_leaves_exit_and_uncaught_errors() {
+        let actual = interpret_failing("1 try { 2 exit } catch { 3 }");
+        assert_eq!(actual.exit_code, Some(2));
+        assert_eq!(actual.stack, vec![1.into()]);
+
+        let actual = interpret_failing("1 \"oops\" throw");
+        assert_eq!(actual.error, "Uncaught throw: oops");
+        assert_eq!(actual.exit_code, None);
+    }
+
+    #[test]
+    fn local_binding_keeps_stack_on_underflow() {
+        let actual = interpret_failing("fn: {\n-> a b\n}\n1 fn");
+        assert_eq!(actual.error, "Stack empty");
+        assert_eq!(actual.stack, vec![1.into()]);
+    }
+
+    #[test]
+    fn records() {
+        let source = "
+record point { x y }
+check: {
+  -> p
+  p point.y
+  p 5 point.with_x dup point.x swap point.y
+  p point.x
+  p 1 2 point ==
+  p 2 1 point ==
+  p \"a\" point.with_y to_string
+}
+1 2 point check";
+        assert_eq!(
+            interpret_source(source),
+            vec![
+                2.into(),
+                5.into(),
+                2.into(),
+                1.into(),
+                true.into(),
+                false.into(),
+                OwnedValue::String("point { x: 1, y: \"a\" }".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn record_type_errors() {
+        let actual = interpret_failing("record point { x }\nrecord size { x }\n1 size point.x");
+        assert_eq!(actual.error, "Expected point on top of stack");
+        assert_eq!(actual.stack.len(), 1);
+
+        let actual = interpret_failing("record point { x y }\n1 point");
+        assert_eq!(actual.error, "Stack empty");
+        assert_eq!(actual.stack, vec![1.into()]);
+    }
+
+    #[test]
+    fn records_from_different_modules() {
+        let mut program = Program::new();
+        let main_id = program.allocate_namespace();
+        // Each file is parsed on its own, like `a.sl` and `b.sl` both declaring the record
+        let imports = ["a", "b"].map(|name| {
+            let module = parse("record point { x y }").unwrap();
+            let id = program.allocate_namespace();
+            program
+                .add_functions(
+                    id,
+                    &module.functions,
+                    FunctionOverwriteStrategy::FailOnDuplicate,
+                )
+                .unwrap();
+            NamespaceImport {
+                id,
+                naming: ImportNaming::Scoped(name.into()),
+            }
+        });
+        program.add_imports(main_id, imports.into());
+
+        let main = parse("1 2 a.point 1 2 a.point == 1 2 a.point 1 2 b.point ==").unwrap();
+        let result = Interpreter::begin(&program).execute(main_id, &main.body);
+        assert_eq!(result.unwrap().stack, vec![true.into(), false.into()]);
+
+        let main = parse("1 2 a.point b.point.x").unwrap();
+        let failure = Interpreter::begin(&program)
+            .execute(main_id, &main.body)
+            .unwrap_err();
+        assert_eq!(failure.error.to_string(), "Expected point on top of stack");
+        assert_eq!(failure.stack.len(), 1);
+    }
+
+    #[test]
+    fn match_variants() {
+        let source = "
+enum shape { circle { r } rect { w h } dot }
+area: {
+  match {
+    (circle) dup * 3 *
+    (rect) *
+    (dot) 0
+  }
+}
+2 circle area
+3 4 rect area
+dot area
+2 circle 2 circle ==
+dot 2 circle ==";
+        assert_eq!(
+            interpret_source(source),
+            vec![12.into(), 12.into(), 0.into(), true.into(), false.into()]
+        );
+    }
+
+    #[test]
+    fn match_errors() {
+        let enums = "enum option { some { value } none }\n";
+
+        let actual = interpret_failing(&format!("{enums}none match {{ (some) }}"));
+        assert_eq!(actual.error, "No match arm for none");
+        assert_eq!(actual.stack.len(), 1);
+
+        let actual = interpret_failing(&format!("{enums}1 match {{ (some) (none) }}"));
+        assert_eq!(actual.error, "Expected option on top of stack");
+        assert_eq!(actual.stack, vec![1.into()]);
+
+        assert_eq!(
+            interpret_failure(&format!("{enums}none match {{ (nothing) }}")),
+            "Unknown variant: nothing"
+        );
+    }
 }
@@ -1,11 +1,15 @@
 #[cfg(test)]
 mod tests {
     use crate::lang::{
-        Block, Branch, Function, Import, ImportLocation, ImportNaming, Loop, Module,
-        SourceLocation, SourceRange, Symbol, Term,
+        Block, Branch, Constant, Enum, Function, Import, ImportLocation, ImportNaming, Local, Loop,
+        Match, Module, Record, RecordOp, SourceLocation, SourceRange, Symbol, Term, Try,
     };
-    use crate::parse_error::{ParseError, UnexpectedError};
-    use crate::parser::parse;
+    use crate::parse_error::{
+        EndOfFileError, ParseError, ReasonExpectingMore, UnexpectedContext, UnexpectedError,
+        WrappedExpression,
+    };
+    use crate::parser::{parse, parse_recovering};
+    use std::rc::Rc;
 
     fn name<T: Into<String>>(t: T) -> Term {
         Term::Name(
@@ -162,6 +166,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "generate".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![36.into(), 6.into(), name("+")],
                 },
@@ -182,6 +187,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "rfib".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![Term::Branch(Branch {
                         arms: vec![(
@@ -221,6 +227,7 @@ mod tests {
         let ast = Module {
             functions: vec![Function {
                 name: "ifib".into(),
+                loc: SourceRange::default(),
                 body: Block {
                     terms: vec![
                         0.into(),
@@ -500,6 +507,534 @@ mod tests {
         assert_eq!(result, ast);
     }
 
+    #[test]
+    fn constants() {
+        let result = parse("const LIMIT: 10 2 *\nconst: 5\nLIMIT").unwrap();
+        let ast = Module {
+            constants: vec![Constant {
+                name: "LIMIT".into(),
+                body: Block {
+                    terms: vec![10f64.into(), 2f64.into(), name("*")],
+                },
+            }],
+            functions: vec![Function {
+                name: "const".into(),
+                loc: SourceRange::default(),
+                body: Block {
+                    terms: vec![5f64.into()],
+                },
+            }],
+            body: Block {
+                terms: vec![name("LIMIT")],
+            },
+            ..Default::default()
+        };
+        assert_eq!(result, ast);
+    }
+
+    #[test]
+    fn constant_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+        assert_eq!(
+            parse("const 5: 1"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Constant,
+                context_start: at(0, 0, 0),
+                loc: at(0, 6, 6),
+            }))
+        );
+        assert_eq!(
+            parse("const A 1"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Constant,
+                context_start: at(0, 0, 0),
+                loc: at(0, 8, 8),
+            }))
+        );
+        assert_eq!(
+            parse("const A:\n1"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Constant,
+                context_start: at(0, 0, 0),
+                loc: at(0, 8, 8),
+            }))
+        );
+        assert_eq!(
+            parse("const A:"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Constant,
+                context_start: at(0, 0, 0),
+                loc: at(0, 0, 0),
+            }))
+        );
+        assert_eq!(
+            parse("const max: 5"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::ConstantIntrinsicName,
+                context_start: at(0, 0, 0),
+                loc: at(0, 6, 6),
+            }))
+        );
+        assert_eq!(
+            parse("const A: -> a"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::BindingOutsideFunction,
+                context_start: at(0, 9, 9),
+                loc: at(0, 9, 9),
+            }))
+        );
+    }
+
+    #[test]
+    fn records() {
+        let result = parse("record point {\n  x y\n}\npoint.x").unwrap();
+        let record = Rc::new(Record {
+            name: "point".into(),
+            fields: vec!["x".into(), "y".into()],
+            variant_of: None,
+        });
+        let function = |name: &str, op| Function {
+            name: name.into(),
+            loc: SourceRange::default(),
+            body: Block {
+                terms: vec![Term::Record(Rc::clone(&record), op)],
+            },
+        };
+        let ast = Module {
+            functions: vec![
+                function("point", RecordOp::Construct),
+                function("point.x", RecordOp::Get(0)),
+                function("point.with_x", RecordOp::With(0)),
+                function("point.y", RecordOp::Get(1)),
+                function("point.with_y", RecordOp::With(1)),
+            ],
+            body: Block {
+                terms: vec![name("point.x")],
+            },
+            ..Default::default()
+        };
+        assert_eq!(result, ast);
+        assert_eq!(parse("record unit {}").unwrap().functions.len(), 1);
+    }
+
+    #[test]
+    fn record_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+        assert_eq!(
+            parse("record 5 { x }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Record,
+                context_start: at(0, 0, 0),
+                loc: at(0, 7, 7),
+            }))
+        );
+        assert_eq!(
+            parse("record point x"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Record,
+                context_start: at(0, 0, 0),
+                loc: at(0, 13, 13),
+            }))
+        );
+        assert_eq!(
+            parse("record point { x x }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Record,
+                context_start: at(0, 0, 0),
+                loc: at(0, 17, 17),
+            }))
+        );
+        assert_eq!(
+            parse("record"),
+            Err(ParseError::EndOfFile(EndOfFileError::ExpectedMoreAfter(
+                ReasonExpectingMore::Record,
+                at(0, 0, 0)
+            )))
+        );
+        assert_eq!(
+            parse("record point { x"),
+            Err(ParseError::EndOfFile(EndOfFileError::UnclosedExpression(
+                WrappedExpression::Record,
+                at(0, 13, 13)
+            )))
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let result = parse("enum option {\n  some { value }\n  none\n}").unwrap();
+        let declared = Rc::new(Enum {
+            name: "option".into(),
+            variants: vec!["some".into(), "none".into()],
+        });
+        let variant = |name: &str, fields: Vec<String>| Function {
+            name: name.into(),
+            loc: SourceRange::default(),
+            body: Block {
+                terms: vec![Term::Record(
+                    Rc::new(Record {
+                        name: name.into(),
+                        fields,
+                        variant_of: Some(Rc::clone(&declared)),
+                    }),
+                    RecordOp::Construct,
+                )],
+            },
+        };
+        assert_eq!(
+            result.functions,
+            vec![
+                variant("some", vec!["value".into()]),
+                variant("none", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn match_arms() {
+        let result = parse("match {\n  (some) 1 +\n  (none) 0\n}").unwrap();
+        let expected = vec![Term::Match(Match {
+            arms: vec![
+                (
+                    "some".into(),
+                    Block {
+                        terms: vec![1f64.into(), name("+")],
+                    },
+                ),
+                (
+                    "none".into(),
+                    Block {
+                        terms: vec![0f64.into()],
+                    },
+                ),
+            ],
+        })];
+        assert_eq!(result.body.terms, expected);
+    }
+
+    #[test]
+    fn enum_and_match_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+        assert_eq!(
+            parse("enum option { some none some }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Enum,
+                context_start: at(0, 0, 0),
+                loc: at(0, 24, 24),
+            }))
+        );
+        assert_eq!(
+            parse("enum option { some { value value } }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Enum,
+                context_start: at(0, 0, 0),
+                loc: at(0, 27, 27),
+            }))
+        );
+        assert_eq!(
+            parse("enum option { some"),
+            Err(ParseError::EndOfFile(EndOfFileError::UnclosedExpression(
+                WrappedExpression::Enum,
+                at(0, 12, 12)
+            )))
+        );
+        assert_eq!(
+            parse("match { (some) (some) }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Match,
+                context_start: at(0, 0, 0),
+                loc: at(0, 16, 16),
+            }))
+        );
+        assert_eq!(
+            parse("match { (1) }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Match,
+                context_start: at(0, 0, 0),
+                loc: at(0, 0, 0),
+            }))
+        );
+        assert_eq!(
+            parse("match"),
+            Err(ParseError::EndOfFile(EndOfFileError::ExpectedMoreAfter(
+                ReasonExpectingMore::Match,
+                at(0, 0, 0)
+            )))
+        );
+    }
+
+    fn local(name: &str, slot: usize) -> Local {
+        Local {
+            name: name.into(),
+            slot,
+        }
+    }
+
+    #[test]
+    fn local_bindings() {
+        let result = parse("fn: {\n  -> a b\n  b a {(a) -> a\n a} a\n}\nb").unwrap();
+        let ast = Module {
+            functions: vec![Function {
+                name: "fn".into(),
+                loc: SourceRange::default(),
+                body: Block {
+                    terms: vec![
+                        Term::Bind(vec![local("a", 0), local("b", 1)]),
+                        Term::Local(local("b", 1)),
+                        Term::Local(local("a", 0)),
+                        Term::Branch(Branch {
+                            arms: vec![(
+                                Block {
+                                    terms: vec![Term::Local(local("a", 0))],
+                                },
+                                Block {
+                                    terms: vec![
+                                        Term::Bind(vec![local("a", 2)]),
+                                        Term::Local(local("a", 2)),
+                                    ],
+                                },
+                            )],
+                        }),
+                        Term::Local(local("a", 0)),
+                    ],
+                },
+            }],
+            body: Block {
+                terms: vec![name("b")],
+            },
+            ..Default::default()
+        };
+        assert_eq!(result, ast);
+    }
+
+    #[test]
+    fn local_bindings_are_block_scoped() {
+        let result = parse("fn: [(1 -> x\n x) x]\ng: x").unwrap();
+        let loop_v = Loop {
+            pre_condition: Some(Block {
+                terms: vec![
+                    1f64.into(),
+                    Term::Bind(vec![local("x", 0)]),
+                    Term::Local(local("x", 0)),
+                ],
+            }),
+            body: Block {
+                terms: vec![name("x")],
+            },
+            post_condition: None,
+        };
+        assert_eq!(result.functions[0].body.terms, vec![Term::Loop(loop_v)]);
+        assert_eq!(result.functions[1].body.terms, vec![name("x")]);
+    }
+
+    #[test]
+    fn local_binding_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+
+        assert_eq!(
+            parse("1 -> x"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::BindingOutsideFunction,
+                context_start: at(0, 2, 2),
+                loc: at(0, 2, 2),
+            }))
+        );
+        assert_eq!(
+            parse("fn: {\n-> x 1\n}"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Binding,
+                context_start: at(1, 6, 0),
+                loc: at(1, 11, 5),
+            }))
+        );
+        assert_eq!(
+            parse("fn: {\n-> }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Binding,
+                context_start: at(1, 6, 0),
+                loc: at(1, 9, 3),
+            }))
+        );
+        assert_eq!(
+            parse("fn: ->"),
+            Err(ParseError::EndOfFile(EndOfFileError::ExpectedMoreAfter(
+                ReasonExpectingMore::Binding,
+                at(0, 4, 4)
+            )))
+        );
+    }
+
+    #[test]
+    fn loop_control() {
+        let result = parse("[(1) {(2) break} [continue] (3)] [continue]").unwrap();
+        let inner = Loop {
+            pre_condition: None,
+            body: Block {
+                terms: vec![Term::Continue],
+            },
+            post_condition: None,
+        };
+        let outer = Loop {
+            pre_condition: Some(Block {
+                terms: vec![1f64.into()],
+            }),
+            body: Block {
+                terms: vec![
+                    Term::Branch(Branch {
+                        arms: vec![(
+                            Block {
+                                terms: vec![2f64.into()],
+                            },
+                            Block {
+                                terms: vec![Term::Break],
+                            },
+                        )],
+                    }),
+                    Term::Loop(inner.clone()),
+                ],
+            },
+            post_condition: Some(Block {
+                terms: vec![3f64.into()],
+            }),
+        };
+        assert_eq!(
+            result.body.terms,
+            vec![Term::Loop(outer), Term::Loop(inner)]
+        );
+    }
+
+    #[test]
+    fn early_return() {
+        let result = parse("fn: {\n{(1) return} [(return) 2]\n}").unwrap();
+        let expected = vec![
+            Term::Branch(Branch {
+                arms: vec![(
+                    Block {
+                        terms: vec![1f64.into()],
+                    },
+                    Block {
+                        terms: vec![Term::Return],
+                    },
+                )],
+            }),
+            Term::Loop(Loop {
+                pre_condition: Some(Block {
+                    terms: vec![Term::Return],
+                }),
+                body: Block {
+                    terms: vec![2f64.into()],
+                },
+                post_condition: None,
+            }),
+        ];
+        assert_eq!(result.functions[0].body.terms, expected);
+
+        let at = SourceLocation {
+            character: 5,
+            line: 0,
+            column: 5,
+        };
+        assert_eq!(
+            parse("[(1) return]"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::ReturnOutsideFunction,
+                context_start: at,
+                loc: at,
+            }))
+        );
+    }
+
+    #[test]
+    fn try_catch() {
+        let result = parse("try { 1 throw } catch {\n  drop\n}").unwrap();
+        let expected = vec![Term::Try(Try {
+            body: Block {
+                terms: vec![1f64.into(), name("throw")],
+            },
+            handler: Block {
+                terms: vec![name("drop")],
+            },
+        })];
+        assert_eq!(result.body.terms, expected);
+    }
+
+    #[test]
+    fn try_catch_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+        assert_eq!(
+            parse("try { 1 } 2"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Try,
+                context_start: at(0, 0, 0),
+                loc: at(0, 10, 10),
+            }))
+        );
+        assert_eq!(
+            parse("1 catch { 2 }"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Try,
+                context_start: at(0, 2, 2),
+                loc: at(0, 2, 2),
+            }))
+        );
+        assert_eq!(
+            parse("try { 1 }"),
+            Err(ParseError::EndOfFile(EndOfFileError::ExpectedMoreAfter(
+                ReasonExpectingMore::Try,
+                at(0, 0, 0)
+            )))
+        );
+    }
+
+    #[test]
+    fn loop_control_errors() {
+        let at = |line, character, column| SourceLocation {
+            character,
+            line,
+            column,
+        };
+        let outside = |loc| {
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::LoopControl,
+                context_start: loc,
+                loc,
+            }))
+        };
+
+        assert_eq!(parse("1 break"), outside(at(0, 2, 2)));
+        assert_eq!(parse("fn: 1 {(1) continue}"), outside(at(0, 11, 11)));
+        assert_eq!(parse("[(break) 1]"), outside(at(0, 2, 2)));
+        assert_eq!(parse("[1 (continue)]"), outside(at(0, 4, 4)));
+        assert_eq!(
+            parse("fn: {\n-> break\n}"),
+            Err(ParseError::Unexpected(UnexpectedError::InContext {
+                context: UnexpectedContext::Binding,
+                context_start: at(1, 6, 0),
+                loc: at(1, 9, 3),
+            }))
+        );
+    }
+
     #[test]
     fn bad_symbols_1() {
         let symbols = [
@@ -541,4 +1076,66 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn recover_at_function_label() {
+        let (result, errors) = parse_recovering("a: {1 ) 2\nb: 3\n4");
+        let ast = Module {
+            functions: vec![Function {
+                name: "b".into(),
+                loc: SourceRange::default(),
+                body: Block {
+                    terms: vec![3f64.into()],
+                },
+            }],
+            body: Block {
+                terms: vec![4f64.into()],
+            },
+            ..Default::default()
+        };
+        assert_eq!(result, ast);
+        assert_eq!(
+            errors,
+            vec![ParseError::Unexpected(UnexpectedError::GeneralSymbol(
+                Symbol::ParenClose,
+                SourceLocation {
+                    line: 0,
+                    character: 6,
+                    column: 6
+                },
+            ))]
+        );
+    }
+
+    #[test]
+    fn recover_at_closing_bracket() {
+        let (result, errors) = parse_recovering("1 {(1) ] 2} 3 ) 4\n5");
+        let ast = Module {
+            body: Block {
+                terms: vec![1f64.into(), 3f64.into(), 5f64.into()],
+            },
+            ..Default::default()
+        };
+        assert_eq!(result, ast);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            ParseError::Unexpected(UnexpectedError::SymbolInSection {
+                symbol: Symbol::SquareClose,
+                ..
+            })
+        ));
+        assert!(matches!(
+            errors[1],
+            ParseError::Unexpected(UnexpectedError::GeneralSymbol(Symbol::ParenClose, _))
+        ));
+    }
+
+    #[test]
+    fn recover_first_error_matches_parse() {
+        let code = "# 5 \"x\"\nf: {(1) 2}\n[1 () 1]";
+        let (_, errors) = parse_recovering(code);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(parse(code).err().as_ref(), errors.first());
+    }
 }
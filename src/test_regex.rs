@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use crate::lang::string::{CharString, Regex, RegexStepLimit, StringApi as _};
+
+    fn find(pattern: &str, text: &str) -> Option<Vec<Option<String>>> {
+        let regex = Regex::new(pattern).unwrap();
+        let text = CharString::from(text);
+        let captures = regex.find_at(&text, 0).unwrap()?;
+        Some(
+            captures
+                .into_iter()
+                .map(|e| e.map(|e| text.substring(e).to_string()))
+                .collect(),
+        )
+    }
+
+    fn found(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values.iter().map(|e| e.map(str::to_owned)).collect()
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for pattern in [
+            "(", ")", "[a", "*a", "a**", "a{2,1}", "a{,2}", "a{1001}", "{", "}", "]", "^*", r"\b",
+            r"\1", r"\-", "(?=a)", "(?<n>a)", "[z-a]", r"[a-\d]", "\\",
+        ] {
+            assert!(Regex::new(pattern).is_none(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn valid_patterns() {
+        for pattern in [
+            "",
+            "a|",
+            "(?:a)",
+            "[]",
+            "[^]",
+            "[a-]",
+            "[-a]",
+            r"[\-\]]",
+            r"\^\$\.\/",
+            "a{2,}?",
+        ] {
+            assert!(Regex::new(pattern).is_some(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn leftmost_match() {
+        assert_eq!(find(r"\d+", "ab12cd345"), Some(found(&[Some("12")])));
+        assert_eq!(find("b|ab", "xab"), Some(found(&[Some("ab")])));
+        assert_eq!(find("a|ab", "ab"), Some(found(&[Some("a")])));
+        assert_eq!(find("^b", "ab"), None);
+        assert_eq!(find("b$", "ab"), Some(found(&[Some("b")])));
+    }
+
+    #[test]
+    fn greedy_and_lazy() {
+        assert_eq!(find("a+", "aaa"), Some(found(&[Some("aaa")])));
+        assert_eq!(find("a+?", "aaa"), Some(found(&[Some("a")])));
+        assert_eq!(find("a{2,3}?", "aaa"), Some(found(&[Some("aa")])));
+        assert_eq!(find("<.*>", "<a><b>"), Some(found(&[Some("<a><b>")])));
+        assert_eq!(find("<.*?>", "<a><b>"), Some(found(&[Some("<a>")])));
+    }
+
+    #[test]
+    fn captures() {
+        assert_eq!(
+            find(r"(\d+)-(\d+)", "on 2024-01"),
+            Some(found(&[Some("2024-01"), Some("2024"), Some("01")]))
+        );
+        assert_eq!(find("(a)|b", "b"), Some(found(&[Some("b"), None])));
+        assert_eq!(find("(?:(a)|b)+", "ab"), Some(found(&[Some("ab"), None])));
+        assert_eq!(find("(a|)*x", "x"), Some(found(&[Some("x"), None])));
+    }
+
+    #[test]
+    fn empty_iterations() {
+        assert_eq!(find("(?:a|)*b", "aab"), Some(found(&[Some("aab")])));
+        assert_eq!(find("(?:||-){0,2}", "-"), Some(found(&[Some("-")])));
+        assert_eq!(find("(?:a*)*", "b"), Some(found(&[Some("")])));
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(find(r"[\w-]+", "a-b c"), Some(found(&[Some("a-b")])));
+        assert_eq!(find(r"\S+", "  😃x "), Some(found(&[Some("😃x")])));
+        assert_eq!(find(r"\s", "a\u{feff}b"), Some(found(&[Some("\u{feff}")])));
+        assert_eq!(find("[^a-c]", "abcd"), Some(found(&[Some("d")])));
+        assert_eq!(find("a.c", "a\nc"), Some(found(&[Some("a\nc")])));
+        assert_eq!(find("[]", "abc"), None);
+    }
+
+    #[test]
+    fn step_limit() {
+        let text = CharString::from("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab");
+        let regex = Regex::new("(a*)*c").unwrap();
+        assert_eq!(regex.find_at(&text, 0), Err(RegexStepLimit));
+        assert_eq!(
+            find("(a*)*b", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab").map(|e| e.len()),
+            Some(2)
+        );
+    }
+}
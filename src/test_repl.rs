@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser as _;
+
+    use crate::ReplArgs;
+    use crate::repl::Repl;
+
+    fn repl() -> Repl {
+        let base = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("int");
+        Repl::new(ReplArgs::parse_from(["scatter"]), base)
+    }
+
+    #[test]
+    fn failed_import_is_not_reused() {
+        let mut repl = repl();
+        let user = repl.program.allocate_namespace();
+        for _ in 0..2 {
+            let error = repl
+                .load_code(user, r#"# * "./lib/text/broken.sl""#)
+                .unwrap_err();
+            assert!(
+                error.to_string().contains("Function redefinition error"),
+                "{error}"
+            );
+            assert_eq!(
+                repl.program.namespaces.len(),
+                1,
+                "partial namespaces remain"
+            );
+        }
+        repl.load_code(user, r#"# * "./lib/greet.sl""#).unwrap();
+    }
+}
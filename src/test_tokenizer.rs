@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use crate::lang::{SourceLocation, Token};
-    use crate::tokenizer::{EscapeSequenceError, TokenizeError, tokenize};
+    use crate::lang::{BigInt, SourceLocation, Token};
+    use crate::tokenizer::{EscapeSequenceError, NumberError, TokenizeError, tokenize};
 
     fn expect_tokens(source: &str, expected: &[Token]) {
         let actual: Vec<Token> = tokenize(source)
@@ -72,6 +72,121 @@ mod tests {
         expect_tokens(source, &expected);
     }
 
+    #[test]
+    fn integer_1() {
+        let source = "42i -7i 42 2dup";
+        let expected: Vec<Token> = vec![
+            Token::Integer(42),
+            Token::Integer(-7),
+            Token::Number(42.0),
+            Token::Name("2dup".into()),
+        ];
+        expect_tokens(source, &expected);
+    }
+
+    #[test]
+    fn integer_2() {
+        let source = "9223372036854775807i -9223372036854775808i";
+        let expected: Vec<Token> = vec![Token::Integer(i64::MAX), Token::Integer(i64::MIN)];
+        expect_tokens(source, &expected);
+    }
+
+    #[test]
+    fn big_1() {
+        let source = "42n -7n 123456789012345678901234567890n";
+        let expected: Vec<Token> = vec![
+            Token::Big(BigInt::from(42)),
+            Token::Big(BigInt::from(-7)),
+            Token::Big(BigInt::parse("123456789012345678901234567890").unwrap()),
+        ];
+        expect_tokens(source, &expected);
+    }
+
+    #[test]
+    fn radix_1() {
+        let source = "0xff -0b1010i 0o17n 0xDEAD_BEEFi";
+        let expected: Vec<Token> = vec![
+            Token::Number(255.0),
+            Token::Integer(-10),
+            Token::Big(BigInt::from(15)),
+            Token::Integer(0xDEAD_BEEF),
+        ];
+        expect_tokens(source, &expected);
+    }
+
+    #[test]
+    fn decimal_1() {
+        let source = "1_000_000 1e-9 2.5E3 -1_0.2_5e+1_0 1_000i";
+        let expected: Vec<Token> = vec![
+            Token::Number(1_000_000.0),
+            Token::Number(1e-9),
+            Token::Number(2500.0),
+            Token::Number(-10.25e10),
+            Token::Integer(1000),
+        ];
+        expect_tokens(source, &expected);
+    }
+
+    #[test]
+    fn exact_decimal_1() {
+        let source = "0.1d 1.50d -2_5.0_5d 1.5e2d 0xffd";
+        let tokens: Vec<String> = tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|f| format!("{:?}", f.value))
+            .collect();
+        assert_eq!(tokens, ["0.1d", "1.50d", "-25.05d", "150d", "4093.0"]);
+    }
+
+    #[test]
+    fn special_1() {
+        let tokens: Vec<Token> = tokenize("inf -inf nan")
+            .unwrap()
+            .into_iter()
+            .map(|f| f.value)
+            .collect();
+        assert_eq!(tokens[0], Token::Number(f64::INFINITY));
+        assert_eq!(tokens[1], Token::Number(f64::NEG_INFINITY));
+        assert!(matches!(tokens[2], Token::Number(n) if n.is_nan()));
+        expect_tokens(
+            "Infinity -nan",
+            &[Token::Name("Infinity".into()), Token::Name("-nan".into())],
+        );
+    }
+
+    fn expect_number_error(source: &str, error: NumberError, start: usize, end: usize) {
+        let location = |character| SourceLocation {
+            line: 0,
+            character,
+            column: character,
+        };
+        let expected = TokenizeError::InvalidNumber(error, (location(start), location(end)).into());
+        expect_error(source, &expected);
+    }
+
+    #[test]
+    fn invalid_number_1() {
+        expect_number_error("1 0x", NumberError::MissingDigits, 2, 3);
+        expect_number_error("1e", NumberError::MissingDigits, 0, 1);
+        expect_number_error("2.", NumberError::MissingDigits, 0, 1);
+        expect_number_error("1e+-5", NumberError::MissingDigits, 0, 4);
+        expect_number_error("0b102", NumberError::InvalidDigit, 0, 4);
+        expect_number_error("1.2.3", NumberError::InvalidDigit, 0, 4);
+        expect_number_error("0xffz", NumberError::InvalidDigit, 0, 4);
+    }
+
+    #[test]
+    fn invalid_number_2() {
+        expect_number_error("1__000", NumberError::MisplacedSeparator, 0, 5);
+        expect_number_error("1_000_", NumberError::MisplacedSeparator, 0, 5);
+        expect_number_error("0x_ff", NumberError::MisplacedSeparator, 0, 4);
+        expect_number_error("1_.5", NumberError::MisplacedSeparator, 0, 3);
+        expect_number_error("1.5i", NumberError::NotAnInteger, 0, 3);
+        expect_number_error("1e3n", NumberError::NotAnInteger, 0, 3);
+        expect_number_error("9223372036854775808i", NumberError::OutOfRange, 0, 19);
+        expect_number_error("0x8000000000000000i", NumberError::OutOfRange, 0, 18);
+    }
+
     #[test]
     fn unbounded_1() {
         let source = "'a";
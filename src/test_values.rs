@@ -2,7 +2,10 @@
 mod tests {
     use std::rc::Rc;
 
-    use crate::lang::{OwnedValue, Value, string::CharString};
+    use crate::lang::{
+        OwnedValue, Value,
+        string::{CharString, StringApi as _},
+    };
 
     #[test]
     fn i32() {
@@ -46,4 +49,25 @@ mod tests {
     fn convert() {
         assert_eq!("😃a😄b😁", &CharString::from("😃a😄b😁").to_string());
     }
+
+    #[test]
+    fn string_operations() {
+        let s = CharString::from("😃a,😄b,😁");
+        let pieces: Vec<String> = s.split(&",".into()).into_iter().map(String::from).collect();
+        assert_eq!(pieces, ["😃a", "😄b", "😁"]);
+        assert_eq!(s.rfind(&",".into()), Some(5));
+        assert_eq!(s.reverse().to_string(), "😁,b😄,a😃");
+        assert_eq!(
+            s.replace(&",".into(), &"; ".into()).to_string(),
+            "😃a; 😄b; 😁"
+        );
+        assert!(s.starts_with(&"😃".into()));
+        assert!(s.ends_with(&",😁".into()));
+        assert_eq!(CharString::from(" \tx y\n").trim().to_string(), "x y");
+        assert_eq!(
+            CharString::from("Straße").to_uppercase().to_string(),
+            "STRASSE"
+        );
+        assert_eq!(CharString::from("ab").repeat(2).to_string(), "abab");
+    }
 }
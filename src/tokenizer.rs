@@ -1,6 +1,9 @@
 use crate::{
     convert::hex_char_to_u8,
-    lang::{ParsedToken, SourceCrawler, SourceLocation, SourcePositions, Symbol, Token},
+    lang::{
+        BigInt, Decimal, ParsedToken, SourceCrawler, SourceLocation, SourcePositions, SourceRange,
+        Symbol, Token,
+    },
 };
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -224,6 +227,117 @@ impl RangeCommentParseState {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum NumberError {
+    MissingDigits,
+    InvalidDigit,
+    MisplacedSeparator,
+    NotAnInteger,
+    OutOfRange,
+}
+
+// Takes digits in `radix` from the start of `source`, which may be grouped by single underscores
+fn take_digits(source: &str, radix: u32) -> Result<(String, &str), NumberError> {
+    let end = source
+        .find(|c: char| !c.is_digit(radix) && c != '_')
+        .unwrap_or(source.len());
+    let (digits, rest) = source.split_at(end);
+    if digits.is_empty() {
+        return Err(NumberError::MissingDigits);
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(NumberError::MisplacedSeparator);
+    }
+    Ok((digits.replace('_', ""), rest))
+}
+
+// Digits with an optional fraction and exponent, like `1_000.5e-3`
+fn take_decimal(source: &str) -> Result<(String, &str), NumberError> {
+    let (mut text, mut rest) = take_digits(source, 10)?;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let (digits, after) = take_digits(fraction, 10)?;
+        text = format!("{text}.{digits}");
+        rest = after;
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let sign = if exponent.starts_with('-') { "-" } else { "" };
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let (digits, after) = take_digits(exponent, 10)?;
+        text = format!("{text}e{sign}{digits}");
+        rest = after;
+    }
+    Ok((text, rest))
+}
+
+// Returns None when `word` isn't a number. Words starting with a digit are only numbers when
+// they use nothing but the characters of one, so names like `2dup` are left alone
+fn number_literal(word: &str) -> Option<Result<Token, NumberError>> {
+    match word {
+        "inf" => return Some(Ok(Token::Number(f64::INFINITY))),
+        "-inf" => return Some(Ok(Token::Number(f64::NEG_INFINITY))),
+        "nan" => return Some(Ok(Token::Number(f64::NAN))),
+        _ => {}
+    }
+    let (sign, body) = match word.strip_prefix('-') {
+        Some(body) => ("-", body),
+        None => ("", word),
+    };
+    let radix = match body.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ if body.starts_with(|c: char| c.is_ascii_digit())
+            && body
+                .trim_end_matches(['i', 'n', 'd'])
+                .chars()
+                .all(|c| c.is_ascii_digit() || "_.eE+-".contains(c)) =>
+        {
+            10
+        }
+        _ => return None,
+    };
+    Some(read_number(sign, body, radix))
+}
+
+fn read_number(sign: &str, body: &str, radix: u32) -> Result<Token, NumberError> {
+    // Integers are written with an `i` suffix, like `42i`, bignums with `n`, like `42n`, and
+    // decimals with `d`, like `0.25d`. A `d` is a digit in hexadecimal, so it only counts in base 10
+    let suffix = body
+        .chars()
+        .last()
+        .filter(|c| matches!(c, 'i' | 'n') || radix == 10 && *c == 'd');
+    let body = &body[..body.len() - suffix.map_or(0, char::len_utf8)];
+    let (digits, rest) = if radix == 10 {
+        take_decimal(body)?
+    } else {
+        take_digits(&body[2..], radix)?
+    };
+    if !rest.is_empty() {
+        return Err(NumberError::InvalidDigit);
+    }
+    if radix == 10 && suffix.is_none() {
+        return format!("{sign}{digits}")
+            .parse()
+            .map(Token::Number)
+            .map_err(|_| NumberError::InvalidDigit);
+    }
+    if suffix == Some('d') {
+        return Decimal::parse(&format!("{sign}{digits}"))
+            .map(Token::Decimal)
+            .ok_or(NumberError::OutOfRange);
+    }
+    let value =
+        BigInt::parse_radix(&format!("{sign}{digits}"), radix).ok_or(NumberError::NotAnInteger)?;
+    match suffix {
+        Some('i') => value
+            .to_i64()
+            .map(Token::Integer)
+            .ok_or(NumberError::OutOfRange),
+        Some(_) => Ok(Token::Big(value)),
+        None => Ok(Token::Number(value.to_f64())),
+    }
+}
+
 #[derive(Debug)]
 struct NormalParseState {
     start: SourceLocation,
@@ -231,35 +345,44 @@ struct NormalParseState {
 }
 
 impl NormalParseState {
-    pub fn take(&mut self) -> Option<Token> {
-        if self.word.is_empty() {
-            return None;
+    pub fn take(&mut self) -> Result<Token, NumberError> {
+        let word = std::mem::take(&mut self.word);
+        if let Some(number) = number_literal(&word) {
+            return number;
         }
-        let word = &mut self.word;
-        let token = match word.parse::<f64>() {
-            Ok(v) => Token::Number(v),
-            Err(_) => match word.as_str() {
-                "true" => Token::Bool(true),
-                "false" => Token::Bool(false),
-                _ => Token::Name(word.clone()),
-            },
-        };
-        word.clear();
-        Some(token)
+        Ok(match word.as_str() {
+            "true" => Token::Bool(true),
+            "false" => Token::Bool(false),
+            _ => Token::Name(word),
+        })
     }
-    pub fn finish(&mut self, tokens: &mut Vec<ParsedToken>, prev: Option<SourceLocation>) {
-        let Some(token) = self.take() else {
-            return;
-        };
+
+    pub fn finish(
+        &mut self,
+        tokens: &mut Vec<ParsedToken>,
+        prev: Option<SourceLocation>,
+    ) -> Result<(), TokenizeError> {
+        if self.word.is_empty() {
+            return Ok(());
+        }
         let end = prev.expect("Must have previous location if word is not empty");
-        tokens.push(token.with_range((self.start, end)));
+        match self.take() {
+            Ok(token) => tokens.push(token.with_range((self.start, end))),
+            Err(e) => return Err(TokenizeError::InvalidNumber(e, (self.start, end).into())),
+        }
+        Ok(())
     }
 
-    pub fn advance(&mut self, tokens: &mut Vec<ParsedToken>, loc: &SourcePositions) {
-        self.finish(tokens, loc.prev);
+    pub fn advance(
+        &mut self,
+        tokens: &mut Vec<ParsedToken>,
+        loc: &SourcePositions,
+    ) -> Result<(), TokenizeError> {
+        self.finish(tokens, loc.prev)?;
         if let Some(next) = loc.next {
             self.start = next;
         }
+        Ok(())
     }
 
     pub fn next(
@@ -268,7 +391,7 @@ impl NormalParseState {
         char: char,
         next_char: Option<char>,
         loc: &SourcePositions,
-    ) -> ParseState {
+    ) -> Result<ParseState, TokenizeError> {
         if let Some(sym) = match char {
             ':' => Some(Symbol::Colon),
             '@' => Some(Symbol::At),
@@ -282,36 +405,36 @@ impl NormalParseState {
             '\n' => Some(Symbol::LineEnd),
             _ => None,
         } {
-            self.advance(tokens, loc);
+            self.advance(tokens, loc)?;
             tokens.push(Token::Symbol(sym).at_location(loc.current));
-            return ParseState::Normal(self);
+            return Ok(ParseState::Normal(self));
         }
         match char {
             '"' => {
-                self.advance(tokens, loc);
-                return ParseState::string(loc.current, StringDelimiter::Double);
+                self.advance(tokens, loc)?;
+                return Ok(ParseState::string(loc.current, StringDelimiter::Double));
             }
             '\'' => {
-                self.advance(tokens, loc);
-                return ParseState::string(loc.current, StringDelimiter::Single);
+                self.advance(tokens, loc)?;
+                return Ok(ParseState::string(loc.current, StringDelimiter::Single));
             }
             '/' if next_char == Some('/') => {
-                self.advance(tokens, loc);
-                return ParseState::comment();
+                self.advance(tokens, loc)?;
+                return Ok(ParseState::comment());
             }
             '/' if next_char == Some('*') => {
-                self.advance(tokens, loc);
-                return ParseState::range_comment(loc.current);
+                self.advance(tokens, loc)?;
+                return Ok(ParseState::range_comment(loc.current));
             }
             ' ' => {
-                self.advance(tokens, loc);
+                self.advance(tokens, loc)?;
             }
             c => {
                 self.word.push(c);
             }
         }
 
-        ParseState::Normal(self)
+        Ok(ParseState::Normal(self))
     }
 }
 
@@ -359,10 +482,7 @@ impl ParseState {
         match self {
             ParseState::LineComment => Ok(()),
             ParseState::String(s) => Err(TokenizeError::UnboundedString(s.start)),
-            ParseState::Normal(mut s) => {
-                s.finish(tokens, Some(loc));
-                Ok(())
-            }
+            ParseState::Normal(mut s) => s.finish(tokens, Some(loc)),
             ParseState::RangeComment(s) => Err(TokenizeError::UnboundedComment(s.start)),
         }
     }
@@ -376,7 +496,7 @@ impl ParseState {
     ) -> Result<Option<ParseState>, TokenizeError> {
         match self {
             ParseState::String(s) => s.next(tokens, char, next_char, loc),
-            ParseState::Normal(s) => Ok(Some(s.next(tokens, char, next_char, loc))),
+            ParseState::Normal(s) => s.next(tokens, char, next_char, loc).map(Some),
             ParseState::RangeComment(s) => Ok(s.next(char, loc)),
             ParseState::LineComment => match (char, &loc.next) {
                 ('\n', Some(next)) => Ok(Some(ParseState::normal(next.to_owned()))),
@@ -392,6 +512,7 @@ pub enum TokenizeError {
     UnboundedString(SourceLocation),
     InvalidEscape(EscapeSequenceError, SourceLocation),
     UnboundedComment(SourceLocation),
+    InvalidNumber(NumberError, SourceRange),
 }
 
 pub fn tokenize(source: &str) -> Result<Vec<ParsedToken>, TokenizeError> {
@@ -1,10 +1,10 @@
 #!/bin/bash
 set -e
 
-echo " 0/10 build"
+echo " 0/13 build"
   cargo build -q
 
-check_count="10"
+check_count="13"
 
 echo " 1/$check_count test"
   cargo test -q > /dev/null
@@ -13,31 +13,44 @@ echo " 2/$check_count e2e (Interpreted)"
   ./target/debug/scatter examples/e2e.sl > /dev/null
 
 echo " 3/$check_count e2e (C)"
+  ./target/debug/scatter --no-fold -g c examples/e2e.sl > gen/gen.c
+  gcc -o gen/out gen/gen.c -Wall -std=c99 -pedantic -lm
+  ./gen/out > /dev/null
+
+echo " 4/$check_count e2e (C, folded)"
   ./target/debug/scatter -g c examples/e2e.sl > gen/gen.c
-  gcc -o gen/out gen/gen.c -Wall -std=c99 -pedantic
+  gcc -o gen/out gen/gen.c -Wall -std=c99 -pedantic -lm
   ./gen/out > /dev/null
 
-echo " 4/$check_count e2e (JS)"
+echo " 5/$check_count e2e (JS)"
+  ./target/debug/scatter --no-fold -g js examples/e2e.sl | node > /dev/null
+
+echo " 6/$check_count e2e (JS, folded)"
   ./target/debug/scatter -g js examples/e2e.sl | node > /dev/null
 
-echo " 5/$check_count e2e (Rust)"
+echo " 7/$check_count e2e (Rust)"
+  ./target/debug/scatter --no-fold -g rs examples/e2e.sl > gen/gen.rs
+  chmod +x gen/gen.rs
+  ./gen/gen.rs > /dev/null 2>/dev/null
+
+echo " 8/$check_count e2e (Rust, folded)"
   ./target/debug/scatter -g rs examples/e2e.sl > gen/gen.rs
   chmod +x gen/gen.rs
   ./gen/gen.rs > /dev/null 2>/dev/null
 
-echo " 6/$check_count unicode (Interpreted)"
+echo " 9/$check_count unicode (Interpreted)"
   ./target/debug/scatter examples/unicode.sl > /dev/null
 
-echo " 7/$check_count unicode (JS)"
+echo "10/$check_count unicode (JS)"
   ./target/debug/scatter -g js examples/unicode.sl | node > /dev/null
 
-echo " 8/$check_count unicode (Rust)"
+echo "11/$check_count unicode (Rust)"
   ./target/debug/scatter -g rs examples/unicode.sl > gen/unicode.rs
   chmod +x gen/unicode.rs
   ./gen/unicode.rs > /dev/null 2>/dev/null
 
-echo " 9/$check_count lint"
+echo "12/$check_count lint"
   cargo clippy -q --all-targets
   cargo fmt --check
 
-echo "10/$check_count success!"
+echo "13/$check_count success!"